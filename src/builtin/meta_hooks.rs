@@ -1,16 +1,16 @@
 use std::collections::HashMap;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::Result;
-use fancy_regex::Regex;
 use itertools::Itertools;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 use crate::cli::run::{get_filenames, FileFilter, FileOptions};
 use crate::config::Language;
 use crate::hook::{Hook, Project};
+use crate::pattern;
 use crate::store::Store;
 
 /// Ensures that the configured hooks apply to at least one file in the repository.
@@ -18,6 +18,7 @@ pub(crate) async fn check_hooks_apply(
     _hook: &Hook,
     filenames: &[&String],
     _env_vars: Arc<HashMap<&'static str, String>>,
+    work_dir: Option<&Path>,
 ) -> Result<(i32, Vec<u8>)> {
     let store = Store::from_settings()?.init()?;
 
@@ -27,14 +28,11 @@ pub(crate) async fn check_hooks_apply(
     let mut output = Vec::new();
 
     for filename in filenames {
-        let mut project = Project::from_config_file(Some(PathBuf::from(filename)))?;
+        let config = work_dir.map_or_else(|| PathBuf::from(filename), |dir| dir.join(filename));
+        let mut project = Project::from_config_file(Some(config))?;
         let hooks = project.init_hooks(&store, None).await?;
 
-        let filter = FileFilter::new(
-            &input,
-            project.config().files.as_deref(),
-            project.config().exclude.as_deref(),
-        )?;
+        let filter = FileFilter::new(&input, project.config(), work_dir)?;
 
         for hook in hooks {
             if hook.always_run || matches!(hook.language, Language::Fail) {
@@ -63,8 +61,8 @@ fn excludes_any<T: AsRef<str> + Sync>(
         return Ok(true);
     }
 
-    let include = include.map(Regex::new).transpose()?;
-    let exclude = exclude.map(Regex::new).transpose()?;
+    let include = include.map(pattern::compile).transpose()?;
+    let exclude = exclude.map(pattern::compile).transpose()?;
     Ok(files.into_par_iter().any(|f| {
         let f = f.as_ref();
         if let Some(re) = &include {
@@ -86,6 +84,7 @@ pub(crate) async fn check_useless_excludes(
     _hook: &Hook,
     filenames: &[&String],
     _env_vars: Arc<HashMap<&'static str, String>>,
+    work_dir: Option<&Path>,
 ) -> Result<(i32, Vec<u8>)> {
     let store = Store::from_settings()?.init()?;
 
@@ -95,7 +94,8 @@ pub(crate) async fn check_useless_excludes(
     let mut output = Vec::new();
 
     for filename in filenames {
-        let mut project = Project::from_config_file(Some(PathBuf::from(filename)))?;
+        let config = work_dir.map_or_else(|| PathBuf::from(filename), |dir| dir.join(filename));
+        let mut project = Project::from_config_file(Some(config))?;
 
         if !excludes_any(&input, None, project.config().exclude.as_deref())? {
             code = 1;
@@ -108,11 +108,7 @@ pub(crate) async fn check_useless_excludes(
 
         let hooks = project.init_hooks(&store, None).await?;
 
-        let filter = FileFilter::new(
-            &input,
-            project.config().files.as_deref(),
-            project.config().exclude.as_deref(),
-        )?;
+        let filter = FileFilter::new(&input, project.config(), work_dir)?;
 
         for hook in hooks {
             let filtered_files = filter.by_tag(&hook);
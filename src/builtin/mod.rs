@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 
@@ -23,13 +24,14 @@ pub async fn run_fast_path(
     hook: &Hook,
     filenames: &[&String],
     env_vars: Arc<HashMap<&'static str, String>>,
+    work_dir: Option<&Path>,
 ) -> anyhow::Result<(i32, Vec<u8>)> {
     match hook.repo() {
-        Repo::Meta { .. } => run_meta_hook(hook, filenames, env_vars).await,
+        Repo::Meta { .. } => run_meta_hook(hook, filenames, env_vars, work_dir).await,
         Repo::Remote { url, .. } if is_pre_commit_hooks(url) => {
             Implemented::from_str(hook.id.as_str())
                 .unwrap()
-                .run(hook, filenames, env_vars)
+                .run(hook, filenames, env_vars, work_dir)
                 .await
         }
         _ => unreachable!(),
@@ -40,11 +42,14 @@ async fn run_meta_hook(
     hook: &Hook,
     filenames: &[&String],
     env_vars: Arc<HashMap<&'static str, String>>,
+    work_dir: Option<&Path>,
 ) -> anyhow::Result<(i32, Vec<u8>)> {
     match hook.id.as_str() {
-        "check-hooks-apply" => meta_hooks::check_hooks_apply(hook, filenames, env_vars).await,
+        "check-hooks-apply" => {
+            meta_hooks::check_hooks_apply(hook, filenames, env_vars, work_dir).await
+        }
         "check-useless-excludes" => {
-            meta_hooks::check_useless_excludes(hook, filenames, env_vars).await
+            meta_hooks::check_useless_excludes(hook, filenames, env_vars, work_dir).await
         }
         "identity" => Ok(meta_hooks::identity(hook, filenames, env_vars)),
         _ => unreachable!(),
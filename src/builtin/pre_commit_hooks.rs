@@ -10,7 +10,7 @@ use futures::StreamExt;
 use url::Url;
 
 use crate::hook::Hook;
-use crate::run::CONCURRENCY;
+use crate::run::concurrency;
 
 pub(crate) enum Implemented {
     TrailingWhitespace,
@@ -33,9 +33,12 @@ impl Implemented {
         hook: &Hook,
         filenames: &[&String],
         env_vars: Arc<HashMap<&'static str, String>>,
+        work_dir: Option<&Path>,
     ) -> Result<(i32, Vec<u8>)> {
         match self {
-            Self::TrailingWhitespace => fix_trailing_whitespace(hook, filenames, env_vars).await,
+            Self::TrailingWhitespace => {
+                fix_trailing_whitespace(hook, filenames, env_vars, work_dir).await
+            }
         }
     }
 }
@@ -57,6 +60,7 @@ pub(crate) async fn fix_trailing_whitespace(
     hook: &Hook,
     filenames: &[&String],
     _env_vars: Arc<HashMap<&'static str, String>>,
+    work_dir: Option<&Path>,
 ) -> Result<(i32, Vec<u8>)> {
     let entry = shlex::split(&hook.entry).ok_or(anyhow::anyhow!("Failed to parse entry"))?;
     let args = Args::try_parse_from(entry.iter().chain(&hook.args))?;
@@ -102,7 +106,11 @@ pub(crate) async fn fix_trailing_whitespace(
                 let is_markdown =
                     force_markdown || ext.map_or(false, |ext| markdown_exts.contains(&ext));
 
-                let content = tokio::fs::read(filename).await?;
+                let path = work_dir.map_or_else(
+                    || Path::new(filename).to_path_buf(),
+                    |dir| dir.join(filename),
+                );
+                let content = tokio::fs::read(&path).await?;
 
                 let mut modified = false;
                 let mut output = Vec::new();
@@ -155,14 +163,14 @@ pub(crate) async fn fix_trailing_whitespace(
                 }
 
                 if modified {
-                    tokio::fs::write(filename, &output).await?;
+                    tokio::fs::write(&path, &output).await?;
                     anyhow::Ok((1, format!("Fixing {filename}\n").into_bytes()))
                 } else {
                     anyhow::Ok((0, Vec::new()))
                 }
             }
         })
-        .buffer_unordered(*CONCURRENCY);
+        .buffer_unordered(concurrency());
 
     let mut code = 0;
     let mut output = Vec::new();
@@ -0,0 +1,217 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use owo_colors::OwoColorize;
+
+use crate::cli::ExitStatus;
+use crate::config::{
+    parse_config_value, parse_manifest, read_config, read_config_value, MANIFEST_FILE,
+};
+use crate::config_edit::ConfigDocument;
+use crate::fs::Simplified;
+use crate::git;
+use crate::hook::Project;
+use crate::printer::Printer;
+use crate::user_config::UserConfig;
+
+/// A remote repo entry of the config file, as it's written.
+struct RepoEntry {
+    index: usize,
+    url: String,
+    rev: String,
+    subdirectory: Option<String>,
+    hook_ids: Vec<String>,
+}
+
+/// The rev to update a repo to.
+struct Update {
+    rev: String,
+    /// The tag the rev is frozen from.
+    frozen: Option<String>,
+}
+
+/// Update the `rev` of the remote repos in the config file to their latest tags.
+///
+/// Only the revs are rewritten, the rest of the file is kept as it's written.
+pub(crate) async fn autoupdate(
+    config: Option<PathBuf>,
+    bleeding_edge: bool,
+    freeze: bool,
+    repo: Option<String>,
+    jobs: usize,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let path = Project::find_config_file(config)?;
+    // Make sure the config is valid before updating it.
+    read_config(&path)?;
+
+    // The repos of the file itself, without the ones of the files it extends.
+    let value = read_config_value(&path)?;
+    let entries: Vec<_> = repo_entries(&value)
+        .into_iter()
+        .filter(|entry| repo.as_ref().is_none_or(|repo| *repo == entry.url))
+        .collect();
+
+    let mut updates = futures::stream::iter(entries.iter().map(|entry| async move {
+        let update = latest_rev(entry, bleeding_edge, freeze).await;
+        (entry, update)
+    }))
+    .buffered(jobs.max(1));
+
+    let mut document = ConfigDocument::parse(&path, fs_err::read_to_string(&path)?)?;
+    // What the config must read as once it's edited.
+    let mut expected = value.clone();
+    let mut modified = false;
+    let mut failed = false;
+    while let Some((entry, update)) = updates.next().await {
+        // A rev from a merge key or an alias can't be edited in place.
+        let update = update.and_then(|update| {
+            if update.rev != entry.rev {
+                document.set_rev(entry.index, &update.rev, update.frozen.as_deref())?;
+            }
+            Ok(update)
+        });
+        match update {
+            Ok(update) if update.rev == entry.rev => {
+                writeln!(
+                    printer.stdout(),
+                    "[{}] already up to date",
+                    entry.url.cyan()
+                )?;
+            }
+            Ok(update) => {
+                writeln!(
+                    printer.stdout(),
+                    "[{}] updating {} -> {}",
+                    entry.url.cyan(),
+                    entry.rev,
+                    update.rev.green()
+                )?;
+                expected["repos"][entry.index]["rev"] = update.rev.into();
+                modified = true;
+            }
+            Err(err) => {
+                writeln!(
+                    printer.stdout(),
+                    "[{}] {}: {err:#}",
+                    entry.url.cyan(),
+                    "failed".red()
+                )?;
+                failed = true;
+            }
+        }
+    }
+
+    if modified {
+        let content = document.finish();
+        // Make sure only the revs changed, like when a rev is also used through an alias.
+        let edited =
+            parse_config_value(&content, &path).context("The updated config is invalid")?;
+        if edited != expected {
+            anyhow::bail!(
+                "The updated config doesn't read as expected, `{}` is left unchanged",
+                path.user_display()
+            );
+        }
+        fs_err::write(&path, content)?;
+        writeln!(printer.stderr(), "Updated `{}`", path.user_display().cyan())?;
+    }
+
+    if failed {
+        Ok(ExitStatus::Failure)
+    } else {
+        Ok(ExitStatus::Success)
+    }
+}
+
+/// The remote repos in the config, local directory repos are left out as they have no rev.
+fn repo_entries(value: &serde_json::Value) -> Vec<RepoEntry> {
+    let repos = value.get("repos").and_then(serde_json::Value::as_array);
+    repos
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .filter_map(|(index, repo)| {
+            let url = repo.get("repo")?.as_str()?;
+            if matches!(url, "local" | "meta") || url.starts_with("./") || url.starts_with("../") {
+                return None;
+            }
+            let hooks = repo.get("hooks").and_then(serde_json::Value::as_array);
+            Some(RepoEntry {
+                index,
+                url: url.to_string(),
+                rev: repo.get("rev")?.as_str()?.to_string(),
+                subdirectory: repo
+                    .get("subdirectory")
+                    .and_then(serde_json::Value::as_str)
+                    .map(ToString::to_string),
+                hook_ids: hooks
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|hook| Some(hook.get("id")?.as_str()?.to_string()))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Find the latest tag of a repo, or its latest commit with `bleeding_edge`,
+/// and check it still has the hooks used in the config.
+async fn latest_rev(entry: &RepoEntry, bleeding_edge: bool, freeze: bool) -> Result<Update> {
+    let temp = tempfile::tempdir()?;
+    let path = temp.path();
+    let url = UserConfig::get().rewrite_url(&entry.url);
+    git::fetch_head(&url, path)
+        .await
+        .context("Failed to fetch the repo")?;
+
+    let tag = if bleeding_edge {
+        None
+    } else {
+        git::describe_tag(path, "FETCH_HEAD").await?
+    };
+    let update = match tag {
+        Some(tag) if freeze => Update {
+            rev: git::rev_parse(path, &tag).await?,
+            frozen: Some(tag),
+        },
+        Some(tag) => Update {
+            rev: tag,
+            frozen: None,
+        },
+        None => Update {
+            rev: git::rev_parse(path, "FETCH_HEAD").await?,
+            frozen: None,
+        },
+    };
+
+    check_hooks(entry, path, &update.rev).await?;
+    Ok(update)
+}
+
+async fn check_hooks(entry: &RepoEntry, path: &Path, rev: &str) -> Result<()> {
+    let file = match &entry.subdirectory {
+        Some(subdirectory) => format!("{}/{MANIFEST_FILE}", subdirectory.trim_end_matches('/')),
+        None => MANIFEST_FILE.to_string(),
+    };
+    let content = git::show_file(path, rev, &file)
+        .await
+        .with_context(|| format!("Failed to read `{file}` at `{rev}`"))?;
+    let manifest = parse_manifest(&content, &file)?;
+
+    let missing: Vec<_> = entry
+        .hook_ids
+        .iter()
+        .filter(|id| !manifest.hooks.iter().any(|hook| hook.id == **id))
+        .map(|id| format!("`{id}`"))
+        .collect();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "Cannot update to `{rev}`, it doesn't have the hooks {}",
+            missing.join(", ")
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,324 @@
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::io::Read;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixStream as StdUnixStream;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, Interest};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::debug;
+
+use crate::cleanup::add_cleanup;
+use crate::cli::reporter::{HookInitReporter, HookInstallReporter};
+use crate::cli::run::LoadedProject;
+use crate::cli::{hook_impl, run, ExitStatus};
+use crate::client::Client;
+use crate::config::HookType;
+use crate::fs::Simplified;
+use crate::git;
+use crate::hook::Project;
+use crate::printer::{Printer, Stderr};
+use crate::store::Store;
+
+/// Whether this process is the daemon, which runs the requests itself.
+static IN_DAEMON: AtomicBool = AtomicBool::new(false);
+
+/// A request to run the hooks of a hook type, sent by an installed hook script.
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    config: PathBuf,
+    hook_type: String,
+    args: Vec<OsString>,
+    quiet: bool,
+    env: Vec<(OsString, OsString)>,
+    cwd: PathBuf,
+}
+
+/// The socket the daemon of the repo at `root` listens on.
+fn socket_path(store: &Store, root: &Path) -> PathBuf {
+    store.path().join("daemon").join(format!(
+        "{:x}.sock",
+        md5::compute(root.as_os_str().as_bytes())
+    ))
+}
+
+/// The project the daemon serves, loaded again when its config changes.
+struct Served {
+    loaded: Rc<LoadedProject>,
+    modified: Option<SystemTime>,
+}
+
+impl Served {
+    /// Initialize the hooks of the config and install their environments.
+    async fn load(config_file: PathBuf, store: &Store, printer: Printer) -> Result<Self> {
+        let modified = fs_err::metadata(&config_file)?.modified().ok();
+        let mut project = Project::new(config_file)?;
+        store.mark_config_used(project.config_file(), project.config())?;
+
+        let reporter = HookInitReporter::from(printer);
+        let hooks = project.init_hooks(store, Some(&reporter)).await?;
+        let reporter = HookInstallReporter::from(printer);
+        run::install_hooks(&hooks, store, &reporter).await?;
+
+        Ok(Self {
+            loaded: Rc::new(LoadedProject { project, hooks }),
+            modified,
+        })
+    }
+
+    /// Whether `config_file` is the loaded config, unchanged since it was loaded.
+    fn is_current(&self, config_file: &Path) -> bool {
+        self.loaded.project.config_file() == config_file
+            && self.modified.is_some()
+            && fs_err::metadata(config_file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                == self.modified
+    }
+}
+
+/// Install the environments of all hooks, then serve the runs of installed hook scripts
+/// in the current repo until interrupted.
+pub(crate) async fn daemon(config: Option<PathBuf>, printer: Printer) -> Result<ExitStatus> {
+    let store = Store::from_settings()?.init()?;
+
+    let config_file = std::path::absolute(Project::find_config_file(config)?)?;
+    let mut served = Served::load(config_file, &store, printer).await?;
+
+    let path = socket_path(&store, &std::env::current_dir()?);
+    fs_err::create_dir_all(path.parent().expect("socket path has a parent"))?;
+    // Remove the socket of a daemon that was not shut down cleanly.
+    if path.exists() {
+        if UnixStream::connect(&path).await.is_ok() {
+            anyhow::bail!("A daemon is already running for this repo");
+        }
+        fs_err::remove_file(&path)?;
+    }
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to listen on `{}`", path.display()))?;
+    {
+        let path = path.clone();
+        add_cleanup(move || {
+            let _ = std::fs::remove_file(&path);
+        });
+    }
+    IN_DAEMON.store(true, Ordering::Relaxed);
+
+    writeln!(
+        printer.stderr(),
+        "Serving hooks on `{}`, press Ctrl-C to stop",
+        path.user_display().cyan()
+    )?;
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                debug!("Failed to accept connection: {err}");
+                continue;
+            }
+        };
+        if let Err(err) = serve(stream, &mut served, &store).await {
+            writeln!(printer.stderr(), "{}: {err:#}", "error".red().bold())?;
+        }
+    }
+}
+
+/// Run a request on behalf of the client, and reply with the exit code.
+///
+/// The output of the run goes to the stdout and stderr of the client, and the commands run
+/// with its environment and working directory.
+async fn serve(mut stream: UnixStream, served: &mut Served, store: &Store) -> Result<()> {
+    let [stdout, stderr] = <[OwnedFd; 2]>::try_from(recv_fds(&stream, 2).await?)
+        .expect("two file descriptors are received");
+    let mut body = Vec::new();
+    stream.read_to_end(&mut body).await?;
+    let request: Request = serde_json::from_slice(&body)?;
+    let hook_type = HookType::from_str(&request.hook_type, false).map_err(anyhow::Error::msg)?;
+    // Progress bars would be drawn on the terminal of the daemon.
+    let printer = if request.quiet {
+        Printer::Quiet
+    } else {
+        Printer::NoProgress
+    };
+
+    // The index changed since the last run, and may be another one in `GIT_INDEX_FILE`.
+    git::forget_listings();
+    let client = Client::new(request.env, request.cwd, stdout.into(), stderr.into());
+    let status = client
+        .scope(async {
+            let result = async {
+                if !served.is_current(&request.config) {
+                    debug!("Loading `{}` again", request.config.display());
+                    *served = Served::load(request.config.clone(), store, printer).await?;
+                }
+                Box::pin(hook_impl::run_hook_type(
+                    Some(request.config),
+                    Some(served.loaded.clone()),
+                    hook_type,
+                    &request.args,
+                    printer,
+                ))
+                .await
+            }
+            .await;
+            result.unwrap_or_else(|err| {
+                let _ = writeln!(Stderr::Enabled, "{}: {err:#}", "error".red().bold());
+                ExitStatus::Error
+            })
+        })
+        .await;
+
+    let code = match status {
+        ExitStatus::Success => 0,
+        ExitStatus::Failure => 1,
+        ExitStatus::Error => 2,
+        ExitStatus::Interrupted => 130,
+        ExitStatus::External(code) => code,
+    };
+    stream.write_all(&[code]).await?;
+    Ok(())
+}
+
+/// Forward the run of a hook script to the daemon of the current repo, if one is running.
+///
+/// Returns `None` if there is no daemon to forward to.
+pub(super) fn forward(
+    config: &Path,
+    hook_type: HookType,
+    args: &[OsString],
+    printer: Printer,
+) -> Result<Option<ExitStatus>> {
+    if IN_DAEMON.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+
+    let store = Store::from_settings()?;
+    let cwd = std::env::current_dir()?;
+    let path = socket_path(&store, &cwd);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut stream = match StdUnixStream::connect(&path) {
+        Ok(stream) => stream,
+        Err(err) => {
+            debug!("Failed to connect to daemon at `{}`: {err}", path.display());
+            return Ok(None);
+        }
+    };
+    debug!("Forwarding to daemon at `{}`", path.display());
+
+    send_fds(&stream, &[1, 2])?;
+    let request = Request {
+        config: std::path::absolute(config)?,
+        hook_type: hook_type.as_str().to_string(),
+        args: args.to_vec(),
+        quiet: printer == Printer::Quiet,
+        env: std::env::vars_os().collect(),
+        cwd,
+    };
+    serde_json::to_writer(&mut stream, &request)?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+
+    let mut code = [0];
+    stream
+        .read_exact(&mut code)
+        .context("The daemon closed the connection without a result")?;
+    Ok(Some(ExitStatus::External(code[0])))
+}
+
+/// Send file descriptors over a Unix socket, with a single byte of data.
+#[allow(clippy::cast_possible_truncation)]
+fn send_fds(stream: &StdUnixStream, fds: &[RawFd]) -> std::io::Result<()> {
+    let data = [0u8];
+    let mut iov = libc::iovec {
+        iov_base: data.as_ptr().cast_mut().cast(),
+        iov_len: data.len(),
+    };
+    let fds_len = std::mem::size_of_val(fds) as u32;
+    let mut control = vec![0u8; unsafe { libc::CMSG_SPACE(fds_len) } as usize];
+
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = control.len() as _;
+
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(fds_len) as _;
+        std::ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(cmsg).cast(), fds.len());
+        if libc::sendmsg(stream.as_raw_fd(), &msg, 0) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Receive `count` file descriptors sent with [`send_fds`].
+async fn recv_fds(stream: &UnixStream, count: usize) -> std::io::Result<Vec<OwnedFd>> {
+    loop {
+        stream.readable().await?;
+        match stream.try_io(Interest::READABLE, || {
+            try_recv_fds(stream.as_raw_fd(), count)
+        }) {
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => continue,
+            result => return result,
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn try_recv_fds(socket: RawFd, count: usize) -> std::io::Result<Vec<OwnedFd>> {
+    let mut data = [0u8];
+    let mut iov = libc::iovec {
+        iov_base: data.as_mut_ptr().cast(),
+        iov_len: data.len(),
+    };
+    let fds_len = (count * std::mem::size_of::<RawFd>()) as u32;
+    let mut control = vec![0u8; unsafe { libc::CMSG_SPACE(fds_len) } as usize];
+
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = control.len() as _;
+
+    let mut fds = Vec::with_capacity(count);
+    unsafe {
+        if libc::recvmsg(socket, &mut msg, 0) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg).cast::<RawFd>();
+                let n = ((*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize)
+                    / std::mem::size_of::<RawFd>();
+                for i in 0..n {
+                    fds.push(OwnedFd::from_raw_fd(data.add(i).read_unaligned()));
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    if fds.len() != count {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Expected {count} file descriptors, got {}", fds.len()),
+        ));
+    }
+    Ok(fds)
+}
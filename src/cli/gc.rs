@@ -0,0 +1,60 @@
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+use tracing::debug;
+
+use crate::cli::ExitStatus;
+use crate::config::read_config;
+use crate::printer::Printer;
+use crate::store::Store;
+
+/// Remove the repos and environments in the store that no config uses anymore.
+///
+/// Configs that are gone or no longer valid are forgotten first, and the repos of the others
+/// are read again, as they may have changed since they were last used.
+pub(crate) async fn gc(max_age: Option<u64>, printer: Printer) -> Result<ExitStatus> {
+    let store = Store::from_settings()?;
+    if !store.path().try_exists()? {
+        writeln!(printer.stdout(), "Nothing to clean")?;
+        return Ok(ExitStatus::Success);
+    }
+    let store = store.init()?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    for config in store.configs()? {
+        let expired = max_age.is_some_and(|days| {
+            now.saturating_sub(config.last_used) > days.saturating_mul(24 * 60 * 60)
+        });
+        match read_config(&config.path) {
+            Ok(content) if !expired => store.update_config(&config.path, &content)?,
+            _ => {
+                debug!(path = %config.path.display(), expired, "Forgetting config");
+                store.unmark_config(&config.path)?;
+            }
+        }
+    }
+
+    let mut repos = 0;
+    let mut envs = 0;
+    for (repo, rev) in store.unreferenced_repos()? {
+        let paths = store.remove_repo(&repo, &rev).await?;
+        repos += 1;
+        // The repo itself and the environments only it used.
+        envs += paths.len().saturating_sub(1);
+    }
+    let (orphan_repos, orphan_envs) = store.remove_orphans().await?;
+    repos += orphan_repos;
+    envs += orphan_envs;
+    store.remove_stale_caches()?;
+
+    writeln!(
+        printer.stdout(),
+        "{} repo(s) and {} environment(s) removed",
+        repos.cyan(),
+        envs.cyan()
+    )?;
+
+    Ok(ExitStatus::Success)
+}
@@ -1,12 +1,18 @@
-use anyhow::Result;
 use std::ffi::OsString;
+use std::fmt::Write;
 use std::path::PathBuf;
+use std::rc::Rc;
+
+use anstream::eprintln;
+use anyhow::Result;
 
+use crate::cli::run::LoadedProject;
 use crate::cli::{self, ExitStatus, RunArgs};
-use crate::config::HookType;
+use crate::client;
+use crate::config::{find_config_in, HookType};
 use crate::env_vars::EnvVars;
+use crate::fs::{Simplified, CWD};
 use crate::printer::Printer;
-use anstream::eprintln;
 
 pub(crate) async fn hook_impl(
     config: Option<PathBuf>,
@@ -18,22 +24,24 @@ pub(crate) async fn hook_impl(
 ) -> Result<ExitStatus> {
     // TODO: run in legacy mode
 
-    if let Some(ref config_file) = config {
-        if !config_file.try_exists()? {
-            return if skip_on_missing_config
-                || std::env::var_os(EnvVars::PRE_COMMIT_ALLOW_NO_CONFIG).is_some()
-            {
-                Ok(ExitStatus::Success)
-            } else {
-                eprintln!("Config file not found: {}", config_file.display());
-                eprintln!(
-                    "- To temporarily silence this, run `PRE_COMMIT_ALLOW_NO_CONFIG=1 git ...`"
-                );
-                eprintln!("- To permanently silence this, install hooks with the `--allow-missing-config` flag");
-                eprintln!("- To uninstall hooks, run `prefligit uninstall`");
-                Ok(ExitStatus::Failure)
-            };
-        }
+    let config_file = config.clone().unwrap_or_else(|| find_config_in(&CWD));
+    if !config_file.try_exists()? {
+        return if skip_on_missing_config
+            || client::var_os(EnvVars::PRE_COMMIT_ALLOW_NO_CONFIG).is_some_and(|v| !v.is_empty())
+        {
+            writeln!(
+                printer.stdout(),
+                "`{}` config file not found. Skipping `prefligit`.",
+                config_file.user_display()
+            )?;
+            Ok(ExitStatus::Success)
+        } else {
+            eprintln!("Config file not found: {}", config_file.user_display());
+            eprintln!("- To temporarily silence this, run `PRE_COMMIT_ALLOW_NO_CONFIG=1 git ...`");
+            eprintln!("- To permanently silence this, install hooks with the `--allow-missing-config` flag");
+            eprintln!("- To uninstall hooks, run `prefligit uninstall`");
+            Ok(ExitStatus::Failure)
+        };
     }
 
     if !hook_type.num_args().contains(&args.len()) {
@@ -41,19 +49,33 @@ pub(crate) async fn hook_impl(
         return Ok(ExitStatus::Failure);
     }
 
-    let run_args = to_run_args(hook_type, &args);
+    #[cfg(unix)]
+    if let Some(status) = cli::daemon::forward(&config_file, hook_type, &args, printer)? {
+        return Ok(status);
+    }
+
+    run_hook_type(config, None, hook_type, &args, printer).await
+}
+
+/// Run the hooks of a hook type, with the arguments git passed to the hook script.
+///
+/// `loaded` is the project of `config` with its hooks, if already initialized.
+pub(super) async fn run_hook_type(
+    config: Option<PathBuf>,
+    loaded: Option<Rc<LoadedProject>>,
+    hook_type: HookType,
+    args: &[OsString],
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let run_args = to_run_args(hook_type, args);
 
     cli::run(
-        config,
-        run_args.hook_id,
-        Some(hook_type.into()),
-        run_args.from_ref,
-        run_args.to_ref,
-        run_args.all_files,
-        vec![],
-        false,
-        run_args.extra,
-        false,
+        cli::RunOptions {
+            configs: config.into_iter().collect(),
+            hook_stage: Some(hook_type.into()),
+            loaded,
+            ..cli::RunOptions::from(run_args)
+        },
         printer,
     )
     .await
@@ -0,0 +1,74 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use owo_colors::OwoColorize;
+
+use crate::cli::ExitStatus;
+use crate::config::read_config;
+use crate::fs::Simplified;
+use crate::printer::Printer;
+use crate::store::{PreCommitStore, Store};
+
+/// Import the repos cloned by the Python pre-commit into the store, and the configs using them.
+///
+/// Hook environments are not imported, they're installed again on the next run.
+pub(crate) async fn import_cache(from: Option<PathBuf>, printer: Printer) -> Result<ExitStatus> {
+    let Some(source) = from.or_else(PreCommitStore::default_path) else {
+        anyhow::bail!("Cannot find the pre-commit cache, use `--from` to specify it");
+    };
+    if !source.join("db.db").is_file() {
+        writeln!(
+            printer.stdout(),
+            "No pre-commit cache found at `{}`",
+            source.user_display().cyan()
+        )?;
+        return Ok(ExitStatus::Success);
+    }
+
+    let pre_commit = PreCommitStore::read(&source).with_context(|| {
+        format!(
+            "Failed to read the pre-commit cache at `{}`",
+            source.display()
+        )
+    })?;
+    let store = Store::from_settings()?.init()?;
+
+    let mut imported = 0;
+    for repo in &pre_commit.repos {
+        match store.import_repo(repo).await {
+            Ok(true) => {
+                writeln!(
+                    printer.stdout(),
+                    "Imported {}",
+                    format!("{}@{}", repo.repo, repo.rev).cyan()
+                )?;
+                imported += 1;
+            }
+            Ok(false) => {}
+            Err(err) => {
+                writeln!(
+                    printer.stderr(),
+                    "{}: Failed to import {}: {err}",
+                    "warning".yellow().bold(),
+                    format!("{}@{}", repo.repo, repo.rev).cyan()
+                )?;
+            }
+        }
+    }
+
+    // Keep the imported repos from being removed by `gc` until the configs change.
+    for path in &pre_commit.configs {
+        if let Ok(config) = read_config(path) {
+            store.update_config(path, &config)?;
+        }
+    }
+
+    writeln!(
+        printer.stdout(),
+        "Imported {imported} repo(s) from `{}`",
+        source.user_display().cyan()
+    )?;
+
+    Ok(ExitStatus::Success)
+}
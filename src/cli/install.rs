@@ -1,32 +1,42 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
 use std::fmt::Write as _;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 use anyhow::Result;
+use clap::ValueEnum;
 use indoc::indoc;
+use itertools::Itertools;
 use owo_colors::OwoColorize;
 use same_file::is_same_file;
+use serde::{Deserialize, Serialize};
 
 use crate::cli::reporter::{HookInitReporter, HookInstallReporter};
 use crate::cli::run;
 use crate::cli::{ExitStatus, HookType};
-use crate::fs::Simplified;
+use crate::config::{self, find_config_in, read_config, Config};
+use crate::fs::{Simplified, CWD};
 use crate::git;
 use crate::git::git_cmd;
 use crate::hook::Project;
 use crate::printer::Printer;
+use crate::run::set_jobs;
 use crate::store::Store;
 
+#[allow(clippy::fn_params_excessive_bools)]
 pub(crate) async fn install(
     config: Option<PathBuf>,
     hook_types: Vec<HookType>,
     install_hooks: bool,
     overwrite: bool,
     allow_missing_config: bool,
+    native: bool,
     printer: Printer,
     git_dir: Option<&Path>,
 ) -> Result<ExitStatus> {
-    if git_dir.is_none() && git::has_hooks_path_set().await? {
+    if git_dir.is_none() && git::has_hooks_path_set()? {
         writeln!(
             printer.stderr(),
             indoc::indoc! {"
@@ -42,33 +52,38 @@ pub(crate) async fn install(
     let hooks_path = if let Some(dir) = git_dir {
         dir.join("hooks")
     } else {
-        git::get_git_common_dir().await?.join("hooks")
+        git::get_git_common_dir()?.join("hooks")
     };
 
     fs_err::create_dir_all(&hooks_path)?;
 
-    let project = Project::from_config_file(config);
-    let config_file = project.as_ref().ok().map(Project::config_file);
+    // Always encode the config path, even if it doesn't exist yet, so the hook script can
+    // decide whether a missing config is fatal or should be skipped.
+    let config_file = config.clone().unwrap_or_else(|| find_config_in(&CWD));
     for hook_type in hook_types {
         install_hook_script(
-            config_file,
+            &config_file,
             hook_type,
             &hooks_path,
             overwrite,
             allow_missing_config,
+            native,
             printer,
         )?;
     }
 
     if install_hooks {
-        let mut project = project?;
+        let mut project = Project::from_config_file(config)?;
+        if let Some(jobs) = project.config().jobs {
+            set_jobs(jobs);
+        }
         let store = Store::from_settings()?.init()?;
-        let _lock = store.lock_async().await?;
+        store.mark_config_used(project.config_file(), project.config())?;
 
         let reporter = HookInitReporter::from(printer);
         let hooks = project.init_hooks(&store, Some(&reporter)).await?;
         let reporter = HookInstallReporter::from(printer);
-        run::install_hooks(&hooks, &reporter).await?;
+        run::install_hooks(&hooks, &store, &reporter).await?;
     }
 
     Ok(ExitStatus::Success)
@@ -98,69 +113,96 @@ fn get_hook_types(config_file: Option<PathBuf>, hook_types: Vec<HookType>) -> Ve
 }
 
 fn install_hook_script(
-    config_file: Option<&Path>,
+    config_file: &Path,
     hook_type: HookType,
     hooks_path: &Path,
     overwrite: bool,
     skip_on_missing_config: bool,
+    native: bool,
     printer: Printer,
 ) -> Result<()> {
-    let hook_path = hooks_path.join(hook_type.as_str());
-
-    if hook_path.try_exists()? {
+    let script_path = hooks_path.join(hook_type.as_str());
+    let shim_path = shim_path(hooks_path, hook_type);
+    let hook_path = if native { &shim_path } else { &script_path };
+
+    // On Windows, git runs a `<hook-type>` script before a `<hook-type>.exe` shim.
+    for path in [&script_path, &shim_path].into_iter().dedup() {
+        if !path.try_exists()? {
+            continue;
+        }
+        let ours = is_our_script(path)?;
         if overwrite {
             writeln!(
                 printer.stdout(),
                 "Overwriting existing hook at {}",
-                hook_path.user_display().cyan()
+                path.user_display().cyan()
             )?;
-        } else {
-            if !is_our_script(&hook_path)? {
-                let legacy_path = format!("{}.legacy", hook_path.display());
-                fs_err::rename(&hook_path, &legacy_path)?;
-                writeln!(
-                    printer.stdout(),
-                    "Hook already exists at {}, move it to {}.",
-                    hook_path.user_display().cyan(),
-                    legacy_path.user_display().yellow()
-                )?;
-            }
+        } else if !ours && (path == hook_path || path == &script_path) {
+            let legacy_path = format!("{}.legacy", path.display());
+            fs_err::rename(path, &legacy_path)?;
+            writeln!(
+                printer.stdout(),
+                "Hook already exists at {}, move it to {}.",
+                path.user_display().cyan(),
+                legacy_path.user_display().yellow()
+            )?;
+            continue;
+        } else if !ours {
+            continue;
         }
+        fs_err::remove_file(path)?;
+    }
+    let settings_path = shim_settings_path(hooks_path, hook_type);
+    if settings_path.try_exists()? {
+        fs_err::remove_file(&settings_path)?;
     }
 
+    let config = config_file.user_display().to_string();
     let mut args = vec![
         "hook-impl".to_string(),
         format!("--hook-type={}", hook_type.as_str()),
     ];
-    if let Some(config_file) = config_file {
-        args.push(format!(r#"--config="{}""#, config_file.user_display()));
-    }
     if skip_on_missing_config {
         args.push("--skip-on-missing-config".to_string());
     }
 
     let prefligit = std::env::current_exe()?;
-    let pre_commit = prefligit.simplified().display().to_string();
-    let hook_script = HOOK_TMPL
-        .replace("ARGS=(hook-impl)", &format!("ARGS=({})", args.join(" ")))
-        .replace(
-            r#"PREFLIGIT="prefligit""#,
-            &format!(r#"PREFLIGIT="{pre_commit}""#),
-        );
-    fs_err::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&hook_path)?
-        .write_all(hook_script.as_bytes())?;
-
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-
-        let mut perms = hook_path.metadata()?.permissions();
-        perms.set_mode(0o755);
-        fs_err::set_permissions(&hook_path, perms)?;
+    if native {
+        args.insert(2, format!("--config={config}"));
+        crate::fs::clone_file(&prefligit, hook_path)?;
+        let settings = ShimSettings {
+            id: CURRENT_HASH.to_string(),
+            prefligit: prefligit.simplified().to_path_buf(),
+            args,
+        };
+        fs_err::write(&settings_path, serde_json::to_string_pretty(&settings)?)?;
+    } else {
+        args.insert(2, format!("--config={}", sh_quote(&config)));
+        let prefligit = prefligit.simplified().display().to_string();
+        let hook_script = HOOK_TMPL
+            .replace(
+                r#"exec "$PREFLIGIT" hook-impl"#,
+                &format!(r#"exec "$PREFLIGIT" {}"#, args.join(" ")),
+            )
+            .replace(
+                "PREFLIGIT='prefligit'",
+                &format!("PREFLIGIT={}", sh_quote(&prefligit)),
+            );
+        fs_err::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(hook_path)?
+            .write_all(hook_script.as_bytes())?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mut perms = hook_path.metadata()?.permissions();
+            perms.set_mode(0o755);
+            fs_err::set_permissions(hook_path, perms)?;
+        }
     }
 
     writeln!(
@@ -172,30 +214,148 @@ fn install_hook_script(
     Ok(())
 }
 
+/// The native shim of a hook, a copy of prefligit git runs without a shell.
+fn shim_path(hooks_path: &Path, hook_type: HookType) -> PathBuf {
+    hooks_path.join(format!(
+        "{}{}",
+        hook_type.as_str(),
+        std::env::consts::EXE_SUFFIX
+    ))
+}
+
+/// The settings of the native shim of a hook, next to it.
+fn shim_settings_path(hooks_path: &Path, hook_type: HookType) -> PathBuf {
+    hooks_path.join(format!("{}.prefligit.json", hook_type.as_str()))
+}
+
+/// How a native shim runs its hook.
+#[derive(Debug, Serialize, Deserialize)]
+struct ShimSettings {
+    /// The ID of the hook scripts, to recognize the shim as ours.
+    id: String,
+    /// The binary the shim was copied from, the shim runs it instead while it still exists,
+    /// so it runs the upgraded binary.
+    prefligit: PathBuf,
+    /// The arguments of the hook, before the ones git passes.
+    args: Vec<String>,
+}
+
+/// A native shim installed by `install --native`, when it's this binary git runs.
+#[derive(Debug)]
+pub(crate) struct Shim {
+    prefligit: PathBuf,
+    /// The command line to run the hook with.
+    pub(crate) args: Vec<OsString>,
+}
+
+impl Shim {
+    /// The shim this binary is, if it's named after a hook type and has settings next to it.
+    pub(crate) fn current() -> Option<Self> {
+        let exe = std::env::current_exe().ok()?;
+        let hooks_path = exe.parent()?;
+        let hook_type = HookType::from_str(exe.file_stem()?.to_str()?, false).ok()?;
+        let settings = fs_err::read(shim_settings_path(hooks_path, hook_type)).ok()?;
+        let settings: ShimSettings = serde_json::from_slice(&settings).ok()?;
+
+        let mut args = vec![OsString::from("prefligit")];
+        args.extend(settings.args.into_iter().map(OsString::from));
+        args.push("--hook-dir".into());
+        args.push(hooks_path.into());
+        args.push("--".into());
+        args.extend(std::env::args_os().skip(1));
+        Some(Self {
+            prefligit: settings.prefligit,
+            args,
+        })
+    }
+
+    /// Run the hook with the binary the shim was copied from, if it's another one now, like
+    /// after an upgrade. Returns `None` to run the hook in this process instead.
+    pub(crate) fn forward(&self) -> Option<ExitCode> {
+        let exe = std::env::current_exe().ok()?;
+        if !self.prefligit.is_file() || is_same_file(&exe, &self.prefligit).unwrap_or(true) {
+            return None;
+        }
+        let mut cmd = std::process::Command::new(&self.prefligit);
+        cmd.args(&self.args[1..]);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+
+            let err = cmd.exec();
+            tracing::debug!("Failed to run `{}`: {err}", self.prefligit.display());
+            None
+        }
+        #[cfg(not(unix))]
+        {
+            let status = cmd.status().ok()?;
+            Some(ExitCode::from(
+                status
+                    .code()
+                    .and_then(|code| u8::try_from(code).ok())
+                    .unwrap_or(1),
+            ))
+        }
+    }
+}
+
+/// Quote a string for use in a POSIX shell script.
+fn sh_quote(s: &str) -> String {
+    shlex::try_quote(s).map_or_else(|_| format!("'{s}'"), std::borrow::Cow::into_owned)
+}
+
+// The script only relies on POSIX `sh`, so it works with `dash`, BusyBox `ash`,
+// and the `sh` bundled with Git for Windows, which git uses to run hooks.
 static HOOK_TMPL: &str = indoc! { r#"
-#!/usr/bin/env bash
+#!/bin/sh
 # File generated by prefligit: https://github.com/j178/prefligit
-# ID: 182c10f181da4464a3eec51b83331688
-
-ARGS=(hook-impl)
+# ID: 0e1f8a7c2b9d4c6e8f3a5b7d9c1e2f40
 
 HERE="$(cd "$(dirname "$0")" && pwd)"
-ARGS+=(--hook-dir "$HERE" -- "$@")
-PREFLIGIT="prefligit"
+PREFLIGIT='prefligit'
 
-exec "$PREFLIGIT" "${ARGS[@]}"
+# The binary may have been moved or upgraded since the hook was installed.
+if [ ! -x "$PREFLIGIT" ]; then
+    if command -v prefligit > /dev/null 2>&1; then
+        PREFLIGIT="prefligit"
+    else
+        echo "\`prefligit\` not found at $PREFLIGIT or in PATH." 1>&2
+        echo "hint: install it with \`pip install prefligit\` or see https://github.com/j178/prefligit#installation" 1>&2
+        exit 1
+    fi
+fi
+
+exec "$PREFLIGIT" hook-impl --hook-dir "$HERE" -- "$@"
 
 "# };
 
-static PRIOR_HASHES: &[&str] = &[];
+static PRIOR_HASHES: &[&str] = &[
+    "182c10f181da4464a3eec51b83331688",
+    "6a9d7b1c0b3e4f2a8c5d4e3f2a1b0c9d",
+];
 
 // Use a different hash for each change to the script.
 // Use a different hash from `pre-commit` since our script is different.
-static CURRENT_HASH: &str = "182c10f181da4464a3eec51b83331688";
+static CURRENT_HASH: &str = "0e1f8a7c2b9d4c6e8f3a5b7d9c1e2f40";
 
-/// Checks if the script contains any of the hashes that `prefligit` has used in the past.
+/// Checks if the script contains any of the hashes that `prefligit` has used in the past,
+/// or is a native shim with its settings next to it.
 fn is_our_script(hook_path: &Path) -> Result<bool> {
-    let content = fs_err::read_to_string(hook_path)?;
+    let hooks_path = hook_path.parent().unwrap_or(Path::new(""));
+    let hook_type = hook_path
+        .file_stem()
+        .and_then(|stem| HookType::from_str(&stem.to_string_lossy(), false).ok());
+    if let Some(hook_type) = hook_type {
+        if hook_path == shim_path(hooks_path, hook_type)
+            && shim_settings_path(hooks_path, hook_type).try_exists()?
+        {
+            return Ok(true);
+        }
+    }
+
+    let content = fs_err::read(hook_path)?;
+    let content = String::from_utf8_lossy(&content);
     Ok(std::iter::once(CURRENT_HASH)
         .chain(PRIOR_HASHES.iter().copied())
         .any(|hash| content.contains(hash)))
@@ -204,12 +364,17 @@ fn is_our_script(hook_path: &Path) -> Result<bool> {
 pub(crate) async fn uninstall(
     config: Option<PathBuf>,
     hook_types: Vec<HookType>,
+    purge: bool,
     printer: Printer,
 ) -> Result<ExitStatus> {
-    for hook_type in get_hook_types(config, hook_types) {
-        let hooks_path = git::get_git_common_dir().await?.join("hooks");
-        let hook_path = hooks_path.join(hook_type.as_str());
-        let legacy_path = hooks_path.join(format!("{}.legacy", hook_type.as_str()));
+    for hook_type in get_hook_types(config.clone(), hook_types) {
+        let hooks_path = git::get_git_common_dir()?.join("hooks");
+        // The hook is a script, or a native shim, they only have different names on Windows.
+        let mut hook_path = hooks_path.join(hook_type.as_str());
+        if !hook_path.try_exists()? && shim_path(&hooks_path, hook_type).try_exists()? {
+            hook_path = shim_path(&hooks_path, hook_type);
+        }
+        let legacy_path = PathBuf::from(format!("{}.legacy", hook_path.display()));
 
         if !hook_path.try_exists()? {
             writeln!(
@@ -225,6 +390,10 @@ pub(crate) async fn uninstall(
             )?;
         } else {
             fs_err::remove_file(&hook_path)?;
+            let settings_path = shim_settings_path(&hooks_path, hook_type);
+            if settings_path.try_exists()? {
+                fs_err::remove_file(&settings_path)?;
+            }
             writeln!(
                 printer.stdout(),
                 "Uninstalled {}",
@@ -242,9 +411,89 @@ pub(crate) async fn uninstall(
         }
     }
 
+    if purge {
+        purge_store(config, printer).await?;
+    }
+
     Ok(ExitStatus::Success)
 }
 
+/// The repos (by URL and revision) a config depends on, and whether it has local hooks.
+fn repos_of(config: &Config) -> (HashSet<(String, String)>, bool) {
+    let mut remotes = HashSet::new();
+    let mut has_local = false;
+    for repo in &config.repos {
+        match repo {
+            config::Repo::Remote(repo) => {
+                remotes.insert((repo.repo.to_string(), repo.rev.clone()));
+            }
+            config::Repo::Local(_) => has_local = true,
+            config::Repo::Meta(_) => {}
+        }
+    }
+    (remotes, has_local)
+}
+
+/// Remove the store entries that are only used by this repository's config.
+async fn purge_store(config: Option<PathBuf>, printer: Printer) -> Result<()> {
+    let config_file = Project::find_config_file(config)?;
+    let project = Project::new(config_file)?;
+
+    let store = Store::from_settings()?;
+    if !store.path().try_exists()? {
+        return Ok(());
+    }
+    let store = store.init()?;
+
+    let (mut remotes, mut has_local) = repos_of(project.config());
+
+    // Keep everything that is still used by other configs.
+    for other in store.configs()? {
+        let other = other.path;
+        if is_same_file(&other, project.config_file()).unwrap_or(false) {
+            continue;
+        }
+        // Configs that no longer exist or fail to parse don't hold any references.
+        let Ok(other) = read_config(&other) else {
+            continue;
+        };
+        let (other_remotes, other_has_local) = repos_of(&other);
+        remotes.retain(|repo| !other_remotes.contains(repo));
+        has_local &= !other_has_local;
+    }
+
+    let mut removed = 0;
+    for (repo, rev) in &remotes {
+        let paths = store.remove_repo(repo, rev).await?;
+        if !paths.is_empty() {
+            writeln!(
+                printer.stdout(),
+                "Removed cached repo {}",
+                format!("{repo}@{rev}").cyan()
+            )?;
+        }
+        removed += paths.len();
+    }
+    if has_local {
+        let paths = store.remove_repo("local", "1").await?;
+        if !paths.is_empty() {
+            writeln!(
+                printer.stdout(),
+                "Removed cached environments for {} hooks",
+                "local".cyan()
+            )?;
+        }
+        removed += paths.len();
+    }
+    store.unmark_config(project.config_file())?;
+
+    if removed == 0 {
+        writeln!(printer.stdout(), "No cached repos to remove")?;
+    }
+
+    Ok(())
+}
+
 pub(crate) async fn init_template_dir(
     directory: PathBuf,
     config: Option<PathBuf>,
@@ -258,6 +507,7 @@ pub(crate) async fn init_template_dir(
         false,
         true,
         !requires_config,
+        false,
         printer,
         Some(&directory),
     )
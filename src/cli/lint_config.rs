@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+
+use crate::cli::run::FilenameFilter;
+use crate::cli::ExitStatus;
+use crate::config::{
+    read_config, read_config_value, Config, HookOptions, Language, Repo, DEPRECATED_STAGES,
+};
+use crate::fs::Simplified;
+use crate::git;
+use crate::hook::Project;
+use crate::printer::Printer;
+
+/// A problem in the config file, with how to fix it.
+struct Lint {
+    message: String,
+    help: String,
+}
+
+/// Check the config file for deprecated options and patterns that are likely mistakes.
+pub(crate) async fn lint_config(config: Option<PathBuf>, printer: Printer) -> Result<ExitStatus> {
+    let path = Project::find_config_file(config)?;
+    let value = read_config_value(&path)?;
+    let config = read_config(&path)?;
+
+    let mut lints = deprecated_stages(&value);
+    lints.extend(mutable_revs(&config));
+    lints.extend(duplicate_ids(&config));
+    lints.extend(shadowed_hooks(&config).await?);
+    lints.extend(unpinned_dependencies(&config));
+
+    for lint in &lints {
+        writeln!(
+            printer.stdout(),
+            "{}: {}\n  {}: {}",
+            "warning".yellow().bold(),
+            lint.message,
+            "help".cyan().bold(),
+            lint.help,
+        )?;
+    }
+
+    if lints.is_empty() {
+        writeln!(
+            printer.stderr(),
+            "No issues found in `{}`",
+            path.user_display().cyan()
+        )?;
+        Ok(ExitStatus::Success)
+    } else {
+        writeln!(
+            printer.stderr(),
+            "Found {} {} in `{}`",
+            lints.len(),
+            if lints.len() == 1 { "issue" } else { "issues" },
+            path.user_display().cyan()
+        )?;
+        Ok(ExitStatus::Failure)
+    }
+}
+
+/// The hooks of the config with their options, meta hooks are left out as they can't be changed much.
+fn hooks(config: &Config) -> impl Iterator<Item = (&str, Option<Language>, &HookOptions)> {
+    config.repos.iter().flat_map(|repo| -> Vec<_> {
+        match repo {
+            Repo::Remote(repo) => repo
+                .hooks
+                .iter()
+                .map(|hook| (hook.id.as_str(), hook.language, &hook.options))
+                .collect(),
+            Repo::Local(repo) => repo
+                .hooks
+                .iter()
+                .map(|hook| (hook.id.as_str(), Some(hook.language), &hook.options))
+                .collect(),
+            Repo::Meta(_) => Vec::new(),
+        }
+    })
+}
+
+/// Stage names that still work, but have been renamed after the git hooks they run in.
+fn deprecated_stages(value: &serde_json::Value) -> Vec<Lint> {
+    fn stages(value: Option<&serde_json::Value>) -> Vec<&str> {
+        value
+            .and_then(serde_json::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(serde_json::Value::as_str)
+            .collect()
+    }
+
+    let mut owners = vec![(
+        "`default_stages`".to_string(),
+        stages(value.get("default_stages")),
+    )];
+    let repos = value.get("repos").and_then(serde_json::Value::as_array);
+    for repo in repos.into_iter().flatten() {
+        let hooks = repo.get("hooks").and_then(serde_json::Value::as_array);
+        for hook in hooks.into_iter().flatten() {
+            let id = hook
+                .get("id")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default();
+            owners.push((format!("Hook `{id}`"), stages(hook.get("stages"))));
+        }
+    }
+
+    let mut lints = Vec::new();
+    for (owner, stages) in owners {
+        for stage in stages {
+            if let Some((_, new)) = DEPRECATED_STAGES.iter().find(|(old, _)| *old == stage) {
+                lints.push(Lint {
+                    message: format!("{owner} uses the deprecated stage `{stage}`"),
+                    help: format!("use `{new}` instead"),
+                });
+            }
+        }
+    }
+    lints
+}
+
+/// Whether a rev names a branch or `HEAD`, which can point to different commits over time.
+///
+/// Tags and commit hashes almost always have a digit in them, branch names rarely do.
+fn is_mutable_rev(rev: &str) -> bool {
+    rev == "HEAD" || !rev.chars().any(|c| c.is_ascii_digit())
+}
+
+fn mutable_revs(config: &Config) -> Vec<Lint> {
+    config
+        .repos
+        .iter()
+        .filter_map(|repo| match repo {
+            Repo::Remote(repo) if !repo.is_linked() && is_mutable_rev(&repo.rev) => Some(Lint {
+                message: format!("Repo `{}` uses the mutable rev `{}`", repo.repo, repo.rev),
+                help: "pin it to a tag or a commit, `prefligit autoupdate` updates it to the latest tag".to_string(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Hooks that can't be told apart, so they can't be selected separately when running.
+fn duplicate_ids(config: &Config) -> Vec<Lint> {
+    let mut aliases: HashMap<&str, Vec<Option<&str>>> = HashMap::new();
+    let mut ids = Vec::new();
+    for (id, _, options) in hooks(config) {
+        if !aliases.contains_key(id) {
+            ids.push(id);
+        }
+        aliases
+            .entry(id)
+            .or_default()
+            .push(options.alias.as_deref());
+    }
+
+    ids.into_iter()
+        .filter_map(|id| {
+            let aliases = &aliases[id];
+            let distinct = aliases
+                .iter()
+                .enumerate()
+                .all(|(i, alias)| !aliases[..i].contains(alias));
+            (!distinct).then(|| Lint {
+                message: format!("Hook id `{id}` is used by {} hooks", aliases.len()),
+                help: "give each of them a distinct `alias`, to run them separately".to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Hooks whose files are all filtered out by the top-level `files` and `exclude`.
+async fn shadowed_hooks(config: &Config) -> Result<Vec<Lint>> {
+    if config.files.is_none()
+        && config.exclude.is_none()
+        && config.files_glob.is_none()
+        && config.exclude_glob.is_none()
+    {
+        return Ok(Vec::new());
+    }
+
+    let global = FilenameFilter::from_config(config)?;
+    let filenames = git::get_all_files().await?;
+
+    let mut lints = Vec::new();
+    for (id, _, options) in hooks(config) {
+        if options.files.is_none() && options.files_glob.is_none() {
+            continue;
+        }
+        let filter = FilenameFilter::new(
+            options.files.as_deref(),
+            options.exclude.as_deref(),
+            options.files_glob.as_deref().unwrap_or_default(),
+            options.exclude_glob.as_deref().unwrap_or_default(),
+        )?;
+        let mut matched = filenames.iter().filter(|filename| filter.filter(filename));
+        let Some(first) = matched.next() else {
+            continue;
+        };
+        if !global.filter(first) && !matched.any(|filename| global.filter(filename)) {
+            lints.push(Lint {
+                message: format!(
+                    "Hook `{id}` matches no files after the top-level `files` and `exclude`"
+                ),
+                help: "widen the top-level patterns to include the files of the hook, or remove the hook".to_string(),
+            });
+        }
+    }
+    Ok(lints)
+}
+
+/// The version a dependency should be pinned to, when it's not pinned yet.
+fn unpinned(language: Language, dependency: &str) -> Option<String> {
+    // Local paths and URLs are pinned by what they point to.
+    if dependency.starts_with('.') || dependency.starts_with('/') || dependency.contains("://") {
+        return None;
+    }
+    match language {
+        Language::Python if !dependency.contains("==") && !dependency.contains('@') => {
+            let name = dependency
+                .split(|c: char| "<>=!~;[ ".contains(c))
+                .next()
+                .unwrap_or(dependency);
+            Some(format!("{name}==<version>"))
+        }
+        Language::Node => {
+            // Scoped packages start with `@`, the version comes after another one.
+            let name = dependency.strip_prefix('@').unwrap_or(dependency);
+            (!name.contains('@')).then(|| format!("{dependency}@<version>"))
+        }
+        _ => None,
+    }
+}
+
+/// Additional dependencies without a version, which change when the environment is rebuilt.
+///
+/// Only hooks with a language in the config are checked, others need their repo to tell.
+fn unpinned_dependencies(config: &Config) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    for (id, language, options) in hooks(config) {
+        let Some(language) = language else {
+            continue;
+        };
+        for dependency in options.additional_dependencies.iter().flatten() {
+            if let Some(pinned) = unpinned(language, dependency) {
+                lints.push(Lint {
+                    message: format!("Hook `{id}` has the unpinned dependency `{dependency}`"),
+                    help: format!("pin it like `{pinned}`, to get the same environment every time"),
+                });
+            }
+        }
+    }
+    lints
+}
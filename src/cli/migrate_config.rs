@@ -0,0 +1,141 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use owo_colors::OwoColorize;
+
+use crate::cli::{ConfigFormat, ExitStatus};
+use crate::config::{is_toml, read_config, DEPRECATED_STAGES};
+use crate::config_edit::{Segment, YamlDocument};
+use crate::fs::Simplified;
+use crate::hook::Project;
+use crate::printer::Printer;
+
+/// Convert the config file to YAML or TOML, and remove the original file.
+///
+/// Comments are not preserved, and YAML merge keys are expanded.
+/// Converting a YAML file to YAML only renames its legacy keys and stages, in place.
+pub(crate) fn migrate_config(
+    config: Option<PathBuf>,
+    to: Option<ConfigFormat>,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let source = Project::find_config_file(config)?;
+    let from = if is_toml(&source) {
+        ConfigFormat::Toml
+    } else {
+        ConfigFormat::Yaml
+    };
+    let to = to.unwrap_or(match from {
+        ConfigFormat::Yaml => ConfigFormat::Toml,
+        ConfigFormat::Toml => ConfigFormat::Yaml,
+    });
+    let mut content = fs_err::read_to_string(&source)?;
+    if from == ConfigFormat::Yaml {
+        let document = migrate_legacy(content.clone());
+        if document.is_modified() && to == ConfigFormat::Yaml {
+            fs_err::write(&source, document.finish())?;
+            if let Err(err) = read_config(&source) {
+                fs_err::write(&source, content)?;
+                return Err(err).context("The migrated config is invalid");
+            }
+            writeln!(
+                printer.stderr(),
+                "Migrated the legacy keys and stages of `{}`",
+                source.user_display().cyan()
+            )?;
+            return Ok(ExitStatus::Success);
+        }
+        content = document.finish();
+    }
+
+    if from == to {
+        writeln!(
+            printer.stderr(),
+            "`{}` is already in {}",
+            source.user_display().cyan(),
+            format!("{to:?}").to_uppercase()
+        )?;
+        return Ok(ExitStatus::Success);
+    }
+
+    let converted = match to {
+        ConfigFormat::Toml => {
+            let mut value: serde_yaml::Value = serde_yaml::from_str(&content)?;
+            value.apply_merge()?;
+            toml::to_string_pretty(&value).context("The config can't be represented in TOML")?
+        }
+        ConfigFormat::Yaml => {
+            let value: toml::Value = toml::from_str(&content)?;
+            serde_yaml::to_string(&value)?
+        }
+    };
+
+    let target = source.with_extension(match to {
+        ConfigFormat::Yaml => "yaml",
+        ConfigFormat::Toml => "toml",
+    });
+    if target.try_exists()? {
+        anyhow::bail!("`{}` already exists", target.user_display());
+    }
+    fs_err::write(&target, converted)?;
+    // Make sure the converted config still reads the same.
+    if let Err(err) = read_config(&target) {
+        fs_err::remove_file(&target)?;
+        return Err(err).context("The converted config is invalid");
+    }
+    fs_err::remove_file(&source)?;
+
+    writeln!(
+        printer.stderr(),
+        "Migrated `{}` to `{}`",
+        source.user_display().cyan(),
+        target.user_display().cyan()
+    )?;
+    Ok(ExitStatus::Success)
+}
+
+/// Rename the keys and stages of the older versions of the config,
+/// keeping the rest of the file as it's written.
+fn migrate_legacy(content: String) -> YamlDocument {
+    let mut document = YamlDocument::parse(content);
+    rename_stages(&mut document, &[Segment::Key("default_stages")]);
+    for index in 0..document.len(&[Segment::Key("repos")]) {
+        let repo = [Segment::Key("repos"), Segment::Index(index)];
+        let keys = document.keys(&repo);
+        if keys.contains(&"sha") && !keys.contains(&"rev") {
+            let sha = [
+                Segment::Key("repos"),
+                Segment::Index(index),
+                Segment::Key("sha"),
+            ];
+            document.rename_key(&sha, "rev");
+        }
+
+        let hooks = [
+            Segment::Key("repos"),
+            Segment::Index(index),
+            Segment::Key("hooks"),
+        ];
+        for hook in 0..document.len(&hooks) {
+            let stages = [
+                Segment::Key("repos"),
+                Segment::Index(index),
+                Segment::Key("hooks"),
+                Segment::Index(hook),
+                Segment::Key("stages"),
+            ];
+            rename_stages(&mut document, &stages);
+        }
+    }
+    document
+}
+
+/// Replace the deprecated stage names in the sequence at `path`.
+fn rename_stages(document: &mut YamlDocument, path: &[Segment]) {
+    for (index, stage) in document.strings(path).into_iter().enumerate() {
+        if let Some((_, new)) = DEPRECATED_STAGES.iter().find(|(old, _)| *old == stage) {
+            document.set_item(path, index, new);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::ffi::OsString;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::process::ExitCode;
 
@@ -6,24 +7,46 @@ use clap::builder::styling::{AnsiColor, Effects};
 use clap::builder::Styles;
 use clap::{ArgAction, Args, Parser, Subcommand};
 
-use crate::config::{HookType, Stage};
+use crate::config::{HookType, Language, Stage};
 
+mod autoupdate;
 mod clean;
+#[cfg(unix)]
+mod daemon;
+mod gc;
 mod hook_impl;
+mod import_cache;
 mod install;
+mod lint_config;
+mod migrate_config;
 mod reporter;
+mod resolve_config;
 pub mod run;
 mod sample_config;
+mod schema;
 mod self_update;
+mod stats;
 mod validate;
+mod watch;
 
+pub(crate) use autoupdate::autoupdate;
 pub(crate) use clean::clean;
+#[cfg(unix)]
+pub(crate) use daemon::daemon;
+pub(crate) use gc::gc;
 pub(crate) use hook_impl::hook_impl;
-pub(crate) use install::{init_template_dir, install, uninstall};
-pub(crate) use run::run;
+pub(crate) use import_cache::import_cache;
+pub(crate) use install::{init_template_dir, install, uninstall, Shim};
+pub(crate) use lint_config::lint_config;
+pub(crate) use migrate_config::migrate_config;
+pub(crate) use resolve_config::resolve_config;
+pub(crate) use run::{is_github_actions, run, RunOptions};
 pub(crate) use sample_config::sample_config;
+pub(crate) use schema::schema;
 pub(crate) use self_update::self_update;
+pub(crate) use stats::stats;
 pub(crate) use validate::{validate_configs, validate_manifest};
+pub(crate) use watch::watch;
 
 #[derive(Copy, Clone)]
 pub(crate) enum ExitStatus {
@@ -67,6 +90,27 @@ pub enum ColorChoice {
     Never,
 }
 
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Print a status line for each hook.
+    #[default]
+    Text,
+
+    /// Print a JSON report of the results of all hooks.
+    Json,
+
+    /// Print a TAP (Test Anything Protocol) report of the results of all hooks.
+    Tap,
+
+    /// Print the diagnostics parsed from the output of failed hooks in reviewdog's
+    /// rdjson format.
+    Rdjson,
+
+    /// Print NUL-terminated records without decoration, selected by `-z`.
+    #[value(skip)]
+    Null,
+}
+
 impl From<ColorChoice> for anstream::ColorChoice {
     fn from(value: ColorChoice) -> Self {
         match value {
@@ -114,8 +158,10 @@ pub(crate) struct Cli {
 #[command(disable_help_flag = true, disable_version_flag = true)]
 pub(crate) struct GlobalArgs {
     /// Path to alternate config file.
-    #[arg(global = true, short, long, value_parser)]
-    pub(crate) config: Option<PathBuf>,
+    /// Can be given multiple times or as a comma-separated list for `run` and `watch`,
+    /// the repos of the other files are appended to the first in order.
+    #[arg(global = true, short, long, value_parser, value_delimiter = ',')]
+    pub(crate) config: Vec<PathBuf>,
 
     /// Whether to use color in output.
     #[arg(
@@ -137,14 +183,20 @@ pub(crate) struct GlobalArgs {
     #[arg(global = true, long)]
     pub no_progress: bool,
 
-    /// Do not print any output.
-    #[arg(global = true, long, short, conflicts_with = "verbose")]
-    pub quiet: bool,
+    /// Do not print any output, except for failing hooks.
+    ///
+    /// Repeating this option, e.g., `-qq`, also hides failing hooks.
+    #[arg(global = true, long, short, action = ArgAction::Count, conflicts_with = "verbose")]
+    pub quiet: u8,
 
     /// Use verbose output.
     #[arg(global = true, short, long, action = ArgAction::Count)]
     pub(crate) verbose: u8,
 
+    /// Fail on unexpected keys in config files, instead of warning about them.
+    #[arg(global = true, long)]
+    pub strict: bool,
+
     /// Display the prefligit version.
     #[arg(global = true, short = 'V', long, action = clap::ArgAction::Version)]
     version: Option<bool>,
@@ -173,13 +225,32 @@ pub(crate) enum Command {
     ValidateManifest(ValidateManifestArgs),
     /// Produce a sample `.pre-commit-config.yaml` file.
     SampleConfig,
+    /// Convert the config file between YAML and TOML, replacing the original file.
+    ///
+    /// With `--to yaml`, the legacy keys and stages of a YAML config are renamed in place.
+    MigrateConfig(MigrateConfigArgs),
+    /// Print the JSON Schema of the config file or the manifest file, for editor support.
+    Schema(SchemaArgs),
+    /// Manage the config file.
+    Config(ConfigNamespace),
     /// Auto-update pre-commit config to the latest repos' versions.
     #[command(name = "autoupdate")]
     AutoUpdate(AutoUpdateArgs),
-    /// Clean unused cached repos.
-    GC,
+    /// Clean unused cached repos and environments.
+    ///
+    /// Repos are kept as long as a config that used them still references them.
+    GC(GcArgs),
     /// Clean out pre-commit files.
     Clean,
+    /// Import the repos cloned by the Python pre-commit, to use them without cloning again.
+    ImportCache(ImportCacheArgs),
+    /// Show statistics of recent runs in the current repo.
+    Stats(StatsArgs),
+    /// Watch the working tree and run hooks on files as they change.
+    Watch(WatchArgs),
+    /// Keep hook environments ready and run the installed hook scripts of the current repo,
+    /// to avoid the startup cost of each run.
+    Daemon,
     /// Install hook script in a directory intended for use with `git config init.templateDir`.
     #[command(name = "init-templatedir")]
     InitTemplateDir(InitTemplateDirArgs),
@@ -200,6 +271,7 @@ pub(crate) enum Command {
 }
 
 #[derive(Debug, Args)]
+#[allow(clippy::struct_excessive_bools)]
 pub(crate) struct InstallArgs {
     /// Overwrite existing hooks.
     #[arg(short = 'f', long)]
@@ -215,12 +287,21 @@ pub(crate) struct InstallArgs {
     /// Allow a missing `pre-commit` configuration file.
     #[arg(long)]
     pub(crate) allow_missing_config: bool,
+
+    /// Install a native executable as the hook instead of a shell script, for systems
+    /// without a POSIX shell, like Windows without Git Bash.
+    #[arg(long)]
+    pub(crate) native: bool,
 }
 
 #[derive(Debug, Args)]
 pub(crate) struct UninstallArgs {
     #[arg(short = 't', long = "hook-type", value_name = "HOOK_TYPE", value_enum)]
     pub(crate) hook_types: Vec<HookType>,
+
+    /// Also remove the cached repos and environments that are only used by this repository.
+    #[arg(long)]
+    pub(crate) purge: bool,
 }
 
 #[derive(Debug, Clone, Default, Args)]
@@ -251,17 +332,47 @@ pub(crate) struct RunExtraArgs {
     pub(crate) rewrite_command: Option<String>,
 }
 
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Clone, Default, Args)]
 pub(crate) struct RunArgs {
-    /// The hook ID to run.
+    /// The hook IDs (or aliases) to run. Runs all hooks if not specified.
     #[arg(value_name = "HOOK")]
-    pub(crate) hook_id: Option<String>,
+    pub(crate) hook_ids: Vec<String>,
+    /// Skip the hook with the given ID (or alias). Can be specified multiple times.
+    /// Combined with the hooks listed in the `SKIP` environment variable.
+    #[arg(long = "skip", value_name = "HOOK")]
+    pub(crate) skips: Vec<String>,
+    /// Also run the hooks in the given profile. Can be specified multiple times.
+    /// Hooks without `profiles` always run.
+    #[arg(long = "profile", value_name = "PROFILE")]
+    pub(crate) profiles: Vec<String>,
+    /// Only run the hooks of the given language. Can be specified multiple times.
+    #[arg(long = "language", value_name = "LANGUAGE", value_enum)]
+    pub(crate) languages: Vec<Language>,
+    /// Only run the hooks with the given tag. Can be specified multiple times.
+    #[arg(long = "tag", value_name = "TAG")]
+    pub(crate) tags: Vec<String>,
     /// Run on all files in the repo.
-    #[arg(short, long, conflicts_with_all = ["files", "from_ref", "to_ref"])]
+    #[arg(short, long, conflicts_with_all = ["files", "files_from", "from_ref", "to_ref", "since"])]
     pub(crate) all_files: bool,
     /// Specific filenames to run hooks on.
-    #[arg(long, conflicts_with_all = ["all_files", "from_ref", "to_ref"])]
+    #[arg(long, conflicts_with_all = ["all_files", "from_ref", "to_ref", "since"])]
     pub(crate) files: Vec<PathBuf>,
+    /// Read filenames to run hooks on from a file, or from stdin if `-` is given.
+    /// Filenames are separated by newlines, or by NUL characters if any are present.
+    /// An empty list runs hooks on no files.
+    ///
+    /// Like `--files`, filenames are relative to the current directory, while `git diff
+    /// --name-only` prints them relative to the repository root: run it from the root, or
+    /// add `--relative` in a subdirectory.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["all_files", "from_ref", "to_ref", "since"])]
+    pub(crate) files_from: Option<PathBuf>,
+    /// Only run on files under the given directory. Can be specified multiple times.
+    #[arg(short = 'd', long = "directory", value_name = "DIR")]
+    pub(crate) directories: Vec<PathBuf>,
+    /// Also run on untracked files that are not ignored.
+    #[arg(long, conflicts_with = "from_index")]
+    pub(crate) include_untracked: bool,
     /// The original ref in a `from_ref...to_ref` diff expression.
     /// Files changed in this diff will be run through the hooks.
     #[arg(short = 's', long, alias = "source", requires = "to_ref")]
@@ -270,12 +381,92 @@ pub(crate) struct RunArgs {
     /// Files changed in this diff will be run through the hooks.
     #[arg(short = 'o', long, alias = "origin", requires = "from_ref")]
     pub(crate) to_ref: Option<String>,
+    /// Run on files changed since the merge base of the given ref and `HEAD`.
+    /// For example, `--since origin/main` checks the files changed on the current branch.
+    #[arg(long, value_name = "REV", conflicts_with_all = ["from_ref", "to_ref"])]
+    pub(crate) since: Option<String>,
     /// The stage during which the hook is fired.
     #[arg(long)]
     pub(crate) hook_stage: Option<Stage>,
     /// When hooks fail, run `git diff` directly afterward.
     #[arg(long)]
     pub(crate) show_diff_on_failure: bool,
+    /// Print a summary of hook results, modified files, and duration after running hooks.
+    #[arg(long)]
+    pub(crate) summary: bool,
+    /// Print the output of hooks line by line as they run, prefixed with the hook id.
+    #[arg(long)]
+    pub(crate) stream: bool,
+    /// Print a table of hooks sorted by duration after running hooks.
+    ///
+    /// Implied by `--verbose`.
+    #[arg(long)]
+    pub(crate) timings: bool,
+    /// Show running hooks with spinners and elapsed times, collapsing hooks that pass.
+    ///
+    /// Falls back to the line-based output when not running in a terminal, or in CI.
+    #[arg(long)]
+    pub(crate) live: bool,
+    /// Pick the hooks to run from a list, which can be filtered by typing.
+    #[arg(short, long)]
+    pub(crate) interactive: bool,
+    /// Also run the config files in subdirectories, each on the files under its directory.
+    /// Implied by the `workspace` config option.
+    #[arg(long)]
+    pub(crate) workspace: bool,
+    /// The format of the results.
+    #[arg(long, value_enum, default_value_t)]
+    pub(crate) output_format: OutputFormat,
+    /// Print NUL-terminated `<hook id>\t<status>` records instead of status lines,
+    /// or `<hook id>\t<file>` records with `--dry-run`.
+    #[arg(short = 'z', long = "null", conflicts_with = "output_format")]
+    pub(crate) null: bool,
+    /// Write the `--output-format json`, `tap` or `rdjson` report to this file instead of stdout.
+    #[arg(long, value_name = "PATH")]
+    pub(crate) output_file: Option<PathBuf>,
+    /// When a hook modifies files, show the changes it made right below its result.
+    #[arg(long)]
+    pub(crate) diff: bool,
+    /// The number of processes used to run a hook on files in parallel,
+    /// and of repos cloned and environments installed at once.
+    /// `1` runs hooks serially, `0` uses the number of CPUs.
+    /// Overrides the `jobs` config option.
+    #[arg(short, long, value_name = "N")]
+    pub(crate) jobs: Option<usize>,
+    /// Run hooks concurrently, starting each hook once the hooks in its `depends_on` have finished.
+    /// Only hooks declared `read_only` run at the same time, other hooks run on their own.
+    #[arg(long)]
+    pub(crate) parallel: bool,
+    /// Kill hooks that run longer than this many seconds and mark them as failed.
+    /// Hooks with a `timeout` option use their own timeout instead.
+    #[arg(long, value_name = "SECONDS")]
+    pub(crate) timeout: Option<u64>,
+    /// Stop running hooks after this many hooks have failed.
+    /// A softer version of `fail_fast`, which stops after the first failure.
+    #[arg(long, value_name = "N")]
+    pub(crate) max_failures: Option<NonZeroUsize>,
+    /// Stage the changes hooks make to staged files, instead of failing.
+    /// Only takes effect when unstaged changes are stashed.
+    #[arg(long, conflicts_with_all = ["no_stash", "from_index"])]
+    pub(crate) autofix_stage: bool,
+    /// Run hooks on the working tree as-is, without stashing unstaged changes.
+    #[arg(long, conflicts_with = "from_index")]
+    pub(crate) no_stash: bool,
+    /// Run hooks on the staged contents of files, checked out to a temporary directory,
+    /// instead of stashing unstaged changes. Changes made by hooks are discarded.
+    #[arg(long)]
+    pub(crate) from_index: bool,
+    /// Show which files each hook would run on, without running any hooks.
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+    /// Run hooks even if they passed before with the same definition, environment, files and
+    /// file contents, when the `cache` config option is set.
+    #[arg(long)]
+    pub(crate) no_cache: bool,
+    /// Run hooks without network access, and only let them write to the repo, their environment
+    /// and the temporary directory. Supported on Linux and macOS.
+    #[arg(long)]
+    pub(crate) sandbox: bool,
 
     #[command(flatten)]
     pub(crate) extra: RunExtraArgs,
@@ -295,14 +486,82 @@ pub(crate) struct ValidateManifestArgs {
     pub(crate) manifests: Vec<PathBuf>,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ConfigFormat {
+    Yaml,
+    Toml,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct MigrateConfigArgs {
+    /// The format to convert the config file to.
+    /// Defaults to the format the config file is not in.
+    #[arg(long, value_enum)]
+    pub(crate) to: Option<ConfigFormat>,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum SchemaKind {
+    /// The schema of `.pre-commit-config.yaml`.
+    #[default]
+    Config,
+    /// The schema of `.pre-commit-hooks.yaml`.
+    Manifest,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct SchemaArgs {
+    /// The file to print the schema of.
+    #[arg(value_enum, default_value_t)]
+    pub(crate) kind: SchemaKind,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct GcArgs {
+    /// Also forget the configs that haven't been used for this many days,
+    /// removing the repos only they reference.
+    #[arg(long, value_name = "DAYS")]
+    pub(crate) max_age: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct ImportCacheArgs {
+    /// The pre-commit cache directory.
+    ///
+    /// Defaults to `$PRE_COMMIT_HOME`, or `pre-commit` in the user cache directory like pre-commit.
+    #[arg(long, value_name = "DIR")]
+    pub(crate) from: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct StatsArgs {
+    /// The number of most recent runs to include.
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    pub(crate) last: usize,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct WatchArgs {
+    /// The hook IDs (or aliases) to run. Runs all hooks if not specified.
+    #[arg(value_name = "HOOK")]
+    pub(crate) hook_ids: Vec<String>,
+    /// How long to wait for files to stop changing before running hooks, in milliseconds.
+    #[arg(long, value_name = "MS", default_value_t = 300)]
+    pub(crate) debounce: u64,
+}
+
 #[derive(Debug, Args)]
 pub(crate) struct AutoUpdateArgs {
-    #[arg(long, default_value_t = true)]
+    /// Update to the latest commit of the default branch instead of the latest tag.
+    #[arg(long)]
     pub(crate) bleeding_edge: bool,
+    /// Pin the revs to commit hashes, with the tags they are frozen from in comments.
     #[arg(long)]
     pub(crate) freeze: bool,
+    /// Only update this repo.
     #[arg(long)]
     pub(crate) repo: Option<String>,
+    /// The number of repos to update concurrently.
     #[arg(short, long, default_value_t = 1)]
     pub(crate) jobs: usize,
 }
@@ -319,6 +578,41 @@ pub(crate) struct HookImplArgs {
     pub(crate) args: Vec<OsString>,
 }
 
+#[derive(Debug, Args)]
+pub(crate) struct ConfigNamespace {
+    #[command(subcommand)]
+    pub(crate) command: ConfigCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum ConfigCommand {
+    /// Check the config file for deprecated options and likely mistakes.
+    ///
+    /// Flags deprecated stage names, branch revs, duplicate hook ids,
+    /// hooks whose files are all excluded by the top-level patterns,
+    /// and additional dependencies without a version.
+    Lint,
+    /// Print the effective config, with the settings of each hook resolved.
+    ///
+    /// The manifest defaults, the overrides in the config file and the
+    /// config-wide defaults are merged into each hook, like when running them.
+    Resolve(ResolveConfigArgs),
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ResolveFormat {
+    #[default]
+    Yaml,
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct ResolveConfigArgs {
+    /// The format to print the config in.
+    #[arg(long, value_enum, default_value_t)]
+    pub(crate) format: ResolveFormat,
+}
+
 #[derive(Debug, Args)]
 pub struct SelfNamespace {
     #[command(subcommand)]
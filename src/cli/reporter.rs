@@ -1,5 +1,7 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt::Write;
+use std::io::IsTerminal;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
@@ -31,6 +33,8 @@ struct ProgressReporter {
     root: ProgressBar,
     state: Arc<Mutex<BarState>>,
     children: MultiProgress,
+    /// Print plain lines instead of progress bars, when stderr is not a terminal.
+    plain: bool,
 }
 
 impl ProgressReporter {
@@ -40,6 +44,7 @@ impl ProgressReporter {
             root,
             state: Arc::default(),
             children,
+            plain: printer == Printer::Default && !std::io::stderr().is_terminal(),
         }
     }
 
@@ -52,13 +57,63 @@ impl ProgressReporter {
             ProgressBar::with_draw_target(None, self.printer.target()),
         );
 
-        progress.set_style(ProgressStyle::with_template("{wide_msg}").unwrap());
+        progress.set_style(ProgressStyle::with_template("{msg} {prefix:.dim}").unwrap());
         progress.set_message(msg);
 
         state.bars.insert(id, progress);
+        self.root
+            .set_length(self.root.length().unwrap_or_default() + 1);
+        self.update_count();
         id
     }
 
+    /// Show how many of the tasks started so far have finished next to the root message.
+    fn update_count(&self) {
+        self.root.set_prefix(format!(
+            "({}/{})",
+            self.root.position(),
+            self.root.length().unwrap_or_default()
+        ));
+    }
+
+    /// Print the message of a bar as a plain line, if progress bars are not shown.
+    fn announce(&self, id: usize) {
+        if !self.plain {
+            return;
+        }
+        let state = self.state.lock().unwrap();
+        if let Some(progress) = state.bars.get(&id) {
+            let _ = writeln!(self.printer.stderr(), "{}", progress.message());
+        }
+    }
+
+    /// Show the current phase of a task next to its message.
+    fn on_phase(&self, id: usize, phase: &str) {
+        let state = self.state.lock().unwrap();
+        if let Some(progress) = state.bars.get(&id) {
+            progress.set_prefix(phase.to_string());
+        }
+    }
+
+    /// Show the current phase of a task with a progress bar of `pos` out of `len`.
+    fn on_update(&self, id: usize, phase: &str, pos: u64, len: u64) {
+        let state = self.state.lock().unwrap();
+        if let Some(progress) = state.bars.get(&id) {
+            if progress.length().is_none() {
+                progress.set_style(
+                    ProgressStyle::with_template(
+                        "{msg} {prefix:.dim} {bar:20.cyan/dim} {pos}/{len}",
+                    )
+                    .unwrap()
+                    .progress_chars("=> "),
+                );
+            }
+            progress.set_prefix(phase.to_string());
+            progress.set_length(len);
+            progress.set_position(pos);
+        }
+    }
+
     fn on_progress(&self, id: usize) {
         let progress = {
             let mut state = self.state.lock().unwrap();
@@ -66,6 +121,7 @@ impl ProgressReporter {
         };
 
         self.root.inc(1);
+        self.update_count();
         progress.finish_and_clear();
     }
 
@@ -85,7 +141,7 @@ impl From<Printer> for HookInitReporter {
         let root = multi.add(ProgressBar::with_draw_target(None, printer.target()));
         root.enable_steady_tick(Duration::from_millis(200));
         root.set_style(
-            ProgressStyle::with_template("{spinner:.white} {msg:.dim}")
+            ProgressStyle::with_template("{spinner:.white} {msg:.dim} {prefix:.dim}")
                 .unwrap()
                 .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]),
         );
@@ -98,8 +154,15 @@ impl From<Printer> for HookInitReporter {
 
 impl hook::HookInitReporter for HookInitReporter {
     fn on_clone_start(&self, repo: &str) -> usize {
-        self.reporter
-            .on_start(format!("{} {}", "Cloning".bold().cyan(), repo.dimmed()))
+        let id = self
+            .reporter
+            .on_start(format!("{} {}", "Cloning".bold().cyan(), repo.dimmed()));
+        self.reporter.announce(id);
+        id
+    }
+
+    fn on_clone_progress(&self, id: usize, phase: &str, pos: u64, len: u64) {
+        self.reporter.on_update(id, phase, pos, len);
     }
 
     fn on_clone_complete(&self, id: usize) {
@@ -121,7 +184,7 @@ impl From<Printer> for HookInstallReporter {
         let root = multi.add(ProgressBar::with_draw_target(None, printer.target()));
         root.enable_steady_tick(Duration::from_millis(200));
         root.set_style(
-            ProgressStyle::with_template("{spinner:.white} {msg:.dim}")
+            ProgressStyle::with_template("{spinner:.white} {msg:.dim} {prefix:.dim}")
                 .unwrap()
                 .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]),
         );
@@ -134,11 +197,17 @@ impl From<Printer> for HookInstallReporter {
 
 impl HookInstallReporter {
     pub fn on_install_start(&self, hook: &Hook) -> usize {
-        self.reporter.on_start(format!(
+        let id = self.reporter.on_start(format!(
             "{} {}",
             "Installing".bold().cyan(),
             hook.id.dimmed(),
-        ))
+        ));
+        self.reporter.announce(id);
+        id
+    }
+
+    pub fn on_install_phase(&self, id: usize, phase: &str) {
+        self.reporter.on_phase(id, phase);
     }
 
     pub fn on_install_complete(&self, id: usize) {
@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::cli::reporter::HookInitReporter;
+use crate::cli::{ExitStatus, ResolveFormat};
+use crate::config::Stage;
+use crate::fs::Simplified;
+use crate::hook::{Hook, Project};
+use crate::printer::Printer;
+use crate::store::Store;
+
+/// The config after all the layering, as the hooks will run with it.
+#[derive(Serialize)]
+struct ResolvedConfig<'a> {
+    config: String,
+    files: Option<&'a str>,
+    exclude: Option<&'a str>,
+    files_glob: &'a [String],
+    exclude_glob: &'a [String],
+    fail_fast: bool,
+    hooks: Vec<ResolvedHook<'a>>,
+}
+
+#[derive(Serialize)]
+#[allow(clippy::struct_excessive_bools)]
+struct ResolvedHook<'a> {
+    id: &'a str,
+    repo: String,
+    name: &'a str,
+    entry: &'a str,
+    language: &'a str,
+    language_version: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    alias: &'a str,
+    stages: Vec<&'a str>,
+    files: Option<&'a str>,
+    exclude: Option<&'a str>,
+    files_glob: &'a [String],
+    exclude_glob: &'a [String],
+    types: &'a [String],
+    types_or: &'a [String],
+    exclude_types: &'a [String],
+    additional_dependencies: &'a [String],
+    args: &'a [String],
+    always_run: bool,
+    fail_fast: bool,
+    pass_filenames: bool,
+    require_serial: bool,
+    verbose: bool,
+    timeout: Option<u64>,
+    retries: u32,
+    depends_on: &'a [String],
+    read_only: bool,
+    profiles: &'a [String],
+    tags: &'a [String],
+    env: BTreeMap<&'a str, &'a str>,
+    clean_env: bool,
+    pass_env: &'a [String],
+    working_directory: Option<&'a str>,
+}
+
+impl<'a> From<&'a Hook> for ResolvedHook<'a> {
+    fn from(hook: &'a Hook) -> Self {
+        Self {
+            id: &hook.id,
+            repo: hook.repo().to_string(),
+            name: &hook.name,
+            entry: &hook.entry,
+            language: hook.language.as_str(),
+            language_version: hook.language_version.as_str(),
+            alias: &hook.alias,
+            stages: hook.stages.iter().map(Stage::as_str).collect(),
+            files: hook.files.as_deref(),
+            exclude: hook.exclude.as_deref(),
+            files_glob: &hook.files_glob,
+            exclude_glob: &hook.exclude_glob,
+            types: &hook.types,
+            types_or: &hook.types_or,
+            exclude_types: &hook.exclude_types,
+            additional_dependencies: &hook.additional_dependencies,
+            args: &hook.args,
+            always_run: hook.always_run,
+            fail_fast: hook.fail_fast,
+            pass_filenames: hook.pass_filenames,
+            require_serial: hook.require_serial,
+            verbose: hook.verbose,
+            timeout: hook.timeout.map(|timeout| timeout.as_secs()),
+            retries: hook.retries,
+            depends_on: &hook.depends_on,
+            read_only: hook.read_only,
+            profiles: &hook.profiles,
+            tags: &hook.tags,
+            env: hook
+                .env
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect(),
+            clean_env: hook.clean_env,
+            pass_env: &hook.pass_env,
+            working_directory: hook.working_directory.as_deref(),
+        }
+    }
+}
+
+/// Print the config with the manifest defaults, the overrides and the config-wide defaults merged into each hook.
+pub(crate) async fn resolve_config(
+    config: Option<PathBuf>,
+    format: ResolveFormat,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let mut project = Project::from_config_file(config)?;
+    let store = Store::from_settings()?.init()?;
+
+    let reporter = HookInitReporter::from(printer);
+    let hooks = project.init_hooks(&store, Some(&reporter)).await?;
+
+    let config = project.config();
+    let resolved = ResolvedConfig {
+        config: project.config_file().user_display().to_string(),
+        files: config.files.as_deref(),
+        exclude: config.exclude.as_deref(),
+        files_glob: config.files_glob.as_deref().unwrap_or_default(),
+        exclude_glob: config.exclude_glob.as_deref().unwrap_or_default(),
+        fail_fast: config.fail_fast.unwrap_or(false),
+        hooks: hooks.iter().map(ResolvedHook::from).collect(),
+    };
+
+    let output = match format {
+        ResolveFormat::Yaml => serde_yaml::to_string(&resolved)?,
+        ResolveFormat::Json => serde_json::to_string_pretty(&resolved)? + "\n",
+    };
+    write!(printer.stdout(), "{output}")?;
+
+    Ok(ExitStatus::Success)
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use rayon::prelude::*;
+use tracing::debug;
+
+use crate::env_vars::EnvVars;
+use crate::hook::Hook;
+use crate::store::Store;
+
+/// The keys of the last successful runs of hooks, to skip hooks whose inputs are unchanged.
+///
+/// A key is a hash of what a hook run depends on: the definition of the hook, the state of
+/// its environment and entry executable, the environment variables of the run, and the files
+/// with their contents.
+pub(crate) struct RunCache {
+    /// The repo the runs are recorded for, `None` if caching is off.
+    repo: Option<PathBuf>,
+    /// The key of the last success of each hook.
+    passed: HashMap<String, String>,
+    /// The keys of the hooks passed in this run.
+    recorded: Mutex<Vec<(String, String)>>,
+}
+
+impl RunCache {
+    pub(crate) fn disabled() -> Self {
+        Self {
+            repo: None,
+            passed: HashMap::new(),
+            recorded: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Load the keys recorded for the hooks of `repo`.
+    pub(crate) fn load(store: &Store, repo: &Path) -> Self {
+        let passed = store
+            .hook_cache(repo)
+            .inspect_err(|err| debug!("Failed to read the run cache: {err}"))
+            .unwrap_or_default();
+        Self {
+            repo: Some(repo.to_path_buf()),
+            passed,
+            recorded: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The key of a run of the hook on `filenames` in `work_dir`, or in the current directory
+    /// if it's `None`, or `None` if caching is off.
+    pub(crate) fn key(
+        &self,
+        hook: &Hook,
+        filenames: &[&String],
+        env_vars: &HashMap<&'static str, String>,
+        work_dir: Option<&Path>,
+    ) -> Option<String> {
+        self.repo.as_ref()?;
+
+        let mut context = md5::Context::new();
+        let mut add = |part: &[u8]| {
+            context.consume(part);
+            context.consume([0]);
+        };
+
+        add(env!("CARGO_PKG_VERSION").as_bytes());
+        add(format!("{hook:#}").as_bytes());
+        add(hook.entry.as_bytes());
+        add(format!("{:?}", hook.language).as_bytes());
+        add(hook.language_version.as_str().as_bytes());
+        for arg in &hook.args {
+            add(arg.as_bytes());
+        }
+        for dep in &hook.additional_dependencies {
+            add(dep.as_bytes());
+        }
+        let mut env: Vec<_> = hook.env.iter().collect();
+        env.sort_unstable();
+        for (key, value) in env {
+            add(format!("{key}={value}").as_bytes());
+        }
+        add(format!(
+            "{:?} {:?} {} {}",
+            hook.working_directory, hook.pass_env, hook.clean_env, hook.pass_filenames
+        )
+        .as_bytes());
+
+        // Reinstalled environments, and updated executables of system hooks.
+        let executable = shlex::split(&hook.entry)
+            .and_then(|entry| entry.into_iter().next())
+            .and_then(|program| which::which(program).ok());
+        for path in hook.installed_state().into_iter().chain(executable) {
+            if let Ok(metadata) = fs_err::metadata(&path) {
+                let mtime = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok())
+                    .unwrap_or_default();
+                add(
+                    format!("{} {} {}", path.display(), metadata.len(), mtime.as_nanos())
+                        .as_bytes(),
+                );
+            }
+        }
+
+        // A checkout of the index is a new directory every run, its files are hashed instead.
+        let mut env_vars: Vec<_> = env_vars
+            .iter()
+            .filter(|(key, _)| !matches!(**key, EnvVars::GIT_DIR | EnvVars::GIT_WORK_TREE))
+            .collect();
+        env_vars.sort_unstable();
+        for (key, value) in env_vars {
+            add(format!("{key}={value}").as_bytes());
+        }
+
+        let mut filenames = filenames.to_vec();
+        filenames.sort_unstable();
+        let digests: Vec<_> = filenames
+            .par_iter()
+            .map(|filename| {
+                let path =
+                    work_dir.map_or_else(|| PathBuf::from(filename), |dir| dir.join(filename));
+                fs_err::read(path).ok().map(md5::compute)
+            })
+            .collect();
+        for (filename, digest) in filenames.iter().zip(digests) {
+            add(filename.as_bytes());
+            add(digest.as_ref().map_or(&[][..], |digest| &digest.0[..]));
+        }
+
+        Some(format!("{:x}", context.compute()))
+    }
+
+    /// Whether the hook passed before with the same key.
+    pub(crate) fn passed(&self, hook: &Hook, key: &str) -> bool {
+        self.passed
+            .get(&hook_name(hook))
+            .is_some_and(|passed| passed == key)
+    }
+
+    /// Record that the hook passed with the key.
+    pub(crate) fn record(&self, hook: &Hook, key: String) {
+        self.recorded.lock().unwrap().push((hook_name(hook), key));
+    }
+
+    /// Save the keys of the hooks passed in this run.
+    pub(crate) fn save(self, store: &Store) {
+        let Some(repo) = self.repo else {
+            return;
+        };
+        let recorded = self.recorded.into_inner().unwrap();
+        if recorded.is_empty() {
+            return;
+        }
+        if let Err(err) = store.update_hook_cache(&repo, &recorded) {
+            debug!("Failed to save the run cache: {err}");
+        }
+    }
+}
+
+/// The name a hook is recorded by, hooks with the same id in different repos are kept apart.
+fn hook_name(hook: &Hook) -> String {
+    format!("{hook:#}")
+}
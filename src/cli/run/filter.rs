@@ -1,28 +1,50 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-use anyhow::Result;
-use fancy_regex as regex;
+use anyhow::{Context, Result};
 use fancy_regex::Regex;
-use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use globset::GlobSet;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use tracing::{debug, error};
 
-use crate::config::Stage;
+use crate::config::{Config, FileTypeRules, Stage, IGNORE_FILE};
 use crate::fs::normalize_path;
 use crate::git;
 use crate::hook::Hook;
-use crate::identify::tags_from_path;
+use crate::identify::{FileHeader, FileInfo, FileStamp};
+use crate::pattern;
+use crate::user_config::UserConfig;
 
 /// Filter filenames by include/exclude patterns.
 pub struct FilenameFilter {
     include: Option<Regex>,
     exclude: Option<Regex>,
+    include_glob: Option<GlobSet>,
+    exclude_glob: Option<GlobSet>,
 }
 
 impl FilenameFilter {
-    pub fn new(include: Option<&str>, exclude: Option<&str>) -> Result<Self, Box<regex::Error>> {
-        let include = include.map(Regex::new).transpose()?;
-        let exclude = exclude.map(Regex::new).transpose()?;
-        Ok(Self { include, exclude })
+    pub fn new(
+        include: Option<&str>,
+        exclude: Option<&str>,
+        include_glob: &[String],
+        exclude_glob: &[String],
+    ) -> Result<Self> {
+        let include = include.map(pattern::compile).transpose()?;
+        let exclude = exclude.map(pattern::compile).transpose()?;
+        let globs = |globs: &[String]| {
+            (!globs.is_empty())
+                .then(|| pattern::compile_globs(globs))
+                .transpose()
+        };
+        Ok(Self {
+            include,
+            exclude,
+            include_glob: globs(include_glob)?,
+            exclude_glob: globs(exclude_glob)?,
+        })
     }
 
     pub fn filter(&self, filename: impl AsRef<str>) -> bool {
@@ -32,16 +54,40 @@ impl FilenameFilter {
                 return false;
             }
         }
+        if let Some(globs) = &self.include_glob {
+            if !globs.is_match(filename) {
+                return false;
+            }
+        }
         if let Some(re) = &self.exclude {
             if re.is_match(filename).unwrap_or(false) {
                 return false;
             }
         }
+        if let Some(globs) = &self.exclude_glob {
+            if globs.is_match(filename) {
+                return false;
+            }
+        }
         true
     }
 
-    pub fn from_hook(hook: &Hook) -> Result<Self, Box<regex::Error>> {
-        Self::new(hook.files.as_deref(), hook.exclude.as_deref())
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Self::new(
+            config.files.as_deref(),
+            config.exclude.as_deref(),
+            config.files_glob.as_deref().unwrap_or_default(),
+            config.exclude_glob.as_deref().unwrap_or_default(),
+        )
+    }
+
+    pub fn from_hook(hook: &Hook) -> Result<Self> {
+        Self::new(
+            hook.files.as_deref(),
+            hook.exclude.as_deref(),
+            &hook.files_glob,
+            &hook.exclude_glob,
+        )
     }
 }
 
@@ -85,15 +131,20 @@ impl<'a> FileTagFilter<'a> {
 
 pub struct FileFilter<'a> {
     filenames: Vec<&'a String>,
+    /// Custom file types of the user and the config, the config ones take precedence.
+    file_types: HashMap<String, FileTypeRules>,
+    /// What tagging each of the files read, once per run for all the hooks.
+    infos: Vec<OnceLock<Option<FileInfo>>>,
+    /// The file headers read by previous runs, from the store.
+    cached: HashMap<String, (FileStamp, FileHeader)>,
+    /// The directory the files are relative to, instead of the current directory.
+    work_dir: Option<PathBuf>,
 }
 
 impl<'a> FileFilter<'a> {
-    pub fn new(
-        filenames: &'a [String],
-        include: Option<&str>,
-        exclude: Option<&str>,
-    ) -> Result<Self, Box<regex::Error>> {
-        let filter = FilenameFilter::new(include, exclude)?;
+    /// Filter the files relative to `work_dir`, or to the current directory if it's `None`.
+    pub fn new(filenames: &'a [String], config: &Config, work_dir: Option<&Path>) -> Result<Self> {
+        let filter = FilenameFilter::from_config(config)?;
 
         let filenames = filenames
             .into_par_iter()
@@ -101,66 +152,111 @@ impl<'a> FileFilter<'a> {
             .filter(|filename| {
                 // TODO: does this check really necessary?
                 // Ignore not existing files.
-                std::fs::symlink_metadata(filename)
+                std::fs::symlink_metadata(resolve(work_dir, filename))
                     .map(|m| m.file_type().is_file())
                     .unwrap_or(false)
             })
             .collect::<Vec<_>>();
 
-        Ok(Self { filenames })
+        let mut file_types: HashMap<_, _> =
+            UserConfig::get().file_types.clone().into_iter().collect();
+        file_types.extend(config.file_types.clone().unwrap_or_default());
+
+        Ok(Self {
+            infos: filenames.iter().map(|_| OnceLock::new()).collect(),
+            filenames,
+            file_types,
+            cached: HashMap::new(),
+            work_dir: work_dir.map(Path::to_path_buf),
+        })
     }
 
-    pub fn len(&self) -> usize {
-        self.filenames.len()
+    /// Reuse the file headers read by previous runs, instead of reading the files again.
+    #[must_use]
+    pub fn with_cached_headers(mut self, cached: HashMap<String, (FileStamp, FileHeader)>) -> Self {
+        self.cached = cached;
+        self
     }
 
-    pub fn by_tag(&self, hook: &Hook) -> Vec<&String> {
-        let filter = FileTagFilter::from_hook(hook);
-        let filenames: Vec<_> = self
-            .filenames
-            .par_iter()
-            .filter(|filename| {
-                let path = Path::new(filename);
-                match tags_from_path(path) {
-                    Ok(tags) => filter.filter(&tags),
-                    Err(err) => {
-                        error!(filename, error = %err, "Failed to get tags");
-                        false
-                    }
+    /// The file headers read in this run that the cache doesn't have yet, to store them.
+    pub fn read_headers(&self) -> Vec<(&'a String, FileStamp, FileHeader)> {
+        self.filenames
+            .iter()
+            .zip(&self.infos)
+            .filter_map(|(filename, info)| match info.get()?.as_ref()? {
+                FileInfo::File { stamp, header, .. } => {
+                    let fresh = self
+                        .cached
+                        .get(*filename)
+                        .is_none_or(|(cached, cached_header)| {
+                            cached != stamp || cached_header != header
+                        });
+                    fresh.then(|| (*filename, *stamp, header.clone()))
                 }
+                FileInfo::Special(_) => None,
             })
-            .copied()
-            .collect();
+            .collect()
+    }
 
-        filenames
+    /// The tags of a file, read at most once per run.
+    fn tags(&self, index: usize) -> Option<Vec<&str>> {
+        let filename = self.filenames[index];
+        let path = Path::new(filename);
+        let info = self.infos[index].get_or_init(|| {
+            FileInfo::read(
+                &resolve(self.work_dir.as_deref(), filename),
+                &self.file_types,
+                self.cached.get(filename),
+            )
+            .inspect_err(|err| error!(filename, error = %err, "Failed to get tags"))
+            .ok()
+        });
+        Some(info.as_ref()?.tags(path, &self.file_types))
     }
 
-    pub fn for_hook(&self, hook: &Hook) -> Result<Vec<&String>, Box<regex::Error>> {
-        let filter = FilenameFilter::from_hook(hook)?;
-        let filenames = self
-            .filenames
-            .par_iter()
-            .filter(|filename| filter.filter(filename));
+    pub fn len(&self) -> usize {
+        self.filenames.len()
+    }
 
+    pub fn by_tag(&self, hook: &Hook) -> Vec<&String> {
         let filter = FileTagFilter::from_hook(hook);
-        let filenames: Vec<_> = filenames
-            .filter(|filename| {
-                let path = Path::new(filename);
-                match tags_from_path(path) {
-                    Ok(tags) => filter.filter(&tags),
-                    Err(err) => {
-                        error!(filename, error = %err, "Failed to get tags");
-                        false
-                    }
-                }
+        (0..self.filenames.len())
+            .into_par_iter()
+            .filter(|&index| self.tags(index).is_some_and(|tags| filter.filter(&tags)))
+            .map(|index| self.filenames[index])
+            .collect()
+    }
+
+    pub fn for_hook(&self, hook: &Hook) -> Result<Vec<&String>> {
+        let filter = FilenameFilter::from_hook(hook)?;
+        let working_directory = hook.working_directory.as_deref();
+        let tag_filter = FileTagFilter::from_hook(hook);
+        let filenames = (0..self.filenames.len())
+            .into_par_iter()
+            .filter(|&index| {
+                let filename = self.filenames[index];
+                filter.filter(filename)
+                    && working_directory.is_none_or(|dir| Path::new(filename).starts_with(dir))
             })
-            .copied()
+            .filter(|&index| {
+                self.tags(index)
+                    .is_some_and(|tags| tag_filter.filter(&tags))
+            })
+            .map(|index| self.filenames[index])
             .collect();
 
         Ok(filenames)
     }
 }
 
+/// The path of a file relative to `work_dir`, or to the current directory if it's `None`.
+fn resolve<'p>(work_dir: Option<&Path>, filename: &'p str) -> Cow<'p, Path> {
+    match work_dir {
+        Some(dir) => Cow::Owned(dir.join(filename)),
+        None => Cow::Borrowed(Path::new(filename)),
+    }
+}
+
 #[derive(Default)]
 pub struct FileOptions {
     pub hook_stage: Option<Stage>,
@@ -168,6 +264,10 @@ pub struct FileOptions {
     pub to_ref: Option<String>,
     pub all_files: bool,
     pub files: Vec<PathBuf>,
+    /// Run on `files` only, even if there are none, instead of the staged files.
+    pub explicit_files: bool,
+    pub directories: Vec<PathBuf>,
+    pub include_untracked: bool,
     pub commit_msg_filename: Option<PathBuf>,
 }
 
@@ -187,6 +287,9 @@ pub async fn get_filenames(opts: FileOptions) -> Result<Vec<String>> {
         to_ref,
         all_files,
         files,
+        explicit_files,
+        directories,
+        include_untracked,
         commit_msg_filename,
     } = opts;
 
@@ -196,6 +299,8 @@ pub async fn get_filenames(opts: FileOptions) -> Result<Vec<String>> {
         to_ref,
         all_files,
         files,
+        explicit_files,
+        include_untracked,
         commit_msg_filename,
     )
     .await?;
@@ -203,6 +308,27 @@ pub async fn get_filenames(opts: FileOptions) -> Result<Vec<String>> {
     for filename in &mut filenames {
         normalize_path(filename);
     }
+
+    if !directories.is_empty() {
+        filenames.retain(|filename| {
+            directories
+                .iter()
+                .any(|dir| Path::new(filename).starts_with(dir))
+        });
+        debug!("Files under the given directories: {}", filenames.len());
+    }
+
+    match fs_err::read_to_string(IGNORE_FILE) {
+        Ok(content) => {
+            let ignore = pattern::IgnoreFile::parse(&content)
+                .with_context(|| format!("Invalid pattern in `{IGNORE_FILE}`"))?;
+            filenames.retain(|filename| !ignore.is_ignored(filename));
+            debug!("Files not ignored by `{IGNORE_FILE}`: {}", filenames.len());
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+
     Ok(filenames)
 }
 
@@ -213,6 +339,8 @@ async fn filenames_for_args(
     to_ref: Option<String>,
     all_files: bool,
     files: Vec<PathBuf>,
+    explicit_files: bool,
+    include_untracked: bool,
     commit_msg_filename: Option<PathBuf>,
 ) -> Result<Vec<String>> {
     if hook_stage.is_some_and(|stage| !stage.operate_on_files()) {
@@ -235,7 +363,7 @@ async fn filenames_for_args(
         return Ok(files);
     }
 
-    if !files.is_empty() {
+    if explicit_files || !files.is_empty() {
         let files: Vec<_> = files
             .into_iter()
             .map(|f| f.to_string_lossy().to_string())
@@ -243,17 +371,23 @@ async fn filenames_for_args(
         debug!("Files passed as arguments: {}", files.len());
         return Ok(files);
     }
-    if all_files {
+    let mut files = if all_files {
         let files = git::get_all_files().await?;
         debug!("All files in the repo: {}", files.len());
-        return Ok(files);
-    }
-    if git::is_in_merge_conflict().await? {
+        files
+    } else if git::is_in_merge_conflict()? {
         let files = git::get_conflicted_files().await?;
         debug!("Conflicted files: {}", files.len());
         return Ok(files);
+    } else {
+        let files = git::get_staged_files().await?;
+        debug!("Staged files: {}", files.len());
+        files
+    };
+    if include_untracked {
+        let untracked = git::get_untracked_files().await?;
+        debug!("Untracked files: {}", untracked.len());
+        files.extend(untracked);
     }
-    let files = git::get_staged_files().await?;
-    debug!("Staged files: {}", files.len());
     Ok(files)
 }
@@ -0,0 +1,101 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::cli::run::run::{HookOutcome, Summary};
+use crate::client;
+use crate::env_vars::EnvVars;
+use crate::printer::Printer;
+
+/// Whether we are running in a GitHub Actions workflow.
+pub(crate) fn is_github_actions() -> bool {
+    client::var_os(EnvVars::GITHUB_ACTIONS).is_some_and(|value| value == "true")
+}
+
+/// Escape the message of a workflow command.
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property value of a workflow command.
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Print an `::error` workflow command for each failed hook,
+/// annotating the files it modified if any.
+pub(super) fn annotate(summary: &Summary, printer: Printer) -> Result<()> {
+    for hook in &summary.hooks {
+        if hook.status != HookOutcome::Failed {
+            continue;
+        }
+
+        let title = escape_property(&hook.name);
+        if hook.files_modified.is_empty() {
+            let message = match hook.exit_code {
+                Some(code) => format!("Hook `{}` failed with exit code {code}", hook.id),
+                None => format!("Hook `{}` timed out", hook.id),
+            };
+            writeln!(
+                printer.failures(),
+                "::error title={title}::{}",
+                escape_data(&message)
+            )?;
+        } else {
+            for file in &hook.files_modified {
+                writeln!(
+                    printer.failures(),
+                    "::error file={},title={title}::{}",
+                    escape_property(file),
+                    escape_data(&format!("File was modified by hook `{}`", hook.id))
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Append a Markdown table of the hook results to the job summary.
+pub(super) fn write_step_summary(summary: &Summary) -> Result<()> {
+    let Some(path) = client::var_os(EnvVars::GITHUB_STEP_SUMMARY) else {
+        return Ok(());
+    };
+
+    let mut markdown = String::new();
+    writeln!(markdown, "### prefligit")?;
+    writeln!(markdown)?;
+    writeln!(
+        markdown,
+        "{} passed, {} failed, {} skipped",
+        summary.passed, summary.failed, summary.skipped
+    )?;
+    writeln!(markdown)?;
+    writeln!(markdown, "| Hook | Status | Duration | Files modified |")?;
+    writeln!(markdown, "| --- | --- | --- | --- |")?;
+    for hook in &summary.hooks {
+        let status = match hook.status {
+            HookOutcome::Passed => ":white_check_mark: Passed",
+            HookOutcome::Failed => ":x: Failed",
+            HookOutcome::Skipped => ":fast_forward: Skipped",
+        };
+        let duration = hook
+            .duration
+            .map(|duration| format!("{duration:.2}s"))
+            .unwrap_or_default();
+        writeln!(
+            markdown,
+            "| {} | {status} | {duration} | {} |",
+            hook.name.replace('|', "\\|"),
+            hook.files_modified.len()
+        )?;
+    }
+
+    let mut file = fs_err::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    std::io::Write::write_all(&mut file, markdown.as_bytes())?;
+    Ok(())
+}
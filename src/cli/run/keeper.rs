@@ -3,13 +3,14 @@ use std::process::Command;
 use std::sync::Mutex;
 
 use anstream::eprintln;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use owo_colors::OwoColorize;
+use tempfile::TempDir;
 use tracing::{error, trace};
 
 use crate::cleanup::add_cleanup;
 use crate::fs::Simplified;
-use crate::git::{self, git_cmd, GIT};
+use crate::git::{self, git_cmd, WorkTree, GIT};
 use crate::store::Store;
 
 static RESTORE_WORKTREE: Mutex<Option<WorkTreeKeeper>> = Mutex::new(None);
@@ -145,14 +146,24 @@ impl WorkingTreeKeeper {
     }
 
     fn git_apply(patch: &Path) -> Result<()> {
-        let status = Command::new(GIT.as_ref()?)
-            .arg("apply")
-            .arg("--whitespace=nowarn")
-            .arg(patch)
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .status()?;
-        if status.success() {
+        let apply = |autocrlf: bool| -> Result<bool> {
+            let mut cmd = Command::new(GIT.as_ref()?);
+            if !autocrlf {
+                cmd.arg("-c").arg("core.autocrlf=false");
+            }
+            let status = cmd
+                .arg("apply")
+                .arg("--whitespace=nowarn")
+                .arg(patch)
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()?;
+            Ok(status.success())
+        };
+
+        // With `core.autocrlf` enabled, git may convert line endings of the patched files
+        // and fail to apply the patch, retry without the conversion.
+        if apply(true)? || apply(false)? {
             Ok(())
         } else {
             Err(anyhow::anyhow!("Failed to apply the patch"))
@@ -173,7 +184,12 @@ impl WorkingTreeKeeper {
             );
 
             Self::checkout_working_tree()?;
-            Self::git_apply(patch)?;
+            Self::git_apply(patch).with_context(|| {
+                format!(
+                    "Unstaged changes are kept in `{}`, apply them with `git apply`",
+                    patch.user_display()
+                )
+            })?;
         };
 
         eprintln!(
@@ -225,7 +241,7 @@ impl WorkTreeKeeper {
     pub async fn clean(store: &Store) -> Result<RestoreGuard> {
         let cleaner = Self {
             intent_to_add: Some(IntentToAddKeeper::clean().await?),
-            working_tree: Some(WorkingTreeKeeper::clean(store.path()).await?),
+            working_tree: Some(WorkingTreeKeeper::clean(&store.patches_path()).await?),
         };
 
         // Set to the global for the cleanup hook.
@@ -247,3 +263,46 @@ impl WorkTreeKeeper {
         self.working_tree.take();
     }
 }
+
+/// A temporary checkout of the staged contents of all files.
+///
+/// Hooks run in the checkout, which git treats as the working tree for them, so they check
+/// files exactly as they will be committed.
+/// Changes made by hooks to the checkout are discarded when dropped.
+pub struct IndexTree {
+    _dir: TempDir,
+    work_tree: WorkTree,
+}
+
+impl IndexTree {
+    pub async fn checkout(store: &Store) -> Result<Self> {
+        let git_dir = std::path::absolute(git::get_git_dir()?)?;
+        let dir = tempfile::Builder::new()
+            .prefix("index-")
+            .tempdir_in(store.path())?;
+
+        git_cmd("git checkout-index")?
+            .arg("checkout-index")
+            .arg("--all")
+            .arg(format!("--prefix={}/", dir.path().display()))
+            .check(true)
+            .output()
+            .await?;
+        trace!("Checked out index to `{}`", dir.path().display());
+
+        // Make sure the checkout is removed when ctrl-c is pressed.
+        let path = dir.path().to_path_buf();
+        add_cleanup(move || {
+            let _ = fs_err::remove_dir_all(&path);
+        });
+
+        Ok(Self {
+            work_tree: WorkTree::new(git_dir, dir.path().to_path_buf()),
+            _dir: dir,
+        })
+    }
+
+    pub fn work_tree(&self) -> &WorkTree {
+        &self.work_tree
+    }
+}
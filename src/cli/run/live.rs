@@ -0,0 +1,114 @@
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::time::Duration;
+
+use anyhow::Result;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use owo_colors::OwoColorize;
+
+use crate::cli::run::run::HookOutcome;
+use crate::client;
+use crate::env_vars::EnvVars;
+use crate::hook::Hook;
+use crate::printer::Printer;
+
+/// A live display of running hooks, with a spinner and the elapsed time of each running hook.
+///
+/// Hooks that pass without output are collapsed into a count, the output of other hooks is
+/// printed above the display as they finish.
+pub(super) struct LiveDisplay {
+    printer: Printer,
+    multi: MultiProgress,
+    bars: Vec<ProgressBar>,
+    collapsed: ProgressBar,
+    passed: usize,
+    skipped: usize,
+}
+
+impl LiveDisplay {
+    /// Create a live display for the hooks, or `None` if the output is not an interactive
+    /// terminal, in which case the line-based output should be used.
+    pub(super) fn new(hooks: &[Hook], printer: Printer) -> Option<Self> {
+        if printer != Printer::Default
+            || !std::io::stdout().is_terminal()
+            || !std::io::stderr().is_terminal()
+            || client::var_os(EnvVars::CI).is_some()
+            // Streamed output would be interleaved with the display.
+            || hooks.iter().any(|hook| hook.stream)
+        {
+            return None;
+        }
+
+        let multi = MultiProgress::with_draw_target(printer.target());
+        let bars = hooks
+            .iter()
+            .map(|hook| {
+                let bar = multi.add(ProgressBar::with_draw_target(None, printer.target()));
+                bar.set_style(ProgressStyle::with_template("  {msg:.dim}").unwrap());
+                bar.set_message(hook.name.clone());
+                bar
+            })
+            .collect();
+        let collapsed = multi.add(ProgressBar::with_draw_target(None, printer.target()));
+        collapsed.set_style(ProgressStyle::with_template("{msg:.dim}").unwrap());
+
+        Some(Self {
+            printer,
+            multi,
+            bars,
+            collapsed,
+            passed: 0,
+            skipped: 0,
+        })
+    }
+
+    /// Show a spinner and the elapsed time for a hook that started running.
+    pub(super) fn start(&self, idx: usize) {
+        let bar = &self.bars[idx];
+        bar.set_style(
+            ProgressStyle::with_template("{spinner:.cyan} {msg} {elapsed:.dim}")
+                .unwrap()
+                .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏", " "]),
+        );
+        bar.reset_elapsed();
+        bar.enable_steady_tick(Duration::from_millis(100));
+    }
+
+    /// Remove a finished hook from the display and print its output, unless it passed
+    /// or was skipped without anything to show besides its status line.
+    pub(super) fn finish(&mut self, idx: usize, outcome: HookOutcome, output: &str) -> Result<()> {
+        self.bars[idx].finish_and_clear();
+        match outcome {
+            HookOutcome::Passed => self.passed += 1,
+            HookOutcome::Skipped => self.skipped += 1,
+            HookOutcome::Failed => {}
+        }
+        self.collapsed
+            .set_message(format!("{} passed, {} skipped", self.passed, self.skipped));
+
+        if outcome != HookOutcome::Failed && output.lines().count() <= 1 {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        self.multi.suspend(|| {
+            result = write!(self.printer.stdout(), "{output}");
+        });
+        Ok(result?)
+    }
+
+    /// Remove the display, leaving a line with the number of collapsed hooks.
+    pub(super) fn clear(self) -> Result<()> {
+        for bar in &self.bars {
+            bar.finish_and_clear();
+        }
+        self.collapsed.finish_and_clear();
+        if self.passed + self.skipped > 0 {
+            writeln!(
+                self.printer.stdout(),
+                "{}",
+                format!("{} passed, {} skipped", self.passed, self.skipped).green()
+            )?;
+        }
+        Ok(())
+    }
+}
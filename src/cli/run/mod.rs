@@ -1,7 +1,13 @@
-pub use filter::{get_filenames, FileFilter, FileOptions};
-pub(crate) use run::{install_hooks, run};
+pub use filter::{get_filenames, FileFilter, FileOptions, FilenameFilter};
+pub(crate) use github::is_github_actions;
+pub(crate) use run::{install_hooks, run, LoadedProject, RunOptions};
 
+mod cache;
 mod filter;
+mod github;
 mod keeper;
+mod live;
+mod reviewdog;
 #[allow(clippy::module_inception)]
 mod run;
+mod workspace;
@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use fancy_regex::Regex;
+use serde::Serialize;
+
+use crate::cli::run::run::{HookOutcome, Summary};
+
+/// Matches `file:line:column: message` and `file:line: message`, as printed by most linters.
+const DEFAULT_OUTPUT_PATTERN: &str =
+    r"^(?P<file>[^:\s][^:]*):(?P<line>\d+):(?:(?P<column>\d+):)?\s*(?P<message>.+)$";
+
+/// A diagnostic result set in reviewdog's rdjson format.
+///
+/// See <https://github.com/reviewdog/reviewdog/tree/master/proto/rdf>.
+#[derive(Debug, Serialize)]
+struct DiagnosticResult {
+    source: Source,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Serialize)]
+struct Source {
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    message: String,
+    location: Location,
+    severity: &'static str,
+    source: Source,
+}
+
+#[derive(Debug, Serialize)]
+struct Location {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<Range>,
+}
+
+#[derive(Debug, Serialize)]
+struct Range {
+    start: Position,
+}
+
+#[derive(Debug, Serialize)]
+struct Position {
+    line: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<u32>,
+}
+
+/// Convert the output of failed hooks into reviewdog diagnostics.
+///
+/// Each line of the output matching the hook's `output_pattern` becomes a diagnostic,
+/// and each file modified by the hook becomes a diagnostic without a range.
+pub(super) fn rdjson_report(summary: &Summary) -> Result<String> {
+    let mut diagnostics = Vec::new();
+
+    for hook in &summary.hooks {
+        if hook.status != HookOutcome::Failed {
+            continue;
+        }
+
+        let pattern = hook
+            .output_pattern
+            .as_deref()
+            .unwrap_or(DEFAULT_OUTPUT_PATTERN);
+        let regex = Regex::new(&format!("(?m){pattern}"))
+            .with_context(|| format!("Invalid `output_pattern` of hook `{}`", hook.id))?;
+
+        for captures in regex.captures_iter(&hook.output) {
+            let captures = captures?;
+            let (Some(file), Some(message)) = (captures.name("file"), captures.name("message"))
+            else {
+                continue;
+            };
+            let number = |name: &str| {
+                captures
+                    .name(name)
+                    .and_then(|value| value.as_str().parse::<u32>().ok())
+            };
+            let range = number("line").map(|line| Range {
+                start: Position {
+                    line,
+                    column: number("column"),
+                },
+            });
+
+            diagnostics.push(Diagnostic {
+                message: message.as_str().trim_end().to_string(),
+                location: Location {
+                    path: file.as_str().to_string(),
+                    range,
+                },
+                severity: "ERROR",
+                source: Source {
+                    name: hook.id.clone(),
+                },
+            });
+        }
+
+        for file in &hook.files_modified {
+            diagnostics.push(Diagnostic {
+                message: format!("File was modified by hook `{}`", hook.id),
+                location: Location {
+                    path: file.clone(),
+                    range: None,
+                },
+                severity: "ERROR",
+                source: Source {
+                    name: hook.id.clone(),
+                },
+            });
+        }
+    }
+
+    let result = DiagnosticResult {
+        source: Source {
+            name: "prefligit".to_string(),
+        },
+        diagnostics,
+    };
+    Ok(serde_json::to_string_pretty(&result)? + "\n")
+}
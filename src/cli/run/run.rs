@@ -1,95 +1,371 @@
+use std::borrow::Cow;
 use std::cmp::max;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write as _;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anstream::ColorChoice;
-use anyhow::Result;
+use anyhow::{Context, Result};
+use futures::stream::FuturesUnordered;
 use futures::StreamExt;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use inquire::{InquireError, MultiSelect};
 use itertools::Itertools;
 use owo_colors::{OwoColorize, Style};
 use rand::prelude::{SliceRandom, StdRng};
 use rand::SeedableRng;
 use tracing::{debug, trace};
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::cli::reporter::{HookInitReporter, HookInstallReporter};
-use crate::cli::run::keeper::WorkTreeKeeper;
-use crate::cli::run::{get_filenames, FileFilter, FileOptions};
-use crate::cli::{ExitStatus, RunExtraArgs};
-use crate::config::Stage;
+use crate::cli::run::cache::RunCache;
+use crate::cli::run::github;
+use crate::cli::run::keeper::{IndexTree, WorkTreeKeeper};
+use crate::cli::run::live::LiveDisplay;
+use crate::cli::run::reviewdog;
+use crate::cli::run::workspace::{self, in_workspace, WorkspaceGuard};
+use crate::cli::run::{get_filenames, is_github_actions, FileFilter, FileOptions};
+use crate::cli::{ExitStatus, OutputFormat, RunArgs, RunExtraArgs};
+use crate::client;
+use crate::config::{Config, Language, Stage};
 use crate::env_vars::EnvVars;
 use crate::fs::Simplified;
 use crate::git;
-use crate::git::{get_diff, git_cmd};
+use crate::git::{git_cmd, WorkTree, WorktreeChanges};
 use crate::hook::{Hook, Project};
-use crate::printer::Printer;
-use crate::store::Store;
-
-#[allow(clippy::too_many_arguments)]
-pub(crate) async fn run(
-    config: Option<PathBuf>,
-    hook_id: Option<String>,
-    hook_stage: Option<Stage>,
-    from_ref: Option<String>,
-    to_ref: Option<String>,
-    all_files: bool,
-    files: Vec<PathBuf>,
-    show_diff_on_failure: bool,
-    extra_args: RunExtraArgs,
-    verbose: bool,
-    printer: Printer,
-) -> Result<ExitStatus> {
+use crate::printer::{Printer, Stdout};
+use crate::remote_cache::{self, RemoteCache};
+use crate::retry;
+use crate::run::{concurrency, set_jobs};
+use crate::store::{HookRunRecord, RunRecord, Store};
+use crate::user_config::UserConfig;
+use crate::warn_user;
+
+/// A project with its hooks initialized, kept by the daemon across runs.
+#[derive(Debug)]
+pub(crate) struct LoadedProject {
+    pub(crate) project: Project,
+    pub(crate) hooks: Vec<Hook>,
+}
+
+/// The options of a run, from the `run` command or the callers running hooks on its behalf.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RunOptions {
+    pub(crate) configs: Vec<PathBuf>,
+    pub(crate) hook_ids: Vec<String>,
+    pub(crate) skips: Vec<String>,
+    pub(crate) profiles: Vec<String>,
+    pub(crate) languages: Vec<Language>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) hook_stage: Option<Stage>,
+    pub(crate) from_ref: Option<String>,
+    pub(crate) to_ref: Option<String>,
+    pub(crate) all_files: bool,
+    pub(crate) files: Vec<PathBuf>,
+    /// Run on `files` only, even if there are none, like an empty `--files-from` list.
+    pub(crate) explicit_files: bool,
+    pub(crate) directories: Vec<PathBuf>,
+    pub(crate) include_untracked: bool,
+    pub(crate) show_diff_on_failure: bool,
+    pub(crate) show_diff: bool,
+    pub(crate) summary: bool,
+    pub(crate) stream: bool,
+    pub(crate) timings: bool,
+    pub(crate) live: bool,
+    pub(crate) interactive: bool,
+    pub(crate) workspace: bool,
+    pub(crate) output_format: OutputFormat,
+    pub(crate) output_file: Option<PathBuf>,
+    pub(crate) jobs: Option<usize>,
+    pub(crate) parallel: bool,
+    pub(crate) timeout: Option<u64>,
+    pub(crate) max_failures: Option<NonZeroUsize>,
+    pub(crate) autofix_stage: bool,
+    pub(crate) no_stash: bool,
+    pub(crate) from_index: bool,
+    /// The working tree hooks run in instead of the current directory, the checkout of the
+    /// index for the projects of a workspace.
+    pub(crate) work_tree: Option<WorkTree>,
+    /// The project of the first config with its hooks, already initialized.
+    pub(crate) loaded: Option<Rc<LoadedProject>>,
+    pub(crate) dry_run: bool,
+    pub(crate) no_cache: bool,
+    pub(crate) extra_args: RunExtraArgs,
+    pub(crate) verbose: bool,
+}
+
+impl From<RunArgs> for RunOptions {
+    fn from(args: RunArgs) -> Self {
+        Self {
+            hook_ids: args.hook_ids,
+            skips: args.skips,
+            profiles: args.profiles,
+            languages: args.languages,
+            tags: args.tags,
+            hook_stage: args.hook_stage,
+            from_ref: args.from_ref,
+            to_ref: args.to_ref,
+            all_files: args.all_files,
+            explicit_files: args.files_from.is_some(),
+            files: args.files,
+            directories: args.directories,
+            include_untracked: args.include_untracked,
+            show_diff_on_failure: args.show_diff_on_failure,
+            show_diff: args.diff,
+            summary: args.summary,
+            stream: args.stream,
+            timings: args.timings,
+            live: args.live,
+            interactive: args.interactive,
+            workspace: args.workspace,
+            output_format: if args.null {
+                OutputFormat::Null
+            } else {
+                args.output_format
+            },
+            output_file: args.output_file,
+            jobs: args.jobs,
+            parallel: args.parallel,
+            timeout: args.timeout,
+            max_failures: args.max_failures,
+            autofix_stage: args.autofix_stage,
+            no_stash: args.no_stash,
+            from_index: args.from_index,
+            dry_run: args.dry_run,
+            no_cache: args.no_cache,
+            extra_args: args.extra,
+            ..Self::default()
+        }
+    }
+}
+
+pub(crate) async fn run(options: RunOptions, printer: Printer) -> Result<ExitStatus> {
+    // Members of a workspace run on the files under their directory, stashed once for all.
+    let member_options = RunOptions {
+        configs: vec![],
+        from_ref: None,
+        to_ref: None,
+        all_files: false,
+        files: vec![],
+        explicit_files: true,
+        directories: vec![],
+        include_untracked: false,
+        workspace: false,
+        autofix_stage: false,
+        no_stash: true,
+        from_index: false,
+        loaded: None,
+        ..options.clone()
+    };
+    let RunOptions {
+        configs,
+        hook_ids,
+        skips,
+        profiles,
+        languages,
+        tags,
+        hook_stage,
+        from_ref,
+        to_ref,
+        all_files,
+        files,
+        explicit_files,
+        directories,
+        include_untracked,
+        show_diff_on_failure,
+        show_diff,
+        summary,
+        stream,
+        timings,
+        live,
+        interactive,
+        workspace,
+        output_format,
+        output_file,
+        jobs,
+        parallel,
+        timeout,
+        max_failures,
+        autofix_stage,
+        no_stash,
+        from_index,
+        work_tree,
+        loaded,
+        dry_run,
+        no_cache,
+        extra_args,
+        verbose,
+    } = options;
+
     // Prevent recursive post-checkout hooks.
     if matches!(hook_stage, Some(Stage::PostCheckout))
-        && std::env::var_os(EnvVars::_PRE_COMMIT_SKIP_POST_CHECKOUT).is_some()
+        && client::var_os(EnvVars::_PRE_COMMIT_SKIP_POST_CHECKOUT).is_some()
     {
         return Ok(ExitStatus::Success);
     }
 
-    let should_stash = !all_files && files.is_empty();
+    let should_stash = !all_files && !explicit_files && files.is_empty() && !dry_run;
 
     // Check if we have unresolved merge conflict files and fail fast.
-    if should_stash && git::has_unmerged_paths().await? {
-        writeln!(
-            printer.stderr(),
-            "You have unmerged paths. Resolve them before running prefligit."
-        )?;
-        return Ok(ExitStatus::Failure);
+    if should_stash {
+        let unmerged = git::get_unmerged_paths().await?;
+        if !unmerged.is_empty() {
+            writeln!(
+                printer.stderr(),
+                "You have unmerged paths. Resolve them before running prefligit."
+            )?;
+            for path in unmerged {
+                writeln!(printer.stderr(), "  {}", path.cyan())?;
+            }
+            return Ok(ExitStatus::Failure);
+        }
     }
 
-    let config_file = Project::find_config_file(config)?;
-    if should_stash && config_not_staged(&config_file).await? {
-        writeln!(
-            printer.stderr(),
-            "Your pre-commit configuration is unstaged.\n`git add {}` to fix this.",
-            &config_file.user_display()
-        )?;
-        return Ok(ExitStatus::Failure);
+    let mut configs = configs.into_iter();
+    let config_file = Project::find_config_file(configs.next())?;
+    let extra_config_files = configs
+        .map(|config| Project::find_config_file(Some(config)))
+        .collect::<Result<Vec<_>, _>>()?;
+    for file in std::iter::once(&config_file).chain(&extra_config_files) {
+        if should_stash && config_not_staged(file).await? {
+            writeln!(
+                printer.stderr(),
+                "Your pre-commit configuration is unstaged.\n`git add {}` to fix this.",
+                &file.user_display()
+            )?;
+            return Ok(ExitStatus::Failure);
+        }
     }
 
     // Set env vars for hooks.
-    let env_vars = fill_envs(from_ref.as_ref(), to_ref.as_ref(), &extra_args);
+    let mut env_vars = fill_envs(from_ref.as_ref(), to_ref.as_ref(), &extra_args);
+
+    let mut project = match &loaded {
+        Some(loaded) => loaded.project.clone(),
+        None => Project::new(config_file)?,
+    };
+    for file in &extra_config_files {
+        project.merge_config_file(file)?;
+    }
+
+    if (workspace || project.config().workspace.unwrap_or(false)) && !in_workspace() {
+        let members = workspace::discover(project.config_file()).await?;
+        for member in &members[1..] {
+            if should_stash && config_not_staged(&member.config).await? {
+                writeln!(
+                    printer.stderr(),
+                    "Your pre-commit configuration is unstaged.\n`git add {}` to fix this.",
+                    &member.config.user_display()
+                )?;
+                return Ok(ExitStatus::Failure);
+            }
+        }
+
+        // Hooks of stages that don't run on the changed files run in every project.
+        let scoped = hook_stage.is_none_or(|stage| {
+            stage.operate_on_files() && !matches!(stage, Stage::PrepareCommitMsg | Stage::CommitMsg)
+        });
+        let mut extra_args = extra_args;
+        if let Some(filename) = &mut extra_args.commit_msg_filename {
+            *filename = std::path::absolute(&*filename)?;
+        }
+
+        // Stash once for all projects, which then run on explicit files.
+        let store = Store::from_settings()?.init()?;
+        let mut _guard = None;
+        let mut index_tree = None;
+        if from_index {
+            index_tree = Some(IndexTree::checkout(&store).await?);
+        } else if should_stash && !no_stash && project.config().stash.unwrap_or(true) {
+            _guard = Some(WorkTreeKeeper::clean(&store).await?);
+        }
+
+        let filenames = get_filenames(FileOptions {
+            hook_stage,
+            from_ref,
+            to_ref,
+            all_files,
+            files,
+            explicit_files,
+            directories,
+            include_untracked,
+            commit_msg_filename: extra_args.commit_msg_filename.clone(),
+        })
+        .await?;
+
+        let mut status = ExitStatus::Success;
+        for (member, files) in members
+            .iter()
+            .zip(workspace::scope_files(&members, filenames))
+        {
+            if scoped && files.is_empty() {
+                continue;
+            }
+            writeln!(
+                printer.stdout(),
+                "{}",
+                format!("Running hooks for `{}`:", member.display()).bold()
+            )?;
 
-    let mut project = Project::new(config_file)?;
+            let _workspace = WorkspaceGuard::enter(&member.dir)?;
+            let member_status = Box::pin(run(
+                RunOptions {
+                    configs: vec![member.config.clone()],
+                    files,
+                    extra_args: extra_args.clone(),
+                    work_tree: index_tree
+                        .as_ref()
+                        .map(|tree| tree.work_tree().subdir(&member.dir)),
+                    ..member_options.clone()
+                },
+                printer,
+            ))
+            .await?;
+            if !matches!(member_status, ExitStatus::Success) {
+                status = member_status;
+            }
+        }
+        return Ok(status);
+    }
+    if let Some(jobs) = jobs.or(project.config().jobs) {
+        set_jobs(jobs);
+    }
     let store = Store::from_settings()?.init()?;
 
     let reporter = HookInitReporter::from(printer);
 
-    let lock = store.lock_async().await?;
-    let hooks = project.init_hooks(&store, Some(&reporter)).await?;
+    let hooks = if let Some(loaded) = loaded.filter(|_| extra_config_files.is_empty()) {
+        loaded.hooks.clone()
+    } else {
+        store.mark_config_used(project.config_file(), project.config())?;
+        project.init_hooks(&store, Some(&reporter)).await?
+    };
+
+    for hook in &hooks {
+        for dep in &hook.depends_on {
+            if !hooks.iter().any(|h| h.id == *dep || h.alias == *dep) {
+                anyhow::bail!("Hook `{}` depends on unknown hook `{dep}`", hook.id);
+            }
+        }
+    }
 
     let hooks: Vec<_> = hooks
         .into_iter()
+        .filter(|h| hook_ids.is_empty() || hook_ids.iter().any(|id| &h.id == id || &h.alias == id))
+        // Hooks selected by id run regardless of the selected profiles.
         .filter(|h| {
-            if let Some(ref hook) = hook_id {
-                &h.id == hook || &h.alias == hook
-            } else {
-                true
-            }
+            !hook_ids.is_empty()
+                || h.profiles.is_empty()
+                || h.profiles.iter().any(|p| profiles.contains(p))
         })
+        .filter(|h| languages.is_empty() || languages.contains(&h.language))
+        .filter(|h| tags.is_empty() || h.tags.iter().any(|tag| tags.contains(tag)))
         .filter(|h| {
             if let Some(stage) = hook_stage {
                 h.stages.contains(&stage)
@@ -97,30 +373,59 @@ pub(crate) async fn run(
                 true
             }
         })
+        .map(|mut h| {
+            if h.timeout.is_none() {
+                h.timeout = timeout.map(Duration::from_secs);
+            }
+            h.stream |= stream;
+            h.warn_slow = project.config().warn_slow_hooks;
+            h
+        })
         .collect();
 
-    if hooks.is_empty() && hook_id.is_some() {
-        if let Some(hook_stage) = hook_stage {
-            writeln!(
-                printer.stderr(),
-                "No hook found for id `{}` and stage `{}`",
-                hook_id.unwrap().cyan(),
-                hook_stage.cyan()
-            )?;
-        } else {
-            writeln!(
-                printer.stderr(),
-                "No hook found for id `{}`",
-                hook_id.unwrap().cyan()
-            )?;
+    let missing: Vec<_> = hook_ids
+        .iter()
+        .filter(|&id| !hooks.iter().any(|h| h.id == *id || h.alias == *id))
+        .collect();
+    if !missing.is_empty() {
+        for hook_id in missing {
+            if let Some(hook_stage) = hook_stage {
+                writeln!(
+                    printer.stderr(),
+                    "No hook found for id `{}` and stage `{}`",
+                    hook_id.cyan(),
+                    hook_stage.cyan()
+                )?;
+            } else {
+                writeln!(
+                    printer.stderr(),
+                    "No hook found for id `{}`",
+                    hook_id.cyan()
+                )?;
+            }
         }
         return Ok(ExitStatus::Failure);
     }
 
-    let skips = get_skips();
+    let hooks = if interactive {
+        let hooks = pick_hooks(hooks)?;
+        if hooks.is_empty() {
+            writeln!(printer.stderr(), "No hooks selected")?;
+            return Ok(ExitStatus::Success);
+        }
+        hooks
+    } else {
+        hooks
+    };
+
+    let skips = Skips::new([
+        (SkipSource::Env, get_skips()),
+        (SkipSource::Flag, skips),
+        (SkipSource::Ci, get_ci_skips(project.config())),
+    ])?;
     let to_run = hooks
         .iter()
-        .filter(|h| !skips.contains(&h.id) && !skips.contains(&h.alias))
+        .filter(|h| skips.contains(h).is_none())
         .cloned()
         .collect::<Vec<_>>();
 
@@ -128,15 +433,40 @@ pub(crate) async fn run(
         "Hooks going to run: {:?}",
         to_run.iter().map(|h| &h.id).collect::<Vec<_>>()
     );
-    let reporter = HookInstallReporter::from(printer);
-    install_hooks(&to_run, &reporter).await?;
-    drop(lock);
+    if !dry_run {
+        let reporter = HookInstallReporter::from(printer);
+        install_hooks(&to_run, &store, &reporter).await?;
+    }
 
     // Clear any unstaged changes from the git working directory.
     let mut _guard = None;
-    if should_stash {
+    let mut index_tree = None;
+    if from_index {
+        index_tree = Some(IndexTree::checkout(&store).await?);
+    } else if should_stash && !no_stash && project.config().stash.unwrap_or(true) {
         _guard = Some(WorkTreeKeeper::clean(&store).await?);
     }
+    let work_tree = index_tree
+        .as_ref()
+        .map(IndexTree::work_tree)
+        .or(work_tree.as_ref());
+    let work_dir = work_tree.map(WorkTree::dir);
+
+    // Without stashing, changes made by hooks can't be told apart from unstaged changes.
+    let autofix_stage = autofix_stage || project.config().autofix_stage.unwrap_or(false);
+    let autofix_stage = if autofix_stage && _guard.is_none() {
+        if !dry_run {
+            warn_user!("Ignoring `autofix_stage` because unstaged changes are not stashed");
+        }
+        false
+    } else {
+        autofix_stage
+    };
+    let staged_files = if autofix_stage {
+        git::get_staged_files().await?
+    } else {
+        vec![]
+    };
 
     let filenames = get_filenames(FileOptions {
         hook_stage,
@@ -144,28 +474,248 @@ pub(crate) async fn run(
         to_ref,
         all_files,
         files,
+        explicit_files,
+        directories,
+        include_untracked,
         commit_msg_filename: extra_args.commit_msg_filename.clone(),
     })
     .await?;
 
-    let filter = FileFilter::new(
-        &filenames,
-        project.config().files.as_deref(),
-        project.config().exclude.as_deref(),
-    )?;
+    let cwd = std::env::current_dir()?;
+    let cached_headers = store
+        .file_headers(&cwd, &filenames)
+        .inspect_err(|err| debug!("Failed to read cached file headers: {err}"))
+        .unwrap_or_default();
+    let filter = FileFilter::new(&filenames, project.config(), work_dir)?
+        .with_cached_headers(cached_headers);
     trace!("Files after filtered: {}", filter.len());
 
-    run_hooks(
+    if dry_run {
+        return dry_run_hooks(
+            &hooks,
+            &skips,
+            &filter,
+            output_format == OutputFormat::Null,
+            printer,
+        );
+    }
+
+    // In GitHub Actions, failures are annotated and summarized from the report.
+    let github_actions = is_github_actions();
+    let show_diff_on_failure = show_diff_on_failure || github_actions;
+    let report = output_format != OutputFormat::Text || github_actions;
+
+    // Without an output file, the report replaces the usual output.
+    let hooks_printer = if output_format != OutputFormat::Text && output_file.is_none() {
+        Printer::Silent
+    } else {
+        printer
+    };
+
+    let cache = if project.config().cache.unwrap_or(false) && !no_cache {
+        RunCache::load(&store, &cwd)
+    } else {
+        RunCache::disabled()
+    };
+
+    // Git run by hooks uses the same working tree.
+    if let Some(work_tree) = work_tree {
+        env_vars.extend(work_tree.envs());
+    }
+
+    let started_at = std::time::SystemTime::now();
+    let start = std::time::Instant::now();
+    let summary = run_hooks(
         &hooks,
         &skips,
         &filter,
+        &cache,
         env_vars,
+        work_tree,
         project.config().fail_fast.unwrap_or(false),
+        max_failures,
+        parallel || project.config().parallel.unwrap_or(false),
+        autofix_stage,
         show_diff_on_failure,
+        show_diff,
+        summary,
+        report,
+        live,
         verbose,
-        printer,
+        hooks_printer,
     )
-    .await
+    .await?;
+
+    if let Err(err) = record_run(&store, &summary, started_at, start.elapsed()) {
+        debug!("Failed to record run statistics: {err}");
+    }
+    if let Err(err) = store.update_file_headers(&cwd, &filter.read_headers()) {
+        debug!("Failed to cache file headers: {err}");
+    }
+    cache.save(&store);
+    if let Some(max_size) = UserConfig::get().max_store_size {
+        prune_store(&store, max_size, project.config(), printer).await?;
+    }
+
+    if timings {
+        print_timings(&summary, hooks_printer)?;
+    }
+
+    if github_actions {
+        github::annotate(&summary, hooks_printer)?;
+        github::write_step_summary(&summary)?;
+    }
+
+    let report = match output_format {
+        OutputFormat::Json => Some(serde_json::to_string_pretty(&summary)? + "\n"),
+        OutputFormat::Tap => Some(tap_report(&summary)?),
+        OutputFormat::Rdjson => Some(reviewdog::rdjson_report(&summary)?),
+        OutputFormat::Null => Some(null_report(&summary)?),
+        OutputFormat::Text => None,
+    };
+    if let Some(report) = report {
+        if let Some(path) = output_file {
+            fs_err::write(path, report)?;
+        } else if output_format == OutputFormat::Null {
+            write_null_records(&report, printer)?;
+        } else {
+            write!(printer.stdout(), "{report}")?;
+        }
+    }
+
+    let status = if summary.success() {
+        ExitStatus::Success
+    } else {
+        ExitStatus::Failure
+    };
+
+    if autofix_stage && !stage_fixes(&staged_files, printer).await? {
+        return Ok(ExitStatus::Failure);
+    }
+
+    Ok(status)
+}
+
+/// Print the hooks that ran sorted by duration, with their share of the total hook time.
+fn print_timings(summary: &Summary, printer: Printer) -> Result<()> {
+    let mut hooks: Vec<_> = summary
+        .hooks
+        .iter()
+        .filter_map(|hook| Some((hook, hook.duration?)))
+        .collect();
+    if hooks.is_empty() {
+        return Ok(());
+    }
+    hooks.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    let total: f64 = hooks.iter().map(|(_, duration)| duration).sum();
+
+    writeln!(printer.stdout(), "{}", "Hook timings:".bold())?;
+    for (hook, duration) in hooks {
+        let percent = if total > 0.0 {
+            duration / total * 100.0
+        } else {
+            0.0
+        };
+        writeln!(
+            printer.stdout(),
+            "{:>9} {:>6} {}",
+            format!("{duration:.2}s"),
+            format!("{percent:.1}%"),
+            hook.name
+        )?;
+    }
+    Ok(())
+}
+
+/// Format the results of all hooks as a TAP version 13 report, with a YAML block
+/// describing each failed hook.
+fn tap_report(summary: &Summary) -> Result<String> {
+    #[derive(serde::Serialize)]
+    struct Diagnostic<'a> {
+        id: &'a str,
+        exit_code: Option<i32>,
+        files_modified: &'a [String],
+        output: &'a str,
+    }
+
+    let mut report = String::new();
+    writeln!(report, "TAP version 13")?;
+    writeln!(report, "1..{}", summary.hooks.len())?;
+    for (i, hook) in summary.hooks.iter().enumerate() {
+        let number = i + 1;
+        match hook.status {
+            HookOutcome::Passed => writeln!(report, "ok {number} - {}", hook.name)?,
+            HookOutcome::Skipped => writeln!(report, "ok {number} - {} # SKIP", hook.name)?,
+            HookOutcome::Failed => {
+                writeln!(report, "not ok {number} - {}", hook.name)?;
+                let diagnostic = serde_yaml::to_string(&Diagnostic {
+                    id: &hook.id,
+                    exit_code: hook.exit_code,
+                    files_modified: &hook.files_modified,
+                    output: hook.output.trim_end(),
+                })?;
+                writeln!(report, "  ---")?;
+                write!(report, "{}", textwrap::indent(&diagnostic, "  "))?;
+                writeln!(report, "  ...")?;
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Format the results of all hooks as NUL-terminated `<hook id>\t<status>` records.
+fn null_report(summary: &Summary) -> Result<String> {
+    let mut report = String::new();
+    for hook in &summary.hooks {
+        write!(report, "{}\t{}\0", hook.id, hook.status)?;
+    }
+    Ok(report)
+}
+
+/// Save the full output of a hook whose output is truncated, returning the file path.
+fn save_full_output(hook: &Hook, output: &[u8]) -> Result<PathBuf> {
+    let logs = Store::from_settings()?.logs_path();
+    fs_err::create_dir_all(&logs)?;
+    let path = logs.join(format!("{}.log", hook.id));
+    fs_err::write(&path, output)?;
+    Ok(path)
+}
+
+/// Stage the changes hooks made to the staged files.
+///
+/// Returns false if hooks modified files that were not staged.
+async fn stage_fixes(staged_files: &[String], printer: Printer) -> Result<bool> {
+    let (fixed, unexpected): (Vec<_>, Vec<_>) = git::get_unstaged_files()
+        .await?
+        .into_iter()
+        .partition(|file| staged_files.contains(file));
+
+    if !fixed.is_empty() {
+        git_cmd("git add")?
+            .arg("add")
+            .arg("--")
+            .args(&fixed)
+            .check(true)
+            .output()
+            .await?;
+        writeln!(printer.stderr(), "Staged fixes made by hooks:")?;
+        for file in &fixed {
+            writeln!(printer.stderr(), "  {}", file.cyan())?;
+        }
+    }
+
+    if !unexpected.is_empty() {
+        writeln!(
+            printer.stderr(),
+            "Hooks modified files that are not staged, review and stage them manually:"
+        )?;
+        for file in &unexpected {
+            writeln!(printer.stderr(), "  {}", file.cyan())?;
+        }
+        return Ok(false);
+    }
+
+    Ok(true)
 }
 
 async fn config_not_staged(config: &Path) -> Result<bool> {
@@ -237,7 +787,7 @@ fn fill_envs(
 }
 
 fn get_skips() -> Vec<String> {
-    match std::env::var_os(EnvVars::SKIP) {
+    match client::var_os(EnvVars::SKIP) {
         Some(s) if !s.is_empty() => s
             .to_string_lossy()
             .split(',')
@@ -248,9 +798,98 @@ fn get_skips() -> Vec<String> {
     }
 }
 
-async fn install_hook(hook: &Hook, env_dir: PathBuf) -> Result<()> {
+/// The hooks to skip from the `ci` settings of the config, when running in CI.
+fn get_ci_skips(config: &Config) -> Vec<String> {
+    if client::var_os(EnvVars::CI).is_none_or(|ci| ci.is_empty()) {
+        return vec![];
+    }
+    let skips = config
+        .ci
+        .as_ref()
+        .map(|ci| ci.skip.clone())
+        .unwrap_or_default();
+    if !skips.is_empty() {
+        debug!("Skipping hooks in CI: {}", skips.join(", "));
+    }
+    skips
+}
+
+/// Where a hook is skipped from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SkipSource {
+    /// The `SKIP` environment variable.
+    Env,
+    /// The `--skip` flag.
+    Flag,
+    /// The `ci.skip` config option, when running in CI.
+    Ci,
+}
+
+impl SkipSource {
+    fn postfix(self) -> &'static str {
+        match self {
+            Self::Env => "(skipped by SKIP)",
+            Self::Flag => "(skipped by --skip)",
+            Self::Ci => "(skipped by ci.skip)",
+        }
+    }
+}
+
+/// Hooks to skip, matched against the hook id or alias.
+///
+/// Each entry can be a glob pattern, e.g. `mypy*` or `*-lint`.
+struct Skips(Vec<(SkipSource, GlobSet)>);
+
+impl Skips {
+    fn new(sources: impl IntoIterator<Item = (SkipSource, Vec<String>)>) -> Result<Self> {
+        let mut skips = Vec::new();
+        for (source, patterns) in sources {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                let glob = Glob::new(&pattern)
+                    .with_context(|| format!("Invalid skip pattern `{pattern}`"))?;
+                builder.add(glob);
+            }
+            skips.push((source, builder.build()?));
+        }
+        Ok(Self(skips))
+    }
+
+    /// Where the hook is skipped from, or `None` if it isn't skipped.
+    fn contains(&self, hook: &Hook) -> Option<SkipSource> {
+        self.0.iter().find_map(|(source, globs)| {
+            (globs.is_match(&hook.id) || (!hook.alias.is_empty() && globs.is_match(&hook.alias)))
+                .then_some(*source)
+        })
+    }
+}
+
+async fn install_hook(
+    hook: &Hook,
+    env_dir: PathBuf,
+    store: &Store,
+    on_phase: &dyn Fn(&str),
+) -> Result<()> {
+    let _lock = Store::lock_entry(&env_dir).await?;
+    // Another process may have installed it while waiting for the lock.
+    if hook.installed().await {
+        return Ok(());
+    }
     debug!(%hook, target = %env_dir.display(), "Install environment");
 
+    // Environments are only cached when their inputs identify them.
+    let cache = RemoteCache::get().zip(remote_cache::env_key(hook));
+    if let Some((cache, key)) = &cache {
+        if env_dir.try_exists()? {
+            fs_err::remove_dir_all(&env_dir)?;
+        }
+        // The environment may not work here, like if its toolchain is missing.
+        if cache.restore(key, &env_dir).await && hook.installed().await {
+            store.insert_env(hook)?;
+            return Ok(());
+        }
+    }
+
     if env_dir.try_exists()? {
         debug!(
             env_dir = %env_dir.display(),
@@ -259,42 +898,71 @@ async fn install_hook(hook: &Hook, env_dir: PathBuf) -> Result<()> {
         fs_err::remove_dir_all(&env_dir)?;
     }
 
-    hook.language.install(hook).await?;
+    // Installs are not retried as a whole, their downloads are, and uv retries its own
+    // requests.
+    hook.language
+        .install(hook, store, on_phase)
+        .await
+        .with_context(|| format!("Failed to install the environment of hook `{}`", hook.id))?;
     hook.mark_installed()?;
+    store.insert_env(hook)?;
+
+    if let Some((cache, key)) = &cache {
+        cache.save(key, &env_dir).await;
+    }
 
     Ok(())
 }
 
-pub async fn install_hooks(hooks: &[Hook], reporter: &HookInstallReporter) -> Result<()> {
-    let to_install = hooks
+pub async fn install_hooks(
+    hooks: &[Hook],
+    store: &Store,
+    reporter: &HookInstallReporter,
+) -> Result<()> {
+    let mut to_install = Vec::new();
+    for (hook, env_dir) in hooks
         .iter()
-        .filter(|hook| !hook.installed())
         .filter_map(|hook| hook.environment_dir().map(|env_dir| (hook, env_dir)))
-        .unique_by(|(_, env_dir)| env_dir.clone());
+        .unique_by(|(_, env_dir)| env_dir.clone())
+    {
+        if !hook.installed().await {
+            to_install.push((hook, env_dir));
+        }
+    }
 
     let mut tasks = futures::stream::iter(to_install)
         .map(|(hook, env_dir)| async move {
             let progress = reporter.on_install_start(hook);
-            let result = install_hook(hook, env_dir).await;
+            let on_phase = |phase: &str| reporter.on_install_phase(progress, phase);
+            let result = install_hook(hook, env_dir, store, &on_phase).await;
             reporter.on_install_complete(progress);
 
             result
         })
-        .buffer_unordered(5);
+        .buffer_unordered(concurrency());
 
     while let Some(result) = tasks.next().await {
         result?;
     }
 
+    // Environments can be shared, record every repo using them, not only the one installing them.
+    for hook in hooks {
+        store.insert_env(hook)?;
+    }
+
     reporter.on_complete();
 
     Ok(())
 }
 
 const SKIPPED: &str = "Skipped";
+const PASSED: &str = "Passed";
 const NO_FILES: &str = "(no files to check)";
+const CACHED: &str = "(unchanged since passed)";
+const ENTRY_NOT_FOUND: &str = "(entry not found)";
 
 fn status_line(start: &str, cols: usize, end_msg: &str, end_color: Style, postfix: &str) -> String {
+    let start = truncate_name(start, cols - end_msg.len() - postfix.len() - 2);
     let dots = cols - start.width_cjk() - end_msg.len() - postfix.len() - 1;
     format!(
         "{}{}{}{}",
@@ -305,75 +973,663 @@ fn status_line(start: &str, cols: usize, end_msg: &str, end_color: Style, postfi
     )
 }
 
+/// Truncate a hook name to fit in `width` columns, ending with an ellipsis if truncated.
+fn truncate_name(name: &str, width: usize) -> Cow<'_, str> {
+    if name.width_cjk() <= width {
+        return Cow::Borrowed(name);
+    }
+
+    let ellipsis = '…'.width_cjk().unwrap_or(1);
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in name.chars() {
+        let w = c.width_cjk().unwrap_or(0);
+        if used + w + ellipsis > width {
+            break;
+        }
+        truncated.push(c);
+        used += w;
+    }
+    truncated.push('…');
+    Cow::Owned(truncated)
+}
+
+/// The width of the terminal, from `COLUMNS` or the terminal attached to stdout.
+fn terminal_width() -> Option<usize> {
+    if let Some(columns) = client::var_os(EnvVars::COLUMNS)
+        .and_then(|columns| columns.into_string().ok())
+        .and_then(|columns| columns.parse::<usize>().ok())
+        .filter(|&columns| columns > 0)
+    {
+        return Some(columns);
+    }
+    client::terminal_width()
+}
+
+/// The narrowest status line, enough for the longest status and a few characters of the name.
+const MIN_COLUMNS: usize = NO_FILES.len() + SKIPPED.len() + 8;
+
 fn calculate_columns(hooks: &[Hook]) -> usize {
     let name_len = hooks
         .iter()
         .map(|hook| hook.name.width_cjk())
         .max()
         .unwrap_or(0);
-    max(80, name_len + 3 + NO_FILES.len() + 1 + SKIPPED.len())
+    let columns = max(80, name_len + 3 + NO_FILES.len() + 1 + SKIPPED.len());
+
+    // Don't exceed the terminal width, long hook names are truncated instead.
+    match terminal_width() {
+        Some(width) => columns.min(width).max(MIN_COLUMNS),
+        None => columns,
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum HookOutcome {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+impl std::fmt::Display for HookOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Passed => write!(f, "passed"),
+            Self::Failed => write!(f, "failed"),
+            Self::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+/// The result of a single hook, as written to the `--output-format json` report.
+#[derive(Debug, serde::Serialize)]
+pub(super) struct HookReport {
+    pub(super) id: String,
+    pub(super) name: String,
+    pub(super) status: HookOutcome,
+    /// The duration of the hook in seconds, `None` if it was skipped.
+    pub(super) duration: Option<f64>,
+    /// The exit code of the hook, `None` if it was skipped or timed out.
+    pub(super) exit_code: Option<i32>,
+    /// The files passed to the hook.
+    pub(super) files: Vec<String>,
+    pub(super) output: String,
+    /// The files modified by the hook, only tracked when a report is requested.
+    pub(super) files_modified: Vec<String>,
+    #[serde(skip)]
+    pub(super) output_pattern: Option<String>,
+}
+
+impl HookReport {
+    fn skipped(hook: &Hook) -> Self {
+        Self {
+            id: hook.id.clone(),
+            name: hook.name.clone(),
+            status: HookOutcome::Skipped,
+            duration: None,
+            exit_code: None,
+            files: vec![],
+            output: String::new(),
+            files_modified: vec![],
+            output_pattern: hook.output_pattern.clone(),
+        }
+    }
+
+    /// A hook skipped because it passed before with the same inputs.
+    fn cached(hook: &Hook, filenames: &[&String]) -> Self {
+        Self {
+            status: HookOutcome::Passed,
+            files: filenames.iter().map(|&filename| filename.clone()).collect(),
+            ..Self::skipped(hook)
+        }
+    }
+}
+
+/// The number of hooks passed, failed, and skipped in a run.
+#[derive(Debug, Default, serde::Serialize)]
+pub(super) struct Summary {
+    pub(super) passed: usize,
+    pub(super) failed: usize,
+    pub(super) skipped: usize,
+    pub(super) hooks: Vec<HookReport>,
+}
+
+impl Summary {
+    fn add(&mut self, report: HookReport) {
+        match report.status {
+            HookOutcome::Passed => self.passed += 1,
+            HookOutcome::Failed => self.failed += 1,
+            HookOutcome::Skipped => self.skipped += 1,
+        }
+        self.hooks.push(report);
+    }
+
+    fn success(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+/// Let the user pick the hooks to run from a list with fuzzy filtering.
+fn pick_hooks(hooks: Vec<Hook>) -> Result<Vec<Hook>> {
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("`--interactive` requires a terminal");
+    }
+
+    let options = hooks
+        .iter()
+        .map(|hook| {
+            if hook.name == hook.id {
+                hook.id.clone()
+            } else {
+                format!("{} ({})", hook.id, hook.name)
+            }
+        })
+        .collect();
+    let selected = match MultiSelect::new("Select hooks to run:", options).raw_prompt() {
+        Ok(selected) => selected,
+        Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => vec![],
+        Err(err) => return Err(err.into()),
+    };
+    let selected: HashSet<_> = selected.into_iter().map(|option| option.index).collect();
+
+    Ok(hooks
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| selected.contains(idx))
+        .map(|(_, hook)| hook)
+        .collect())
+}
+
+/// Remove the least recently used repos from the store when it's larger than `max_size` MiB.
+async fn prune_store(
+    store: &Store,
+    max_size: u64,
+    config: &Config,
+    printer: Printer,
+) -> Result<()> {
+    let evicted = match store
+        .prune_to_size(max_size.saturating_mul(1024 * 1024), config)
+        .await
+    {
+        Ok(evicted) => evicted,
+        Err(err) => {
+            warn_user!("Failed to prune the store: {err}");
+            return Ok(());
+        }
+    };
+    for repo in evicted {
+        // Linked repos have no rev.
+        let name = if repo.rev.is_empty() {
+            repo.repo
+        } else {
+            format!("{}@{}", repo.repo, repo.rev)
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let size = repo.size as f64 / (1024.0 * 1024.0);
+        writeln!(
+            printer.stderr(),
+            "Removed `{}` from the store to keep it under {max_size} MiB ({} environment(s), {size:.1} MiB)",
+            name.cyan(),
+            repo.envs,
+        )?;
+    }
+    Ok(())
+}
+
+/// Record the outcome of this run in the store, for `prefligit stats`.
+fn record_run(
+    store: &Store,
+    summary: &Summary,
+    started_at: std::time::SystemTime,
+    duration: Duration,
+) -> Result<()> {
+    let run = RunRecord {
+        started_at: started_at
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        duration: duration.as_secs_f64(),
+        success: summary.success(),
+        hooks: summary
+            .hooks
+            .iter()
+            .map(|hook| HookRunRecord {
+                hook_id: hook.id.clone(),
+                status: hook.status.to_string(),
+                duration: hook.duration,
+            })
+            .collect(),
+    };
+    store.record_run(&std::env::current_dir()?, &run)?;
+    Ok(())
 }
 
 /// Run all hooks.
-pub async fn run_hooks(
+#[allow(clippy::fn_params_excessive_bools)]
+async fn run_hooks(
     hooks: &[Hook],
-    skips: &[String],
+    skips: &Skips,
     filter: &FileFilter<'_>,
+    cache: &RunCache,
     env_vars: HashMap<&'static str, String>,
+    work_tree: Option<&WorkTree>,
     fail_fast: bool,
+    max_failures: Option<NonZeroUsize>,
+    parallel: bool,
+    autofix_stage: bool,
     show_diff_on_failure: bool,
+    show_diff: bool,
+    print_summary: bool,
+    report: bool,
+    live: bool,
     verbose: bool,
     printer: Printer,
-) -> Result<ExitStatus> {
+) -> Result<Summary> {
     let env_vars = Arc::new(env_vars);
 
     let columns = calculate_columns(hooks);
-    let mut success = true;
+    let mut live = if live {
+        LiveDisplay::new(hooks, printer)
+    } else {
+        None
+    };
 
-    let mut diff = get_diff().await?;
-    // hooks must run in serial
-    for hook in hooks {
-        let (hook_success, new_diff) = run_hook(
-            hook,
-            filter,
-            env_vars.clone(),
+    let start = std::time::Instant::now();
+    let tree = if print_summary {
+        Some(git::write_worktree_tree(work_tree).await?)
+    } else {
+        None
+    };
+
+    let summary = if parallel {
+        run_hooks_parallel(
+            hooks,
             skips,
-            diff,
+            filter,
+            cache,
+            env_vars,
+            work_tree,
+            fail_fast,
+            max_failures,
+            autofix_stage,
+            show_diff,
+            report,
             columns,
             verbose,
+            live.as_mut(),
             printer,
         )
-        .await?;
+        .await?
+    } else {
+        let mut summary = Summary::default();
+        let mut changes = WorktreeChanges::read(work_tree).await?;
+        // Repos with `fail_fast` that had a failing hook, their remaining hooks are not run.
+        let mut failed_repos = HashSet::new();
+        for (idx, hook) in hooks.iter().enumerate() {
+            if failed_repos.contains(&hook.repo_index) {
+                continue;
+            }
+            if let Some(live) = &live {
+                live.start(idx);
+            }
+            // In quiet mode, only the output of failed hooks is printed.
+            let mut output = String::new();
+            let mut stdout = printer.stdout();
+            let mut out: &mut dyn std::fmt::Write = if printer == Printer::Quiet || live.is_some() {
+                &mut output
+            } else {
+                &mut stdout
+            };
+            let (hook_report, new_changes) = run_hook(
+                hook,
+                filter,
+                cache,
+                env_vars.clone(),
+                work_tree,
+                skips,
+                changes,
+                autofix_stage,
+                show_diff,
+                report,
+                columns,
+                verbose,
+                &mut out,
+            )
+            .await?;
+            if let Some(live) = &mut live {
+                live.finish(idx, hook_report.status, &output)?;
+            } else if hook_report.status == HookOutcome::Failed {
+                write!(printer.failures(), "{output}")?;
+            }
+            if hook_report.status == HookOutcome::Failed && hook.repo_fail_fast {
+                failed_repos.insert(hook.repo_index);
+            }
 
-        success &= hook_success;
-        diff = new_diff;
-        if !success && (fail_fast || hook.fail_fast) {
-            break;
+            summary.add(hook_report);
+            changes = new_changes;
+            if !summary.success() && (fail_fast || hook.fail_fast)
+                || max_failures.is_some_and(|max| summary.failed >= max.get())
+            {
+                break;
+            }
         }
+        summary
+    };
+    if let Some(live) = live {
+        live.clear()?;
+    }
+    let success = summary.success();
+
+    if let Some(tree) = tree {
+        let new_tree = git::write_worktree_tree(work_tree).await?;
+        let modified = git::get_tree_changed_files(&tree, &new_tree).await?;
+        writeln!(
+            printer.stdout(),
+            "{} passed, {} failed, {} skipped, {} files modified in {:.2}s",
+            summary.passed,
+            summary.failed,
+            summary.skipped,
+            modified.len(),
+            start.elapsed().as_secs_f64()
+        )?;
     }
 
     if !success && show_diff_on_failure {
         writeln!(printer.stdout(), "All changes made by hooks:")?;
-        let color = match ColorChoice::global() {
-            ColorChoice::Auto => "--color=auto",
-            ColorChoice::Always | ColorChoice::AlwaysAnsi => "--color=always",
-            ColorChoice::Never => "--color=never",
-        };
-        git_cmd("git diff")?
-            .arg("--no-pager")
+        let mut cmd = git_cmd("git diff")?;
+        if let Some(work_tree) = work_tree {
+            work_tree.apply(&mut cmd);
+        }
+        cmd.arg("--no-pager")
             .arg("diff")
             .arg("--no-ext-diff")
-            .arg(color)
+            .arg(diff_color())
             .check(true)
             .spawn()?
             .wait()
             .await?;
     };
 
-    if success {
-        Ok(ExitStatus::Success)
-    } else {
-        Ok(ExitStatus::Failure)
+    Ok(summary)
+}
+
+/// Resolve the `depends_on` of each hook to the indices of the hooks it waits for.
+///
+/// Dependencies that are not selected to run are ignored.
+fn resolve_dependencies(hooks: &[Hook]) -> Result<Vec<Vec<usize>>> {
+    hooks
+        .iter()
+        .enumerate()
+        .map(|(idx, hook)| {
+            hook.depends_on
+                .iter()
+                .filter_map(|dep| {
+                    let pos = hooks.iter().position(|h| h.id == *dep || h.alias == *dep)?;
+                    if pos < idx {
+                        Some(Ok(pos))
+                    } else {
+                        Some(Err(anyhow::anyhow!(
+                            "Hook `{}` depends on `{dep}`, which must be defined before it",
+                            hook.id
+                        )))
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Run hooks concurrently, starting each hook once the hooks it depends on have finished.
+///
+/// Only `read_only` hooks run at the same time. A hook that may modify files waits for the
+/// running hooks, and the hooks after it wait for it, so no hook sees files change while it
+/// runs, or is blamed for the changes of another.
+///
+/// The output of each hook is buffered and printed in the order the hooks are defined.
+#[allow(clippy::fn_params_excessive_bools)]
+async fn run_hooks_parallel(
+    hooks: &[Hook],
+    skips: &Skips,
+    filter: &FileFilter<'_>,
+    cache: &RunCache,
+    env_vars: Arc<HashMap<&'static str, String>>,
+    work_tree: Option<&WorkTree>,
+    fail_fast: bool,
+    max_failures: Option<NonZeroUsize>,
+    autofix_stage: bool,
+    show_diff: bool,
+    report: bool,
+    columns: usize,
+    verbose: bool,
+    mut live: Option<&mut LiveDisplay>,
+    printer: Printer,
+) -> Result<Summary> {
+    let dependencies = resolve_dependencies(hooks)?;
+
+    let mut started = vec![false; hooks.len()];
+    let mut finished = vec![false; hooks.len()];
+    let mut outputs: Vec<Option<String>> = vec![None; hooks.len()];
+    let mut reports: Vec<Option<HookReport>> = hooks.iter().map(|_| None).collect();
+    let mut printed = 0;
+    let mut failures = 0;
+    let mut stopped = false;
+    // Repos with `fail_fast` that had a failing hook, their remaining hooks are not started.
+    let mut failed_repos = HashSet::new();
+    // The running hook that may modify files, no other hook runs with it.
+    let mut exclusive = None;
+    // Whether a hook will never start.
+    let aborted = |idx: usize, started: &[bool], failed_repos: &HashSet<usize>| {
+        !started[idx] && failed_repos.contains(&hooks[idx].repo_index)
+    };
+
+    let mut running = FuturesUnordered::new();
+    loop {
+        if !stopped {
+            for (idx, hook) in hooks.iter().enumerate() {
+                if running.len() >= concurrency() || exclusive.is_some() {
+                    break;
+                }
+                if started[idx]
+                    || failed_repos.contains(&hook.repo_index)
+                    || !dependencies[idx]
+                        .iter()
+                        .all(|&dep| finished[dep] || aborted(dep, &started, &failed_repos))
+                {
+                    continue;
+                }
+                if !hook.read_only {
+                    // The hooks after it wait too, to see the files it modifies like in a
+                    // serial run.
+                    if !running.is_empty() {
+                        break;
+                    }
+                    exclusive = Some(idx);
+                }
+
+                started[idx] = true;
+                if let Some(live) = &live {
+                    live.start(idx);
+                }
+                let env_vars = env_vars.clone();
+                running.push(async move {
+                    let mut output = String::new();
+                    let changes = WorktreeChanges::read(work_tree).await?;
+                    let (hook_report, _) = run_hook(
+                        hook,
+                        filter,
+                        cache,
+                        env_vars,
+                        work_tree,
+                        skips,
+                        changes,
+                        autofix_stage,
+                        show_diff,
+                        report,
+                        columns,
+                        verbose,
+                        &mut output,
+                    )
+                    .await?;
+                    anyhow::Ok((idx, hook_report, output))
+                });
+            }
+        }
+
+        let Some(result) = running.next().await else {
+            break;
+        };
+        let (idx, hook_report, mut output) = result?;
+        let outcome = hook_report.status;
+        finished[idx] = true;
+        if exclusive == Some(idx) {
+            exclusive = None;
+        }
+        // In quiet mode, only the output of failed hooks is printed.
+        if printer == Printer::Quiet && outcome != HookOutcome::Failed {
+            output.clear();
+        }
+        outputs[idx] = Some(output);
+        reports[idx] = Some(hook_report);
+        if outcome == HookOutcome::Failed {
+            failures += 1;
+            if fail_fast
+                || hooks[idx].fail_fast
+                || max_failures.is_some_and(|max| failures >= max.get())
+            {
+                stopped = true;
+            }
+        }
+        if outcome == HookOutcome::Failed && hooks[idx].repo_fail_fast {
+            failed_repos.insert(hooks[idx].repo_index);
+        }
+        // The live display shows hooks as soon as they finish.
+        if let Some(live) = &mut live {
+            live.finish(idx, outcome, &outputs[idx].take().unwrap_or_default())?;
+            continue;
+        }
+
+        // Print finished hooks in order, hooks that will never start are left out.
+        while printed < hooks.len() {
+            if let Some(output) = outputs[printed].take() {
+                write!(printer.failures(), "{output}")?;
+            } else if started[printed] || !(stopped || aborted(printed, &started, &failed_repos)) {
+                break;
+            }
+            printed += 1;
+        }
+    }
+
+    for output in outputs.into_iter().flatten() {
+        write!(printer.failures(), "{output}")?;
+    }
+
+    let mut summary = Summary::default();
+    for hook_report in reports.into_iter().flatten() {
+        summary.add(hook_report);
+    }
+    Ok(summary)
+}
+
+/// Print the files each hook would run on, without running anything.
+fn dry_run_hooks(
+    hooks: &[Hook],
+    skips: &Skips,
+    filter: &FileFilter<'_>,
+    null: bool,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    if null {
+        let mut records = String::new();
+        for hook in hooks {
+            if skips.contains(hook).is_some() || !hook.pass_filenames {
+                continue;
+            }
+            for filename in filter.for_hook(hook)? {
+                write!(records, "{}\t{filename}\0", hook.id)?;
+            }
+        }
+        write_null_records(&records, printer)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    let columns = calculate_columns(hooks);
+
+    for hook in hooks {
+        if let Some(source) = skips.contains(hook) {
+            writeln!(
+                printer.stdout(),
+                "{}",
+                status_line(
+                    &hook.name,
+                    columns,
+                    SKIPPED,
+                    Style::new().black().on_yellow(),
+                    source.postfix(),
+                )
+            )?;
+            continue;
+        }
+
+        let filenames = filter.for_hook(hook)?;
+        if filenames.is_empty() && !hook.always_run {
+            writeln!(
+                printer.stdout(),
+                "{}",
+                status_line(
+                    &hook.name,
+                    columns,
+                    SKIPPED,
+                    Style::new().black().on_cyan(),
+                    NO_FILES,
+                )
+            )?;
+            continue;
+        }
+
+        writeln!(printer.stdout(), "{}", &hook.name)?;
+        writeln!(
+            printer.stdout(),
+            "{}",
+            format!("- hook id: {}", hook.id).dimmed()
+        )?;
+        if !hook.pass_filenames {
+            writeln!(
+                printer.stdout(),
+                "{}",
+                "- filenames are not passed to this hook".dimmed()
+            )?;
+            continue;
+        }
+        writeln!(
+            printer.stdout(),
+            "{}",
+            format!("- files ({}):", filenames.len()).dimmed()
+        )?;
+        for filename in filenames {
+            writeln!(printer.stdout(), "  {filename}")?;
+        }
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+/// Write NUL-terminated records to stdout as-is, bypassing the printer which strips control
+/// characters when stdout is not a terminal.
+fn write_null_records(records: &str, printer: Printer) -> Result<()> {
+    if printer.stdout() == Stdout::Enabled {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(records.as_bytes())?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+fn diff_color() -> &'static str {
+    match ColorChoice::global() {
+        ColorChoice::Auto => "--color=auto",
+        ColorChoice::Always | ColorChoice::AlwaysAnsi => "--color=always",
+        ColorChoice::Never => "--color=never",
     }
 }
 
@@ -385,36 +1641,75 @@ fn shuffle<T>(filenames: &mut [T]) {
     filenames.shuffle(&mut rng);
 }
 
+/// Run the hook once, killing it if it exceeds its timeout.
+///
+/// Returns the exit status, the output, and whether the hook timed out.
+async fn execute_hook(
+    hook: &Hook,
+    filenames: &[&String],
+    env_vars: Arc<HashMap<&'static str, String>>,
+    work_dir: Option<&Path>,
+) -> Result<(i32, Vec<u8>, bool)> {
+    let run = hook.language.run(hook, filenames, env_vars, work_dir);
+    if let Some(timeout) = hook.timeout {
+        match tokio::time::timeout(timeout, run).await {
+            Ok(result) => {
+                let (status, output) = result?;
+                Ok((status, output, false))
+            }
+            Err(_) => Ok((1, Vec::new(), true)),
+        }
+    } else {
+        let (status, output) = run.await?;
+        Ok((status, output, false))
+    }
+}
+
+/// The executable of a `system` hook with `skip_if_missing_entry`, if it can't be found.
+fn missing_entry(hook: &Hook) -> Option<String> {
+    if !hook.skip_if_missing_entry || hook.language != Language::System {
+        return None;
+    }
+    let executable = shlex::split(&hook.entry)?.into_iter().next()?;
+    which::which(&executable).is_err().then_some(executable)
+}
+
+#[allow(clippy::fn_params_excessive_bools)]
 async fn run_hook(
     hook: &Hook,
     filter: &FileFilter<'_>,
+    cache: &RunCache,
     env_vars: Arc<HashMap<&'static str, String>>,
-    skips: &[String],
-    diff: Vec<u8>,
+    work_tree: Option<&WorkTree>,
+    skips: &Skips,
+    changes: WorktreeChanges,
+    allow_modifications: bool,
+    show_diff: bool,
+    report: bool,
     columns: usize,
     verbose: bool,
-    printer: Printer,
-) -> Result<(bool, Vec<u8>)> {
-    if skips.contains(&hook.id) || skips.contains(&hook.alias) {
+    out: &mut impl std::fmt::Write,
+) -> Result<(HookReport, WorktreeChanges)> {
+    if let Some(source) = skips.contains(hook) {
         writeln!(
-            printer.stdout(),
+            out,
             "{}",
             status_line(
                 &hook.name,
                 columns,
                 SKIPPED,
                 Style::new().black().on_yellow(),
-                "",
+                source.postfix(),
             )
         )?;
-        return Ok((true, diff));
+        return Ok((HookReport::skipped(hook), changes));
     }
 
     let mut filenames = filter.for_hook(hook)?;
 
     if filenames.is_empty() && !hook.always_run {
         writeln!(
-            printer.stdout(),
+            out,
             "{}",
             status_line(
                 &hook.name,
@@ -424,69 +1719,164 @@ async fn run_hook(
                 NO_FILES,
             )
         )?;
-        return Ok((true, diff));
+        return Ok((HookReport::skipped(hook), changes));
     }
 
-    write!(
-        printer.stdout(),
-        "{}{}",
-        &hook.name,
-        ".".repeat(columns - hook.name.width_cjk() - 6 - 1)
-    )?;
-    std::io::stdout().flush()?;
+    if let Some(executable) = missing_entry(hook) {
+        writeln!(
+            out,
+            "{}",
+            status_line(
+                &hook.name,
+                columns,
+                SKIPPED,
+                Style::new().black().on_yellow(),
+                ENTRY_NOT_FOUND,
+            )
+        )?;
+        writeln!(
+            out,
+            "{}",
+            format!("- `{executable}` is not installed").dimmed()
+        )?;
+        return Ok((HookReport::skipped(hook), changes));
+    }
 
-    let start = std::time::Instant::now();
+    let work_dir = work_tree.map(WorkTree::dir);
+    let key = cache.key(hook, &filenames, &env_vars, work_dir);
+    if key.as_ref().is_some_and(|key| cache.passed(hook, key)) {
+        writeln!(
+            out,
+            "{}",
+            status_line(&hook.name, columns, PASSED, Style::new().on_green(), CACHED,)
+        )?;
+        return Ok((HookReport::cached(hook, &filenames), changes));
+    }
+
+    // Streamed output is printed while the hook runs, so print the name once it finishes.
+    let name = truncate_name(&hook.name, columns - 6 - 2);
+    let running = format!("{}{}", name, ".".repeat(columns - name.width_cjk() - 6 - 1));
+    if !hook.stream {
+        write!(out, "{running}")?;
+        std::io::stdout().flush()?;
+    }
+
+    let tree = if show_diff || report {
+        Some(git::write_worktree_tree(work_tree).await?)
+    } else {
+        None
+    };
 
-    let (status, output) = if hook.pass_filenames {
+    if hook.pass_filenames {
         shuffle(&mut filenames);
-        hook.language.run(hook, &filenames, env_vars).await?
     } else {
-        hook.language.run(hook, &[], env_vars).await?
+        filenames.clear();
+    }
+
+    // Only the hook processes are timed, not the snapshots of the work tree around them.
+    let mut duration = Duration::ZERO;
+    let mut retries = 0;
+    let (status, output, timed_out, new_changes) = loop {
+        let start = std::time::Instant::now();
+        let (status, output, timed_out) =
+            execute_hook(hook, &filenames, env_vars.clone(), work_dir).await?;
+        duration += start.elapsed();
+        let new_changes = changes.refresh().await?;
+
+        // Don't retry hooks that modified files, the failure is not transient.
+        let failed = status != 0 || timed_out;
+        if !failed || changes != new_changes || retries >= hook.retries {
+            break (status, output, timed_out, new_changes);
+        }
+
+        retries += 1;
+        let backoff = retry::backoff(retries);
+        debug!(
+            "Hook `{}` failed, retrying in {backoff:?} ({retries}/{})",
+            hook.id, hook.retries
+        );
+        tokio::time::sleep(backoff).await;
     };
 
-    let duration = start.elapsed();
+    let slow = hook
+        .warn_slow
+        .filter(|threshold| duration > *threshold)
+        .map(|threshold| {
+            format!(
+                " ({:.2?}s, slower than {threshold:?})",
+                duration.as_secs_f64()
+            )
+        })
+        .unwrap_or_default();
 
-    let new_diff = get_diff().await?;
-    let file_modified = diff != new_diff;
-    let success = status == 0 && !file_modified;
+    let file_modified = changes != new_changes;
+    let success = status == 0 && !timed_out && (!file_modified || allow_modifications);
+    if let Some(key) = key.filter(|_| success && !file_modified) {
+        cache.record(hook, key);
+    }
 
+    let new_tree = match tree {
+        Some(_) if file_modified => Some(git::write_worktree_tree(work_tree).await?),
+        _ => None,
+    };
+    let files_modified = match (&tree, &new_tree) {
+        (Some(tree), Some(new_tree)) if report => {
+            git::get_tree_changed_files(tree, new_tree).await?
+        }
+        _ => vec![],
+    };
+
+    if hook.stream {
+        write!(out, "{running}")?;
+    }
     if success {
-        writeln!(printer.stdout(), "{}", "Passed".on_green())?;
+        writeln!(out, "{}{}", "Passed".on_green(), slow.yellow())?;
     } else {
-        writeln!(printer.stdout(), "{}", "Failed".on_red())?;
+        writeln!(out, "{}{}", "Failed".on_red(), slow.yellow())?;
     }
 
-    if verbose || hook.verbose || !success {
-        writeln!(
-            printer.stdout(),
-            "{}",
-            format!("- hook id: {}", hook.id).dimmed()
-        )?;
+    let details = verbose || hook.verbose || !success || file_modified;
+    if details {
+        writeln!(out, "{}", format!("- hook id: {}", hook.id).dimmed())?;
         if verbose || hook.verbose {
             writeln!(
-                printer.stdout(),
+                out,
                 "{}",
                 format!("- duration: {:.2?}s", duration.as_secs_f64()).dimmed()
             )?;
         }
-        if status != 0 {
+        if retries > 0 {
+            writeln!(out, "{}", format!("- retries: {retries}").dimmed())?;
+        }
+        if timed_out {
             writeln!(
-                printer.stdout(),
+                out,
                 "{}",
-                format!("- exit code: {status}").dimmed()
+                format!(
+                    "- timed out after {}s",
+                    hook.timeout.unwrap_or_default().as_secs()
+                )
+                .dimmed()
             )?;
+        } else if status != 0 {
+            writeln!(out, "{}", format!("- exit code: {status}").dimmed())?;
         }
         if file_modified {
-            writeln!(
-                printer.stdout(),
-                "{}",
-                "- files were modified by this hook".dimmed()
-            )?;
+            writeln!(out, "{}", "- files were modified by this hook".dimmed())?;
+            if let (true, Some(tree), Some(new_tree)) = (show_diff, &tree, &new_tree) {
+                // The diff is captured, so resolve `auto` against our own stdout.
+                let color = match anstream::AutoStream::choice(&std::io::stdout()) {
+                    ColorChoice::Always | ColorChoice::AlwaysAnsi => "--color=always",
+                    _ => "--color=never",
+                };
+                let diff = git::get_tree_diff(tree, new_tree, color).await?;
+                write!(out, "{}", String::from_utf8_lossy(&diff))?;
+            }
         }
 
         // To be consistent with pre-commit, merge stderr into stdout.
         let stdout = output.trim_ascii();
-        if !stdout.is_empty() {
+        if details && !stdout.is_empty() {
             if let Some(file) = hook.log_file.as_deref() {
                 fs_err::OpenOptions::new()
                     .create(true)
@@ -496,15 +1886,47 @@ async fn run_hook(
                         f.write_all(stdout)?;
                         Ok(())
                     })?;
-            } else {
-                writeln!(
-                    printer.stdout(),
-                    "{}",
-                    textwrap::indent(&String::from_utf8_lossy(stdout), "  ").dimmed()
-                )?;
+            } else if !hook.stream {
+                let stdout = String::from_utf8_lossy(stdout);
+                let lines = stdout.lines().count();
+                match hook.max_output_lines {
+                    Some(max_lines) if lines > max_lines => {
+                        let log = save_full_output(hook, stdout.as_bytes())?;
+                        let shown = stdout.lines().take(max_lines).join("\n");
+                        writeln!(out, "{}", textwrap::indent(&shown, "  ").dimmed())?;
+                        writeln!(
+                            out,
+                            "{}",
+                            format!(
+                                "  ... {} more lines, full output in {}",
+                                lines - max_lines,
+                                log.user_display()
+                            )
+                            .dimmed()
+                        )?;
+                    }
+                    _ => {
+                        writeln!(out, "{}", textwrap::indent(&stdout, "  ").dimmed())?;
+                    }
+                }
             };
         }
     }
 
-    Ok((success, new_diff))
+    let hook_report = HookReport {
+        id: hook.id.clone(),
+        name: hook.name.clone(),
+        status: if success {
+            HookOutcome::Passed
+        } else {
+            HookOutcome::Failed
+        },
+        duration: Some(duration.as_secs_f64()),
+        exit_code: (!timed_out).then_some(status),
+        files: filenames.into_iter().cloned().collect(),
+        output: String::from_utf8_lossy(&output).into_owned(),
+        files_modified,
+        output_pattern: hook.output_pattern.clone(),
+    };
+    Ok((hook_report, new_changes))
 }
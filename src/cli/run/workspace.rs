@@ -0,0 +1,117 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use tracing::{debug, error};
+
+use crate::config::{is_toml, CONFIG_FILE, CONFIG_FILE_TOML};
+use crate::git;
+
+/// Whether the projects of a workspace are being run, so they don't discover projects again.
+static IN_WORKSPACE: AtomicBool = AtomicBool::new(false);
+
+/// A project of a workspace, a directory with its own config file.
+pub(super) struct Member {
+    /// The directory of the project, relative to the root of the workspace.
+    pub(super) dir: PathBuf,
+    /// The absolute path of the config file of the project.
+    pub(super) config: PathBuf,
+}
+
+impl Member {
+    pub(super) fn display(&self) -> String {
+        if self.dir.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            self.dir.to_string_lossy().to_string()
+        }
+    }
+}
+
+/// Find the config files in the subdirectories of the repo, the root config comes first.
+pub(super) async fn discover(root_config: &Path) -> Result<Vec<Member>> {
+    let mut members = vec![Member {
+        dir: PathBuf::new(),
+        config: std::path::absolute(root_config)?,
+    }];
+
+    let mut nested: Vec<_> = git::get_all_files()
+        .await?
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|file| {
+            file.file_name()
+                .is_some_and(|name| name == CONFIG_FILE || name == CONFIG_FILE_TOML)
+                && file.parent().is_some_and(|dir| !dir.as_os_str().is_empty())
+        })
+        .collect();
+    // Prefer the YAML config of a directory that has both.
+    nested.sort_by_key(|file| (file.parent().map(Path::to_path_buf), is_toml(file)));
+    nested.dedup_by(|a, b| a.parent() == b.parent());
+    for config in nested {
+        let dir = config.parent().expect("config has a parent").to_path_buf();
+        members.push(Member {
+            config: std::path::absolute(&config)?,
+            dir,
+        });
+    }
+
+    debug!(
+        "Workspace projects: {:?}",
+        members.iter().map(Member::display).collect::<Vec<_>>()
+    );
+    Ok(members)
+}
+
+/// Split the files between the projects, each file belongs to the deepest project containing it.
+///
+/// The files of each project are relative to its directory.
+pub(super) fn scope_files(members: &[Member], filenames: Vec<String>) -> Vec<Vec<PathBuf>> {
+    let mut scoped = vec![vec![]; members.len()];
+    for filename in filenames {
+        let path = Path::new(&filename);
+        let owner = members
+            .iter()
+            .enumerate()
+            .filter(|(_, member)| path.starts_with(&member.dir))
+            .max_by_key(|(_, member)| member.dir.components().count());
+        if let Some((idx, member)) = owner {
+            let relative = path.strip_prefix(&member.dir).unwrap_or(path);
+            scoped[idx].push(relative.to_path_buf());
+        }
+    }
+    scoped
+}
+
+pub(super) fn in_workspace() -> bool {
+    IN_WORKSPACE.load(Ordering::Relaxed)
+}
+
+/// Marks the projects of a workspace as running, and changes into a project directory,
+/// until dropped.
+pub(super) struct WorkspaceGuard {
+    previous_dir: PathBuf,
+}
+
+impl WorkspaceGuard {
+    pub(super) fn enter(dir: &Path) -> Result<Self> {
+        let previous_dir = std::env::current_dir()?;
+        if !dir.as_os_str().is_empty() {
+            std::env::set_current_dir(dir)?;
+        }
+        IN_WORKSPACE.store(true, Ordering::Relaxed);
+        Ok(Self { previous_dir })
+    }
+}
+
+impl Drop for WorkspaceGuard {
+    fn drop(&mut self) {
+        IN_WORKSPACE.store(false, Ordering::Relaxed);
+        if let Err(err) = std::env::set_current_dir(&self.previous_dir) {
+            error!(
+                "Failed to go back to `{}`: {err}",
+                self.previous_dir.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::cli::{ExitStatus, SchemaKind};
+use crate::config::{Config, Manifest};
+use crate::printer::Printer;
+
+pub(crate) fn schema(kind: SchemaKind, printer: Printer) -> Result<ExitStatus> {
+    let schema = match kind {
+        SchemaKind::Config => schemars::schema_for!(Config),
+        SchemaKind::Manifest => schemars::schema_for!(Manifest),
+    };
+    writeln!(
+        printer.stdout(),
+        "{}",
+        serde_json::to_string_pretty(&schema)?
+    )?;
+    Ok(ExitStatus::Success)
+}
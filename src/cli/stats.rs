@@ -0,0 +1,64 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+
+use crate::cli::ExitStatus;
+use crate::printer::Printer;
+use crate::store::Store;
+
+pub(crate) fn stats(last: usize, printer: Printer) -> Result<ExitStatus> {
+    let store = Store::from_settings()?.init()?;
+    let stats = store.run_stats(&std::env::current_dir()?, last)?;
+
+    if stats.runs == 0 {
+        writeln!(printer.stdout(), "No runs recorded for this repo")?;
+        return Ok(ExitStatus::Success);
+    }
+
+    writeln!(
+        printer.stdout(),
+        "Last {} runs: {} passed, {} failed, average {:.2}s",
+        stats.runs.bold(),
+        (stats.runs - stats.failed_runs).green(),
+        stats.failed_runs.red(),
+        stats.average_duration,
+    )?;
+
+    if stats.hooks.is_empty() {
+        return Ok(ExitStatus::Success);
+    }
+
+    let width = stats
+        .hooks
+        .iter()
+        .map(|hook| hook.hook_id.len())
+        .max()
+        .unwrap_or_default()
+        .max("Hook".len());
+
+    writeln!(printer.stdout())?;
+    writeln!(
+        printer.stdout(),
+        "{}",
+        format!(
+            "{:<width$}  {:>5}  {:>8}  {:>8}",
+            "Hook", "Runs", "Failures", "Average"
+        )
+        .bold()
+    )?;
+    for hook in &stats.hooks {
+        #[allow(clippy::cast_precision_loss)]
+        let failure_rate = hook.failures as f64 / hook.runs as f64 * 100.0;
+        writeln!(
+            printer.stdout(),
+            "{:<width$}  {:>5}  {:>7.1}%  {:>7.2}s",
+            hook.hook_id,
+            hook.runs,
+            failure_rate,
+            hook.average_duration,
+        )?;
+    }
+
+    Ok(ExitStatus::Success)
+}
@@ -0,0 +1,126 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use notify::{EventKind, RecursiveMode, Watcher};
+use owo_colors::OwoColorize;
+use tokio::sync::mpsc;
+
+use crate::cli::{self, ExitStatus, RunOptions};
+use crate::git;
+use crate::printer::Printer;
+
+/// Watch the working tree and run the hooks on the files that change.
+pub(crate) async fn watch(
+    configs: Vec<PathBuf>,
+    hook_ids: Vec<String>,
+    debounce: Duration,
+    printer: Printer,
+) -> Result<ExitStatus> {
+    let root = dunce::canonicalize(std::env::current_dir()?)?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+
+    writeln!(
+        printer.stderr(),
+        "Watching for changes in `{}`, press Ctrl-C to stop",
+        root.display().cyan()
+    )?;
+
+    // The content of the files as the hooks left them, to tell the changes made by the hooks
+    // from the edits made while they ran.
+    let mut left_by_hooks = HashMap::new();
+    let mut changed = BTreeSet::new();
+    loop {
+        if changed.is_empty() {
+            let Some(event) = rx.recv().await else {
+                break;
+            };
+            collect_changes(event, &root, &mut changed);
+        }
+        // Wait until the files stop changing, e.g. an editor writing a file in several steps.
+        while let Ok(Some(event)) = tokio::time::timeout(debounce, rx.recv()).await {
+            collect_changes(event, &root, &mut changed);
+        }
+
+        let paths: Vec<_> = std::mem::take(&mut changed).into_iter().collect();
+        let files: Vec<_> = git::get_unignored_files(&paths)
+            .await?
+            .into_iter()
+            .filter(|file| {
+                content_digest(file).is_some_and(|digest| left_by_hooks.get(file) != Some(&digest))
+            })
+            .collect();
+        if files.is_empty() {
+            continue;
+        }
+
+        writeln!(
+            printer.stderr(),
+            "\n{} {}",
+            "Changed:".bold(),
+            files.join(", ").dimmed()
+        )?;
+        let result = cli::run(
+            RunOptions {
+                configs: configs.clone(),
+                hook_ids: hook_ids.clone(),
+                files: files.iter().map(PathBuf::from).collect(),
+                explicit_files: true,
+                ..RunOptions::default()
+            },
+            printer,
+        )
+        .await;
+        if let Err(err) = result {
+            writeln!(printer.stderr(), "{}: {err:#}", "error".red().bold())?;
+        }
+
+        // Changes to the files the hooks ran on are taken as made by the hooks, unless the files
+        // change again. The other changes made meanwhile are run on next.
+        for file in files {
+            if let Some(digest) = content_digest(&file) {
+                left_by_hooks.insert(file, digest);
+            }
+        }
+        while let Ok(event) = rx.try_recv() {
+            collect_changes(event, &root, &mut changed);
+        }
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+/// A digest of the content of a file, or `None` if it isn't a readable file.
+fn content_digest(path: impl AsRef<Path>) -> Option<md5::Digest> {
+    fs_err::read(path).ok().map(md5::compute)
+}
+
+/// Add the paths of a file change event, relative to `root`, to `changed`.
+fn collect_changes(
+    event: notify::Result<notify::Event>,
+    root: &Path,
+    changed: &mut BTreeSet<String>,
+) {
+    let Ok(event) = event else {
+        return;
+    };
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return;
+    }
+    for path in event.paths {
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        if relative.starts_with(".git") {
+            continue;
+        }
+        changed.insert(relative.to_string_lossy().to_string());
+    }
+}
@@ -0,0 +1,115 @@
+//! The client a run is served for, when the daemon runs hooks on behalf of a hook script.
+//!
+//! The daemon serves every client in the same process, so instead of changing the environment,
+//! the working directory or the stdio of the process, the run reads them from the client it
+//! runs in the scope of.
+
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::future::Future;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anstream::AutoStream;
+
+tokio::task_local! {
+    static CLIENT: Arc<Client>;
+}
+
+#[derive(Debug)]
+pub(crate) struct Client {
+    env: Vec<(OsString, OsString)>,
+    cwd: PathBuf,
+    terminal_width: Option<usize>,
+    stdout: Mutex<AutoStream<File>>,
+    stderr: Mutex<AutoStream<File>>,
+}
+
+impl Client {
+    pub(crate) fn new(
+        env: Vec<(OsString, OsString)>,
+        cwd: PathBuf,
+        stdout: File,
+        stderr: File,
+    ) -> Self {
+        Self {
+            env,
+            cwd,
+            terminal_width: terminal_size::terminal_size_of(&stdout)
+                .map(|(width, _)| usize::from(width.0)),
+            stdout: Mutex::new(AutoStream::auto(stdout)),
+            stderr: Mutex::new(AutoStream::auto(stderr)),
+        }
+    }
+
+    /// Run `future` on behalf of this client.
+    pub(crate) async fn scope<F: Future>(self, future: F) -> F::Output {
+        CLIENT.scope(Arc::new(self), future).await
+    }
+}
+
+/// Get an environment variable of the client, or of the process when not serving a client.
+pub(crate) fn var_os(key: impl AsRef<OsStr>) -> Option<OsString> {
+    let key = key.as_ref();
+    CLIENT
+        .try_with(|client| {
+            client
+                .env
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.clone())
+        })
+        .unwrap_or_else(|_| std::env::var_os(key))
+}
+
+/// Get the environment variables of the client, or of the process when not serving a client.
+pub(crate) fn vars_os() -> Vec<(OsString, OsString)> {
+    CLIENT
+        .try_with(|client| client.env.clone())
+        .unwrap_or_else(|_| std::env::vars_os().collect())
+}
+
+/// Get the working directory of the client, or of the process when not serving a client.
+pub(crate) fn current_dir() -> std::io::Result<PathBuf> {
+    CLIENT
+        .try_with(|client| client.cwd.clone())
+        .map_or_else(|_| std::env::current_dir(), Ok)
+}
+
+/// Start `cmd` with the environment and in the working directory of the client, if any.
+pub(crate) fn apply(cmd: &mut tokio::process::Command) {
+    let _ = CLIENT.try_with(|client| {
+        cmd.env_clear()
+            .envs(client.env.iter().map(|(key, value)| (key, value)))
+            .current_dir(&client.cwd);
+    });
+}
+
+/// Get the width of the terminal attached to the stdout of the client, or of the process when
+/// not serving a client.
+pub(crate) fn terminal_width() -> Option<usize> {
+    CLIENT
+        .try_with(|client| client.terminal_width)
+        .unwrap_or_else(|_| {
+            terminal_size::terminal_size_of(std::io::stdout())
+                .map(|(width, _)| usize::from(width.0))
+        })
+}
+
+/// Write to the stdout of the client, returns `false` when not serving a client.
+pub(crate) fn write_stdout(s: &str) -> bool {
+    CLIENT.try_with(|client| write(&client.stdout, s)).is_ok()
+}
+
+/// Write to the stderr of the client, returns `false` when not serving a client.
+pub(crate) fn write_stderr(s: &str) -> bool {
+    CLIENT.try_with(|client| write(&client.stderr, s)).is_ok()
+}
+
+fn write(stream: &Mutex<AutoStream<File>>, s: &str) {
+    if let Ok(mut stream) = stream.lock() {
+        // A client that went away doesn't stop the run.
+        let _ = stream.write_all(s.as_bytes()).and_then(|()| stream.flush());
+    }
+}
@@ -1,21 +1,36 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::ops::RangeInclusive;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+use std::time::Duration;
 
 use anyhow::Result;
+use clap::ValueEnum;
 use fancy_regex as regex;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{Schema, SchemaObject, SubschemaValidation};
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize};
 use url::Url;
 
 use crate::fs::Simplified;
+use crate::http;
+use crate::pattern;
+use crate::warn_user_once;
 
 pub const CONFIG_FILE: &str = ".pre-commit-config.yaml";
+pub const CONFIG_FILE_TOML: &str = ".pre-commit-config.toml";
 pub const MANIFEST_FILE: &str = ".pre-commit-hooks.yaml";
+/// Gitignore-style patterns of files that no hook runs on.
+pub const IGNORE_FILE: &str = ".prefligitignore";
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, clap::ValueEnum, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
 pub enum Language {
     Conda,
     Coursier,
@@ -81,7 +96,7 @@ impl Display for Language {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, Deserialize, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, Default, Deserialize, clap::ValueEnum, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum HookType {
     CommitMsg,
@@ -136,8 +151,8 @@ impl Display for HookType {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Deserialize, clap::ValueEnum)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum, JsonSchema)]
+#[schemars(rename_all = "kebab-case")]
 pub enum Stage {
     Manual,
     CommitMsg,
@@ -145,11 +160,8 @@ pub enum Stage {
     PostCommit,
     PostMerge,
     PostRewrite,
-    #[serde(alias = "commit")]
     PreCommit,
-    #[serde(alias = "merge-commit")]
     PreMergeCommit,
-    #[serde(alias = "push")]
     PrePush,
     PreRebase,
     PrepareCommitMsg,
@@ -196,6 +208,50 @@ impl Display for Stage {
     }
 }
 
+/// The deprecated stage names, and the names that replace them.
+pub const DEPRECATED_STAGES: &[(&str, &str)] = &[
+    ("commit", "pre-commit"),
+    ("merge-commit", "pre-merge-commit"),
+    ("push", "pre-push"),
+];
+
+impl<'de> Deserialize<'de> for Stage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        // The deprecated names still work, `load_config` warns about them.
+        let new = DEPRECATED_STAGES
+            .iter()
+            .find(|(old, _)| *old == name)
+            .map_or(name.as_str(), |(_, new)| new);
+        if let Some(stage) = Stage::value_variants()
+            .iter()
+            .find(|stage| stage.as_str() == new)
+        {
+            return Ok(*stage);
+        }
+
+        let mut message = format!("Invalid stage `{name}`");
+        if let Some((_, suggestion)) = Stage::value_variants()
+            .iter()
+            .map(|stage| (strsim::jaro(&name, stage.as_str()), stage))
+            .filter(|(confidence, _)| *confidence > 0.7)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+        {
+            message.push_str(&format!(", did you mean `{suggestion}`?"));
+        } else {
+            let stages: Vec<_> = Stage::value_variants()
+                .iter()
+                .map(|stage| format!("`{stage}`"))
+                .collect();
+            message.push_str(&format!(", expected one of {}", stages.join(", ")));
+        }
+        Err(serde::de::Error::custom(message))
+    }
+}
+
 impl Stage {
     pub fn operate_on_files(self) -> bool {
         matches!(
@@ -210,13 +266,17 @@ impl Stage {
     }
 }
 
-// TODO: warn unexpected keys
-// TODO: warn deprecated stage
 // TODO: warn sensible regex
 // TODO: check minimum_pre_commit_version
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct Config {
+    /// Other configuration files to extend, as paths relative to this file or URLs.
+    /// Their repos run before the repos of this file,
+    /// and their options are used unless this file sets them.
+    #[serde(default, deserialize_with = "deserialize_extends")]
+    #[schemars(with = "Option<OneOrMany>")]
+    pub extends: Vec<String>,
     pub repos: Vec<Repo>,
     /// A list of --hook-types which will be used by default when running pre-commit install.
     /// Default is `[pre-commit]`.
@@ -227,15 +287,248 @@ pub struct Config {
     /// Default to all stages.
     pub default_stages: Option<Vec<Stage>>,
     /// Global file include pattern.
+    #[serde(default, deserialize_with = "deserialize_regex")]
     pub files: Option<String>,
     /// Global file exclude pattern.
+    #[serde(default, deserialize_with = "deserialize_regex")]
     pub exclude: Option<String>,
+    /// Global gitignore-style file include patterns, files must also match `files` if set.
+    #[serde(default, deserialize_with = "deserialize_globs")]
+    #[schemars(with = "Option<OneOrMany>")]
+    pub files_glob: Option<Vec<String>>,
+    /// Global gitignore-style file exclude patterns, in addition to `exclude`.
+    #[serde(default, deserialize_with = "deserialize_globs")]
+    #[schemars(with = "Option<OneOrMany>")]
+    pub exclude_glob: Option<Vec<String>>,
     /// Set to true to have pre-commit stop running hooks after the first failure.
     /// Default is false.
     pub fail_fast: Option<bool>,
+    /// The number of processes used to run a hook on files in parallel,
+    /// and of repos cloned and environments installed at once.
+    /// `1` runs hooks serially, `0` uses the number of CPUs.
+    /// Default is 0.
+    pub jobs: Option<usize>,
+    /// Set to true to run hooks concurrently, see `depends_on` and `read_only` on hooks.
+    /// Default is false.
+    pub parallel: Option<bool>,
+    /// Set to false to run hooks on the working tree as-is, without stashing unstaged changes.
+    /// Default is true.
+    pub stash: Option<bool>,
+    /// Set to true to stage the changes hooks make to staged files, instead of failing.
+    /// Default is false.
+    pub autofix_stage: Option<bool>,
+    /// Set to true to skip hooks that passed before with the same definition, environment,
+    /// files and file contents. `run --no-cache` runs them anyway.
+    /// Default is false.
+    pub cache: Option<bool>,
+    /// A configuration-wide default for the `clean_env` property of hooks.
+    pub clean_env: Option<bool>,
+    /// A configuration-wide default for the `pass_env` property of hooks.
+    pub pass_env: Option<Vec<String>>,
+    /// A configuration-wide default for the `max_output_lines` property of hooks.
+    pub max_output_lines: Option<usize>,
+    /// A configuration-wide default for the `expand_env` property of hooks.
+    pub expand_env: Option<bool>,
+    /// Show the duration of hooks that run longer than this, e.g. `10s` or `500ms`,
+    /// even without `--verbose`.
+    /// Default is no threshold.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    #[schemars(with = "Option<RawDuration>")]
+    pub warn_slow_hooks: Option<Duration>,
+    /// Set to true to also run the config files in subdirectories,
+    /// each on the files under its directory.
+    /// Default is false.
+    pub workspace: Option<bool>,
+    /// Custom file types, which can be used in `types`, `types_or` and `exclude_types` of hooks.
+    pub file_types: Option<HashMap<String, FileTypeRules>>,
     pub minimum_pre_commit_version: Option<String>,
     /// Configuration for pre-commit.ci service.
-    pub ci: Option<HashMap<String, serde_yaml::Value>>,
+    pub ci: Option<CiConfig>,
+}
+
+/// The rules of a custom file type, a file has the type if it matches any of them.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub struct FileTypeRules {
+    /// File extensions without the leading dot, like `proto`.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// File names, like `Jenkinsfile`, which also match names with another extension appended.
+    #[serde(default)]
+    pub filenames: Vec<String>,
+    /// Interpreters in the shebang of executable files, like `python3`.
+    #[serde(default)]
+    pub interpreters: Vec<String>,
+}
+
+/// How often pre-commit.ci updates the repos of the config.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoupdateSchedule {
+    Weekly,
+    Monthly,
+    Quarterly,
+}
+
+/// The settings of pre-commit.ci, see <https://pre-commit.ci/#configuration>.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct CiConfig {
+    /// The commit message of the fixes pushed to pull requests.
+    pub autofix_commit_msg: Option<String>,
+    /// Whether to push the fixes of hooks to pull requests.
+    /// Default is true.
+    pub autofix_prs: Option<bool>,
+    /// The branch to send the autoupdate pull requests to.
+    /// Default is the default branch of the repository.
+    pub autoupdate_branch: Option<String>,
+    /// The commit message of the autoupdate pull requests.
+    pub autoupdate_commit_msg: Option<String>,
+    /// How often to update the repos of the config.
+    /// Default is `weekly`.
+    pub autoupdate_schedule: Option<AutoupdateSchedule>,
+    /// Ids of hooks to skip when running in CI.
+    /// Default is `[]`.
+    #[serde(default)]
+    pub skip: Vec<String>,
+    /// Whether to check out the submodules of the repository.
+    /// Default is false.
+    pub submodules: Option<bool>,
+}
+
+impl Config {
+    /// Inherit the repos of `base`, and its options that are not set in this config.
+    fn with_base(mut self, mut base: Config) -> Self {
+        macro_rules! inherit_if_none {
+            ($($field:ident),* $(,)?) => {
+                $(
+                if self.$field.is_none() {
+                    self.$field = base.$field.take();
+                }
+                )*
+            };
+        }
+
+        inherit_if_none!(
+            default_install_hook_types,
+            default_language_version,
+            default_stages,
+            files,
+            exclude,
+            files_glob,
+            exclude_glob,
+            fail_fast,
+            jobs,
+            parallel,
+            stash,
+            autofix_stage,
+            cache,
+            clean_env,
+            pass_env,
+            max_output_lines,
+            expand_env,
+            warn_slow_hooks,
+            workspace,
+            minimum_pre_commit_version,
+            ci,
+        );
+        if let Some(mut file_types) = base.file_types.take() {
+            file_types.extend(self.file_types.take().unwrap_or_default());
+            self.file_types = Some(file_types);
+        }
+        base.repos.append(&mut self.repos);
+        self.repos = base.repos;
+        self
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Accept a single configuration file to extend, or a list of them.
+fn deserialize_extends<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(extends) => vec![extends],
+        OneOrMany::Many(extends) => extends,
+    })
+}
+
+/// Accept a single glob or a list of them, and check that they compile.
+fn deserialize_globs<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let globs = match Option::<OneOrMany>::deserialize(deserializer)? {
+        Some(OneOrMany::One(glob)) => vec![glob],
+        Some(OneOrMany::Many(globs)) => globs,
+        None => return Ok(None),
+    };
+    pattern::compile_globs(&globs).map_err(serde::de::Error::custom)?;
+    Ok(Some(globs))
+}
+
+/// Parse a duration from a number of seconds or a string like `10s`, `500ms`, `2m` or `1h`.
+fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value: f64 = value.parse().ok()?;
+    let secs = match unit.trim() {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// A duration as a number of seconds, or a string like `10s` or `500ms`.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum RawDuration {
+    Seconds(f64),
+    String(String),
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(raw) = Option::<RawDuration>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let duration = match raw {
+        RawDuration::Seconds(secs) => Duration::try_from_secs_f64(secs).ok(),
+        RawDuration::String(s) => parse_duration(&s),
+    };
+    duration.map(Some).ok_or_else(|| {
+        serde::de::Error::custom("Invalid duration, expected a value like `10s` or `500ms`")
+    })
+}
+
+const INVALID_REGEX: &str = "Invalid regex";
+
+/// Check that a file pattern compiles, so an invalid one is reported where it is set.
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let pattern = Option::<String>::deserialize(deserializer)?;
+    if let Some(pattern) = &pattern {
+        pattern::compile(pattern).map_err(|err| {
+            serde::de::Error::custom(format!("{INVALID_REGEX} `{pattern}`: {err}"))
+        })?;
+    }
+    Ok(pattern)
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -244,6 +537,8 @@ pub enum RepoLocation {
     Local,
     Meta,
     Remote(Url),
+    /// A directory relative to the configuration file.
+    Path(String),
 }
 
 impl FromStr for RepoLocation {
@@ -253,6 +548,9 @@ impl FromStr for RepoLocation {
         match s {
             "local" => Ok(RepoLocation::Local),
             "meta" => Ok(RepoLocation::Meta),
+            _ if s.starts_with("./") || s.starts_with("../") => {
+                Ok(RepoLocation::Path(s.to_string()))
+            }
             _ => Url::parse(s).map(RepoLocation::Remote),
         }
     }
@@ -274,6 +572,7 @@ impl RepoLocation {
             RepoLocation::Local => "local",
             RepoLocation::Meta => "meta",
             RepoLocation::Remote(url) => url.as_str(),
+            RepoLocation::Path(path) => path,
         }
     }
 }
@@ -310,6 +609,16 @@ impl<'de> Deserialize<'de> for LanguageVersion {
     }
 }
 
+impl JsonSchema for LanguageVersion {
+    fn schema_name() -> String {
+        "LanguageVersion".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
 impl LanguageVersion {
     pub fn is_default(&self) -> bool {
         matches!(self, Self::Default)
@@ -334,16 +643,43 @@ impl Display for LanguageVersion {
     }
 }
 
+/// Limits of the resources each process of a hook can use, so a runaway tool can't take down
+/// the machine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceLimits {
+    /// The CPU time in seconds, the process is killed once it used more.
+    pub cpu_time: Option<u64>,
+    /// The address space in MiB, allocating more fails. On Unix it counts the memory mapped
+    /// by the process, not only the memory in use, so runtimes that reserve a lot upfront, like
+    /// Go, Java or Node.js, need a larger limit than they use.
+    pub memory: Option<u64>,
+    /// The number of files open at once. Not supported on Windows.
+    pub open_files: Option<u64>,
+}
+
 /// Common hook options.
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
 pub struct HookOptions {
     /// Not documented in the official docs.
     pub alias: Option<String>,
     /// The pattern of files to run on.
+    #[serde(default, deserialize_with = "deserialize_regex")]
     pub files: Option<String>,
     /// Exclude files that were matched by `files`.
     /// Default is `$^`, which matches nothing.
+    #[serde(default, deserialize_with = "deserialize_regex")]
     pub exclude: Option<String>,
+    /// Gitignore-style patterns of files to run on, files must also match `files` if set.
+    /// Default is `[]`, which matches all files.
+    #[serde(default, deserialize_with = "deserialize_globs")]
+    #[schemars(with = "Option<OneOrMany>")]
+    pub files_glob: Option<Vec<String>>,
+    /// Gitignore-style patterns of files to exclude, in addition to `exclude`.
+    /// Default is `[]`.
+    #[serde(default, deserialize_with = "deserialize_globs")]
+    #[schemars(with = "Option<OneOrMany>")]
+    pub exclude_glob: Option<Vec<String>>,
     /// List of file types to run on (AND).
     /// Default is `[file]`, which matches all files.
     pub types: Option<Vec<String>>,
@@ -384,6 +720,69 @@ pub struct HookOptions {
     /// Print the output of the hook even if it passes.
     /// Default is false.
     pub verbose: Option<bool>,
+    /// Kill the hook and mark it as failed if it runs longer than this many seconds.
+    /// Default is no timeout.
+    pub timeout: Option<u64>,
+    /// Re-run the hook up to this many times if it fails, waiting longer between each attempt.
+    /// Default is 0.
+    pub retries: Option<u32>,
+    /// Limit the CPU time, memory and open files of the hook, with rlimits on Unix and a job
+    /// object on Windows. For `docker` hooks, they're passed to the container.
+    /// Default is no limits.
+    pub limits: Option<ResourceLimits>,
+    /// Ids (or aliases) of hooks that must finish before this hook starts
+    /// when hooks run concurrently.
+    /// Default is `[]`.
+    pub depends_on: Option<Vec<String>>,
+    /// The hook doesn't modify files, so it can run at the same time as other read-only hooks
+    /// when hooks run concurrently. Other hooks run on their own.
+    /// Default is false.
+    pub read_only: Option<bool>,
+    /// Only run this hook when one of these profiles is selected with `run --profile`.
+    /// Default is `[]`, which runs the hook regardless of the selected profiles.
+    pub profiles: Option<Vec<String>>,
+    /// Free-form tags of the hook, like `formatter` or `linter`, to select it with `run --tag`.
+    /// Default is `[]`.
+    pub tags: Option<Vec<String>>,
+    /// Environment variables to set when running the hook.
+    /// Default is `{}`.
+    pub env: Option<HashMap<String, String>>,
+    /// Run the hook with only the host environment variables listed in `pass_env`,
+    /// instead of inheriting the whole environment.
+    /// Default is false.
+    pub clean_env: Option<bool>,
+    /// Host environment variables passed through to the hook when `clean_env` is set,
+    /// in addition to `PATH` and the variables set by pre-commit.
+    /// Default is `[]`.
+    pub pass_env: Option<Vec<String>>,
+    /// Relay the output of the hook line by line as it runs, prefixed with the hook id,
+    /// instead of printing it after the hook finishes.
+    /// Default is false.
+    pub stream: Option<bool>,
+    /// Run the hook in a pseudo-terminal, so tools that check for a terminal keep their colors
+    /// and progress output. Only supported on Unix.
+    /// Default is false.
+    pub pty: Option<bool>,
+    /// Print at most this many lines of the hook output, the full output is saved to a file.
+    /// Default is no limit.
+    pub max_output_lines: Option<usize>,
+    /// A regex to parse diagnostics from the hook output for `run --output-format rdjson`,
+    /// with the named groups `file`, `line`, `column` and `message`.
+    /// Default matches `file:line:column: message` and `file:line: message`.
+    pub output_pattern: Option<String>,
+    /// Report a `system` hook as skipped instead of failing when its executable is not found,
+    /// for optional tools that not every contributor has installed.
+    /// Default is false.
+    pub skip_if_missing_entry: Option<bool>,
+    /// The directory to run the hook in, relative to the root of the repo.
+    /// Only the files under it are passed to the hook, relative to it.
+    /// Default is the root of the repo.
+    #[serde(default, deserialize_with = "deserialize_relative_dir")]
+    pub working_directory: Option<String>,
+    /// Expand `${VAR}` and `${VAR:-default}` in `entry`, `args` and `additional_dependencies`
+    /// from the `env` of the hook or the environment, `$${` is a literal `${`.
+    /// Default is true.
+    pub expand_env: Option<bool>,
     pub minimum_pre_commit_version: Option<String>,
 }
 
@@ -403,6 +802,8 @@ impl HookOptions {
             alias,
             files,
             exclude,
+            files_glob,
+            exclude_glob,
             types,
             types_or,
             exclude_types,
@@ -417,6 +818,23 @@ impl HookOptions {
             require_serial,
             stages,
             verbose,
+            timeout,
+            retries,
+            limits,
+            depends_on,
+            read_only,
+            profiles,
+            tags,
+            env,
+            clean_env,
+            pass_env,
+            stream,
+            pty,
+            max_output_lines,
+            output_pattern,
+            skip_if_missing_entry,
+            working_directory,
+            expand_env,
             minimum_pre_commit_version,
         );
     }
@@ -425,7 +843,7 @@ impl HookOptions {
 /// A remote hook in the configuration file.
 ///
 /// All keys in manifest hook dict are valid in a config hook dict, but are optional.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct RemoteHook {
     /// The id of the hook.
@@ -445,7 +863,7 @@ pub struct RemoteHook {
 /// It's the same as the manifest hook definition.
 pub type LocalHook = ManifestHook;
 
-#[derive(Debug, Copy, Clone, Deserialize)]
+#[derive(Debug, Copy, Clone, Deserialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum MetaHookID {
     CheckHooksApply,
@@ -552,9 +970,33 @@ impl From<MetaHook> for ManifestHook {
 pub struct RemoteRepo {
     pub repo: Url,
     pub rev: String,
+    /// The directory of the hooks in the repo, for repos that hold more than one project.
+    pub subdirectory: Option<String>,
+    /// Stop running the remaining hooks of this repo after the first failure.
+    pub fail_fast: bool,
     pub hooks: Vec<RemoteHook>,
 }
 
+/// Deserialize a directory of a repo, which must stay inside the repo.
+fn deserialize_relative_dir<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let dir = String::deserialize(deserializer)?;
+    let inside = Path::new(&dir).components().all(|component| {
+        matches!(
+            component,
+            std::path::Component::Normal(_) | std::path::Component::CurDir
+        )
+    });
+    if !inside {
+        return Err(serde::de::Error::custom(format!(
+            "Expected a relative path inside the repo, got `{dir}`"
+        )));
+    }
+    Ok(Some(dir))
+}
+
 impl PartialEq for RemoteRepo {
     fn eq(&self, other: &Self) -> bool {
         self.repo == other.repo && self.rev == other.rev
@@ -570,14 +1012,35 @@ impl std::hash::Hash for RemoteRepo {
     }
 }
 
+impl RemoteRepo {
+    /// The directory of the hooks in a clone of the repo at `path`.
+    pub fn hooks_path(&self, path: &Path) -> PathBuf {
+        match &self.subdirectory {
+            Some(subdirectory) => path.join(subdirectory),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Whether the repo is a local directory linked into the store, instead of cloned at `rev`.
+    pub fn is_linked(&self) -> bool {
+        self.rev.is_empty()
+    }
+}
+
 impl Display for RemoteRepo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}@{}", self.repo, self.rev)
+        if self.is_linked() {
+            write!(f, "{}", self.repo)
+        } else {
+            write!(f, "{}@{}", self.repo, self.rev)
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct LocalRepo {
+    /// Stop running the remaining hooks of this repo after the first failure.
+    pub fail_fast: bool,
     pub hooks: Vec<LocalHook>,
 }
 
@@ -589,6 +1052,8 @@ impl Display for LocalRepo {
 
 #[derive(Debug, Clone)]
 pub struct MetaRepo {
+    /// Stop running the remaining hooks of this repo after the first failure.
+    pub fail_fast: bool,
     pub hooks: Vec<MetaHook>,
 }
 
@@ -605,6 +1070,92 @@ pub enum Repo {
     Meta(MetaRepo),
 }
 
+/// The schema of a remote repo, repos are told apart by `repo` when deserializing.
+#[derive(JsonSchema)]
+#[schemars(rename = "RemoteRepo")]
+struct RemoteRepoSchema {
+    /// The URL of the git repo to clone, or a directory relative to the config file,
+    /// starting with `./` or `../`.
+    repo: String,
+    /// The revision or tag to clone.
+    ///
+    /// Without it, the hooks of a directory are used as they are, instead of cloned.
+    rev: Option<String>,
+    /// The directory of `.pre-commit-hooks.yaml` and the hook package in the repo,
+    /// to use hooks from a repo that holds more than one project.
+    subdirectory: Option<String>,
+    /// Stop running the remaining hooks of this repo after the first failure.
+    #[serde(default)]
+    fail_fast: bool,
+    hooks: Vec<RemoteHook>,
+}
+
+#[derive(JsonSchema)]
+enum LocalRepoLocation {
+    #[serde(rename = "local")]
+    Local,
+}
+
+/// The schema of the repo of hooks defined in the config file.
+#[derive(JsonSchema)]
+#[schemars(rename = "LocalRepo")]
+#[serde(deny_unknown_fields)]
+struct LocalRepoSchema {
+    repo: LocalRepoLocation,
+    /// Stop running the remaining hooks of this repo after the first failure.
+    #[serde(default)]
+    fail_fast: bool,
+    hooks: Vec<LocalHook>,
+}
+
+#[derive(JsonSchema)]
+enum MetaRepoLocation {
+    #[serde(rename = "meta")]
+    Meta,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "MetaHook")]
+struct MetaHookSchema {
+    id: MetaHookID,
+    name: Option<String>,
+    #[serde(flatten)]
+    options: HookOptions,
+}
+
+/// The schema of the repo of hooks predefined in pre-commit.
+#[derive(JsonSchema)]
+#[schemars(rename = "MetaRepo")]
+#[serde(deny_unknown_fields)]
+struct MetaRepoSchema {
+    repo: MetaRepoLocation,
+    /// Stop running the remaining hooks of this repo after the first failure.
+    #[serde(default)]
+    fail_fast: bool,
+    hooks: Vec<MetaHookSchema>,
+}
+
+impl JsonSchema for Repo {
+    fn schema_name() -> String {
+        "Repo".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![
+                    gen.subschema_for::<RemoteRepoSchema>(),
+                    gen.subschema_for::<LocalRepoSchema>(),
+                    gen.subschema_for::<MetaRepoSchema>(),
+                ]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 impl<'de> Deserialize<'de> for Repo {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -624,14 +1175,63 @@ impl<'de> Deserialize<'de> for Repo {
                 #[derive(Deserialize)]
                 struct _RemoteRepo {
                     rev: String,
+                    #[serde(default, deserialize_with = "deserialize_relative_dir")]
+                    subdirectory: Option<String>,
+                    #[serde(default)]
+                    fail_fast: bool,
                     hooks: Vec<RemoteHook>,
                 }
-                let _RemoteRepo { rev, hooks } = _RemoteRepo::deserialize(rest)
+                let _RemoteRepo {
+                    rev,
+                    subdirectory,
+                    fail_fast,
+                    hooks,
+                } = _RemoteRepo::deserialize(rest)
                     .map_err(|e| serde::de::Error::custom(format!("Invalid remote repo: {e}")))?;
 
                 Ok(Repo::Remote(RemoteRepo {
                     repo: url,
                     rev,
+                    subdirectory,
+                    fail_fast,
+                    hooks,
+                }))
+            }
+            RepoLocation::Path(path) => {
+                #[derive(Deserialize)]
+                struct _PathRepo {
+                    rev: Option<String>,
+                    #[serde(default, deserialize_with = "deserialize_relative_dir")]
+                    subdirectory: Option<String>,
+                    #[serde(default)]
+                    fail_fast: bool,
+                    hooks: Vec<RemoteHook>,
+                }
+                let _PathRepo {
+                    rev,
+                    subdirectory,
+                    fail_fast,
+                    hooks,
+                } = _PathRepo::deserialize(rest)
+                    .map_err(|e| serde::de::Error::custom(format!("Invalid remote repo: {e}")))?;
+
+                let Some(dir) = CONFIG_DIR.with_borrow(Clone::clone) else {
+                    return Err(serde::de::Error::custom(format!(
+                        "Repo `{path}` is a local path, which is only supported in config files on disk"
+                    )));
+                };
+                let url = dunce::canonicalize(dir.join(&path))
+                    .ok()
+                    .and_then(|path| Url::from_directory_path(path).ok())
+                    .ok_or_else(|| {
+                        serde::de::Error::custom(format!("Repo directory `{path}` does not exist"))
+                    })?;
+
+                Ok(Repo::Remote(RemoteRepo {
+                    repo: url,
+                    rev: rev.unwrap_or_default(),
+                    subdirectory,
+                    fail_fast,
                     hooks,
                 }))
             }
@@ -639,27 +1239,31 @@ impl<'de> Deserialize<'de> for Repo {
                 #[derive(Deserialize)]
                 #[serde(deny_unknown_fields)]
                 struct _LocalRepo {
+                    #[serde(default)]
+                    fail_fast: bool,
                     hooks: Vec<LocalHook>,
                 }
-                let _LocalRepo { hooks } = _LocalRepo::deserialize(rest)
+                let _LocalRepo { fail_fast, hooks } = _LocalRepo::deserialize(rest)
                     .map_err(|e| serde::de::Error::custom(format!("Invalid local repo: {e}")))?;
-                Ok(Repo::Local(LocalRepo { hooks }))
+                Ok(Repo::Local(LocalRepo { fail_fast, hooks }))
             }
             RepoLocation::Meta => {
                 #[derive(Deserialize)]
                 #[serde(deny_unknown_fields)]
                 struct _MetaRepo {
+                    #[serde(default)]
+                    fail_fast: bool,
                     hooks: Vec<MetaHook>,
                 }
-                let _MetaRepo { hooks } = _MetaRepo::deserialize(rest)
+                let _MetaRepo { fail_fast, hooks } = _MetaRepo::deserialize(rest)
                     .map_err(|e| serde::de::Error::custom(format!("Invalid meta repo: {e}")))?;
-                Ok(Repo::Meta(MetaRepo { hooks }))
+                Ok(Repo::Meta(MetaRepo { fail_fast, hooks }))
             }
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct ManifestHook {
     /// The id of the hook.
@@ -674,7 +1278,7 @@ pub struct ManifestHook {
     pub options: HookOptions,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 #[serde(transparent)]
 pub struct Manifest {
@@ -690,32 +1294,488 @@ pub enum Error {
     Io(#[from] std::io::Error),
 
     #[error("Failed to parse `{0}`")]
-    Yaml(String, #[source] serde_yaml::Error),
+    Yaml(String, #[source] YamlError),
+
+    #[error("Failed to parse `{0}`")]
+    Toml(String, #[source] toml::de::Error),
 
     #[error("Invalid repo URL: {0}")]
     RepoUrl(#[from] url::ParseError),
+
+    #[error("Failed to fetch `{0}`")]
+    Fetch(String, #[source] reqwest::Error),
+
+    #[error("Config file `{0}` extends itself")]
+    CircularExtends(String),
+
+    #[error("Unexpected keys in config files:\n{0}")]
+    UnexpectedKeys(String),
 }
 
-/// Read the configuration file from the given path.
-pub fn read_config(path: &Path) -> Result<Config, Error> {
-    let content = match fs_err::read_to_string(path) {
-        Ok(content) => content,
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            return Err(Error::NotFound(path.user_display().to_string()));
+/// A YAML error, with the source line it points at.
+#[derive(Debug)]
+pub struct YamlError {
+    error: serde_yaml::Error,
+    snippet: Option<String>,
+}
+
+impl YamlError {
+    fn new(error: serde_yaml::Error, file: &str, content: &str) -> Self {
+        let snippet = error.location().and_then(|location| {
+            let (mut line, mut column, mut width) = (location.line(), location.column(), 1);
+            // Errors of a value point at the mapping it is in, find the bad pattern itself.
+            let message = error.to_string();
+            if let Some(pattern) = message
+                .split_once(&format!("{INVALID_REGEX} `"))
+                .and_then(|(_, rest)| rest.split_once("`: "))
+                .map(|(pattern, _)| pattern)
+            {
+                if let Some((idx, start)) = content
+                    .lines()
+                    .enumerate()
+                    .skip(line.saturating_sub(1))
+                    .find_map(|(idx, source)| source.find(pattern).map(|start| (idx, start)))
+                {
+                    line = idx + 1;
+                    column = start + 1;
+                    width = pattern.len();
+                }
+            }
+            // Errors at the start of the document are not located, like in the message.
+            if location.index() == 0 && width == 1 {
+                return None;
+            }
+            snippet(file, content, line, column, width)
+        });
+        Self { error, snippet }
+    }
+}
+
+impl Display for YamlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n{snippet}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for YamlError {}
+
+/// Render the line of `content` at `line` (1-based),
+/// underlining `width` characters from `column` (1-based).
+fn snippet(file: &str, content: &str, line: usize, column: usize, width: usize) -> Option<String> {
+    let source = content.lines().nth(line.checked_sub(1)?)?;
+    let indent = source
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+    let carets = "^".repeat(width.max(1));
+    let gutter = " ".repeat(line.to_string().len());
+
+    Some(format!(
+        "{gutter}--> {file}:{line}:{column}\n{gutter} |\n{line} | {source}\n{gutter} | {indent}{carets}"
+    ))
+}
+
+/// Where a configuration file is read from.
+enum ConfigSource {
+    Path(PathBuf),
+    Url(Url),
+}
+
+impl ConfigSource {
+    /// Resolve a file in `extends`, relative paths are relative to this file.
+    fn join(&self, extends: &str) -> Result<Self, Error> {
+        if let Ok(url) = Url::parse(extends) {
+            if matches!(url.scheme(), "http" | "https") {
+                return Ok(Self::Url(url));
+            }
+        }
+        match self {
+            Self::Path(path) => Ok(Self::Path(
+                path.parent().unwrap_or(Path::new("")).join(extends),
+            )),
+            Self::Url(url) => Ok(Self::Url(url.join(extends)?)),
+        }
+    }
+
+    fn is_toml(&self) -> bool {
+        let path = match self {
+            Self::Path(path) => path.as_path(),
+            Self::Url(url) => Path::new(url.path()),
+        };
+        is_toml(path)
+    }
+
+    /// An identifier of the file, the same for different paths to the same file.
+    fn id(&self) -> String {
+        match self {
+            Self::Path(path) => dunce::canonicalize(path)
+                .unwrap_or_else(|_| path.clone())
+                .to_string_lossy()
+                .to_string(),
+            Self::Url(url) => url.to_string(),
+        }
+    }
+
+    fn read(&self) -> Result<String, Error> {
+        match self {
+            Self::Path(path) => match fs_err::read_to_string(path) {
+                Ok(content) => Ok(content),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    Err(Error::NotFound(path.user_display().to_string()))
+                }
+                Err(e) => Err(e.into()),
+            },
+            Self::Url(url) => fetch(url).map_err(|e| Error::Fetch(url.to_string(), e)),
+        }
+    }
+}
+
+impl Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Path(path) => write!(f, "{}", path.user_display()),
+            Self::Url(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// Download a remote configuration file.
+///
+/// Configs are read synchronously, possibly from within the async runtime,
+/// so the request runs on its own thread and runtime.
+fn fetch(url: &Url) -> Result<String, reqwest::Error> {
+    let url = url.clone();
+    std::thread::spawn(move || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to build tokio runtime")
+            .block_on(async {
+                http::client()
+                    .get(url)
+                    .timeout(Duration::from_secs(30))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await
+            })
+    })
+    .join()
+    .expect("Failed to fetch config")
+}
+
+/// Deserialize a YAML document, with `<<` merge keys applied.
+///
+/// Anchors and aliases are resolved by the parser, merge keys are commonly used with them
+/// to share options between hooks.
+fn from_yaml<T: serde::de::DeserializeOwned>(content: &str) -> Result<T, serde_yaml::Error> {
+    // Errors of a merged document have no locations, so only merge when needed.
+    if !content.contains("<<") {
+        return serde_yaml::from_str(content);
+    }
+    let mut value: serde_yaml::Value = serde_yaml::from_str(content)?;
+    value.apply_merge()?;
+    T::deserialize(value)
+}
+
+thread_local! {
+    /// The directory of the configuration file being deserialized, local path repos are relative to it.
+    static CONFIG_DIR: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Whether unexpected keys in configuration files are errors instead of warnings.
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Fail on unexpected keys in configuration files, instead of warning about them.
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+/// A level of the configuration file, to point out keys that are set on the wrong one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum KeyLevel {
+    Hook,
+    Repo,
+    Config,
+}
+
+impl KeyLevel {
+    fn name(self) -> &'static str {
+        match self {
+            KeyLevel::Hook => "hooks",
+            KeyLevel::Repo => "repos",
+            KeyLevel::Config => "the top level",
+        }
+    }
+}
+
+/// The keys accepted on each level, taken from the schema so they can't get out of sync.
+static KNOWN_KEYS: LazyLock<[(KeyLevel, Vec<String>); 3]> = LazyLock::new(|| {
+    let root = schemars::schema_for!(Config);
+    let properties = |schema: Option<&Schema>| -> Vec<String> {
+        match schema {
+            Some(Schema::Object(object)) => object
+                .object
+                .as_ref()
+                .map(|object| object.properties.keys().cloned().collect())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    };
+
+    [
+        (
+            KeyLevel::Hook,
+            properties(root.definitions.get("RemoteHook")),
+        ),
+        (
+            KeyLevel::Repo,
+            properties(root.definitions.get("RemoteRepo")),
+        ),
+        (
+            KeyLevel::Config,
+            properties(Some(&Schema::Object(root.schema.clone()))),
+        ),
+    ]
+});
+
+fn known_keys(level: KeyLevel) -> &'static [String] {
+    KNOWN_KEYS
+        .iter()
+        .find(|(l, _)| *l == level)
+        .map(|(_, keys)| keys.as_slice())
+        .unwrap_or_default()
+}
+
+/// Describe a key that is not accepted on `level`, with a hint of what was meant.
+fn unexpected_key(key: &str, level: KeyLevel, location: &str) -> String {
+    let mut message = format!("Unexpected key `{key}` {location}");
+
+    if let Some(other) = [KeyLevel::Hook, KeyLevel::Repo, KeyLevel::Config]
+        .into_iter()
+        .filter(|other| *other != level)
+        .find(|other| known_keys(*other).iter().any(|known| known == key))
+    {
+        message.push_str(&format!(", it belongs to {}", other.name()));
+    } else if let Some((_, suggestion)) = known_keys(level)
+        .iter()
+        .map(|known| (strsim::jaro(key, known), known))
+        .filter(|(confidence, _)| *confidence > 0.7)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+    {
+        message.push_str(&format!(", did you mean `{suggestion}`?"));
+    }
+
+    message
+}
+
+/// The mappings of a configuration file that hold options, with where they are.
+fn mappings<'a>(
+    config: &'a serde_json::Value,
+    file: &str,
+) -> Vec<(
+    KeyLevel,
+    String,
+    &'a serde_json::Map<String, serde_json::Value>,
+)> {
+    let mut mappings = Vec::new();
+    let Some(map) = config.as_object() else {
+        return mappings;
+    };
+    mappings.push((
+        KeyLevel::Config,
+        format!("at the top level of `{file}`"),
+        map,
+    ));
+
+    let repos = config.get("repos").and_then(serde_json::Value::as_array);
+    for repo in repos.into_iter().flatten() {
+        let Some(map) = repo.as_object() else {
+            continue;
+        };
+        let name = repo
+            .get("repo")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or_default();
+        mappings.push((KeyLevel::Repo, format!("in repo `{name}` of `{file}`"), map));
+
+        let hooks = repo.get("hooks").and_then(serde_json::Value::as_array);
+        for hook in hooks.into_iter().flatten() {
+            let Some(map) = hook.as_object() else {
+                continue;
+            };
+            let id = hook
+                .get("id")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default();
+            mappings.push((KeyLevel::Hook, format!("in hook `{id}` of `{file}`"), map));
+        }
+    }
+
+    mappings
+}
+
+/// Find the keys of a configuration file that are not used, instead of silently ignoring them.
+fn unexpected_keys(config: &serde_json::Value, file: &str) -> Vec<String> {
+    let mut messages = Vec::new();
+    for (level, location, map) in mappings(config, file) {
+        for key in map.keys() {
+            if !known_keys(level).iter().any(|known| known == key) {
+                messages.push(unexpected_key(key, level, &location));
+            }
+        }
+    }
+    messages
+}
+
+/// Find the deprecated stage names in a configuration file, they are renamed after the git hooks they run in.
+fn deprecated_stages(config: &serde_json::Value, file: &str) -> Vec<String> {
+    let mut messages = Vec::new();
+    for (level, location, map) in mappings(config, file) {
+        let key = match level {
+            KeyLevel::Config => "default_stages",
+            KeyLevel::Hook => "stages",
+            KeyLevel::Repo => continue,
+        };
+        let stages = map.get(key).and_then(serde_json::Value::as_array);
+        for stage in stages
+            .into_iter()
+            .flatten()
+            .filter_map(serde_json::Value::as_str)
+        {
+            if let Some((_, new)) = DEPRECATED_STAGES.iter().find(|(old, _)| *old == stage) {
+                messages.push(format!(
+                    "The stage `{stage}` {location} is deprecated, use `{new}` instead"
+                ));
+            }
         }
-        Err(e) => return Err(e.into()),
+    }
+    messages
+}
+
+/// Read a configuration file, and merge in the files it extends.
+fn load_config(source: &ConfigSource, seen: &mut Vec<String>) -> Result<Config, Error> {
+    let name = source.to_string();
+    let id = source.id();
+    if seen.contains(&id) {
+        return Err(Error::CircularExtends(name));
+    }
+
+    let content = source.read()?;
+    CONFIG_DIR.set(match source {
+        ConfigSource::Path(path) => Some(path.parent().unwrap_or(Path::new("")).to_path_buf()),
+        ConfigSource::Url(_) => None,
+    });
+    let config = if source.is_toml() {
+        toml::from_str(&content).map_err(|e| Error::Toml(name.clone(), e))
+    } else {
+        from_yaml(&content)
+            .map_err(|e| Error::Yaml(name.clone(), YamlError::new(e, &name, &content)))
     };
-    let config = serde_yaml::from_str(&content)
-        .map_err(|e| Error::Yaml(path.user_display().to_string(), e))?;
-    Ok(config)
+    CONFIG_DIR.set(None);
+    let mut config: Config = config?;
+
+    let value: Option<serde_json::Value> = if source.is_toml() {
+        toml::from_str(&content).ok()
+    } else {
+        from_yaml(&content).ok()
+    };
+    for message in value
+        .as_ref()
+        .map(|value| deprecated_stages(value, &name))
+        .unwrap_or_default()
+    {
+        warn_user_once!("{message}");
+    }
+    let messages = value
+        .as_ref()
+        .map(|value| unexpected_keys(value, &name))
+        .unwrap_or_default();
+    if !messages.is_empty() {
+        if STRICT.load(Ordering::Relaxed) {
+            let messages: Vec<_> = messages.iter().map(|m| format!("  - {m}")).collect();
+            return Err(Error::UnexpectedKeys(messages.join("\n")));
+        }
+        for message in messages {
+            warn_user_once!("{message}");
+        }
+    }
+
+    seen.push(id);
+    let mut base: Option<Config> = None;
+    for extends in std::mem::take(&mut config.extends) {
+        let next = load_config(&source.join(&extends)?, seen)?;
+        base = Some(match base {
+            Some(base) => next.with_base(base),
+            None => next,
+        });
+    }
+    seen.pop();
+
+    Ok(match base {
+        Some(base) => config.with_base(base),
+        None => config,
+    })
+}
+
+/// Whether a configuration file is in TOML, by its extension.
+pub fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
+}
+
+/// The configuration file in `dir`, the YAML file is preferred if both exist.
+pub fn find_config_in(dir: &Path) -> PathBuf {
+    let yaml = dir.join(CONFIG_FILE);
+    if !yaml.exists() {
+        let toml = dir.join(CONFIG_FILE_TOML);
+        if toml.exists() {
+            return toml;
+        }
+    }
+    yaml
+}
+
+/// Read the configuration file from the given path, with the files it extends merged in.
+pub fn read_config(path: &Path) -> Result<Config, Error> {
+    load_config(&ConfigSource::Path(path.to_path_buf()), &mut Vec::new())
+}
+
+/// Read a configuration file as it's written, without the files it extends.
+///
+/// The deprecated stage names are aliases when deserializing, so they can only be seen here.
+pub fn read_config_value(path: &Path) -> Result<serde_json::Value, Error> {
+    let content = fs_err::read_to_string(path)?;
+    parse_config_value(&content, path)
+}
+
+/// Parse the content of the configuration file at `path` as it's written, like
+/// [`read_config_value`].
+pub fn parse_config_value(content: &str, path: &Path) -> Result<serde_json::Value, Error> {
+    let name = path.user_display().to_string();
+    if is_toml(path) {
+        toml::from_str(content).map_err(|e| Error::Toml(name, e))
+    } else {
+        from_yaml(content).map_err(|e| Error::Yaml(name.clone(), YamlError::new(e, &name, content)))
+    }
 }
 
 // TODO: check id duplication?
 /// Read the manifest file from the given path.
 pub fn read_manifest(path: &Path) -> Result<Manifest, Error> {
     let content = fs_err::read_to_string(path)?;
-    let manifest = serde_yaml::from_str(&content)
-        .map_err(|e| Error::Yaml(path.user_display().to_string(), e))?;
+    parse_manifest(&content, &path.user_display().to_string())
+}
+
+/// Parse the content of a manifest file, `name` is where it's from in errors.
+pub fn parse_manifest(content: &str, name: &str) -> Result<Manifest, Error> {
+    let manifest = from_yaml(content)
+        .map_err(|e| Error::Yaml(name.to_string(), YamlError::new(e, name, content)))?;
     Ok(manifest)
 }
 
@@ -723,6 +1783,18 @@ pub fn read_manifest(path: &Path) -> Result<Manifest, Error> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("10"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_duration("10s"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("soon"), None);
+        assert_eq!(parse_duration("10d"), None);
+    }
+
     #[test]
     fn parse_repos() {
         // Local hook should not have `rev`
@@ -739,9 +1811,11 @@ mod tests {
         insta::assert_debug_snapshot!(result, @r#"
         Ok(
             Config {
+                extends: [],
                 repos: [
                     Local(
                         LocalRepo {
+                            fail_fast: false,
                             hooks: [
                                 ManifestHook {
                                     id: "cargo-fmt",
@@ -752,6 +1826,8 @@ mod tests {
                                         alias: None,
                                         files: None,
                                         exclude: None,
+                                        files_glob: None,
+                                        exclude_glob: None,
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
@@ -766,6 +1842,23 @@ mod tests {
                                         require_serial: None,
                                         stages: None,
                                         verbose: None,
+                                        timeout: None,
+                                        retries: None,
+                                        limits: None,
+                                        depends_on: None,
+                                        read_only: None,
+                                        profiles: None,
+                                        tags: None,
+                                        env: None,
+                                        clean_env: None,
+                                        pass_env: None,
+                                        stream: None,
+                                        pty: None,
+                                        max_output_lines: None,
+                                        output_pattern: None,
+                                        skip_if_missing_entry: None,
+                                        working_directory: None,
+                                        expand_env: None,
                                         minimum_pre_commit_version: None,
                                     },
                                 },
@@ -778,7 +1871,21 @@ mod tests {
                 default_stages: None,
                 files: None,
                 exclude: None,
+                files_glob: None,
+                exclude_glob: None,
                 fail_fast: None,
+                jobs: None,
+                parallel: None,
+                stash: None,
+                autofix_stage: None,
+                cache: None,
+                clean_env: None,
+                pass_env: None,
+                max_output_lines: None,
+                expand_env: None,
+                warn_slow_hooks: None,
+                workspace: None,
+                file_types: None,
                 minimum_pre_commit_version: None,
                 ci: None,
             },
@@ -796,11 +1903,11 @@ mod tests {
                       - rust
         "};
         let result = serde_yaml::from_str::<Config>(yaml);
-        insta::assert_debug_snapshot!(result, @r###"
+        insta::assert_debug_snapshot!(result, @r#"
         Err(
-            Error("repos: Invalid local repo: unknown field `rev`, expected `hooks`", line: 2, column: 3),
+            Error("repos: Invalid local repo: unknown field `rev`, expected `fail_fast` or `hooks`", line: 2, column: 3),
         )
-        "###);
+        "#);
 
         // Remote hook should have `rev`.
         let yaml = indoc::indoc! {r"
@@ -814,6 +1921,7 @@ mod tests {
         insta::assert_debug_snapshot!(result, @r#"
         Ok(
             Config {
+                extends: [],
                 repos: [
                     Remote(
                         RemoteRepo {
@@ -833,6 +1941,8 @@ mod tests {
                                 fragment: None,
                             },
                             rev: "v1.0.0",
+                            subdirectory: None,
+                            fail_fast: false,
                             hooks: [
                                 RemoteHook {
                                     id: "typos",
@@ -843,6 +1953,8 @@ mod tests {
                                         alias: None,
                                         files: None,
                                         exclude: None,
+                                        files_glob: None,
+                                        exclude_glob: None,
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
@@ -857,6 +1969,23 @@ mod tests {
                                         require_serial: None,
                                         stages: None,
                                         verbose: None,
+                                        timeout: None,
+                                        retries: None,
+                                        limits: None,
+                                        depends_on: None,
+                                        read_only: None,
+                                        profiles: None,
+                                        tags: None,
+                                        env: None,
+                                        clean_env: None,
+                                        pass_env: None,
+                                        stream: None,
+                                        pty: None,
+                                        max_output_lines: None,
+                                        output_pattern: None,
+                                        skip_if_missing_entry: None,
+                                        working_directory: None,
+                                        expand_env: None,
                                         minimum_pre_commit_version: None,
                                     },
                                 },
@@ -869,7 +1998,21 @@ mod tests {
                 default_stages: None,
                 files: None,
                 exclude: None,
+                files_glob: None,
+                exclude_glob: None,
                 fail_fast: None,
+                jobs: None,
+                parallel: None,
+                stash: None,
+                autofix_stage: None,
+                cache: None,
+                clean_env: None,
+                pass_env: None,
+                max_output_lines: None,
+                expand_env: None,
+                warn_slow_hooks: None,
+                workspace: None,
+                file_types: None,
                 minimum_pre_commit_version: None,
                 ci: None,
             },
@@ -939,9 +2082,11 @@ mod tests {
         insta::assert_debug_snapshot!(result, @r#"
         Ok(
             Config {
+                extends: [],
                 repos: [
                     Local(
                         LocalRepo {
+                            fail_fast: false,
                             hooks: [
                                 ManifestHook {
                                     id: "cargo-fmt",
@@ -952,6 +2097,8 @@ mod tests {
                                         alias: None,
                                         files: None,
                                         exclude: None,
+                                        files_glob: None,
+                                        exclude_glob: None,
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
@@ -966,6 +2113,23 @@ mod tests {
                                         require_serial: None,
                                         stages: None,
                                         verbose: None,
+                                        timeout: None,
+                                        retries: None,
+                                        limits: None,
+                                        depends_on: None,
+                                        read_only: None,
+                                        profiles: None,
+                                        tags: None,
+                                        env: None,
+                                        clean_env: None,
+                                        pass_env: None,
+                                        stream: None,
+                                        pty: None,
+                                        max_output_lines: None,
+                                        output_pattern: None,
+                                        skip_if_missing_entry: None,
+                                        working_directory: None,
+                                        expand_env: None,
                                         minimum_pre_commit_version: None,
                                     },
                                 },
@@ -978,7 +2142,21 @@ mod tests {
                 default_stages: None,
                 files: None,
                 exclude: None,
+                files_glob: None,
+                exclude_glob: None,
                 fail_fast: None,
+                jobs: None,
+                parallel: None,
+                stash: None,
+                autofix_stage: None,
+                cache: None,
+                clean_env: None,
+                pass_env: None,
+                max_output_lines: None,
+                expand_env: None,
+                warn_slow_hooks: None,
+                workspace: None,
+                file_types: None,
                 minimum_pre_commit_version: None,
                 ci: None,
             },
@@ -998,11 +2176,11 @@ mod tests {
                     alias: typo
         "};
         let result = serde_yaml::from_str::<Config>(yaml);
-        insta::assert_debug_snapshot!(result, @r###"
+        insta::assert_debug_snapshot!(result, @r#"
         Err(
-            Error("repos: Invalid meta repo: unknown field `rev`, expected `hooks`", line: 2, column: 3),
+            Error("repos: Invalid meta repo: unknown field `rev`, expected `fail_fast` or `hooks`", line: 2, column: 3),
         )
-        "###);
+        "#);
 
         // Invalid meta hook id
         let yaml = indoc::indoc! { r"
@@ -1061,9 +2239,11 @@ mod tests {
         insta::assert_debug_snapshot!(result, @r#"
         Ok(
             Config {
+                extends: [],
                 repos: [
                     Meta(
                         MetaRepo {
+                            fail_fast: false,
                             hooks: [
                                 MetaHook(
                                     ManifestHook {
@@ -1077,6 +2257,8 @@ mod tests {
                                                 "^\\.pre-commit-config\\.yaml$",
                                             ),
                                             exclude: None,
+                                            files_glob: None,
+                                            exclude_glob: None,
                                             types: None,
                                             types_or: None,
                                             exclude_types: None,
@@ -1091,6 +2273,23 @@ mod tests {
                                             require_serial: None,
                                             stages: None,
                                             verbose: None,
+                                            timeout: None,
+                                            retries: None,
+                                            limits: None,
+                                            depends_on: None,
+                                            read_only: None,
+                                            profiles: None,
+                                            tags: None,
+                                            env: None,
+                                            clean_env: None,
+                                            pass_env: None,
+                                            stream: None,
+                                            pty: None,
+                                            max_output_lines: None,
+                                            output_pattern: None,
+                                            skip_if_missing_entry: None,
+                                            working_directory: None,
+                                            expand_env: None,
                                             minimum_pre_commit_version: None,
                                         },
                                     },
@@ -1107,6 +2306,8 @@ mod tests {
                                                 "^\\.pre-commit-config\\.yaml$",
                                             ),
                                             exclude: None,
+                                            files_glob: None,
+                                            exclude_glob: None,
                                             types: None,
                                             types_or: None,
                                             exclude_types: None,
@@ -1121,6 +2322,23 @@ mod tests {
                                             require_serial: None,
                                             stages: None,
                                             verbose: None,
+                                            timeout: None,
+                                            retries: None,
+                                            limits: None,
+                                            depends_on: None,
+                                            read_only: None,
+                                            profiles: None,
+                                            tags: None,
+                                            env: None,
+                                            clean_env: None,
+                                            pass_env: None,
+                                            stream: None,
+                                            pty: None,
+                                            max_output_lines: None,
+                                            output_pattern: None,
+                                            skip_if_missing_entry: None,
+                                            working_directory: None,
+                                            expand_env: None,
                                             minimum_pre_commit_version: None,
                                         },
                                     },
@@ -1135,6 +2353,8 @@ mod tests {
                                             alias: None,
                                             files: None,
                                             exclude: None,
+                                            files_glob: None,
+                                            exclude_glob: None,
                                             types: None,
                                             types_or: None,
                                             exclude_types: None,
@@ -1151,6 +2371,23 @@ mod tests {
                                             verbose: Some(
                                                 true,
                                             ),
+                                            timeout: None,
+                                            retries: None,
+                                            limits: None,
+                                            depends_on: None,
+                                            read_only: None,
+                                            profiles: None,
+                                            tags: None,
+                                            env: None,
+                                            clean_env: None,
+                                            pass_env: None,
+                                            stream: None,
+                                            pty: None,
+                                            max_output_lines: None,
+                                            output_pattern: None,
+                                            skip_if_missing_entry: None,
+                                            working_directory: None,
+                                            expand_env: None,
                                             minimum_pre_commit_version: None,
                                         },
                                     },
@@ -1164,7 +2401,21 @@ mod tests {
                 default_stages: None,
                 files: None,
                 exclude: None,
+                files_glob: None,
+                exclude_glob: None,
                 fail_fast: None,
+                jobs: None,
+                parallel: None,
+                stash: None,
+                autofix_stage: None,
+                cache: None,
+                clean_env: None,
+                pass_env: None,
+                max_output_lines: None,
+                expand_env: None,
+                warn_slow_hooks: None,
+                workspace: None,
+                file_types: None,
                 minimum_pre_commit_version: None,
                 ci: None,
             },
@@ -1198,9 +2449,11 @@ mod tests {
         insta::assert_debug_snapshot!(result, @r#"
         Ok(
             Config {
+                extends: [],
                 repos: [
                     Local(
                         LocalRepo {
+                            fail_fast: false,
                             hooks: [
                                 ManifestHook {
                                     id: "hook-1",
@@ -1211,6 +2464,8 @@ mod tests {
                                         alias: None,
                                         files: None,
                                         exclude: None,
+                                        files_glob: None,
+                                        exclude_glob: None,
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
@@ -1227,6 +2482,23 @@ mod tests {
                                         require_serial: None,
                                         stages: None,
                                         verbose: None,
+                                        timeout: None,
+                                        retries: None,
+                                        limits: None,
+                                        depends_on: None,
+                                        read_only: None,
+                                        profiles: None,
+                                        tags: None,
+                                        env: None,
+                                        clean_env: None,
+                                        pass_env: None,
+                                        stream: None,
+                                        pty: None,
+                                        max_output_lines: None,
+                                        output_pattern: None,
+                                        skip_if_missing_entry: None,
+                                        working_directory: None,
+                                        expand_env: None,
                                         minimum_pre_commit_version: None,
                                     },
                                 },
@@ -1239,6 +2511,8 @@ mod tests {
                                         alias: None,
                                         files: None,
                                         exclude: None,
+                                        files_glob: None,
+                                        exclude_glob: None,
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
@@ -1255,6 +2529,23 @@ mod tests {
                                         require_serial: None,
                                         stages: None,
                                         verbose: None,
+                                        timeout: None,
+                                        retries: None,
+                                        limits: None,
+                                        depends_on: None,
+                                        read_only: None,
+                                        profiles: None,
+                                        tags: None,
+                                        env: None,
+                                        clean_env: None,
+                                        pass_env: None,
+                                        stream: None,
+                                        pty: None,
+                                        max_output_lines: None,
+                                        output_pattern: None,
+                                        skip_if_missing_entry: None,
+                                        working_directory: None,
+                                        expand_env: None,
                                         minimum_pre_commit_version: None,
                                     },
                                 },
@@ -1267,6 +2558,8 @@ mod tests {
                                         alias: None,
                                         files: None,
                                         exclude: None,
+                                        files_glob: None,
+                                        exclude_glob: None,
                                         types: None,
                                         types_or: None,
                                         exclude_types: None,
@@ -1285,6 +2578,23 @@ mod tests {
                                         require_serial: None,
                                         stages: None,
                                         verbose: None,
+                                        timeout: None,
+                                        retries: None,
+                                        limits: None,
+                                        depends_on: None,
+                                        read_only: None,
+                                        profiles: None,
+                                        tags: None,
+                                        env: None,
+                                        clean_env: None,
+                                        pass_env: None,
+                                        stream: None,
+                                        pty: None,
+                                        max_output_lines: None,
+                                        output_pattern: None,
+                                        skip_if_missing_entry: None,
+                                        working_directory: None,
+                                        expand_env: None,
                                         minimum_pre_commit_version: None,
                                     },
                                 },
@@ -1297,7 +2607,21 @@ mod tests {
                 default_stages: None,
                 files: None,
                 exclude: None,
+                files_glob: None,
+                exclude_glob: None,
                 fail_fast: None,
+                jobs: None,
+                parallel: None,
+                stash: None,
+                autofix_stage: None,
+                cache: None,
+                clean_env: None,
+                pass_env: None,
+                max_output_lines: None,
+                expand_env: None,
+                warn_slow_hooks: None,
+                workspace: None,
+                file_types: None,
                 minimum_pre_commit_version: None,
                 ci: None,
             },
@@ -1318,4 +2642,51 @@ mod tests {
         insta::assert_debug_snapshot!(manifest);
         Ok(())
     }
+
+    #[test]
+    fn merge_keys() -> Result<()> {
+        let yaml = indoc::indoc! { r"
+            repos:
+              - repo: local
+                hooks:
+                  - &check
+                    id: check-1
+                    name: check
+                    entry: check
+                    language: system
+                    files: \.py$
+                  - <<: *check
+                    id: check-2
+                    args: [--strict]
+                  - *check
+        "};
+        let config: Config = from_yaml(yaml)?;
+        let Repo::Local(repo) = &config.repos[0] else {
+            panic!("expected a local repo");
+        };
+        let hooks: Vec<_> = repo
+            .hooks
+            .iter()
+            .map(|hook| {
+                (
+                    hook.id.as_str(),
+                    hook.entry.as_str(),
+                    hook.options.files.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            hooks,
+            [
+                ("check-1", "check", Some("\\.py$")),
+                ("check-2", "check", Some("\\.py$")),
+                ("check-1", "check", Some("\\.py$")),
+            ]
+        );
+        assert_eq!(
+            repo.hooks[1].options.args,
+            Some(vec!["--strict".to_string()])
+        );
+        Ok(())
+    }
 }
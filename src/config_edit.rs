@@ -0,0 +1,822 @@
+//! Edit config files in place, keeping everything that isn't edited as it's written.
+//!
+//! Re-serializing a config loses its comments, quoting style, anchors and blank lines,
+//! so `autoupdate` and `migrate-config` change only the text of the values and keys they update.
+
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::config::is_toml;
+
+/// A step of the path to a node in the document.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// A block-style YAML document, with the positions of its keys and values in the source.
+///
+/// Only the structure of block mappings and sequences is parsed, flow collections, block
+/// scalars and multi-line plain scalars are kept as opaque values.
+#[derive(Debug)]
+pub(crate) struct YamlDocument {
+    source: String,
+    root: Node,
+    edits: Vec<(Range<usize>, String)>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Mapping(Vec<Entry>),
+    Sequence(Vec<Node>),
+    Scalar(Scalar),
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: String,
+    key_range: Range<usize>,
+    value: Node,
+}
+
+#[derive(Debug)]
+struct Scalar {
+    /// The value as written, without its anchor or tag.
+    range: Range<usize>,
+    /// The whitespace and comment after the value, up to the end of its line.
+    trailing: Range<usize>,
+}
+
+/// A line with content, its start moves past `- ` when the item of a sequence is parsed.
+#[derive(Debug, Copy, Clone)]
+struct Line {
+    start: usize,
+    end: usize,
+    indent: usize,
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    lines: Vec<Line>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        let mut lines = Vec::new();
+        let mut offset = 0;
+        for raw in source.split_inclusive('\n') {
+            let text = raw.trim_end_matches(['\n', '\r']);
+            let content = text.trim_start_matches(' ');
+            let indent = text.len() - content.len();
+            let skip = content.is_empty()
+                || content.starts_with('#')
+                || content.starts_with('%')
+                || content == "---"
+                || content == "...";
+            if !skip {
+                lines.push(Line {
+                    start: offset + indent,
+                    end: offset + text.len(),
+                    indent,
+                });
+            }
+            offset += raw.len();
+        }
+        Self {
+            source,
+            lines,
+            pos: 0,
+        }
+    }
+
+    fn line(&self) -> Option<Line> {
+        self.lines.get(self.pos).copied()
+    }
+
+    fn text(&self, line: Line) -> &'a str {
+        &self.source[line.start..line.end]
+    }
+
+    fn is_item(&self, line: Line) -> bool {
+        let text = self.text(line);
+        text == "-" || text.starts_with("- ")
+    }
+
+    /// Parse the node starting at the current line.
+    fn node(&mut self) -> Node {
+        let Some(line) = self.line() else {
+            return Node::Scalar(Scalar {
+                range: self.source.len()..self.source.len(),
+                trailing: self.source.len()..self.source.len(),
+            });
+        };
+        if self.is_item(line) {
+            self.sequence(line.indent)
+        } else if split_key(self.text(line)).is_some() {
+            self.mapping(line.indent)
+        } else {
+            Node::Scalar(self.scalar(line.start, line.indent.saturating_sub(1)))
+        }
+    }
+
+    fn sequence(&mut self, indent: usize) -> Node {
+        let mut items = Vec::new();
+        while let Some(line) = self.line() {
+            if line.indent != indent || !self.is_item(line) {
+                break;
+            }
+            let rest = &self.text(line)[1..];
+            let content = rest.trim_start_matches(' ');
+            if content.is_empty() || content.starts_with('#') {
+                // The item is on the following lines.
+                self.pos += 1;
+                let after = line.start + 1;
+                items.push(self.nested(indent, false, after..line.end));
+            } else {
+                let start = line.end - content.len();
+                self.lines[self.pos] = Line {
+                    start,
+                    end: line.end,
+                    indent: indent + (start - line.start),
+                };
+                items.push(self.node());
+            }
+        }
+        Node::Sequence(items)
+    }
+
+    fn mapping(&mut self, indent: usize) -> Node {
+        let mut entries = Vec::new();
+        while let Some(line) = self.line() {
+            if line.indent != indent || self.is_item(line) {
+                break;
+            }
+            let text = self.text(line);
+            let Some((key_end, value_start)) = split_key(text) else {
+                break;
+            };
+            let key_range = line.start..line.start + key_end;
+            let key = unquote(&text[..key_end]);
+
+            // Skip the anchor or tag of the value.
+            let colon_end = line.start + value_start;
+            let mut rest = self.source[colon_end..line.end].trim_start_matches(' ');
+            while rest.starts_with('&') || rest.starts_with('!') {
+                let token = rest.find(' ').unwrap_or(rest.len());
+                rest = rest[token..].trim_start_matches(' ');
+            }
+            let value_start = line.end - rest.len();
+
+            let value = if rest.is_empty() || rest.starts_with('#') {
+                self.pos += 1;
+                // After the colon, or the anchor or tag.
+                let empty_at = self.source[..value_start].trim_end().len();
+                self.nested(indent, true, empty_at..line.end)
+            } else {
+                Node::Scalar(self.scalar(value_start, indent))
+            };
+            entries.push(Entry {
+                key,
+                key_range,
+                value,
+            });
+        }
+        Node::Mapping(entries)
+    }
+
+    /// Parse the node on the lines after a key or `-` without a value on its line.
+    ///
+    /// Sequences can be at the same indentation as the key of their mapping.
+    /// Without one, the value is empty, at the start of `rest`, the rest of the line.
+    fn nested(&mut self, indent: usize, allow_same: bool, rest: Range<usize>) -> Node {
+        match self.line() {
+            Some(line)
+                if line.indent > indent
+                    || (allow_same && line.indent == indent && self.is_item(line)) =>
+            {
+                self.node()
+            }
+            _ => Node::Scalar(Scalar {
+                range: rest.start..rest.start,
+                trailing: rest,
+            }),
+        }
+    }
+
+    /// Parse a scalar starting at `start` on the current line, skipping its continuation lines,
+    /// which are indented more than `indent`.
+    fn scalar(&mut self, start: usize, indent: usize) -> Scalar {
+        let line = self.lines[self.pos];
+        let mut end = start + value_len(&self.source[start..line.end]);
+        let mut line_end = line.end;
+        self.pos += 1;
+
+        // A flow collection can span lines until its brackets are balanced.
+        let mut depth = if self.source[start..end].starts_with(['[', '{']) {
+            flow_depth(&self.source[start..end])
+        } else {
+            0
+        };
+        while depth > 0 {
+            let Some(next) = self.line() else {
+                break;
+            };
+            let text = self.text(next);
+            let len = value_len(text);
+            depth += flow_depth(&text[..len]);
+            end = next.start + len;
+            line_end = next.end;
+            self.pos += 1;
+        }
+
+        while self.line().is_some_and(|next| next.indent > indent) {
+            self.pos += 1;
+        }
+
+        Scalar {
+            range: start..end,
+            trailing: end..line_end,
+        }
+    }
+}
+
+/// Find the end of the key and the start of the value in a `key: value` line.
+fn split_key(text: &str) -> Option<(usize, usize)> {
+    let key_end = match text.chars().next()? {
+        quote @ ('"' | '\'') => quoted_len(text, quote)?,
+        '[' | '{' | '#' | '&' | '*' | '!' | '|' | '>' => return None,
+        _ => {
+            let mut key_end = None;
+            for (i, _) in text.match_indices(':') {
+                let after = &text[i + 1..];
+                if after.is_empty() || after.starts_with(' ') {
+                    key_end = Some(i);
+                    break;
+                }
+            }
+            let key_end = key_end?;
+            // A comment before the colon means there is no key.
+            if text[..key_end].contains(" #") {
+                return None;
+            }
+            return Some((text[..key_end].trim_end().len(), key_end + 1));
+        }
+    };
+    let rest = &text[key_end..];
+    let colon = rest.len() - rest.trim_start_matches(' ').len();
+    let after = &rest[colon..];
+    if after.starts_with(':') && (after.len() == 1 || after[1..].starts_with(' ')) {
+        Some((key_end, key_end + colon + 1))
+    } else {
+        None
+    }
+}
+
+/// The length of a quoted string at the start of `text`, including the quotes.
+fn quoted_len(text: &str, quote: char) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if quote == '"' && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            if quote == '\'' && text[i + 1..].starts_with('\'') {
+                // `''` is an escaped single quote.
+                chars.next();
+            } else {
+                return Some(i + 1);
+            }
+        }
+    }
+    None
+}
+
+/// The length of the value at the start of `text`, without the comment and whitespace after it.
+fn value_len(text: &str) -> usize {
+    let mut i = 0;
+    let mut token_start = true;
+    while let Some(c) = text[i..].chars().next() {
+        if token_start && (c == '"' || c == '\'') {
+            i += quoted_len(&text[i..], c).unwrap_or(text.len() - i);
+            token_start = false;
+            continue;
+        }
+        if c == '#' && text[..i].ends_with(' ') {
+            break;
+        }
+        token_start = matches!(c, '[' | '{' | ',' | ' ' | ':');
+        i += c.len_utf8();
+    }
+    text[..i].trim_end().len()
+}
+
+/// How many flow collections are opened and not closed in `text`.
+fn flow_depth(text: &str) -> i32 {
+    let mut depth = 0;
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        match c {
+            '"' | '\'' => {
+                i += quoted_len(&text[i..], c).unwrap_or(text.len() - i);
+                continue;
+            }
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth -= 1,
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    depth
+}
+
+/// The ranges of the items of a single-line flow sequence like `[a, 'b']`, relative to it.
+fn flow_items(text: &str) -> Option<Vec<Range<usize>>> {
+    if !text.starts_with('[') || !text.ends_with(']') {
+        return None;
+    }
+    let end = text.len() - 1;
+    let mut items = Vec::new();
+    let mut start = 1;
+    let mut i = 1;
+    while i < end {
+        let c = text[i..].chars().next()?;
+        match c {
+            '"' | '\'' => {
+                i += quoted_len(&text[i..end], c)?;
+                continue;
+            }
+            // Nested collections can't be edited as strings.
+            '[' | ']' | '{' | '}' | '\n' => return None,
+            ',' => {
+                items.push(start..i);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    items.push(start..end);
+
+    Some(
+        items
+            .into_iter()
+            .filter_map(|range| {
+                // The last item is empty after a trailing comma.
+                let item = &text[range.clone()];
+                let start = range.start + (item.len() - item.trim_start().len());
+                let end = start + item.trim().len();
+                (start < end).then_some(start..end)
+            })
+            .collect(),
+    )
+}
+
+fn unquote(text: &str) -> String {
+    if let Some(inner) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+        inner.replace("''", "'")
+    } else if text.starts_with('"') {
+        serde_yaml::from_str(text).unwrap_or_else(|_| text.to_string())
+    } else {
+        text.to_string()
+    }
+}
+
+/// Write a string in the same quoting style as `old`, quoting it if it can't be a plain scalar.
+fn quote_like(old: &str, value: &str) -> String {
+    if old.starts_with('"') {
+        return serde_json::to_string(value).expect("strings can always be serialized");
+    }
+    let is_plain = !old.starts_with('\'')
+        && !value.is_empty()
+        && !value.starts_with(|c: char| c.is_whitespace() || "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        && !value.ends_with(char::is_whitespace)
+        && !value.contains(": ")
+        && !value.contains(" #")
+        && matches!(
+            serde_yaml::from_str::<serde_yaml::Value>(value),
+            Ok(serde_yaml::Value::String(_))
+        );
+    if is_plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+impl YamlDocument {
+    pub(crate) fn parse(source: String) -> Self {
+        let root = Parser::new(&source).node();
+        Self {
+            source,
+            root,
+            edits: Vec::new(),
+        }
+    }
+
+    fn entry(&self, path: &[Segment]) -> Option<&Entry> {
+        let (last, parents) = path.split_last()?;
+        let Segment::Key(key) = last else {
+            return None;
+        };
+        let Node::Mapping(entries) = self.node(parents)? else {
+            return None;
+        };
+        entries.iter().find(|entry| entry.key == *key)
+    }
+
+    fn node(&self, path: &[Segment]) -> Option<&Node> {
+        let mut node = &self.root;
+        for segment in path {
+            node = match (node, segment) {
+                (Node::Mapping(entries), Segment::Key(key)) => {
+                    &entries.iter().find(|entry| entry.key == *key)?.value
+                }
+                (Node::Sequence(items), Segment::Index(index)) => items.get(*index)?,
+                _ => return None,
+            };
+        }
+        Some(node)
+    }
+
+    fn scalar(&self, path: &[Segment]) -> Option<&Scalar> {
+        match self.node(path)? {
+            Node::Scalar(scalar) => Some(scalar),
+            _ => None,
+        }
+    }
+
+    /// The keys of the mapping at `path`, in their order in the document.
+    pub(crate) fn keys(&self, path: &[Segment]) -> Vec<&str> {
+        match self.node(path) {
+            Some(Node::Mapping(entries)) => {
+                entries.iter().map(|entry| entry.key.as_str()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The number of items of the sequence at `path`.
+    pub(crate) fn len(&self, path: &[Segment]) -> usize {
+        match self.node(path) {
+            Some(Node::Sequence(items)) => items.len(),
+            _ => 0,
+        }
+    }
+
+    /// The ranges of the items of the sequence at `path`, in block or flow style.
+    fn item_ranges(&self, path: &[Segment]) -> Option<Vec<Range<usize>>> {
+        match self.node(path)? {
+            Node::Sequence(items) => items
+                .iter()
+                .map(|item| match item {
+                    Node::Scalar(scalar) => Some(scalar.range.clone()),
+                    _ => None,
+                })
+                .collect(),
+            Node::Scalar(scalar) => {
+                let ranges = flow_items(&self.source[scalar.range.clone()])?;
+                Some(
+                    ranges
+                        .into_iter()
+                        .map(|range| {
+                            scalar.range.start + range.start..scalar.range.start + range.end
+                        })
+                        .collect(),
+                )
+            }
+            Node::Mapping(_) => None,
+        }
+    }
+
+    /// The strings of the sequence at `path`, empty if it's not a sequence of strings.
+    pub(crate) fn strings(&self, path: &[Segment]) -> Vec<String> {
+        self.item_ranges(path)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|range| unquote(&self.source[range]))
+            .collect()
+    }
+
+    /// Set the item at `index` of the sequence of strings at `path`, in the quoting style it's written in.
+    pub(crate) fn set_item(&mut self, path: &[Segment], index: usize, value: &str) -> bool {
+        let Some(range) = self
+            .item_ranges(path)
+            .and_then(|ranges| ranges.into_iter().nth(index))
+        else {
+            return false;
+        };
+        let new = quote_like(&self.source[range.clone()], value);
+        self.edits.push((range, new));
+        true
+    }
+
+    /// Set the string at `path`, in the quoting style it's written in.
+    ///
+    /// Returns `false` if there is no scalar at `path`.
+    pub(crate) fn set_string(&mut self, path: &[Segment], value: &str) -> bool {
+        let Some(scalar) = self.scalar(path) else {
+            return false;
+        };
+        let range = scalar.range.clone();
+        let old = &self.source[range.clone()];
+        let mut new = quote_like(old, value);
+        if range.is_empty() {
+            new.insert(0, ' ');
+        }
+        self.edits.push((range, new));
+        true
+    }
+
+    /// Replace or remove the comment after the scalar at `path`.
+    pub(crate) fn set_comment(&mut self, path: &[Segment], comment: Option<&str>) -> bool {
+        let Some(scalar) = self.scalar(path) else {
+            return false;
+        };
+        let trailing = scalar.trailing.clone();
+        let new = comment
+            .map(|comment| format!("  # {comment}"))
+            .unwrap_or_default();
+        self.edits.push((trailing, new));
+        true
+    }
+
+    /// The comment after the scalar at `path`, without the `#`.
+    pub(crate) fn comment(&self, path: &[Segment]) -> Option<&str> {
+        let scalar = self.scalar(path)?;
+        let trailing = self.source[scalar.trailing.clone()].trim();
+        trailing.strip_prefix('#').map(str::trim)
+    }
+
+    /// Rename the key at `path`, keeping its value and position.
+    pub(crate) fn rename_key(&mut self, path: &[Segment], key: &str) -> bool {
+        let Some(entry) = self.entry(path) else {
+            return false;
+        };
+        let range = entry.key_range.clone();
+        let new = quote_like(&self.source[range.clone()], key);
+        self.edits.push((range, new));
+        true
+    }
+
+    pub(crate) fn is_modified(&self) -> bool {
+        !self.edits.is_empty()
+    }
+
+    /// The source with all the edits applied.
+    pub(crate) fn finish(mut self) -> String {
+        // From the end, so the ranges of the remaining edits stay valid.
+        self.edits
+            .sort_by_key(|(range, _)| std::cmp::Reverse((range.start, range.end)));
+        for (range, new) in self.edits {
+            self.source.replace_range(range, &new);
+        }
+        self.source
+    }
+}
+
+/// A config file in YAML or TOML, edited in place.
+pub(crate) enum ConfigDocument {
+    Yaml(YamlDocument),
+    Toml(toml_edit::DocumentMut),
+}
+
+impl ConfigDocument {
+    pub(crate) fn parse(path: &Path, source: String) -> Result<Self> {
+        if is_toml(path) {
+            let document = source
+                .parse()
+                .with_context(|| format!("Failed to parse `{}`", path.display()))?;
+            Ok(Self::Toml(document))
+        } else {
+            Ok(Self::Yaml(YamlDocument::parse(source)))
+        }
+    }
+
+    /// Set the `rev` of the repo at `index`, with a `# frozen: <tag>` comment when it's a frozen tag.
+    pub(crate) fn set_rev(&mut self, index: usize, rev: &str, frozen: Option<&str>) -> Result<()> {
+        let comment = frozen.map(|tag| format!("frozen: {tag}"));
+        let found = match self {
+            Self::Yaml(document) => {
+                let path = [
+                    Segment::Key("repos"),
+                    Segment::Index(index),
+                    Segment::Key("rev"),
+                ];
+                // Only replace the comments written by `--freeze`, others are kept.
+                let frozen_comment = document
+                    .comment(&path)
+                    .is_some_and(|comment| comment.starts_with("frozen:"));
+                let found = document.set_string(&path, rev);
+                if found && (comment.is_some() || frozen_comment) {
+                    document.set_comment(&path, comment.as_deref());
+                }
+                found
+            }
+            Self::Toml(document) => {
+                let value = document
+                    .get_mut("repos")
+                    .and_then(|repos| repos.get_mut(index))
+                    .and_then(|repo| repo.get_mut("rev"))
+                    .and_then(toml_edit::Item::as_value_mut);
+                if let Some(value) = value {
+                    let mut decor = value.decor().clone();
+                    let frozen_comment = decor
+                        .suffix()
+                        .and_then(|suffix| suffix.as_str())
+                        .is_some_and(|suffix| suffix.trim().starts_with("# frozen:"));
+                    if let Some(comment) = &comment {
+                        decor.set_suffix(format!("  # {comment}"));
+                    } else if frozen_comment {
+                        decor.set_suffix("");
+                    }
+                    // Keep literal strings literal, if the rev can be one.
+                    let literal = matches!(
+                        value,
+                        toml_edit::Value::String(old) if old.display_repr().starts_with('\'')
+                    );
+                    *value = if literal && !rev.contains(['\'', '\n']) {
+                        format!("'{rev}'").parse()?
+                    } else {
+                        rev.into()
+                    };
+                    *value.decor_mut() = decor;
+                    true
+                } else {
+                    false
+                }
+            }
+        };
+        if !found {
+            anyhow::bail!(
+                "Failed to find the `rev` of repo #{} in the config",
+                index + 1
+            );
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> String {
+        match self {
+            Self::Yaml(document) => document.finish(),
+            Self::Toml(document) => document.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = indoc::indoc! {r#"
+        # The hooks of the project.
+        default_stages: [pre-commit, pre-push]
+
+        repos:
+          - repo: https://github.com/pre-commit/pre-commit-hooks
+            rev: v4.0.0  # the last release
+            hooks:
+              - id: trailing-whitespace
+                args: [
+                  --markdown-linebreak-ext=md,
+                ]
+              - id: check-yaml
+          -   repo: 'https://github.com/psf/black'
+              sha: &black "22.3.0"
+              hooks:
+              - id: black
+                description: |
+                  rev: not a key
+          - repo: local
+            hooks:
+            - id: echo
+              name: echo
+              entry: echo
+              language: system
+    "#};
+
+    #[test]
+    fn parse_structure() {
+        let document = YamlDocument::parse(CONFIG.to_string());
+        assert_eq!(document.keys(&[]), ["default_stages", "repos"]);
+        let repos = [Segment::Key("repos")];
+        assert_eq!(document.len(&repos), 3);
+        assert_eq!(
+            document.keys(&[Segment::Key("repos"), Segment::Index(1)]),
+            ["repo", "sha", "hooks"]
+        );
+        let hooks = [
+            Segment::Key("repos"),
+            Segment::Index(0),
+            Segment::Key("hooks"),
+        ];
+        assert_eq!(document.len(&hooks), 2);
+        let hook = [
+            Segment::Key("repos"),
+            Segment::Index(1),
+            Segment::Key("hooks"),
+            Segment::Index(0),
+        ];
+        assert_eq!(document.keys(&hook), ["id", "description"]);
+        let hook = [
+            Segment::Key("repos"),
+            Segment::Index(2),
+            Segment::Key("hooks"),
+            Segment::Index(0),
+        ];
+        assert_eq!(document.keys(&hook), ["id", "name", "entry", "language"]);
+        let rev = [
+            Segment::Key("repos"),
+            Segment::Index(0),
+            Segment::Key("rev"),
+        ];
+        assert_eq!(document.comment(&rev), Some("the last release"));
+    }
+
+    #[test]
+    fn edit_in_place() {
+        let mut document = YamlDocument::parse(CONFIG.to_string());
+        let rev = [
+            Segment::Key("repos"),
+            Segment::Index(0),
+            Segment::Key("rev"),
+        ];
+        assert!(document.set_string(&rev, "v5.0.0"));
+        let sha = [
+            Segment::Key("repos"),
+            Segment::Index(1),
+            Segment::Key("sha"),
+        ];
+        assert!(document.set_string(&sha, "24.1.0"));
+        assert!(document.set_comment(&sha, Some("frozen: 24.1.0")));
+        assert!(document.rename_key(&sha, "rev"));
+        let args = [
+            Segment::Key("repos"),
+            Segment::Index(0),
+            Segment::Key("hooks"),
+            Segment::Index(0),
+            Segment::Key("args"),
+        ];
+        assert!(document.set_string(&args, "[]"));
+        assert!(!document.set_string(&[Segment::Key("missing")], "value"));
+
+        insta::assert_snapshot!(document.finish(), @r#"
+        # The hooks of the project.
+        default_stages: [pre-commit, pre-push]
+
+        repos:
+          - repo: https://github.com/pre-commit/pre-commit-hooks
+            rev: v5.0.0  # the last release
+            hooks:
+              - id: trailing-whitespace
+                args: '[]'
+              - id: check-yaml
+          -   repo: 'https://github.com/psf/black'
+              rev: &black "24.1.0"  # frozen: 24.1.0
+              hooks:
+              - id: black
+                description: |
+                  rev: not a key
+          - repo: local
+            hooks:
+            - id: echo
+              name: echo
+              entry: echo
+              language: system
+        "#);
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote_like("v1", "v2.0.0"), "v2.0.0");
+        assert_eq!(quote_like("v1", "1.0"), "'1.0'");
+        assert_eq!(quote_like("v1", "true"), "'true'");
+        assert_eq!(quote_like("v1", "it's"), "it's");
+        assert_eq!(quote_like("'v1'", "it's"), "'it''s'");
+        assert_eq!(quote_like("\"v1\"", "v\"2"), "\"v\\\"2\"");
+    }
+
+    #[test]
+    fn set_toml_rev() -> Result<()> {
+        let source = indoc::indoc! {r#"
+            # The hooks of the project.
+            [[repos]]
+            repo = "https://github.com/pre-commit/pre-commit-hooks"
+            rev = 'v4.0.0' # frozen: v4.0.0
+            hooks = [{ id = "trailing-whitespace" }]
+        "#};
+        let mut document = ConfigDocument::parse(Path::new("config.toml"), source.to_string())?;
+        document.set_rev(0, "v5.0.0", None)?;
+        assert!(document.set_rev(1, "v5.0.0", None).is_err());
+        insta::assert_snapshot!(document.finish(), @r#"
+        # The hooks of the project.
+        [[repos]]
+        repo = "https://github.com/pre-commit/pre-commit-hooks"
+        rev = 'v5.0.0'
+        hooks = [{ id = "trailing-whitespace" }]
+        "#);
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use reqwest::header::RANGE;
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tracing::debug;
+
+use crate::http;
+
+/// The file a download of `dest` is written to until it's complete.
+fn part_path(dest: &Path) -> PathBuf {
+    let mut name = OsString::from(dest.as_os_str());
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+/// Fetch the sha256 checksum published next to a release file, in `<url>.sha256`
+/// like `<checksum>  <file name>`.
+pub(crate) async fn fetch_checksum(url: &str) -> Result<String> {
+    let content = http::client()
+        .get(format!("{url}.sha256"))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let checksum = content
+        .split_whitespace()
+        .next()
+        .with_context(|| format!("No checksum found for `{url}`"))?;
+    Ok(checksum.to_string())
+}
+
+/// Download `url` to `dest`, verifying it has the given sha256 checksum.
+///
+/// The content is written to `<dest>.part` first, so a download interrupted by a network
+/// failure or a killed process is resumed by the next call, if the server supports range
+/// requests. It's moved to `dest` only once complete and verified, a corrupt download
+/// is removed instead.
+pub(crate) async fn download(url: &str, sha256: &str, dest: &Path) -> Result<()> {
+    let part = part_path(dest);
+    let mut hasher = Sha256::new();
+    let mut offset = 0;
+    if let Ok(content) = fs_err::read(&part) {
+        hasher.update(&content);
+        offset = content.len() as u64;
+    }
+
+    let mut request = http::client().get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={offset}-"));
+    }
+    let response = request.send().await?;
+
+    // The previous download got all the content, but wasn't moved in place.
+    if offset == 0 || response.status() != StatusCode::RANGE_NOT_SATISFIABLE {
+        let mut response = response.error_for_status()?;
+        let mut options = tokio::fs::OpenOptions::new();
+        options.create(true);
+        if response.status() == StatusCode::PARTIAL_CONTENT {
+            debug!(url, offset, "Resuming download");
+            options.append(true);
+        } else {
+            debug!(url, dest = %dest.display(), "Downloading");
+            hasher = Sha256::new();
+            options.write(true).truncate(true);
+        }
+        let mut file = options
+            .open(&part)
+            .await
+            .with_context(|| format!("Failed to open `{}`", part.display()))?;
+
+        while let Some(chunk) = response.chunk().await? {
+            hasher.update(&chunk);
+            file.write_all(&chunk).await?;
+        }
+        file.sync_all().await?;
+    }
+
+    let checksum = format!("{:x}", hasher.finalize());
+    if !checksum.eq_ignore_ascii_case(sha256) {
+        fs_err::remove_file(&part)?;
+        anyhow::bail!("Checksum mismatch of `{url}`, expected {sha256} but got {checksum}");
+    }
+    fs_err::rename(&part, dest)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+
+    const CONTENT: &[u8] = b"the content of a toolchain archive";
+
+    /// Serve `CONTENT` to `requests` requests, honoring `Range` headers.
+    fn serve(requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/archive.tar.gz", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut offset = 0;
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(range) = line.to_lowercase().strip_prefix("range: bytes=") {
+                        offset = range.trim_end_matches('-').parse().unwrap();
+                    }
+                }
+                let response = if offset >= CONTENT.len() {
+                    b"HTTP/1.1 416 Range Not Satisfiable\r\ncontent-length: 0\r\n\r\n".to_vec()
+                } else {
+                    let status = if offset > 0 {
+                        "206 Partial Content"
+                    } else {
+                        "200 OK"
+                    };
+                    let body = &CONTENT[offset..];
+                    let mut response = format!(
+                        "HTTP/1.1 {status}\r\ncontent-length: {}\r\n\r\n",
+                        body.len()
+                    )
+                    .into_bytes();
+                    response.extend_from_slice(body);
+                    response
+                };
+                stream.write_all(&response).unwrap();
+            }
+        });
+        url
+    }
+
+    fn checksum() -> String {
+        format!("{:x}", Sha256::digest(CONTENT))
+    }
+
+    #[tokio::test]
+    async fn download_verified() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dest = dir.path().join("archive.tar.gz");
+
+        download(&serve(1), &checksum(), &dest).await?;
+        assert_eq!(fs_err::read(&dest)?, CONTENT);
+        assert!(!part_path(&dest).exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_resumed() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dest = dir.path().join("archive.tar.gz");
+        let url = serve(2);
+
+        // An interrupted download continues where it stopped.
+        fs_err::write(part_path(&dest), &CONTENT[..10])?;
+        download(&url, &checksum(), &dest).await?;
+        assert_eq!(fs_err::read(&dest)?, CONTENT);
+
+        // A complete download that wasn't moved in place yet.
+        fs_err::remove_file(&dest)?;
+        fs_err::write(part_path(&dest), CONTENT)?;
+        download(&url, &checksum(), &dest).await?;
+        assert_eq!(fs_err::read(&dest)?, CONTENT);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_corrupt() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dest = dir.path().join("archive.tar.gz");
+
+        let err = download(&serve(1), &"0".repeat(64), &dest)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Checksum mismatch"));
+        assert!(!dest.exists());
+        assert!(!part_path(&dest).exists());
+
+        Ok(())
+    }
+}
@@ -2,10 +2,25 @@ pub struct EnvVars;
 
 impl EnvVars {
     pub const PATH: &'static str = "PATH";
+    pub const COLUMNS: &'static str = "COLUMNS";
+    pub const CI: &'static str = "CI";
+    pub const XDG_CACHE_HOME: &'static str = "XDG_CACHE_HOME";
+    pub const HTTP_PROXY: &'static str = "HTTP_PROXY";
+    pub const HTTP_PROXY_LOWER: &'static str = "http_proxy";
+    pub const SSL_CERT_FILE: &'static str = "SSL_CERT_FILE";
+    pub const GIT_SSL_CAINFO: &'static str = "GIT_SSL_CAINFO";
+    pub const GIT_DIR: &'static str = "GIT_DIR";
+    pub const GIT_WORK_TREE: &'static str = "GIT_WORK_TREE";
+    pub const GIT_INDEX_FILE: &'static str = "GIT_INDEX_FILE";
+    pub const PIP_CERT: &'static str = "PIP_CERT";
+    pub const NODE_EXTRA_CA_CERTS: &'static str = "NODE_EXTRA_CA_CERTS";
 
     pub const SKIP: &'static str = "SKIP";
 
     pub const PREFLIGIT_HOME: &'static str = "PREFLIGIT_HOME";
+    pub const PREFLIGIT_USER_CONFIG: &'static str = "PREFLIGIT_USER_CONFIG";
+    pub const PREFLIGIT_NETWORK_RETRIES: &'static str = "PREFLIGIT_NETWORK_RETRIES";
+    pub const PREFLIGIT_REMOTE_CACHE_TOKEN: &'static str = "PREFLIGIT_REMOTE_CACHE_TOKEN";
 
     // Pre-commit specific environment variables
     pub const PRE_COMMIT_HOME: &'static str = "PRE_COMMIT_HOME";
@@ -13,6 +28,13 @@ impl EnvVars {
     pub const PRE_COMMIT_NO_CONCURRENCY: &'static str = "PRE_COMMIT_NO_CONCURRENCY";
     pub const _PRE_COMMIT_SKIP_POST_CHECKOUT: &'static str = "_PRE_COMMIT_SKIP_POST_CHECKOUT";
 
+    // GitHub Actions specific environment variables
+    pub const GITHUB_ACTIONS: &'static str = "GITHUB_ACTIONS";
+    pub const GITHUB_STEP_SUMMARY: &'static str = "GITHUB_STEP_SUMMARY";
+
     pub const UV_NO_CACHE: &'static str = "UV_NO_CACHE";
+    pub const UV_DEFAULT_INDEX: &'static str = "UV_DEFAULT_INDEX";
+    pub const UV_PYTHON_INSTALL_MIRROR: &'static str = "UV_PYTHON_INSTALL_MIRROR";
     pub const UV_PYTHON_INSTALL_DIR: &'static str = "UV_PYTHON_INSTALL_DIR";
+    pub const UV_NATIVE_TLS: &'static str = "UV_NATIVE_TLS";
 }
@@ -169,21 +169,97 @@ pub fn copy_atomic(from: impl AsRef<Path>, to: impl AsRef<Path>) -> std::io::Res
     Ok(())
 }
 
-/// Recursively copy a directory and its contents.
-pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> std::io::Result<()> {
-    fs_err::create_dir_all(&dst)?;
-    for entry in fs_err::read_dir(src.as_ref())? {
+/// Copy the directory `from` to `to`, sharing the data of the files where the filesystem can.
+///
+/// Files are cloned copy-on-write where supported (reflinks on Linux, `clonefile` on macOS),
+/// hard linked otherwise, and copied as a last resort. Repos and environments are not changed
+/// in place once installed, so their files can be shared.
+pub fn clone_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs_err::create_dir_all(to)?;
+    for entry in fs_err::read_dir(from)? {
         let entry = entry?;
-        let ty = entry.file_type()?;
-        if ty.is_dir() {
-            copy_dir_all(entry.path(), dst.as_ref().join(entry.file_name()))?;
+        let source = entry.path();
+        let dest = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            symlink(fs_err::read_link(&source)?, &dest)?;
+        } else if file_type.is_dir() {
+            clone_dir(&source, &dest)?;
         } else {
-            fs_err::copy(entry.path(), dst.as_ref().join(entry.file_name()))?;
+            clone_file(&source, &dest)?;
         }
     }
     Ok(())
 }
 
+/// Clone a file like [`clone_dir`] clones the files of a tree.
+pub fn clone_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Err(err) = reflink(from, to) {
+        trace!(from = %from.display(), "Failed to reflink, falling back to a hard link: {err}");
+    } else {
+        return Ok(());
+    }
+    if std::fs::hard_link(from, to).is_ok() {
+        return Ok(());
+    }
+    fs_err::copy(from, to)?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(from: &Path, to: &Path) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let source = std::fs::File::open(from)?;
+    let dest = std::fs::File::create_new(to)?;
+    if unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE as _, source.as_raw_fd()) } == 0 {
+        dest.set_permissions(source.metadata()?.permissions())?;
+        return Ok(());
+    }
+    let err = std::io::Error::last_os_error();
+    drop(dest);
+    let _ = std::fs::remove_file(to);
+    Err(err)
+}
+
+#[cfg(target_os = "macos")]
+fn reflink(from: &Path, to: &Path) -> std::io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let from = CString::new(from.as_os_str().as_bytes())?;
+    let to = CString::new(to.as_os_str().as_bytes())?;
+    if unsafe { libc::clonefile(from.as_ptr(), to.as_ptr(), 0) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink(_from: &Path, _to: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Copy-on-write clones are not supported on this platform",
+    ))
+}
+
+/// Create a symbolic link at `link` pointing to `original`.
+#[cfg(unix)]
+pub fn symlink(original: impl AsRef<Path>, link: impl AsRef<Path>) -> std::io::Result<()> {
+    fs_err::os::unix::fs::symlink(original, link)
+}
+
+/// Create a symbolic link at `link` pointing to `original`.
+#[cfg(windows)]
+pub fn symlink(original: impl AsRef<Path>, link: impl AsRef<Path>) -> std::io::Result<()> {
+    if original.as_ref().is_dir() {
+        fs_err::os::windows::fs::symlink_dir(original, link)
+    } else {
+        fs_err::os::windows::fs::symlink_file(original, link)
+    }
+}
+
 /// Normalizes a path to use `/` as a separator everywhere, even on platforms
 /// that recognize other characters as separators.
 #[cfg(unix)]
@@ -1,12 +1,17 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
 use std::path::{Path, PathBuf};
-use std::sync::LazyLock;
+use std::sync::{LazyLock, Mutex};
 
 use anyhow::Result;
+use gix::bstr::ByteSlice;
 use tracing::warn;
 
+use crate::client;
+use crate::env_vars::EnvVars;
 use crate::process;
 use crate::process::Cmd;
+use crate::retry::retry_network;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -16,6 +21,14 @@ pub enum Error {
     GitNotFound(#[from] which::Error),
     #[error(transparent)]
     Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Repo(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Error {
+    fn repo(err: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self::Repo(Box::new(err))
+    }
 }
 
 pub static GIT: LazyLock<Result<PathBuf, which::Error>> = LazyLock::new(|| which::which("git"));
@@ -33,14 +46,23 @@ static GIT_ENV: LazyLock<Vec<(String, String)>> = LazyLock::new(|| {
         "GIT_ASKPASS",
     ];
 
-    std::env::vars()
+    let mut envs: Vec<_> = std::env::vars()
         .filter(|(k, _)| {
             !k.starts_with("GIT_")
                 || k.starts_with("GIT_CONFIG_KEY_")
                 || k.starts_with("GIT_CONFIG_VALUE_")
                 || keep.contains(&k.as_str())
         })
-        .collect()
+        .collect();
+
+    // curl ignores `HTTP_PROXY`, but reads `http_proxy`, honor it like the other downloads.
+    if std::env::var_os(EnvVars::HTTP_PROXY_LOWER).is_none() {
+        if let Ok(proxy) = std::env::var(EnvVars::HTTP_PROXY) {
+            envs.push((EnvVars::HTTP_PROXY_LOWER.to_string(), proxy));
+        }
+    }
+
+    envs
 });
 
 pub fn git_cmd(summary: &str) -> Result<Cmd, Error> {
@@ -51,6 +73,59 @@ pub fn git_cmd(summary: &str) -> Result<Cmd, Error> {
     Ok(cmd)
 }
 
+/// Open the repo of the current directory, to read it without spawning git.
+///
+/// Like git, the repo is the one of `GIT_DIR` when it's set. The directory and the environment
+/// are the ones of the client when the daemon serves one.
+fn open_repo() -> Result<gix::Repository, Error> {
+    let cwd = client::current_dir()?;
+    match client::var_os(EnvVars::GIT_DIR) {
+        Some(git_dir) => gix::open(cwd.join(git_dir)).map_err(Error::repo),
+        None => gix::discover(cwd).map_err(Error::repo),
+    }
+}
+
+/// The top-level directory of the working tree of `repo`, `GIT_WORK_TREE` when it's set.
+fn work_tree(repo: &gix::Repository) -> Result<PathBuf, Error> {
+    let root = match client::var_os(EnvVars::GIT_WORK_TREE) {
+        Some(root) => client::current_dir()?.join(root),
+        None => repo
+            .work_dir()
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("`{}` has no working tree", repo.git_dir().display()),
+                )
+            })?
+            .to_path_buf(),
+    };
+    Ok(dunce::canonicalize(root)?)
+}
+
+/// Read the index of `repo`, or the one in `GIT_INDEX_FILE` when it's set, like git sets it to
+/// a temporary index for the hooks of `git commit -a` and `git commit <paths>`.
+fn read_index(repo: &gix::Repository) -> Result<gix::index::State, Error> {
+    let path = match client::var_os(EnvVars::GIT_INDEX_FILE) {
+        Some(path) => client::current_dir()?.join(path),
+        None => repo.index_path(),
+    };
+    match gix::index::File::at(
+        &path,
+        repo.object_hash(),
+        false,
+        gix::index::decode::Options::default(),
+    ) {
+        Ok(index) => Ok(index.into()),
+        // Repos without a commit may have no index yet.
+        Err(gix::index::file::init::Error::Io(err))
+            if err.kind() == std::io::ErrorKind::NotFound =>
+        {
+            Ok(gix::index::State::new(repo.object_hash()))
+        }
+        Err(err) => Err(Error::repo(err)),
+    }
+}
+
 fn zsplit(s: &[u8]) -> Vec<String> {
     let s = String::from_utf8_lossy(s);
     let s = s.trim_end_matches('\0');
@@ -75,65 +150,220 @@ pub async fn intent_to_add_files() -> Result<Vec<String>, Error> {
     Ok(zsplit(&output.stdout))
 }
 
+/// Get the files changed between `old` and `new`, since their merge base.
 pub async fn get_changed_files(old: &str, new: &str) -> Result<Vec<String>, Error> {
+    // `git merge-base` exits with 1 when there's no merge base, and fails otherwise
+    // on bad revisions, which it reports on stderr.
+    let mut merge_base = git_cmd("get merge base")?;
+    merge_base
+        .arg("merge-base")
+        .arg(old)
+        .arg(new)
+        .stdout(std::process::Stdio::null())
+        .check(false);
+    let status = merge_base.status().await?;
+    let range = if status.code() == Some(1) {
+        // Unrelated histories, like a branch pushed to a new remote.
+        warn!("No merge base found between {old} and {new}, falling back to a direct diff");
+        format!("{old}..{new}")
+    } else {
+        merge_base.check_status(status)?;
+        format!("{old}...{new}")
+    };
+
     let output = git_cmd("get changed files")?
         .arg("diff")
         .arg("--name-only")
         .arg("--diff-filter=ACMRT")
         .arg("--no-ext-diff") // Disable external diff drivers
         .arg("-z") // Use NUL as line terminator
-        .arg(format!("{old}...{new}"))
+        .arg(range)
         .check(true)
         .output()
         .await?;
     Ok(zsplit(&output.stdout))
 }
 
-pub async fn get_all_files() -> Result<Vec<String>, Error> {
-    let output = git_cmd("get git all files")?
-        .arg("ls-files")
-        .arg("-z")
+/// Find the best common ancestor of two revisions.
+pub async fn get_merge_base(a: &str, b: &str) -> Result<String, Error> {
+    let output = git_cmd("get merge base")?
+        .arg("merge-base")
+        .arg(a)
+        .arg(b)
         .check(true)
         .output()
         .await?;
-    Ok(zsplit(&output.stdout))
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
-pub async fn get_git_dir() -> Result<PathBuf, Error> {
-    let output = git_cmd("get git dir")?
-        .arg("rev-parse")
-        .arg("--git-dir")
+/// A listing of files, by its kind and the directory it was done in.
+type ListingKey = (&'static str, PathBuf);
+
+/// The file listings of the index done in this run.
+static LISTINGS: LazyLock<Mutex<HashMap<ListingKey, Vec<String>>>> = LazyLock::new(Mutex::default);
+
+/// Forget the file listings, the daemon lists the files again for each run it serves.
+pub(crate) fn forget_listings() {
+    LISTINGS.lock().unwrap().clear();
+}
+
+/// List the files of the index once per directory in a run, as the workspace discovery,
+/// the projects and the meta hooks all need them.
+///
+/// Only for listings of the index, which hooks don't change while they run, changes they
+/// make are staged after the files are listed.
+async fn cached_listing<F>(kind: &'static str, list: F) -> Result<Vec<String>, Error>
+where
+    F: Future<Output = Result<Vec<String>, Error>>,
+{
+    let key = (kind, client::current_dir()?);
+    if let Some(files) = LISTINGS.lock().unwrap().get(&key) {
+        return Ok(files.clone());
+    }
+    let files = list.await?;
+    LISTINGS.lock().unwrap().insert(key, files.clone());
+    Ok(files)
+}
+
+/// Get the files in the index below the current directory, relative to it, like `git ls-files`.
+pub async fn get_all_files() -> Result<Vec<String>, Error> {
+    cached_listing("all", async {
+        let repo = open_repo()?;
+        let prefix = prefix(&repo)?;
+        let index = read_index(&repo)?;
+        let mut files: Vec<String> = index
+            .entries()
+            .iter()
+            .filter_map(|entry| {
+                let path = entry.path(&index).to_str_lossy();
+                Some(path.strip_prefix(prefix.as_str())?.to_string())
+            })
+            .collect();
+        // Unmerged files have an entry per stage.
+        files.dedup();
+        Ok(files)
+    })
+    .await
+}
+
+pub fn get_git_dir() -> Result<PathBuf, Error> {
+    Ok(open_repo()?.git_dir().to_path_buf())
+}
+
+pub fn get_git_common_dir() -> Result<PathBuf, Error> {
+    Ok(open_repo()?.common_dir().to_path_buf())
+}
+
+/// Get the files staged to be committed, except the deleted ones, relative to the root of
+/// the repo, like `git diff --staged --name-only --diff-filter=ACMRTUXB`.
+pub async fn get_staged_files() -> Result<Vec<String>, Error> {
+    cached_listing("staged", async {
+        let repo = open_repo()?;
+        let index = read_index(&repo)?;
+        let head = repo.head().map_err(Error::repo)?;
+        // Everything in the index is added before the first commit.
+        let head = if head.is_unborn() {
+            None
+        } else {
+            let tree = repo.head_tree_id().map_err(Error::repo)?;
+            Some(repo.index_from_tree(&tree).map_err(Error::repo)?)
+        };
+        let committed: HashMap<_, _> = head
+            .iter()
+            .flat_map(|head| {
+                head.entries()
+                    .iter()
+                    .map(|entry| (entry.path(head), (entry.id, entry.mode)))
+            })
+            .collect();
+
+        let mut files: Vec<String> = index
+            .entries()
+            .iter()
+            // Files added with `git add --intent-to-add` aren't staged yet.
+            .filter(|entry| {
+                !entry
+                    .flags
+                    .contains(gix::index::entry::Flags::INTENT_TO_ADD)
+            })
+            .filter(|entry| {
+                entry.stage_raw() != 0
+                    || committed.get(entry.path(&index)) != Some(&(entry.id, entry.mode))
+            })
+            .map(|entry| entry.path(&index).to_str_lossy().into_owned())
+            .collect();
+        files.dedup();
+        Ok(files)
+    })
+    .await
+}
+
+/// The path of the current directory relative to the root of the repo, like `src/`.
+fn prefix(repo: &gix::Repository) -> Result<String, Error> {
+    let cwd = dunce::canonicalize(client::current_dir()?)?;
+    let prefix = cwd.strip_prefix(work_tree(repo)?).unwrap_or(Path::new(""));
+    let mut prefix = prefix.to_string_lossy().replace('\\', "/");
+    if !prefix.is_empty() {
+        prefix.push('/');
+    }
+    Ok(prefix)
+}
+
+/// Get the untracked files that are not ignored.
+///
+/// Listed by `git status`, which unlike `git ls-files` uses the fsmonitor and the untracked
+/// cache when the repo enables them, instead of walking the whole worktree.
+pub async fn get_untracked_files() -> Result<Vec<String>, Error> {
+    let prefix = prefix(&open_repo()?)?;
+    let output = git_cmd("get untracked files")?
+        .arg("status")
+        .arg("--porcelain")
+        .arg("-z")
+        .arg("--untracked-files=all")
+        .arg("--ignore-submodules")
+        .arg("--no-renames")
+        .arg("--")
+        .arg(".")
         .check(true)
         .output()
         .await?;
-    Ok(PathBuf::from(
-        String::from_utf8_lossy(&output.stdout).trim(),
-    ))
+
+    // The paths of `git status --porcelain` are relative to the root of the repo.
+    Ok(zsplit(&output.stdout)
+        .into_iter()
+        .filter_map(|entry| {
+            let path = entry.strip_prefix("?? ")?;
+            Some(path.strip_prefix(&prefix).unwrap_or(path).to_string())
+        })
+        .collect())
 }
 
-pub async fn get_git_common_dir() -> Result<PathBuf, Error> {
-    let output = git_cmd("get git common dir")?
-        .arg("rev-parse")
-        .arg("--git-common-dir")
+/// Get the files among `paths` that are tracked or untracked but not ignored.
+pub async fn get_unignored_files(paths: &[String]) -> Result<Vec<String>, Error> {
+    if paths.is_empty() {
+        return Ok(vec![]);
+    }
+    let output = git_cmd("get unignored files")?
+        .arg("ls-files")
+        .arg("--cached")
+        .arg("--others")
+        .arg("--exclude-standard")
+        .arg("-z")
+        .arg("--")
+        .args(paths)
         .check(true)
         .output()
         .await?;
-    if output.stdout.trim_ascii().is_empty() {
-        Ok(get_git_dir().await?)
-    } else {
-        Ok(PathBuf::from(
-            String::from_utf8_lossy(&output.stdout).trim(),
-        ))
-    }
+    Ok(zsplit(&output.stdout))
 }
 
-pub async fn get_staged_files() -> Result<Vec<String>, Error> {
-    let output = git_cmd("get staged files")?
+/// Get the tracked files that have changes in the working tree that are not staged.
+pub async fn get_unstaged_files() -> Result<Vec<String>, Error> {
+    let output = git_cmd("get unstaged files")?
         .arg("diff")
-        .arg("--staged")
         .arg("--name-only")
-        .arg("--diff-filter=ACMRTUXB") // Everything except for D
         .arg("--no-ext-diff") // Disable external diff drivers
+        .arg("--ignore-submodules")
         .arg("-z") // Use NUL as line terminator
         .check(true)
         .output()
@@ -141,18 +371,22 @@ pub async fn get_staged_files() -> Result<Vec<String>, Error> {
     Ok(zsplit(&output.stdout))
 }
 
-pub async fn has_unmerged_paths() -> Result<bool, Error> {
-    let output = git_cmd("check has unmerged paths")?
-        .arg("ls-files")
-        .arg("--unmerged")
+/// Get the paths that have unresolved merge conflicts in the index.
+pub async fn get_unmerged_paths() -> Result<Vec<String>, Error> {
+    let output = git_cmd("get unmerged paths")?
+        .arg("diff")
+        .arg("--name-only")
+        .arg("--diff-filter=U")
+        .arg("--no-ext-diff")
+        .arg("-z")
         .check(true)
         .output()
         .await?;
-    Ok(!String::from_utf8_lossy(&output.stdout).trim().is_empty())
+    Ok(zsplit(&output.stdout))
 }
 
-pub async fn is_in_merge_conflict() -> Result<bool, Error> {
-    let git_dir = get_git_dir().await?;
+pub fn is_in_merge_conflict() -> Result<bool, Error> {
+    let git_dir = get_git_dir()?;
     Ok(git_dir.join("MERGE_HEAD").try_exists()? && git_dir.join("MERGE_MSG").try_exists()?)
 }
 
@@ -178,14 +412,14 @@ pub async fn get_conflicted_files() -> Result<Vec<String>, Error> {
 
     Ok(zsplit(&output.stdout)
         .into_iter()
-        .chain(parse_merge_msg_for_conflicts().await?)
+        .chain(parse_merge_msg_for_conflicts()?)
         .collect::<HashSet<String>>()
         .into_iter()
         .collect())
 }
 
-async fn parse_merge_msg_for_conflicts() -> Result<Vec<String>, Error> {
-    let git_dir = get_git_dir().await?;
+fn parse_merge_msg_for_conflicts() -> Result<Vec<String>, Error> {
+    let git_dir = get_git_dir()?;
     let merge_msg = git_dir.join("MERGE_MSG");
     let content = fs_err::read_to_string(&merge_msg)?;
     let conflicts = content
@@ -197,16 +431,111 @@ async fn parse_merge_msg_for_conflicts() -> Result<Vec<String>, Error> {
     Ok(conflicts)
 }
 
-pub async fn get_diff() -> Result<Vec<u8>, Error> {
-    let output = git_cmd("git diff")?
-        .arg("diff")
-        .arg("--no-ext-diff") // Disable external diff drivers
-        .arg("--no-textconv")
-        .arg("--ignore-submodules")
-        .check(true)
-        .output()
-        .await?;
-    Ok(output.stdout)
+/// A directory git treats as the working tree instead of the current one, like a checkout of
+/// the index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkTree {
+    git_dir: PathBuf,
+    root: PathBuf,
+    /// The directory relative paths are resolved in, the root or a subdirectory of it.
+    dir: PathBuf,
+}
+
+impl WorkTree {
+    pub fn new(git_dir: PathBuf, root: PathBuf) -> Self {
+        Self {
+            git_dir,
+            dir: root.clone(),
+            root,
+        }
+    }
+
+    /// The same working tree, with relative paths resolved in a subdirectory of it.
+    #[must_use]
+    pub fn subdir(&self, dir: &Path) -> Self {
+        Self {
+            dir: self.dir.join(dir),
+            ..self.clone()
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The environment variables that make git use this working tree.
+    pub fn envs(&self) -> [(&'static str, String); 2] {
+        [
+            (EnvVars::GIT_DIR, self.git_dir.to_string_lossy().to_string()),
+            (
+                EnvVars::GIT_WORK_TREE,
+                self.root.to_string_lossy().to_string(),
+            ),
+        ]
+    }
+
+    /// Run a git command in this working tree.
+    pub fn apply(&self, cmd: &mut Cmd) {
+        cmd.current_dir(&self.dir).envs(self.envs());
+    }
+}
+
+/// The files with unstaged changes and the digests of their content, compared before and
+/// after a hook runs to tell whether it modified files.
+///
+/// Much cheaper than comparing the whole `git diff` on large worktrees, git finds the changed
+/// files by their stat, or the fsmonitor when it's enabled, and only those are read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorktreeChanges {
+    root: PathBuf,
+    work_tree: Option<WorkTree>,
+    /// The digests by path relative to the root, `None` for deleted files.
+    files: BTreeMap<String, Option<md5::Digest>>,
+}
+
+impl WorktreeChanges {
+    /// Read the changes of the working tree, the current one if `work_tree` is `None`.
+    pub async fn read(work_tree: Option<&WorkTree>) -> Result<Self, Error> {
+        let root = match work_tree {
+            Some(work_tree) => work_tree.root.clone(),
+            None => get_root()?,
+        };
+        Self::read_in(root, work_tree.cloned()).await
+    }
+
+    /// Read the changes again, to compare them with these ones.
+    pub async fn refresh(&self) -> Result<Self, Error> {
+        Self::read_in(self.root.clone(), self.work_tree.clone()).await
+    }
+
+    async fn read_in(root: PathBuf, work_tree: Option<WorkTree>) -> Result<Self, Error> {
+        let mut cmd = git_cmd("git diff")?;
+        if let Some(work_tree) = &work_tree {
+            work_tree.apply(&mut cmd);
+        }
+        let output = cmd
+            .current_dir(&root)
+            .arg("diff")
+            .arg("--name-only")
+            .arg("-z")
+            .arg("--no-ext-diff") // Disable external diff drivers
+            .arg("--ignore-submodules")
+            .check(true)
+            .output()
+            .await?;
+        let files = zsplit(&output.stdout)
+            .into_iter()
+            .map(|path| {
+                let digest = fs_err::read(root.join(&path)).ok().map(md5::compute);
+                (path, digest)
+            })
+            .collect();
+        Ok(Self {
+            root,
+            work_tree,
+            files,
+        })
+    }
 }
 
 /// Create a tree object from the current index.
@@ -222,17 +551,67 @@ pub async fn write_tree() -> Result<String, Error> {
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
-/// Get the path of the top-level directory of the working tree.
-pub async fn get_root() -> Result<PathBuf, Error> {
-    let output = git_cmd("get git root")?
-        .arg("rev-parse")
-        .arg("--show-toplevel")
+/// Create a tree object from the current contents of the tracked files in the working tree,
+/// the current one if `work_tree` is `None`, without touching the index.
+pub async fn write_worktree_tree(work_tree: Option<&WorkTree>) -> Result<String, Error> {
+    let index = tempfile::NamedTempFile::new()?;
+    let git_index = get_git_dir()?.join("index");
+    if git_index.try_exists()? {
+        fs_err::copy(&git_index, index.path())?;
+    }
+
+    let mut cmd = git_cmd("git add")?;
+    if let Some(work_tree) = work_tree {
+        work_tree.apply(&mut cmd);
+    }
+    cmd.arg("add")
+        .arg("--update")
+        .env("GIT_INDEX_FILE", index.path())
         .check(true)
         .output()
         .await?;
-    Ok(PathBuf::from(
-        String::from_utf8_lossy(&output.stdout).trim(),
-    ))
+    let output = git_cmd("git write-tree")?
+        .arg("write-tree")
+        .env("GIT_INDEX_FILE", index.path())
+        .check(true)
+        .output()
+        .await?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Get the diff between two tree objects.
+pub async fn get_tree_diff(old: &str, new: &str, color: &str) -> Result<Vec<u8>, Error> {
+    let output = git_cmd("git diff")?
+        .arg("diff")
+        .arg("--no-ext-diff") // Disable external diff drivers
+        .arg("--no-textconv")
+        .arg(color)
+        .arg(old)
+        .arg(new)
+        .check(true)
+        .output()
+        .await?;
+    Ok(output.stdout)
+}
+
+/// Get the files that differ between two tree objects.
+pub async fn get_tree_changed_files(old: &str, new: &str) -> Result<Vec<String>, Error> {
+    let output = git_cmd("git diff")?
+        .arg("diff")
+        .arg("--name-only")
+        .arg("--no-ext-diff") // Disable external diff drivers
+        .arg("-z") // Use NUL as line terminator
+        .arg(old)
+        .arg(new)
+        .check(true)
+        .output()
+        .await?;
+    Ok(zsplit(&output.stdout))
+}
+
+/// Get the path of the top-level directory of the working tree.
+pub fn get_root() -> Result<PathBuf, Error> {
+    work_tree(&open_repo()?)
 }
 
 pub async fn is_dirty(path: &Path) -> Result<bool, Error> {
@@ -276,17 +655,40 @@ async fn init_repo(url: &str, path: &Path) -> Result<(), Error> {
     Ok(())
 }
 
-async fn shallow_clone(rev: &str, path: &Path) -> Result<(), Error> {
+/// Receives the progress of a clone, e.g. `("Receiving objects", 450, 1000)`.
+pub type CloneProgress<'a> = &'a dyn Fn(&str, u64, u64);
+
+/// Parse a line of `git fetch --progress` output like
+/// `remote: Counting objects:  45% (450/1000), done.` into its phase and counts.
+fn parse_progress(line: &str) -> Option<(&str, u64, u64)> {
+    let line = line.strip_prefix("remote: ").unwrap_or(line);
+    let (phase, rest) = line.split_once(':')?;
+    let (_, counts) = rest.split_once('(')?;
+    let (counts, _) = counts.split_once(')')?;
+    let (pos, len) = counts.split_once('/')?;
+    Some((
+        phase.trim(),
+        pos.trim().parse().ok()?,
+        len.trim().parse().ok()?,
+    ))
+}
+
+async fn shallow_clone(rev: &str, path: &Path, progress: CloneProgress<'_>) -> Result<(), Error> {
     git_cmd("git shallow clone")?
         .current_dir(path)
         .arg("-c")
         .arg("protocol.version=2")
         .arg("fetch")
+        .arg("--progress")
         .arg("origin")
         .arg(rev)
         .arg("--depth=1")
         .check(true)
-        .output()
+        .output_with_progress(|line| {
+            if let Some((phase, pos, len)) = parse_progress(line) {
+                progress(phase, pos, len);
+            }
+        })
         .await?;
 
     git_cmd("git checkout")?
@@ -313,14 +715,24 @@ async fn shallow_clone(rev: &str, path: &Path) -> Result<(), Error> {
     Ok(())
 }
 
-async fn full_clone(rev: &str, path: &Path) -> Result<(), Error> {
+/// Fetch the whole history without trees and blobs, for revs the remote can't serve directly,
+/// like abbreviated hashes. Checking out `rev` then fetches only the files it needs.
+///
+/// Remotes without partial clone support ignore the filter and send everything.
+async fn full_clone(rev: &str, path: &Path, progress: CloneProgress<'_>) -> Result<(), Error> {
     git_cmd("git full clone")?
         .current_dir(path)
         .arg("fetch")
+        .arg("--progress")
+        .arg("--filter=tree:0")
         .arg("origin")
         .arg("--tags")
         .check(true)
-        .output()
+        .output_with_progress(|line| {
+            if let Some((phase, pos, len)) = parse_progress(line) {
+                progress(phase, pos, len);
+            }
+        })
         .await?;
 
     git_cmd("git checkout")?
@@ -344,28 +756,93 @@ async fn full_clone(rev: &str, path: &Path) -> Result<(), Error> {
     Ok(())
 }
 
-pub async fn clone_repo(url: &str, rev: &str, path: &Path) -> Result<(), Error> {
+pub async fn clone_repo(
+    url: &str,
+    rev: &str,
+    path: &Path,
+    progress: CloneProgress<'_>,
+) -> Result<(), Error> {
     init_repo(url, path).await?;
 
-    if let Err(err) = shallow_clone(rev, path).await {
+    if let Err(err) = shallow_clone(rev, path, progress).await {
         warn!(?err, "Failed to shallow clone, falling back to full clone");
-        full_clone(rev, path).await
+        // Start over, the failed attempt may have left a shallow history behind.
+        fs_err::remove_dir_all(path)?;
+        fs_err::create_dir_all(path)?;
+        init_repo(url, path).await?;
+        full_clone(rev, path, progress).await
     } else {
         Ok(())
     }
 }
 
-pub async fn has_hooks_path_set() -> Result<bool> {
-    let output = git_cmd("get git hooks path")?
-        .arg("config")
-        .arg("--get")
-        .arg("core.hooksPath")
+/// Fetch the default branch of a remote repo with its tags into a new repo at `path`.
+pub async fn fetch_head(url: &str, path: &Path) -> Result<(), Error> {
+    init_repo(url, path).await?;
+
+    retry_network(&format!("fetch `{url}`"), || async {
+        git_cmd("git fetch")?
+            .current_dir(path)
+            .arg("fetch")
+            .arg("origin")
+            .arg("HEAD")
+            .arg("--tags")
+            .check(true)
+            .output()
+            .await?;
+        Ok::<_, Error>(())
+    })
+    .await
+}
+
+/// The latest tag reachable from `rev`, if there is one.
+pub async fn describe_tag(path: &Path, rev: &str) -> Result<Option<String>, Error> {
+    let output = git_cmd("git describe")?
+        .current_dir(path)
+        .arg("describe")
+        .arg("--tags")
+        .arg("--abbrev=0")
+        .arg(rev)
         .check(false)
         .output()
         .await?;
     if output.status.success() {
-        Ok(!String::from_utf8_lossy(&output.stdout).trim().is_empty())
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
     } else {
-        Ok(false)
+        Ok(None)
     }
 }
+
+/// The commit hash `rev` points to.
+pub async fn rev_parse(path: &Path, rev: &str) -> Result<String, Error> {
+    let output = git_cmd("git rev-parse")?
+        .current_dir(path)
+        .arg("rev-parse")
+        .arg(format!("{rev}^{{commit}}"))
+        .check(true)
+        .output()
+        .await?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The content of a file at `rev`.
+pub async fn show_file(path: &Path, rev: &str, file: &str) -> Result<String, Error> {
+    let output = git_cmd("git show")?
+        .current_dir(path)
+        .arg("show")
+        .arg(format!("{rev}:{file}"))
+        .check(true)
+        .output()
+        .await?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub fn has_hooks_path_set() -> Result<bool> {
+    let repo = open_repo()?;
+    Ok(repo
+        .config_snapshot()
+        .string("core.hooksPath")
+        .is_some_and(|path| !path.trim().is_empty()))
+}
@@ -1,24 +1,31 @@
 use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::fmt::Display;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::Mutex;
+use std::time::Duration;
 
 use anyhow::Result;
 use clap::ValueEnum;
 use futures::StreamExt;
 use itertools::zip_eq;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 use tracing::{debug, error};
 use url::Url;
 
+use crate::client;
 use crate::config::{
     self, read_config, read_manifest, Config, Language, LanguageVersion, LocalHook, ManifestHook,
-    MetaHook, RemoteHook, Stage, CONFIG_FILE, MANIFEST_FILE,
+    MetaHook, RemoteHook, ResourceLimits, Stage, MANIFEST_FILE,
 };
+use crate::env_vars::EnvVars;
 use crate::fs::{Simplified, CWD};
+use crate::run::concurrency;
 use crate::store::Store;
-use crate::warn_user;
+use crate::{warn_user, warn_user_once};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -103,6 +110,7 @@ impl Repo {
 impl Display for Repo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Repo::Remote { url, rev, .. } if rev.is_empty() => write!(f, "{url}"),
             Repo::Remote { url, rev, .. } => write!(f, "{url}@{rev}"),
             Repo::Local { .. } => write!(f, "local"),
             Repo::Meta { .. } => write!(f, "meta"),
@@ -110,6 +118,7 @@ impl Display for Repo {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct Project {
     config_path: PathBuf,
     config: Config,
@@ -119,7 +128,7 @@ pub struct Project {
 impl Project {
     /// Find the configuration file in the given path or the current working directory.
     pub fn find_config_file(config: Option<PathBuf>) -> Result<PathBuf, Error> {
-        let file = config.unwrap_or_else(|| CWD.join(CONFIG_FILE));
+        let file = config.unwrap_or_else(|| config::find_config_in(&CWD));
         if file.try_exists()? {
             return Ok(file);
         }
@@ -148,6 +157,17 @@ impl Project {
         })
     }
 
+    /// Append the repos of another configuration file, its other options are ignored.
+    pub fn merge_config_file(&mut self, config_path: &Path) -> Result<(), Error> {
+        debug!(
+            path = %config_path.display(),
+            "Merging project configuration"
+        );
+        let config = read_config(config_path)?;
+        self.config.repos.extend(config.repos);
+        Ok(())
+    }
+
     pub fn config(&self) -> &Config {
         &self.config
     }
@@ -174,12 +194,21 @@ impl Project {
             .map(|repo_config| {
                 let remote_repos = remote_repos.clone();
                 async move {
-                    let progress = reporter.map(|reporter| {
-                        (reporter, reporter.on_clone_start(&format!("{repo_config}")))
-                    });
+                    // Only report repos that need to be cloned.
+                    let progress = match reporter {
+                        Some(reporter) if !store.is_cloned(repo_config).map_err(Box::new)? => {
+                            Some((reporter, reporter.on_clone_start(&format!("{repo_config}"))))
+                        }
+                        _ => None,
+                    };
 
+                    let on_progress = |phase: &str, pos: u64, len: u64| {
+                        if let Some((reporter, progress)) = progress {
+                            reporter.on_clone_progress(progress, phase, pos, len);
+                        }
+                    };
                     let path = store
-                        .prepare_remote_repo(repo_config, &[])
+                        .prepare_remote_repo(repo_config, &on_progress)
                         .await
                         .map_err(Box::new)?;
 
@@ -187,20 +216,12 @@ impl Project {
                         reporter.on_clone_complete(progress);
                     }
 
-                    let repo = Rc::new(Repo::remote(
-                        repo_config.repo.as_str(),
-                        &repo_config.rev,
-                        &path.to_string_lossy(),
-                    )?);
-                    remote_repos
-                        .lock()
-                        .unwrap()
-                        .insert(repo_config, repo.clone());
+                    remote_repos.lock().unwrap().insert(repo_config, path);
 
                     Ok::<(), Error>(())
                 }
             })
-            .buffer_unordered(5);
+            .buffer_unordered(concurrency());
 
         while let Some(result) = tasks.next().await {
             result?;
@@ -210,9 +231,16 @@ impl Project {
         let remote_repos = remote_repos.lock().unwrap();
         for repo in &self.config.repos {
             match repo {
-                config::Repo::Remote(repo) => {
-                    let repo = remote_repos.get(repo).expect("repo not found");
-                    repos.push(repo.clone());
+                config::Repo::Remote(repo_config) => {
+                    // Repos cloned once are shared by the entries using different subdirectories.
+                    let path = remote_repos.get(repo_config).expect("repo not found");
+                    let path = repo_config.hooks_path(path);
+                    let repo = Repo::remote(
+                        repo_config.repo.as_str(),
+                        &repo_config.rev,
+                        &path.to_string_lossy(),
+                    )?;
+                    repos.push(Rc::new(repo));
                 }
                 config::Repo::Local(repo) => {
                     let repo = Repo::local(repo.hooks.clone());
@@ -240,7 +268,16 @@ impl Project {
 
         let mut hooks = Vec::new();
 
-        for (repo_config, repo) in zip_eq(self.config.repos.iter(), self.repos.iter()) {
+        for (repo_index, (repo_config, repo)) in
+            zip_eq(self.config.repos.iter(), self.repos.iter()).enumerate()
+        {
+            let repo_fail_fast = match repo_config {
+                config::Repo::Remote(repo_config) => repo_config.fail_fast,
+                config::Repo::Local(repo_config) => repo_config.fail_fast,
+                config::Repo::Meta(repo_config) => repo_config.fail_fast,
+            };
+            let start = hooks.len();
+
             match repo_config {
                 config::Repo::Remote(repo_config) => {
                     for hook_config in &repo_config.hooks {
@@ -258,19 +295,8 @@ impl Project {
                         builder.combine(&self.config);
                         let mut hook = builder.build();
 
-                        if hook.additional_dependencies.is_empty() {
-                            // Use the shared repo environment.
-                            let path = hook.repo.path().to_path_buf();
-                            hook = hook.with_path(path);
-                        } else {
-                            // Prepare hooks with `additional_dependencies` (they need separate environments).
-                            let path = store
-                                .prepare_remote_repo(repo_config, &hook.additional_dependencies)
-                                .await
-                                .map_err(Box::new)?;
-
-                            hook = hook.with_path(path);
-                        }
+                        let path = hook.repo.path().to_path_buf();
+                        hook = hook.with_path(path);
 
                         hooks.push(hook);
                     }
@@ -284,9 +310,7 @@ impl Project {
 
                         // If the hook doesn't need an environment, don't do any preparation.
                         if hook.language.environment_dir().is_some() {
-                            let path = store
-                                .prepare_local_repo(&hook, &hook.additional_dependencies)
-                                .map_err(Box::new)?;
+                            let path = store.prepare_local_repo(&hook).await.map_err(Box::new)?;
 
                             hook = hook.with_path(path);
                         } else {
@@ -311,6 +335,12 @@ impl Project {
                     }
                 }
             }
+
+            for hook in &mut hooks[start..] {
+                hook.env_path = store.env_path(hook);
+                hook.repo_index = repo_index;
+                hook.repo_fail_fast = repo_fail_fast;
+            }
         }
 
         reporter.map(HookInitReporter::on_complete);
@@ -319,8 +349,63 @@ impl Project {
     }
 }
 
+/// Expand `${VAR}` and `${VAR:-default}` in `value`, `$${` is a literal `${`.
+///
+/// Other uses of `$`, like `$VAR` or `${1}`, are left for the shell of the hook.
+fn expand_vars(
+    value: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+    mut on_missing: impl FnMut(&str),
+) -> String {
+    let is_name = |name: &str| {
+        name.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(idx) = rest.find('$') {
+        expanded.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        if let Some(after) = rest.strip_prefix("$${") {
+            expanded.push_str("${");
+            rest = after;
+            continue;
+        }
+        if let Some((expr, after)) = rest
+            .strip_prefix("${")
+            .and_then(|after| after.split_once('}'))
+        {
+            let (name, default) = match expr.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (expr, None),
+            };
+            if is_name(name) {
+                match (lookup(name), default) {
+                    (Some(value), None) => expanded.push_str(&value),
+                    (Some(value), Some(_)) if !value.is_empty() => expanded.push_str(&value),
+                    (_, Some(default)) => expanded.push_str(default),
+                    (None, None) => on_missing(name),
+                }
+                rest = after;
+                continue;
+            }
+        }
+
+        expanded.push('$');
+        rest = &rest[1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
 pub trait HookInitReporter {
     fn on_clone_start(&self, repo: &str) -> usize;
+    /// Called with the current phase of a clone, e.g. `Receiving objects`, and its progress.
+    fn on_clone_progress(&self, id: usize, phase: &str, pos: u64, len: u64);
     fn on_clone_complete(&self, id: usize);
     fn on_complete(&self);
 }
@@ -356,7 +441,12 @@ impl HookBuilder {
     fn combine(&mut self, config: &Config) {
         let options = &mut self.config.options;
         let language = self.config.language;
-        if options.language_version.is_none() {
+        // Like pre-commit, an explicit `default` also uses the configuration-wide default.
+        if options
+            .language_version
+            .as_ref()
+            .is_none_or(LanguageVersion::is_default)
+        {
             options.language_version = config
                 .default_language_version
                 .as_ref()
@@ -366,6 +456,18 @@ impl HookBuilder {
         if options.stages.is_none() {
             options.stages.clone_from(&config.default_stages);
         }
+        if options.clean_env.is_none() {
+            options.clean_env = config.clean_env;
+        }
+        if options.pass_env.is_none() {
+            options.pass_env.clone_from(&config.pass_env);
+        }
+        if options.max_output_lines.is_none() {
+            options.max_output_lines = config.max_output_lines;
+        }
+        if options.expand_env.is_none() {
+            options.expand_env = config.expand_env;
+        }
     }
 
     /// Fill in the default values for the hook configuration.
@@ -377,11 +479,25 @@ impl HookBuilder {
         options.types.get_or_insert(vec!["file".to_string()]);
         options.types_or.get_or_insert_default();
         options.exclude_types.get_or_insert_default();
+        options.files_glob.get_or_insert_default();
+        options.exclude_glob.get_or_insert_default();
         options.always_run.get_or_insert(false);
         options.fail_fast.get_or_insert(false);
         options.pass_filenames.get_or_insert(true);
         options.require_serial.get_or_insert(false);
         options.verbose.get_or_insert(false);
+        options.retries.get_or_insert(0);
+        options.depends_on.get_or_insert_default();
+        options.read_only.get_or_insert(false);
+        options.profiles.get_or_insert_default();
+        options.tags.get_or_insert_default();
+        options.env.get_or_insert_default();
+        options.clean_env.get_or_insert(false);
+        options.pass_env.get_or_insert_default();
+        options.stream.get_or_insert(false);
+        options.pty.get_or_insert(false);
+        options.skip_if_missing_entry.get_or_insert(false);
+        options.expand_env.get_or_insert(true);
         options
             .stages
             .get_or_insert(Stage::value_variants().to_vec());
@@ -400,6 +516,12 @@ impl HookBuilder {
                 );
             }
         }
+        if language != Language::System && options.skip_if_missing_entry == Some(true) {
+            warn_user!(
+                "Language {} does not support skipping missing entries, but skip_if_missing_entry is set",
+                language
+            );
+        }
         if options
             .language_version
             .as_ref()
@@ -419,15 +541,53 @@ impl HookBuilder {
         }
     }
 
+    /// Expand the environment variables in the entry, args and additional dependencies.
+    fn expand_env(&mut self) {
+        let options = &mut self.config.options;
+        if options.expand_env != Some(true) {
+            return;
+        }
+
+        let env = options.env.clone().unwrap_or_default();
+        let id = &self.config.id;
+        let expand = |value: &mut String| {
+            *value = expand_vars(
+                value,
+                |name| {
+                    env.get(name)
+                        .cloned()
+                        .or_else(|| client::var_os(name)?.into_string().ok())
+                },
+                |name| {
+                    warn_user_once!(
+                        "Environment variable `{name}` used by hook `{id}` is not set, expanding to an empty string"
+                    );
+                },
+            );
+        };
+
+        expand(&mut self.config.entry);
+        for value in options
+            .args
+            .iter_mut()
+            .chain(options.additional_dependencies.iter_mut())
+            .flatten()
+        {
+            expand(value);
+        }
+    }
+
     /// Build the hook.
     fn build(mut self) -> Hook {
         self.check();
         self.fill_in_defaults();
+        self.expand_env();
 
         let options = self.config.options;
         Hook {
             repo: self.repo,
             path: None,
+            env_path: None,
             id: self.config.id,
             name: self.config.name,
             entry: self.config.entry,
@@ -435,6 +595,8 @@ impl HookBuilder {
             alias: options.alias.expect("alias not set"),
             files: options.files,
             exclude: options.exclude,
+            files_glob: options.files_glob.expect("files_glob not set"),
+            exclude_glob: options.exclude_glob.expect("exclude_glob not set"),
             types: options.types.expect("types not set"),
             types_or: options.types_or.expect("types_or not set"),
             exclude_types: options.exclude_types.expect("exclude_types not set"),
@@ -444,6 +606,8 @@ impl HookBuilder {
             args: options.args.expect("args not set"),
             always_run: options.always_run.expect("always_run not set"),
             fail_fast: options.fail_fast.expect("fail_fast not set"),
+            repo_index: 0,
+            repo_fail_fast: false,
             pass_filenames: options.pass_filenames.expect("pass_filenames not set"),
             description: options.description,
             language_version: options.language_version.expect("language_version not set"),
@@ -451,6 +615,33 @@ impl HookBuilder {
             require_serial: options.require_serial.expect("require_serial not set"),
             stages: options.stages.expect("stages not set"),
             verbose: options.verbose.expect("verbose not set"),
+            timeout: options.timeout.map(Duration::from_secs),
+            retries: options.retries.expect("retries not set"),
+            limits: options.limits.unwrap_or_default(),
+            depends_on: options.depends_on.expect("depends_on not set"),
+            read_only: options.read_only.expect("read_only not set"),
+            profiles: options.profiles.expect("profiles not set"),
+            tags: options.tags.expect("tags not set"),
+            env: options.env.expect("env not set"),
+            clean_env: options.clean_env.expect("clean_env not set"),
+            pass_env: options.pass_env.expect("pass_env not set"),
+            stream: options.stream.expect("stream not set"),
+            pty: options.pty.expect("pty not set"),
+            max_output_lines: options.max_output_lines,
+            output_pattern: options.output_pattern,
+            skip_if_missing_entry: options
+                .skip_if_missing_entry
+                .expect("skip_if_missing_entry not set"),
+            // `.` and a trailing `/` are the same as no working directory.
+            working_directory: options
+                .working_directory
+                .map(|dir| {
+                    dir.trim_start_matches("./")
+                        .trim_end_matches('/')
+                        .to_string()
+                })
+                .filter(|dir| !dir.is_empty() && dir != "."),
+            warn_slow: None,
             minimum_pre_commit_version: options.minimum_pre_commit_version,
         }
     }
@@ -461,6 +652,8 @@ impl HookBuilder {
 pub struct Hook {
     repo: Rc<Repo>,
     path: Option<PathBuf>,
+    /// The environment of the hook in the store.
+    env_path: Option<PathBuf>,
 
     pub id: String,
     pub name: String,
@@ -469,6 +662,8 @@ pub struct Hook {
     pub alias: String,
     pub files: Option<String>,
     pub exclude: Option<String>,
+    pub files_glob: Vec<String>,
+    pub exclude_glob: Vec<String>,
     pub types: Vec<String>,
     pub types_or: Vec<String>,
     pub exclude_types: Vec<String>,
@@ -476,6 +671,10 @@ pub struct Hook {
     pub args: Vec<String>,
     pub always_run: bool,
     pub fail_fast: bool,
+    /// The index of the repo entry of the hook in the config.
+    pub repo_index: usize,
+    /// Skip the remaining hooks of the same repo entry after this hook fails.
+    pub repo_fail_fast: bool,
     pub pass_filenames: bool,
     pub description: Option<String>,
     pub language_version: LanguageVersion,
@@ -483,6 +682,25 @@ pub struct Hook {
     pub require_serial: bool,
     pub stages: Vec<Stage>,
     pub verbose: bool,
+    pub timeout: Option<Duration>,
+    pub retries: u32,
+    pub limits: ResourceLimits,
+    pub depends_on: Vec<String>,
+    pub read_only: bool,
+    pub profiles: Vec<String>,
+    pub tags: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub clean_env: bool,
+    pub pass_env: Vec<String>,
+    pub stream: bool,
+    pub pty: bool,
+    pub max_output_lines: Option<usize>,
+    pub output_pattern: Option<String>,
+    pub skip_if_missing_entry: bool,
+    /// The directory to run the hook in, relative to the root of the repo.
+    pub working_directory: Option<String>,
+    /// Show the duration of the hook if it runs longer than this.
+    pub warn_slow: Option<Duration>,
     pub minimum_pre_commit_version: Option<String>,
 }
 
@@ -522,34 +740,108 @@ impl Hook {
     }
 
     /// Get the environment directory that the hook will be installed to.
+    ///
+    /// Hooks with the same language version and additional dependencies share an environment,
+    /// unless the language installs the hook repo into it.
     pub fn environment_dir(&self) -> Option<PathBuf> {
-        let env_dir = self.language.environment_dir()?;
+        self.env_path.clone()
+    }
+
+    /// Get the host environment variables to run the hook with,
+    /// or `None` if the hook inherits the whole environment.
+    pub fn host_env(&self) -> Option<Vec<(String, OsString)>> {
+        if !self.clean_env {
+            return None;
+        }
+
+        let mut keys = vec![EnvVars::PATH.to_string()];
+        // Windows can't start processes without `SYSTEMROOT`.
+        if cfg!(windows) {
+            keys.push("SYSTEMROOT".to_string());
+        }
+        keys.extend(self.pass_env.iter().cloned());
+
         Some(
-            self.path()
-                .join(format!("{}-{}", env_dir, &self.language_version)),
+            keys.into_iter()
+                .filter_map(|key| client::var_os(&key).map(|value| (key, value)))
+                .collect(),
         )
     }
 
-    // TODO: health check
-    /// Check if the hook is installed in the environment.
-    pub fn installed(&self) -> bool {
+    /// The inputs of the environment of the hook, the environment is installed again when they change.
+    fn env_state(&self) -> EnvState {
+        EnvState {
+            language: self.language.as_str().to_string(),
+            language_version: self.language_version.to_string(),
+            additional_dependencies: format!(
+                "{:x}",
+                md5::compute(self.additional_dependencies.join("\0"))
+            ),
+            // Environments shared by repos don't depend on the repo.
+            repo: self.language.installs_repo().then(|| self.repo.to_string()),
+            installer_version: self.language.installer_version().map(ToString::to_string),
+        }
+    }
+
+    /// A digest of the inputs of the environment of the hook, `None` if the environment isn't
+    /// identified by them, like when it has the content of a local or linked repo installed.
+    pub fn env_digest(&self) -> Option<String> {
+        if self.language.installs_repo()
+            && !matches!(&*self.repo, Repo::Remote { rev, .. } if !rev.is_empty())
+        {
+            return None;
+        }
+        let state = serde_json::to_string(&self.env_state()).ok()?;
+        Some(format!("{:x}", Sha256::digest(state)))
+    }
+
+    /// Check if the environment of the hook is installed with the current inputs and still works.
+    pub async fn installed(&self) -> bool {
         let Some(env) = self.environment_dir() else {
             return true;
         };
 
-        let state_file_v2 = env.join(".install_state_v2");
-        state_file_v2.exists()
-        // Drop support for state file v1.
+        // Drop support for state file v1 and v2, they don't record the inputs.
+        let state = fs_err::read_to_string(env.join(STATE_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str::<EnvState>(&content).ok());
+        if state.as_ref() != Some(&self.env_state()) {
+            debug!(hook = %self, ?state, "Environment state changed");
+            return false;
+        }
+
+        if let Err(err) = self.language.check_health(self).await {
+            debug!(hook = %self, "Environment health check failed: {err:#}");
+            return false;
+        }
+        true
     }
 
     /// Write a state file to mark the hook as installed.
+    /// The file recording the state of the installed environment, `None` if the hook has no
+    /// environment. It's rewritten each time the environment is installed.
+    pub fn installed_state(&self) -> Option<PathBuf> {
+        Some(self.environment_dir()?.join(STATE_FILE))
+    }
+
     pub fn mark_installed(&self) -> Result<(), Error> {
         let env = self.environment_dir().unwrap();
-        let state_file_v2 = env.join(".install_state_v2");
-        fs_err::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(&state_file_v2)?;
+        let state = serde_json::to_string(&self.env_state()).expect("Failed to serialize state");
+        fs_err::write(env.join(STATE_FILE), state)?;
         Ok(())
     }
 }
+
+/// The file in the environment recording the [`EnvState`] it's installed with.
+const STATE_FILE: &str = ".install_state_v3";
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct EnvState {
+    language: String,
+    language_version: String,
+    /// A hash of the additional dependencies.
+    additional_dependencies: String,
+    /// The repo and rev installed into the environment.
+    repo: Option<String>,
+    installer_version: Option<String>,
+}
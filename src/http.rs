@@ -0,0 +1,109 @@
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use reqwest::Certificate;
+use tracing::debug;
+
+use crate::env_vars::EnvVars;
+use crate::fs::Simplified;
+use crate::user_config::UserConfig;
+use crate::warn_user_once;
+
+/// The CA bundles of the common systems, where `native_certificates` finds them.
+const SYSTEM_CA_BUNDLES: &[&str] = &[
+    // Debian, Ubuntu, Arch, Alpine
+    "/etc/ssl/certs/ca-certificates.crt",
+    // Fedora, RHEL
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    // openSUSE
+    "/etc/ssl/ca-bundle.pem",
+    // macOS, BSDs
+    "/etc/ssl/cert.pem",
+];
+
+/// The certificates trusted besides the built-in ones, from the user config or `SSL_CERT_FILE`.
+static CA_CERTIFICATES: LazyLock<Vec<Certificate>> = LazyLock::new(|| {
+    let config = UserConfig::get();
+    let mut files = Vec::new();
+    if config.native_certificates {
+        let bundle = SYSTEM_CA_BUNDLES
+            .iter()
+            .map(Path::new)
+            .find(|path| path.is_file());
+        if let Some(bundle) = bundle {
+            files.push(bundle.to_path_buf());
+        } else {
+            warn_user_once!("No CA certificates of the system found");
+        }
+    }
+    if let Some(path) = config
+        .ca_certificates
+        .clone()
+        .or_else(|| std::env::var_os(EnvVars::SSL_CERT_FILE).map(PathBuf::from))
+    {
+        files.push(path);
+    }
+
+    files
+        .iter()
+        .flat_map(|path| {
+            debug!(path = %path.display(), "Loading CA certificates");
+            let certificates = fs_err::read(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|pem| Ok(Certificate::from_pem_bundle(&pem)?));
+            certificates.unwrap_or_else(|err| {
+                warn_user_once!(
+                    "Failed to load CA certificates from `{}`: {err}",
+                    path.user_display()
+                );
+                Vec::new()
+            })
+        })
+        .collect()
+});
+
+/// A client for HTTP requests.
+///
+/// It goes through the proxies of `HTTPS_PROXY`, `HTTP_PROXY` and `ALL_PROXY` except for the
+/// hosts in `NO_PROXY`, and trusts the CA certificates of the user config.
+pub(crate) fn client() -> reqwest::Client {
+    let builder = CA_CERTIFICATES
+        .iter()
+        .cloned()
+        .fold(reqwest::Client::builder(), |builder, certificate| {
+            builder.add_root_certificate(certificate)
+        });
+    builder.build().unwrap_or_else(|err| {
+        warn_user_once!("Failed to set up the HTTP client: {err}");
+        reqwest::Client::new()
+    })
+}
+
+/// Pass the CA certificates of the user config to git, uv, pip and npm, through the
+/// variables they read, unless they're set already.
+///
+/// Must run before spawning any process, `SSL_CERT_FILE`, `GIT_SSL_CAINFO` and `PIP_CERT`
+/// replace their own certificates, `NODE_EXTRA_CA_CERTS` adds to them.
+pub(crate) fn export_certificates() {
+    let config = UserConfig::get();
+    let mut vars = Vec::new();
+    if config.native_certificates {
+        vars.push((EnvVars::UV_NATIVE_TLS, PathBuf::from("1")));
+    }
+    if let Some(path) = &config.ca_certificates {
+        for var in [
+            EnvVars::SSL_CERT_FILE,
+            EnvVars::GIT_SSL_CAINFO,
+            EnvVars::PIP_CERT,
+            EnvVars::NODE_EXTRA_CA_CERTS,
+        ] {
+            vars.push((var, path.clone()));
+        }
+    }
+    for (var, value) in vars {
+        if std::env::var_os(var).is_none() {
+            debug!(var, value = %value.display(), "Exporting CA certificates");
+            std::env::set_var(var, value);
+        }
+    }
+}
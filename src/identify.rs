@@ -7,10 +7,13 @@ use std::os::unix::fs::FileTypeExt;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
 use std::vec;
 
 use anyhow::Result;
 
+use crate::config::FileTypeRules;
+
 mod tags {
     pub const DIRECTORY: &str = "directory";
     pub const SYMLINK: &str = "symlink";
@@ -635,63 +638,169 @@ fn is_encoding_tag(tag: &str) -> bool {
     matches!(tag, tags::TEXT | tags::BINARY)
 }
 
-pub fn tags_from_path(path: &Path) -> Result<Vec<&str>> {
-    let metadata = std::fs::metadata(path)?;
-    if metadata.is_dir() {
-        return Ok(vec![tags::DIRECTORY]);
-    } else if metadata.is_symlink() {
-        return Ok(vec![tags::SYMLINK]);
-    }
-    #[cfg(unix)]
-    {
-        let file_type = metadata.file_type();
-        if file_type.is_socket() {
-            return Ok(vec![tags::SOCKET]);
-        } else if file_type.is_fifo() {
-            return Ok(vec![tags::FIFO]);
-        } else if file_type.is_block_device() {
-            return Ok(vec![tags::BLOCK_DEVICE]);
-        } else if file_type.is_char_device() {
-            return Ok(vec![tags::CHARACTER_DEVICE]);
-        }
-    };
+/// The stat of a file, a cached [`FileHeader`] is valid for as long as it doesn't change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileStamp {
+    pub(crate) size: u64,
+    /// The modification time, in nanoseconds since the epoch.
+    pub(crate) mtime: i64,
+    pub(crate) mode: u32,
+}
 
-    let mut tags = HashSet::new();
-    tags.insert(tags::FILE);
+impl FileStamp {
+    fn new(metadata: &std::fs::Metadata) -> Self {
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |time| i64::try_from(time.as_nanos()).unwrap_or(i64::MAX));
+        #[cfg(unix)]
+        let mode = metadata.permissions().mode();
+        #[cfg(not(unix))]
+        let mode = u32::from(metadata.permissions().readonly());
+        Self {
+            size: metadata.len(),
+            mtime,
+            mode,
+        }
+    }
+}
 
-    #[cfg(unix)]
-    let executable = metadata.permissions().mode() & 0o111 != 0;
-    #[cfg(not(unix))]
-    let executable = {
-        let ext = path.extension().and_then(|ext| ext.to_str());
-        ext.map_or(false, |ext| ext == "exe" || ext == "bat" || ext == "cmd")
-    };
+/// What tagging a file reads from its content, cached in the store between runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct FileHeader {
+    /// The command of the shebang, read for executables only.
+    pub(crate) shebang: Option<Vec<String>>,
+    /// Whether the file is text, read only when its name doesn't tell.
+    pub(crate) text: Option<bool>,
+}
 
-    if executable {
-        tags.insert(tags::EXECUTABLE);
-    } else {
-        tags.insert(tags::NON_EXECUTABLE);
-    }
+/// What tagging a file reads from the file system.
+#[derive(Debug, Clone)]
+pub(crate) enum FileInfo {
+    /// A directory, a symlink or a special file, tagged by its type only.
+    Special(&'static str),
+    File {
+        executable: bool,
+        stamp: FileStamp,
+        header: FileHeader,
+    },
+}
 
-    tags.extend(tags_from_filename(path));
-    if executable {
-        if let Ok(shebang) = parse_shebang(path) {
-            tags.extend(tags_from_interpreter(&shebang));
+impl FileInfo {
+    /// Read what tagging a file with the `custom` file types needs, reusing the `cached`
+    /// header if the file hasn't changed since it was read.
+    pub(crate) fn read(
+        path: &Path,
+        custom: &HashMap<String, FileTypeRules>,
+        cached: Option<&(FileStamp, FileHeader)>,
+    ) -> Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        if metadata.is_dir() {
+            return Ok(Self::Special(tags::DIRECTORY));
+        } else if metadata.is_symlink() {
+            return Ok(Self::Special(tags::SYMLINK));
         }
+        #[cfg(unix)]
+        {
+            let file_type = metadata.file_type();
+            if file_type.is_socket() {
+                return Ok(Self::Special(tags::SOCKET));
+            } else if file_type.is_fifo() {
+                return Ok(Self::Special(tags::FIFO));
+            } else if file_type.is_block_device() {
+                return Ok(Self::Special(tags::BLOCK_DEVICE));
+            } else if file_type.is_char_device() {
+                return Ok(Self::Special(tags::CHARACTER_DEVICE));
+            }
+        };
+
+        #[cfg(unix)]
+        let executable = metadata.permissions().mode() & 0o111 != 0;
+        #[cfg(not(unix))]
+        let executable = {
+            let ext = path.extension().and_then(|ext| ext.to_str());
+            ext.map_or(false, |ext| ext == "exe" || ext == "bat" || ext == "cmd")
+        };
+
+        let stamp = FileStamp::new(&metadata);
+        let header = match cached {
+            Some((cached, header)) if *cached == stamp => header.clone(),
+            _ => FileHeader {
+                shebang: if executable {
+                    parse_shebang(path).ok()
+                } else {
+                    None
+                },
+                text: None,
+            },
+        };
+        let mut info = Self::File {
+            executable,
+            stamp,
+            header,
+        };
+
+        let encoding = info.tags(path, custom).into_iter().any(is_encoding_tag);
+        if let Self::File { header, .. } = &mut info {
+            if header.text.is_none() && !encoding {
+                header.text = Some(is_text_file(path));
+            }
+        }
+
+        Ok(info)
     }
 
-    if !tags.iter().any(|&tag| is_encoding_tag(tag)) {
-        if is_text_file(path) {
-            tags.insert(tags::TEXT);
+    /// The tags of the file, including the `custom` file types it matches.
+    pub(crate) fn tags<'a>(
+        &self,
+        path: &Path,
+        custom: &'a HashMap<String, FileTypeRules>,
+    ) -> Vec<&'a str> {
+        let (executable, header) = match self {
+            Self::Special(tag) => return vec![tag],
+            Self::File {
+                executable, header, ..
+            } => (*executable, header),
+        };
+
+        let mut tags = HashSet::new();
+        tags.insert(tags::FILE);
+        if executable {
+            tags.insert(tags::EXECUTABLE);
         } else {
-            tags.insert(tags::BINARY);
+            tags.insert(tags::NON_EXECUTABLE);
         }
-    }
 
-    Ok(tags.into_iter().collect())
+        tags.extend(tags_from_filename(path));
+        let shebang = header.shebang.as_deref();
+        if let Some(shebang) = shebang {
+            tags.extend(tags_from_interpreter(shebang));
+        }
+        tags.extend(
+            custom
+                .iter()
+                .filter(|(_, rules)| matches_rules(rules, path, shebang))
+                .map(|(tag, _)| tag.as_str()),
+        );
+
+        if !tags.iter().any(|&tag| is_encoding_tag(tag)) {
+            match header.text {
+                Some(true) => {
+                    tags.insert(tags::TEXT);
+                }
+                Some(false) => {
+                    tags.insert(tags::BINARY);
+                }
+                None => {}
+            }
+        }
+
+        tags.into_iter().collect()
+    }
 }
 
-fn tags_from_filename(filename: &Path) -> Vec<&str> {
+fn tags_from_filename(filename: &Path) -> Vec<&'static str> {
     let ext = filename.extension().and_then(|ext| ext.to_str());
     let filename = filename
         .file_name()
@@ -721,8 +830,35 @@ fn tags_from_filename(filename: &Path) -> Vec<&str> {
     result.into_iter().collect()
 }
 
-fn tags_from_interpreter(_interpreter: &[String]) -> Vec<&'static str> {
-    todo!()
+/// The names to look an interpreter up by, like `python3.12` and then `python3`.
+fn interpreter_names(interpreter: &[String]) -> impl Iterator<Item = &str> {
+    let name = interpreter
+        .first()
+        .map(|interpreter| interpreter.rsplit('/').next().unwrap_or(interpreter));
+    std::iter::successors(name, |name| name.rsplit_once('.').map(|(name, _)| name))
+}
+
+fn tags_from_interpreter(interpreter: &[String]) -> Vec<&'static str> {
+    interpreter_names(interpreter)
+        .find_map(|name| by_interpreter().get(name))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Whether a file matches the rules of a custom file type.
+fn matches_rules(rules: &FileTypeRules, path: &Path, shebang: Option<&[String]>) -> bool {
+    let filename = path.file_name().and_then(|name| name.to_str());
+    let ext = path.extension().and_then(|ext| ext.to_str());
+
+    ext.is_some_and(|ext| rules.extensions.iter().any(|e| e == ext))
+        || filename.is_some_and(|filename| {
+            // Like the builtin names, e.g. `Dockerfile.xenial` matches `Dockerfile`.
+            let name = filename.split('.').next().unwrap_or(filename);
+            rules.filenames.iter().any(|f| f == filename || f == name)
+        })
+        || shebang.is_some_and(|shebang| {
+            interpreter_names(shebang).any(|name| rules.interpreters.iter().any(|i| i == name))
+        })
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -744,6 +880,7 @@ fn parse_shebang(path: &Path) -> Result<Vec<String>, ShebangError> {
     let mut reader = std::io::BufReader::new(file);
     let mut line = String::new();
     reader.read_line(&mut line)?;
+    let line = line.trim_end_matches(['\r', '\n']);
     if !line.starts_with("#!") {
         return Err(ShebangError::NoShebang);
     }
@@ -811,4 +948,19 @@ mod tests {
         let tags = super::tags_from_filename(Path::new("data.json"));
         assert_eq!(tags, vec!["json", "text"]);
     }
+
+    #[test]
+    fn tags_from_interpreter() {
+        let shebang = |cmd: &str| vec![cmd.to_string()];
+        assert_eq!(
+            super::tags_from_interpreter(&shebang("/usr/bin/python3.12")),
+            vec!["python", "python3"]
+        );
+        assert_eq!(
+            super::tags_from_interpreter(&shebang("bash")),
+            vec!["shell", "bash"]
+        );
+        assert!(super::tags_from_interpreter(&shebang("unknown")).is_empty());
+        assert!(super::tags_from_interpreter(&[]).is_empty());
+    }
 }
@@ -2,6 +2,8 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use anstream::ColorChoice;
@@ -9,17 +11,61 @@ use anyhow::Result;
 use fancy_regex::Regex;
 use tracing::trace;
 
+use crate::config::ResourceLimits;
 use crate::fs::CWD;
 use crate::hook::Hook;
 use crate::languages::LanguageImpl;
 use crate::process::Cmd;
 use crate::run::run_by_batch;
+use crate::sandbox;
+use crate::store::Store;
 
 const PRE_COMMIT_LABEL: &str = "PRE_COMMIT";
 
 #[derive(Debug, Copy, Clone)]
 pub struct Docker;
 
+/// A container started by `docker run`, killed if the run is cancelled before it exits.
+///
+/// Killing the `docker run` client, e.g. when a hook times out, leaves the container running.
+pub(crate) struct Container(Option<String>);
+
+impl Container {
+    fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        Self(Some(format!(
+            "prefligit-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        )))
+    }
+
+    fn name(&self) -> &str {
+        self.0.as_deref().unwrap_or_default()
+    }
+
+    /// The container exited, along with `docker run`.
+    pub(crate) fn exited(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for Container {
+    fn drop(&mut self) {
+        let Some(name) = self.0.take() else {
+            return;
+        };
+        trace!(name, "Killing container");
+        // Dropping can't wait for it, the container is killed in the background.
+        let _ = tokio::process::Command::new("docker")
+            .arg("kill")
+            .arg(name)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+    }
+}
+
 impl Docker {
     fn docker_tag(hook: &Hook) -> Option<String> {
         hook.path()
@@ -126,15 +172,51 @@ impl Docker {
         Ok(Cow::Borrowed(path))
     }
 
-    pub(crate) async fn docker_cmd() -> Result<Cmd> {
+    pub(crate) async fn docker_cmd(
+        env: &HashMap<String, String>,
+        tty: bool,
+        work_dir: Option<&Path>,
+        working_directory: Option<&str>,
+        limits: &ResourceLimits,
+    ) -> Result<(Cmd, Container)> {
+        let container = Container::new();
         let mut command = Cmd::new("docker", "run container");
-        command.arg("run").arg("--rm");
+        // `--init` forwards signals to the entry, which doesn't get them as PID 1.
+        command
+            .arg("run")
+            .arg("--rm")
+            .arg("--init")
+            .arg("--name")
+            .arg(container.name());
+
+        // The container is the sandbox of docker hooks, only the network needs to be denied.
+        if sandbox::enabled() {
+            command.arg("--network").arg("none");
+        }
 
-        match ColorChoice::global() {
-            ColorChoice::Always | ColorChoice::AlwaysAnsi => {
-                command.arg("--tty");
-            }
-            _ => {}
+        // The limits apply to the container, not to the docker client.
+        if let Some(seconds) = limits.cpu_time {
+            command.arg("--ulimit").arg(format!("cpu={seconds}"));
+        }
+        if let Some(memory) = limits.memory {
+            command.arg("--memory").arg(format!("{memory}m"));
+        }
+        if let Some(files) = limits.open_files {
+            command.arg("--ulimit").arg(format!("nofile={files}"));
+        }
+
+        // Environment variables must be passed into the container explicitly.
+        for (key, value) in env {
+            command.arg("--env").arg(format!("{key}={value}"));
+        }
+
+        if tty
+            || matches!(
+                ColorChoice::global(),
+                ColorChoice::Always | ColorChoice::AlwaysAnsi
+            )
+        {
+            command.arg("--tty");
         }
 
         // Run as a non-root user
@@ -151,12 +233,15 @@ impl Docker {
             // https://docs.docker.com/engine/reference/commandline/run/#mount-volumes-from-container-volumes-from
             .arg(format!(
                 "{}:/src:ro,Z",
-                Self::get_docker_path(&CWD.to_string_lossy()).await?
+                Self::get_docker_path(&work_dir.unwrap_or(CWD.as_path()).to_string_lossy()).await?
             ))
             .arg("--workdir")
-            .arg("/src");
+            .arg(match working_directory {
+                Some(dir) => format!("/src/{dir}"),
+                None => "/src".to_string(),
+            });
 
-        Ok(command)
+        Ok((command, container))
     }
 }
 
@@ -165,16 +250,29 @@ impl LanguageImpl for Docker {
         Some("docker")
     }
 
-    async fn install(&self, hook: &Hook) -> Result<()> {
+    async fn install(&self, hook: &Hook, _store: &Store, on_phase: &dyn Fn(&str)) -> Result<()> {
         let env = hook.environment_dir().expect("No environment dir found");
 
+        on_phase("building image");
         Docker::build_docker_image(hook, true).await?;
         fs_err::create_dir_all(env)?;
         Ok(())
     }
 
-    async fn check_health(&self) -> Result<()> {
-        todo!()
+    fn installer_version(&self) -> Option<&str> {
+        None
+    }
+
+    /// Check the image of the hook hasn't been removed.
+    async fn check_health(&self, hook: &Hook) -> Result<()> {
+        Cmd::new("docker", "inspect docker image")
+            .arg("image")
+            .arg("inspect")
+            .arg(Self::docker_tag(hook).expect("Failed to get docker tag"))
+            .check(true)
+            .output()
+            .await?;
+        Ok(())
     }
 
     async fn run(
@@ -182,6 +280,7 @@ impl LanguageImpl for Docker {
         hook: &Hook,
         filenames: &[&String],
         env_vars: Arc<HashMap<&'static str, String>>,
+        work_dir: Option<&Path>,
     ) -> Result<(i32, Vec<u8>)> {
         Docker::build_docker_image(hook, false).await?;
 
@@ -191,16 +290,33 @@ impl LanguageImpl for Docker {
 
         let cmds = Arc::new(cmds);
         let hook_args = Arc::new(hook.args.clone());
+        let hook_env = Arc::new(hook.env.clone());
+        let stream = hook.stream.then(|| hook.id.clone());
+        let pty = hook.pty;
+        let limits = hook.limits;
+        let working_directory = Arc::new(hook.working_directory.clone());
+        let work_dir = Arc::new(work_dir.map(Path::to_path_buf));
 
         let run = move |batch: Vec<String>| {
             let cmds = cmds.clone();
+            let stream = stream.clone();
             let docker_tag = docker_tag.clone();
             let hook_args = hook_args.clone();
             let env_vars = env_vars.clone();
+            let hook_env = hook_env.clone();
+            let working_directory = working_directory.clone();
+            let work_dir = work_dir.clone();
 
             async move {
                 // docker run [OPTIONS] IMAGE [COMMAND] [ARG...]
-                let mut cmd = Docker::docker_cmd().await?;
+                let (mut cmd, container) = Docker::docker_cmd(
+                    &hook_env,
+                    pty,
+                    work_dir.as_deref(),
+                    working_directory.as_deref(),
+                    &limits,
+                )
+                .await?;
                 let cmd = cmd
                     .arg("--entrypoint")
                     .arg(&cmds[0])
@@ -209,9 +325,14 @@ impl LanguageImpl for Docker {
                     .args(hook_args.as_ref())
                     .args(batch)
                     .check(false)
+                    .kill_on_drop(true)
+                    .stream(stream)
+                    .pty(pty)
                     .envs(env_vars.as_ref());
 
-                let mut output = cmd.output().await?;
+                let output = cmd.output().await;
+                container.exited();
+                let mut output = output?;
                 output.stdout.extend(output.stderr);
                 let code = output.status.code().unwrap_or(1);
                 anyhow::Ok((code, output.stdout))
@@ -1,10 +1,12 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::hook::Hook;
 use crate::languages::docker::Docker;
 use crate::languages::LanguageImpl;
 use crate::run::run_by_batch;
+use crate::store::Store;
 
 #[derive(Debug, Copy, Clone)]
 pub struct DockerImage;
@@ -14,12 +16,16 @@ impl LanguageImpl for DockerImage {
         None
     }
 
-    async fn install(&self, _: &Hook) -> anyhow::Result<()> {
+    async fn install(&self, _: &Hook, _: &Store, _: &dyn Fn(&str)) -> anyhow::Result<()> {
         Ok(())
     }
 
-    async fn check_health(&self) -> anyhow::Result<()> {
-        todo!()
+    fn installer_version(&self) -> Option<&str> {
+        None
+    }
+
+    async fn check_health(&self, _hook: &Hook) -> anyhow::Result<()> {
+        Ok(())
     }
 
     async fn run(
@@ -27,27 +33,50 @@ impl LanguageImpl for DockerImage {
         hook: &Hook,
         filenames: &[&String],
         env_vars: Arc<HashMap<&'static str, String>>,
+        work_dir: Option<&Path>,
     ) -> anyhow::Result<(i32, Vec<u8>)> {
         let cmds = shlex::split(&hook.entry).ok_or(anyhow::anyhow!("Failed to parse entry"))?;
 
         let cmds = Arc::new(cmds);
         let hook_args = Arc::new(hook.args.clone());
+        let hook_env = Arc::new(hook.env.clone());
+        let stream = hook.stream.then(|| hook.id.clone());
+        let pty = hook.pty;
+        let limits = hook.limits;
+        let working_directory = Arc::new(hook.working_directory.clone());
+        let work_dir = Arc::new(work_dir.map(Path::to_path_buf));
 
         let run = move |batch: Vec<String>| {
             let cmds = cmds.clone();
+            let stream = stream.clone();
             let hook_args = hook_args.clone();
             let env_vars = env_vars.clone();
+            let hook_env = hook_env.clone();
+            let working_directory = working_directory.clone();
+            let work_dir = work_dir.clone();
 
             async move {
-                let mut cmd = Docker::docker_cmd().await?;
+                let (mut cmd, container) = Docker::docker_cmd(
+                    &hook_env,
+                    pty,
+                    work_dir.as_deref(),
+                    working_directory.as_deref(),
+                    &limits,
+                )
+                .await?;
                 let cmd = cmd
                     .args(&cmds[..])
                     .args(hook_args.as_ref())
                     .args(batch)
                     .check(false)
+                    .kill_on_drop(true)
+                    .stream(stream)
+                    .pty(pty)
                     .envs(env_vars.as_ref());
 
-                let mut output = cmd.output().await?;
+                let output = cmd.output().await;
+                container.exited();
+                let mut output = output?;
                 output.stdout.extend(output.stderr);
                 let code = output.status.code().unwrap_or(1);
                 anyhow::Ok((code, output.stdout))
@@ -1,7 +1,9 @@
+use std::path::Path;
 use std::{collections::HashMap, sync::Arc};
 
 use crate::hook::Hook;
 use crate::languages::LanguageImpl;
+use crate::store::Store;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Fail;
@@ -11,11 +13,20 @@ impl LanguageImpl for Fail {
         None
     }
 
-    async fn install(&self, _hook: &Hook) -> anyhow::Result<()> {
+    async fn install(
+        &self,
+        _hook: &Hook,
+        _store: &Store,
+        _on_phase: &dyn Fn(&str),
+    ) -> anyhow::Result<()> {
         Ok(())
     }
 
-    async fn check_health(&self) -> anyhow::Result<()> {
+    fn installer_version(&self) -> Option<&str> {
+        None
+    }
+
+    async fn check_health(&self, _hook: &Hook) -> anyhow::Result<()> {
         Ok(())
     }
 
@@ -24,6 +35,7 @@ impl LanguageImpl for Fail {
         hook: &Hook,
         filenames: &[&String],
         _env_vars: Arc<HashMap<&'static str, String>>,
+        _work_dir: Option<&Path>,
     ) -> anyhow::Result<(i32, Vec<u8>)> {
         let mut out = hook.entry.as_bytes().to_vec();
         out.extend(b"\n\n");
@@ -1,9 +1,11 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::builtin;
 use crate::config::Language;
 use crate::hook::Hook;
+use crate::store::Store;
 use anyhow::Result;
 
 mod docker;
@@ -22,13 +24,24 @@ static DOCKER_IMAGE: docker_image::DockerImage = docker_image::DockerImage;
 
 trait LanguageImpl {
     fn environment_dir(&self) -> Option<&str>;
-    async fn install(&self, hook: &Hook) -> Result<()>;
-    async fn check_health(&self) -> Result<()>;
+    /// Install the environment of the hook, reporting each phase of the installation
+    /// (e.g. `creating venv`) to `on_phase`.
+    ///
+    /// Tools needed for the installation are installed in the `store`.
+    async fn install(&self, hook: &Hook, store: &Store, on_phase: &dyn Fn(&str)) -> Result<()>;
+    /// The version of the tool installing the environment, if it's managed by the store.
+    ///
+    /// Environments are installed again when it changes.
+    fn installer_version(&self) -> Option<&str>;
+    /// Check the installed environment of the hook still works.
+    async fn check_health(&self, hook: &Hook) -> Result<()>;
+    /// Run the hook on the files, in `work_dir` instead of the current directory if given.
     async fn run(
         &self,
         hook: &Hook,
         filenames: &[&String],
         env_vars: Arc<HashMap<&'static str, String>>,
+        work_dir: Option<&Path>,
     ) -> Result<(i32, Vec<u8>)>;
 }
 
@@ -45,26 +58,45 @@ impl Language {
         }
     }
 
-    pub async fn install(&self, hook: &Hook) -> Result<()> {
+    /// Whether the environment has the hook repo installed into it, like a Python package,
+    /// so it can't be shared with the hooks of other repos.
+    pub fn installs_repo(self) -> bool {
+        matches!(self, Self::Python | Self::Docker)
+    }
+
+    pub async fn install(&self, hook: &Hook, store: &Store, on_phase: &dyn Fn(&str)) -> Result<()> {
         match self {
-            Self::Python => PYTHON.install(hook).await,
-            Self::Node => NODE.install(hook).await,
-            Self::System => SYSTEM.install(hook).await,
-            Self::Fail => FAIL.install(hook).await,
-            Self::Docker => DOCKER.install(hook).await,
-            Self::DockerImage => DOCKER_IMAGE.install(hook).await,
+            Self::Python => PYTHON.install(hook, store, on_phase).await,
+            Self::Node => NODE.install(hook, store, on_phase).await,
+            Self::System => SYSTEM.install(hook, store, on_phase).await,
+            Self::Fail => FAIL.install(hook, store, on_phase).await,
+            Self::Docker => DOCKER.install(hook, store, on_phase).await,
+            Self::DockerImage => DOCKER_IMAGE.install(hook, store, on_phase).await,
             _ => todo!(),
         }
     }
 
-    pub async fn check_health(&self) -> Result<()> {
+    pub fn installer_version(&self) -> Option<&str> {
+        match self {
+            Self::Python => PYTHON.installer_version(),
+            Self::Node => NODE.installer_version(),
+            Self::System => SYSTEM.installer_version(),
+            Self::Fail => FAIL.installer_version(),
+            Self::Docker => DOCKER.installer_version(),
+            Self::DockerImage => DOCKER_IMAGE.installer_version(),
+            // Other languages are not supported yet, so nothing installs their environments.
+            _ => None,
+        }
+    }
+
+    pub async fn check_health(&self, hook: &Hook) -> Result<()> {
         match self {
-            Self::Python => PYTHON.check_health().await,
-            Self::Node => NODE.check_health().await,
-            Self::System => SYSTEM.check_health().await,
-            Self::Fail => FAIL.check_health().await,
-            Self::Docker => DOCKER.check_health().await,
-            Self::DockerImage => DOCKER_IMAGE.check_health().await,
+            Self::Python => PYTHON.check_health(hook).await,
+            Self::Node => NODE.check_health(hook).await,
+            Self::System => SYSTEM.check_health(hook).await,
+            Self::Fail => FAIL.check_health(hook).await,
+            Self::Docker => DOCKER.check_health(hook).await,
+            Self::DockerImage => DOCKER_IMAGE.check_health(hook).await,
             _ => todo!(),
         }
     }
@@ -74,20 +106,53 @@ impl Language {
         hook: &Hook,
         filenames: &[&String],
         env_vars: Arc<HashMap<&'static str, String>>,
+        work_dir: Option<&Path>,
     ) -> Result<(i32, Vec<u8>)> {
         // fast path for hooks implemented in Rust
         if builtin::check_fast_path(hook) {
-            return builtin::run_fast_path(hook, filenames, env_vars).await;
+            return builtin::run_fast_path(hook, filenames, env_vars, work_dir).await;
         }
 
+        // Hooks run in their working directory, so the filenames must be relative to it.
+        let relative: Vec<String>;
+        let relative_refs: Vec<&String>;
+        let filenames = if let Some(dir) = &hook.working_directory {
+            relative = filenames
+                .iter()
+                .map(|filename| {
+                    Path::new(filename.as_str()).strip_prefix(dir).map_or_else(
+                        |_| (*filename).clone(),
+                        |path| path.to_string_lossy().to_string(),
+                    )
+                })
+                .collect();
+            relative_refs = relative.iter().collect();
+            &relative_refs[..]
+        } else {
+            filenames
+        };
+
         match self {
-            Self::Python => PYTHON.run(hook, filenames, env_vars).await,
-            Self::Node => NODE.run(hook, filenames, env_vars).await,
-            Self::System => SYSTEM.run(hook, filenames, env_vars).await,
-            Self::Fail => FAIL.run(hook, filenames, env_vars).await,
-            Self::Docker => DOCKER.run(hook, filenames, env_vars).await,
-            Self::DockerImage => DOCKER_IMAGE.run(hook, filenames, env_vars).await,
+            Self::Python => PYTHON.run(hook, filenames, env_vars, work_dir).await,
+            Self::Node => NODE.run(hook, filenames, env_vars, work_dir).await,
+            Self::System => SYSTEM.run(hook, filenames, env_vars, work_dir).await,
+            Self::Fail => FAIL.run(hook, filenames, env_vars, work_dir).await,
+            Self::Docker => DOCKER.run(hook, filenames, env_vars, work_dir).await,
+            Self::DockerImage => DOCKER_IMAGE.run(hook, filenames, env_vars, work_dir).await,
             _ => todo!(),
         }
     }
 }
+
+/// The directory a hook runs in, its `working_directory` in `work_dir`, or in the current
+/// directory if `work_dir` is `None`.
+fn hook_dir(hook: &Hook, work_dir: Option<&Path>) -> Option<PathBuf> {
+    match work_dir {
+        Some(work_dir) => Some(
+            hook.working_directory
+                .as_ref()
+                .map_or_else(|| work_dir.to_path_buf(), |dir| work_dir.join(dir)),
+        ),
+        None => hook.working_directory.as_ref().map(PathBuf::from),
+    }
+}
@@ -1,8 +1,10 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::hook::Hook;
 use crate::languages::LanguageImpl;
+use crate::store::Store;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Node;
@@ -12,7 +14,12 @@ impl LanguageImpl for Node {
         Some("node_env")
     }
 
-    async fn install(&self, hook: &Hook) -> anyhow::Result<()> {
+    async fn install(
+        &self,
+        hook: &Hook,
+        _store: &Store,
+        _on_phase: &dyn Fn(&str),
+    ) -> anyhow::Result<()> {
         // TODO: install node automatically
         let env = hook.environment_dir().expect("No environment dir found");
         fs_err::create_dir_all(env)?;
@@ -20,8 +27,16 @@ impl LanguageImpl for Node {
         Ok(())
     }
 
-    async fn check_health(&self) -> anyhow::Result<()> {
-        todo!()
+    fn installer_version(&self) -> Option<&str> {
+        None
+    }
+
+    async fn check_health(&self, hook: &Hook) -> anyhow::Result<()> {
+        let env = hook.environment_dir().expect("No environment dir found");
+        if !env.is_dir() {
+            anyhow::bail!("Environment `{}` not found", env.display());
+        }
+        Ok(())
     }
 
     async fn run(
@@ -29,6 +44,7 @@ impl LanguageImpl for Node {
         _hook: &Hook,
         _filenames: &[&String],
         _env_vars: Arc<HashMap<&'static str, String>>,
+        _work_dir: Option<&Path>,
     ) -> anyhow::Result<(i32, Vec<u8>)> {
         Ok((0, Vec::new()))
     }
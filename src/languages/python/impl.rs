@@ -2,14 +2,21 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::client;
 use crate::config::LanguageVersion;
 use crate::env_vars::EnvVars;
 use crate::hook::Hook;
-use crate::languages::python::uv::UvInstaller;
-use crate::languages::LanguageImpl;
+use crate::languages::python::uv::{UvInstaller, PYPI_URL, UV_VERSION};
+use crate::languages::{hook_dir, LanguageImpl};
 use crate::process::Cmd;
 use crate::run::run_by_batch;
+use crate::sandbox::{hook_command, Sandbox};
 use crate::store::{Store, ToolBucket};
+use crate::user_config::UserConfig;
+
+/// Where uv downloads Python from, it can be rewritten to a mirror in the user config.
+const PYTHON_DOWNLOADS_URL: &str =
+    "https://github.com/astral-sh/python-build-standalone/releases/download";
 
 #[derive(Debug, Copy, Clone)]
 pub struct Python;
@@ -20,12 +27,17 @@ impl LanguageImpl for Python {
     }
 
     // TODO: fallback to virtualenv, pip
-    async fn install(&self, hook: &Hook) -> anyhow::Result<()> {
+    async fn install(
+        &self,
+        hook: &Hook,
+        store: &Store,
+        on_phase: &dyn Fn(&str),
+    ) -> anyhow::Result<()> {
         let venv = hook.environment_dir().expect("No environment dir found");
 
-        let uv = UvInstaller::install().await?;
+        on_phase("installing uv");
+        let uv = UvInstaller::install(store).await?;
 
-        let store = Store::from_settings()?;
         let python_install_dir = store.tools_path(ToolBucket::Python);
 
         let uv_cmd = |summary| {
@@ -37,10 +49,20 @@ impl LanguageImpl for Python {
             cmd.env(EnvVars::UV_NO_CACHE, "1");
 
             cmd.env(EnvVars::UV_PYTHON_INSTALL_DIR, &python_install_dir);
+            let user_config = UserConfig::get();
+            let index = user_config.rewrite_url(PYPI_URL);
+            if index != PYPI_URL {
+                cmd.env(EnvVars::UV_DEFAULT_INDEX, &*index);
+            }
+            let pythons = user_config.rewrite_url(PYTHON_DOWNLOADS_URL);
+            if pythons != PYTHON_DOWNLOADS_URL {
+                cmd.env(EnvVars::UV_PYTHON_INSTALL_MIRROR, &*pythons);
+            }
             cmd
         };
 
         // Create venv
+        on_phase("creating venv");
         let mut cmd = uv_cmd("create venv");
         cmd.arg("venv").arg(&venv);
         match hook.language_version {
@@ -57,6 +79,7 @@ impl LanguageImpl for Python {
         cmd.check(true).output().await?;
 
         // Install dependencies
+        on_phase("installing dependencies");
         uv_cmd("install dependencies")
             .arg("pip")
             .arg("install")
@@ -71,8 +94,36 @@ impl LanguageImpl for Python {
         Ok(())
     }
 
-    async fn check_health(&self) -> anyhow::Result<()> {
-        todo!()
+    fn installer_version(&self) -> Option<&str> {
+        Some(UV_VERSION)
+    }
+
+    /// Check the interpreter of the venv still runs, it's gone if the Python it was created from
+    /// is removed, and that it's still the version the venv was created with. The Python of
+    /// `system` or `default` venvs may be upgraded in place, leaving the installed packages
+    /// behind in the directory of the old version.
+    async fn check_health(&self, hook: &Hook) -> anyhow::Result<()> {
+        let env_dir = hook
+            .environment_dir()
+            .expect("No environment dir for Python");
+        let python = bin_dir(&env_dir)
+            .join("python")
+            .with_extension(std::env::consts::EXE_EXTENSION);
+        let output = Cmd::new(&python, "check python")
+            .arg("-c")
+            .arg("import platform; print(platform.python_version())")
+            .check(true)
+            .output()
+            .await?;
+
+        // Packages are installed by the minor version, patch upgrades keep working.
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if let Some(created) = venv_python_version(&env_dir) {
+            if minor_version(&created) != minor_version(&version) {
+                anyhow::bail!("The venv was created with Python {created}, but it runs {version}");
+            }
+        }
+        Ok(())
     }
 
     async fn run(
@@ -80,6 +131,7 @@ impl LanguageImpl for Python {
         hook: &Hook,
         filenames: &[&String],
         env_vars: Arc<HashMap<&'static str, String>>,
+        work_dir: Option<&Path>,
     ) -> anyhow::Result<(i32, Vec<u8>)> {
         // Get environment directory and parse command
         let env_dir = hook
@@ -92,7 +144,7 @@ impl LanguageImpl for Python {
         // Construct PATH with venv bin directory first
         let new_path = std::env::join_paths(
             std::iter::once(bin_dir(env_dir.as_path())).chain(
-                std::env::var_os(EnvVars::PATH)
+                client::var_os(EnvVars::PATH)
                     .as_ref()
                     .iter()
                     .flat_map(std::env::split_paths),
@@ -101,6 +153,13 @@ impl LanguageImpl for Python {
 
         let cmds = Arc::new(cmds);
         let hook_args = Arc::new(hook.args.clone());
+        let host_env = Arc::new(hook.host_env());
+        let hook_env = Arc::new(hook.env.clone());
+        let stream = hook.stream.then(|| hook.id.clone());
+        let pty = hook.pty;
+        let limits = hook.limits;
+        let sandbox = Arc::new(Sandbox::for_hook(hook, work_dir)?);
+        let working_directory = Arc::new(hook_dir(hook, work_dir));
         let env_dir = Arc::new(env_dir.clone());
         let new_path = Arc::new(new_path);
 
@@ -108,15 +167,29 @@ impl LanguageImpl for Python {
             // This closure should be Fn, as it is called for each batch. We need to clone the variables,
             // otherwise it will be moved into the async block and can't be used again.
             let cmds = cmds.clone();
+            let stream = stream.clone();
             let hook_args = hook_args.clone();
             let env_dir = env_dir.clone();
             let new_path = new_path.clone();
             let env_vars = env_vars.clone();
+            let host_env = host_env.clone();
+            let hook_env = hook_env.clone();
+            let working_directory = working_directory.clone();
+            let sandbox = sandbox.clone();
 
             // TODO: combine stdout and stderr
             async move {
-                let mut output = Cmd::new(&cmds[0], "run python command")
+                let mut cmd =
+                    hook_command(sandbox.as_ref().as_ref(), &cmds[0], "run python command");
+                if let Some(host_env) = host_env.as_ref() {
+                    cmd.env_clear().envs(host_env.iter().cloned());
+                }
+                if let Some(dir) = working_directory.as_ref() {
+                    cmd.current_dir(dir);
+                }
+                let mut output = cmd
                     .args(&cmds[1..])
+                    .envs(hook_env.as_ref())
                     .env("VIRTUAL_ENV", env_dir.as_ref())
                     .env("PATH", new_path.as_ref())
                     .env_remove("PYTHONHOME")
@@ -124,6 +197,10 @@ impl LanguageImpl for Python {
                     .args(hook_args.as_slice())
                     .args(batch)
                     .check(false)
+                    .kill_on_drop(true)
+                    .limits(limits)
+                    .stream(stream)
+                    .pty(pty)
                     .output()
                     .await?;
 
@@ -148,6 +225,20 @@ impl LanguageImpl for Python {
     }
 }
 
+/// The Python version the venv was created with, from its `pyvenv.cfg`.
+fn venv_python_version(venv: &Path) -> Option<String> {
+    let cfg = fs_err::read_to_string(venv.join("pyvenv.cfg")).ok()?;
+    cfg.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        matches!(key.trim(), "version_info" | "version").then(|| value.trim().to_string())
+    })
+}
+
+/// The `major.minor` part of a Python version.
+fn minor_version(version: &str) -> String {
+    version.split('.').take(2).collect::<Vec<_>>().join(".")
+}
+
 fn bin_dir(venv: &Path) -> PathBuf {
     if cfg!(windows) {
         venv.join("Scripts")
@@ -2,17 +2,28 @@ use std::env;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use anyhow::Result;
-use axoupdater::{AxoUpdater, ReleaseSource, ReleaseSourceType, UpdateRequest};
+use anyhow::{Context, Result};
 use tokio::task::JoinSet;
-use tracing::{debug, enabled, trace, warn};
+use tracing::{debug, trace, warn};
 
+use crate::download::{download, fetch_checksum};
 use crate::fs::LockedFile;
+use crate::http;
 use crate::process::Cmd;
+use crate::retry::retry_network;
 use crate::store::{Store, ToolBucket};
+use crate::user_config::UserConfig;
 
 // The version of `uv` to install. Should update periodically.
-const UV_VERSION: &str = "0.5.8";
+pub(crate) const UV_VERSION: &str = "0.5.8";
+
+/// The index of `PyPi`, it can be rewritten to a mirror in the user config.
+pub(crate) const PYPI_URL: &str = "https://pypi.org/simple/";
+
+/// The base URL of the uv release archives on GitHub.
+fn github_url() -> String {
+    format!("https://github.com/astral-sh/uv/releases/download/{UV_VERSION}/")
+}
 
 #[derive(Debug)]
 enum PyPiMirror {
@@ -29,7 +40,7 @@ enum PyPiMirror {
 impl PyPiMirror {
     fn url(&self) -> &str {
         match self {
-            Self::Pypi => "https://pypi.org/simple/",
+            Self::Pypi => PYPI_URL,
             Self::Tuna => "https://pypi.tuna.tsinghua.edu.cn/simple/",
             Self::Aliyun => "https://mirrors.aliyun.com/pypi/simple/",
             Self::Tencent => "https://mirrors.cloud.tencent.com/pypi/simple/",
@@ -42,10 +53,28 @@ impl PyPiMirror {
     }
 }
 
+/// The name of the uv release archive of the current platform.
+fn release_archive() -> Option<String> {
+    let arch = match env::consts::ARCH {
+        "x86" => "i686",
+        arch @ ("x86_64" | "aarch64") => arch,
+        _ => return None,
+    };
+    let (platform, extension) = match env::consts::OS {
+        "linux" if cfg!(target_env = "musl") => ("unknown-linux-musl", "tar.gz"),
+        "linux" => ("unknown-linux-gnu", "tar.gz"),
+        "macos" => ("apple-darwin", "tar.gz"),
+        "windows" => ("pc-windows-msvc", "zip"),
+        _ => return None,
+    };
+    Some(format!("uv-{arch}-{platform}.{extension}"))
+}
+
 #[derive(Debug)]
 enum InstallSource {
-    /// Download uv from GitHub releases.
-    GitHub,
+    /// Download uv from GitHub releases or a mirror of them at the given base URL, verified by
+    /// their checksums.
+    GitHub(String),
     /// Download uv from `PyPi`.
     PyPi(PyPiMirror),
     /// Install uv by running `pip install uv`.
@@ -55,67 +84,83 @@ enum InstallSource {
 impl InstallSource {
     async fn install(&self, target: &Path) -> Result<()> {
         match self {
-            Self::GitHub => self.install_from_github(target).await,
+            Self::GitHub(base) => self.install_from_github(target, base).await,
             Self::PyPi(source) => self.install_from_pypi(target, source).await,
-            Self::Pip => self.install_from_pip(target).await,
+            Self::Pip => self.install_from_pip(target, None).await,
         }
     }
 
-    async fn install_from_github(&self, target: &Path) -> Result<()> {
-        let mut installer = AxoUpdater::new_for("uv");
-        installer.configure_version_specifier(UpdateRequest::SpecificTag(UV_VERSION.to_string()));
-        installer.always_update(true);
-        installer.set_install_dir(&target.to_string_lossy());
-        installer.set_release_source(ReleaseSource {
-            release_type: ReleaseSourceType::GitHub,
-            owner: "astral-sh".to_string(),
-            name: "uv".to_string(),
-            app_name: "uv".to_string(),
-        });
-        if enabled!(tracing::Level::DEBUG) {
-            installer.enable_installer_output();
-            env::set_var("INSTALLER_PRINT_VERBOSE", "1");
-        } else {
-            installer.disable_installer_output();
+    /// The source to install uv from when the user config rewrites its URLs to mirrors. Then
+    /// nothing is downloaded from GitHub or the public `PyPi`.
+    fn configured(user_config: &UserConfig) -> Option<Self> {
+        let github = github_url();
+        let rewritten = user_config.rewrite_url(&github);
+        if rewritten != github {
+            return Some(Self::GitHub(rewritten.into_owned()));
         }
-        // We don't want the installer to modify the PATH, and don't need the receipt.
-        env::set_var("UV_UNMANAGED_INSTALL", "1");
-
-        match installer.run().await {
-            Ok(Some(result)) => {
-                debug!(
-                    uv = %target.display(),
-                    version = result.new_version_tag,
-                    "Successfully installed uv"
-                );
-                Ok(())
-            }
-            Ok(None) => Ok(()),
-            Err(err) => {
-                warn!(?err, "Failed to install uv");
-                Err(err.into())
-            }
+        let index = user_config.rewrite_url(PYPI_URL);
+        if index != PYPI_URL {
+            return Some(Self::PyPi(PyPiMirror::Custom(index.into_owned())));
         }
+        None
+    }
+
+    async fn install_from_github(&self, target: &Path, base: &str) -> Result<()> {
+        let archive = release_archive().context("No uv release for this platform")?;
+        let url = format!("{base}{archive}");
+        let checksum = retry_network("fetch the checksum of uv", || fetch_checksum(&url)).await?;
+        let path = target.join(&archive);
+        retry_network("download uv", || download(&url, &checksum, &path)).await?;
+
+        // Extract next to uv, and move it in place once complete.
+        let temp = tempfile::tempdir_in(target)?;
+        Cmd::new("tar", "extract uv")
+            .arg("-xf")
+            .arg(&path)
+            .arg("-C")
+            .arg(temp.path())
+            .check(true)
+            .output()
+            .await?;
+        let uv = PathBuf::from("uv").with_extension(env::consts::EXE_EXTENSION);
+        // Unix archives have uv in a directory named after the archive.
+        let extracted = [
+            temp.path().join(&uv),
+            temp.path()
+                .join(archive.trim_end_matches(".tar.gz"))
+                .join(&uv),
+        ]
+        .into_iter()
+        .find(|path| path.is_file())
+        .with_context(|| format!("No `{}` found in `{archive}`", uv.display()))?;
+        fs_err::rename(extracted, target.join(&uv))?;
+        fs_err::remove_file(&path)?;
+
+        debug!(uv = %target.display(), version = UV_VERSION, "Successfully installed uv");
+        Ok(())
     }
 
-    async fn install_from_pypi(&self, target: &Path, _source: &PyPiMirror) -> Result<()> {
+    async fn install_from_pypi(&self, target: &Path, source: &PyPiMirror) -> Result<()> {
         // TODO: Implement this, currently just fallback to pip install
         // Determine the host system
         // Get the html page
         // Parse html, get the latest version url
         // Download the tarball
         // Extract the tarball
-        self.install_from_pip(target).await
+        self.install_from_pip(target, Some(source.url())).await
     }
 
-    async fn install_from_pip(&self, target: &Path) -> Result<()> {
-        Cmd::new("python3", "pip install uv")
-            .arg("-m")
+    async fn install_from_pip(&self, target: &Path, index: Option<&str>) -> Result<()> {
+        let mut cmd = Cmd::new("python3", "pip install uv");
+        cmd.arg("-m")
             .arg("pip")
             .arg("install")
             .arg("--prefix")
-            .arg(target)
-            .arg(format!("uv=={UV_VERSION}"))
+            .arg(target);
+        if let Some(index) = index {
+            cmd.arg("--index-url").arg(index);
+        }
+        cmd.arg(format!("uv=={UV_VERSION}"))
             .check(true)
             .output()
             .await?;
@@ -143,9 +188,11 @@ pub struct UvInstaller;
 impl UvInstaller {
     async fn select_source() -> Result<InstallSource> {
         async fn check_github(client: &reqwest::Client) -> Result<bool> {
-            let url = format!("https://github.com/astral-sh/uv/releases/download/{UV_VERSION}/uv-x86_64-unknown-linux-gnu.tar.gz");
+            let Some(archive) = release_archive() else {
+                return Ok(false);
+            };
             let response = client
-                .head(url)
+                .head(format!("{}{archive}", github_url()))
                 .timeout(Duration::from_secs(3))
                 .send()
                 .await?;
@@ -183,9 +230,15 @@ impl UvInstaller {
             Ok(best)
         }
 
-        let client = reqwest::Client::new();
+        // Without access to GitHub and PyPi, use the mirrors of the user config.
+        if let Some(source) = InstallSource::configured(UserConfig::get()) {
+            trace!(?source, "Selected uv source from user config");
+            return Ok(source);
+        }
+
+        let client = http::client();
         let source = tokio::select! {
-            Ok(true) = check_github(&client) => InstallSource::GitHub,
+            Ok(true) = check_github(&client) => InstallSource::GitHub(github_url()),
             Ok(source) = select_best_pypi(&client) => InstallSource::PyPi(source),
             else => {
                 warn!("Failed to check uv source availability, falling back to pip install");
@@ -197,7 +250,7 @@ impl UvInstaller {
         Ok(source)
     }
 
-    pub async fn install() -> Result<PathBuf> {
+    pub async fn install(store: &Store) -> Result<PathBuf> {
         // 1) Check if `uv` is installed already.
         if let Ok(uv) = which::which("uv") {
             trace!(uv = %uv.display(), "Found uv from PATH");
@@ -205,9 +258,7 @@ impl UvInstaller {
         }
 
         // 2) Check if `uv` is installed by `prefligit`
-        let store = Store::from_settings()?;
-
-        let uv_dir = store.tools_path(ToolBucket::Uv);
+        let uv_dir = store.toolchain_path(ToolBucket::Uv, UV_VERSION);
         let uv = uv_dir.join("uv").with_extension(env::consts::EXE_EXTENSION);
         if uv.is_file() {
             trace!(uv = %uv.display(), "Found managed uv");
@@ -223,8 +274,56 @@ impl UvInstaller {
         }
 
         let source = Self::select_source().await?;
-        source.install(&uv_dir).await?;
+        // Only the downloads are retried, pip retries its own requests.
+        source
+            .install(&uv_dir)
+            .await
+            .context("Failed to install uv")?;
+        store.insert_toolchain(ToolBucket::Uv, UV_VERSION)?;
 
         Ok(uv)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configured(user_config: &str) -> Option<InstallSource> {
+        InstallSource::configured(&toml::from_str(user_config).unwrap())
+    }
+
+    #[test]
+    fn configured_source() {
+        assert!(configured("").is_none());
+
+        // Both the archive and its checksum are downloaded from the mirror of GitHub.
+        let source = configured(
+            r#"
+            [url."https://git.example.com/github/"]
+            instead_of = ["https://github.com/"]
+            [url."https://pypi.example.com/simple/"]
+            instead_of = ["https://pypi.org/simple/"]
+            "#,
+        );
+        let Some(InstallSource::GitHub(base)) = source else {
+            panic!("Unexpected source: {source:?}");
+        };
+        assert_eq!(
+            base,
+            format!("https://git.example.com/github/astral-sh/uv/releases/download/{UV_VERSION}/")
+        );
+
+        // pip only installs from the mirror of PyPi.
+        let source = configured(
+            r#"
+            [url."https://pypi.example.com/"]
+            instead_of = ["https://pypi.org/"]
+            "#,
+        );
+        let Some(InstallSource::PyPi(mirror)) = source else {
+            panic!("Unexpected source: {source:?}");
+        };
+        assert_eq!(mirror.url(), "https://pypi.example.com/simple/");
+    }
+}
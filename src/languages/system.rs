@@ -1,10 +1,12 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::hook::Hook;
-use crate::languages::LanguageImpl;
-use crate::process::Cmd;
+use crate::languages::{hook_dir, LanguageImpl};
 use crate::run::run_by_batch;
+use crate::sandbox::{hook_command, Sandbox};
+use crate::store::Store;
 
 #[derive(Debug, Copy, Clone)]
 pub struct System;
@@ -14,11 +16,20 @@ impl LanguageImpl for System {
         None
     }
 
-    async fn install(&self, _hook: &Hook) -> anyhow::Result<()> {
+    async fn install(
+        &self,
+        _hook: &Hook,
+        _store: &Store,
+        _on_phase: &dyn Fn(&str),
+    ) -> anyhow::Result<()> {
         Ok(())
     }
 
-    async fn check_health(&self) -> anyhow::Result<()> {
+    fn installer_version(&self) -> Option<&str> {
+        None
+    }
+
+    async fn check_health(&self, _hook: &Hook) -> anyhow::Result<()> {
         Ok(())
     }
 
@@ -27,24 +38,50 @@ impl LanguageImpl for System {
         hook: &Hook,
         filenames: &[&String],
         env_vars: Arc<HashMap<&'static str, String>>,
+        work_dir: Option<&Path>,
     ) -> anyhow::Result<(i32, Vec<u8>)> {
         let cmds = shlex::split(&hook.entry).ok_or(anyhow::anyhow!("Failed to parse entry"))?;
 
         let cmds = Arc::new(cmds);
         let hook_args = Arc::new(hook.args.clone());
+        let host_env = Arc::new(hook.host_env());
+        let hook_env = Arc::new(hook.env.clone());
+        let stream = hook.stream.then(|| hook.id.clone());
+        let pty = hook.pty;
+        let limits = hook.limits;
+        let sandbox = Arc::new(Sandbox::for_hook(hook, work_dir)?);
+        let working_directory = Arc::new(hook_dir(hook, work_dir));
 
         let run = move |batch: Vec<String>| {
             let cmds = cmds.clone();
+            let stream = stream.clone();
             let hook_args = hook_args.clone();
             let env_vars = env_vars.clone();
+            let host_env = host_env.clone();
+            let hook_env = hook_env.clone();
+            let working_directory = working_directory.clone();
+            let sandbox = sandbox.clone();
 
             async move {
-                let mut output = Cmd::new(&cmds[0], "run system command")
+                let mut cmd =
+                    hook_command(sandbox.as_ref().as_ref(), &cmds[0], "run system command");
+                if let Some(host_env) = host_env.as_ref() {
+                    cmd.env_clear().envs(host_env.iter().cloned());
+                }
+                if let Some(dir) = working_directory.as_ref() {
+                    cmd.current_dir(dir);
+                }
+                let mut output = cmd
                     .args(&cmds[1..])
                     .args(hook_args.as_ref())
                     .args(batch)
+                    .envs(hook_env.as_ref())
                     .envs(env_vars.as_ref())
                     .check(false)
+                    .kill_on_drop(true)
+                    .limits(limits)
+                    .stream(stream)
+                    .pty(pty)
                     .output()
                     .await?;
 
@@ -1,7 +1,9 @@
 use std::fmt::Write;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::str::FromStr;
+use std::time::Duration;
 
 use anstream::{eprintln, ColorChoice};
 use anyhow::{Context, Result};
@@ -12,26 +14,38 @@ use tracing_subscriber::filter::Directive;
 use tracing_subscriber::EnvFilter;
 
 use crate::cleanup::cleanup;
-use crate::cli::{Cli, Command, ExitStatus, SelfCommand, SelfNamespace, SelfUpdateArgs};
+use crate::cli::{
+    Cli, Command, ConfigCommand, ConfigNamespace, ExitStatus, SelfCommand, SelfNamespace,
+    SelfUpdateArgs,
+};
 use crate::git::get_root;
 use crate::printer::Printer;
 
 mod builtin;
 mod cleanup;
 mod cli;
+mod client;
 mod config;
+mod config_edit;
+mod download;
 mod env_vars;
 mod fs;
 mod git;
 mod hook;
+mod http;
 mod identify;
 mod languages;
+mod pattern;
 mod printer;
 mod process;
 #[cfg(all(unix, feature = "profiler"))]
 mod profiler;
+mod remote_cache;
+mod retry;
 mod run;
+mod sandbox;
 mod store;
+mod user_config;
 mod version;
 mod warnings;
 
@@ -77,9 +91,41 @@ fn setup_logging(level: Level) -> Result<()> {
     Ok(())
 }
 
+/// Read a list of filenames from a file, or from stdin if the path is `-`.
+///
+/// The list is NUL-delimited if it contains any NUL characters, otherwise newline-delimited.
+fn read_files_from(path: &Path) -> Result<Vec<PathBuf>> {
+    let content = if path == Path::new("-") {
+        let mut content = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut content)
+            .context("Failed to read filenames from stdin")?;
+        content
+    } else {
+        fs_err::read(path)?
+    };
+    let content = String::from_utf8(content).context("Filenames must be valid UTF-8")?;
+
+    let delimiter = if content.contains('\0') { '\0' } else { '\n' };
+    Ok(content
+        .split(delimiter)
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// The config file of commands that support only one.
+fn single_config(configs: Vec<PathBuf>) -> Result<Option<PathBuf>> {
+    if configs.len() > 1 {
+        anyhow::bail!("Multiple `--config` files are only supported by `run` and `watch`");
+    }
+    Ok(configs.into_iter().next())
+}
+
 /// Adjusts relative paths in the CLI arguments to be relative to the new working directory.
 fn adjust_relative_paths(cli: &mut Cli, new_cwd: &Path) -> Result<()> {
-    if let Some(path) = &mut cli.globals.config {
+    for path in &mut cli.globals.config {
         if path.exists() {
             *path = std::path::absolute(&*path)?;
         }
@@ -91,6 +137,16 @@ fn adjust_relative_paths(cli: &mut Cli, new_cwd: &Path) -> Result<()> {
             .iter()
             .map(|path| fs::relative_to(std::path::absolute(path)?, new_cwd))
             .collect::<Result<Vec<PathBuf>, std::io::Error>>()?;
+        args.directories = args
+            .directories
+            .iter()
+            .map(|path| fs::relative_to(std::path::absolute(path)?, new_cwd))
+            .collect::<Result<Vec<PathBuf>, std::io::Error>>()?;
+        args.output_file = args
+            .output_file
+            .as_ref()
+            .map(std::path::absolute)
+            .transpose()?;
         args.extra.commit_msg_filename = args
             .extra
             .commit_msg_filename
@@ -103,7 +159,12 @@ fn adjust_relative_paths(cli: &mut Cli, new_cwd: &Path) -> Result<()> {
 }
 
 async fn run(mut cli: Cli) -> Result<ExitStatus> {
-    ColorChoice::write_global(cli.globals.color.into());
+    // GitHub Actions renders colors, but doesn't run commands in a terminal.
+    let color = match cli.globals.color {
+        cli::ColorChoice::Auto if cli::is_github_actions() => cli::ColorChoice::Always,
+        color => color,
+    };
+    ColorChoice::write_global(color.into());
 
     setup_logging(match cli.globals.verbose {
         0 => Level::Default,
@@ -111,7 +172,9 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
         _ => Level::ExtraVerbose,
     })?;
 
-    let printer = if cli.globals.quiet {
+    let printer = if cli.globals.quiet > 1 {
+        Printer::Silent
+    } else if cli.globals.quiet > 0 {
         Printer::Quiet
     } else if cli.globals.verbose > 0 {
         Printer::Verbose
@@ -121,11 +184,13 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
         Printer::Default
     };
 
-    if cli.globals.quiet {
+    if cli.globals.quiet > 0 {
         warnings::disable();
     } else {
         warnings::enable();
     }
+    config::set_strict(cli.globals.strict);
+    http::export_certificates();
 
     if cli.command.is_none() {
         cli.command = Some(Command::Run(Box::new(cli.run_args.clone())));
@@ -133,7 +198,15 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
 
     debug!("prefligit: {}", version::version());
 
-    match get_root().await {
+    if let Some(Command::Run(ref mut args) | Command::TryRepo(ref mut args)) = cli.command {
+        if let Some(path) = &args.files_from {
+            let files = read_files_from(path)?;
+            debug!("Files read from `{}`: {}", path.display(), files.len());
+            args.files.extend(files);
+        }
+    }
+
+    match get_root() {
         Ok(root) => {
             debug!("Git root: {}", root.display());
 
@@ -167,11 +240,12 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
             show_settings!(args);
 
             cli::install(
-                cli.globals.config,
+                single_config(cli.globals.config)?,
                 args.hook_types,
                 args.install_hooks,
                 args.overwrite,
                 args.allow_missing_config,
+                args.native,
                 printer,
                 None,
             )
@@ -180,22 +254,32 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
         Command::Uninstall(args) => {
             show_settings!(args);
 
-            cli::uninstall(cli.globals.config, args.hook_types, printer).await
+            cli::uninstall(
+                single_config(cli.globals.config)?,
+                args.hook_types,
+                args.purge,
+                printer,
+            )
+            .await
         }
-        Command::Run(args) => {
+        Command::Run(mut args) => {
             show_settings!(args);
 
+            sandbox::set_enabled(args.sandbox);
+
+            if let Some(since) = args.since.take() {
+                args.from_ref = Some(git::get_merge_base(&since, "HEAD").await?);
+                args.to_ref = Some("HEAD".to_string());
+            }
+
+            let verbose = cli.globals.verbose > 0;
             cli::run(
-                cli.globals.config,
-                args.hook_id,
-                args.hook_stage,
-                args.from_ref,
-                args.to_ref,
-                args.all_files,
-                args.files,
-                args.show_diff_on_failure,
-                args.extra,
-                cli.globals.verbose > 0,
+                cli::RunOptions {
+                    configs: cli.globals.config,
+                    timings: args.timings || verbose,
+                    verbose,
+                    ..cli::RunOptions::from(*args)
+                },
                 printer,
             )
             .await
@@ -204,7 +288,7 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
             show_settings!(args);
 
             cli::hook_impl(
-                cli.globals.config,
+                single_config(cli.globals.config)?,
                 args.hook_type,
                 args.hook_dir,
                 args.skip_on_missing_config,
@@ -214,6 +298,36 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
             .await
         }
         Command::Clean => cli::clean(printer),
+        Command::ImportCache(args) => {
+            show_settings!(args);
+
+            cli::import_cache(args.from, printer).await
+        }
+        Command::GC(args) => {
+            show_settings!(args);
+
+            cli::gc(args.max_age, printer).await
+        }
+        #[cfg(unix)]
+        Command::Daemon => cli::daemon(single_config(cli.globals.config)?, printer).await,
+        #[cfg(not(unix))]
+        Command::Daemon => anyhow::bail!("`prefligit daemon` is only supported on Unix"),
+        Command::Watch(args) => {
+            show_settings!(args);
+
+            cli::watch(
+                cli.globals.config,
+                args.hook_ids,
+                Duration::from_millis(args.debounce),
+                printer,
+            )
+            .await
+        }
+        Command::Stats(args) => {
+            show_settings!(args);
+
+            cli::stats(args.last, printer)
+        }
         Command::ValidateConfig(args) => {
             show_settings!(args);
 
@@ -225,6 +339,33 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
             Ok(cli::validate_manifest(args.manifests))
         }
         Command::SampleConfig => Ok(cli::sample_config()),
+        Command::Schema(args) => {
+            show_settings!(args);
+
+            cli::schema(args.kind, printer)
+        }
+        Command::MigrateConfig(args) => {
+            show_settings!(args);
+
+            cli::migrate_config(single_config(cli.globals.config)?, args.to, printer)
+        }
+        Command::AutoUpdate(args) => {
+            cli::autoupdate(
+                single_config(cli.globals.config)?,
+                args.bleeding_edge,
+                args.freeze,
+                args.repo,
+                args.jobs,
+                printer,
+            )
+            .await
+        }
+        Command::Config(ConfigNamespace {
+            command: ConfigCommand::Lint,
+        }) => cli::lint_config(single_config(cli.globals.config)?, printer).await,
+        Command::Config(ConfigNamespace {
+            command: ConfigCommand::Resolve(args),
+        }) => cli::resolve_config(single_config(cli.globals.config)?, args.format, printer).await,
         Command::Self_(SelfNamespace {
             command:
                 SelfCommand::Update(SelfUpdateArgs {
@@ -248,7 +389,7 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
 
             cli::init_template_dir(
                 args.directory,
-                cli.globals.config,
+                single_config(cli.globals.config)?,
                 args.hook_types,
                 args.no_allow_missing_config,
                 printer,
@@ -264,6 +405,7 @@ async fn run(mut cli: Cli) -> Result<ExitStatus> {
 
 fn main() -> ExitCode {
     ctrlc::set_handler(move || {
+        process::interrupt_running();
         cleanup();
 
         #[allow(clippy::exit, clippy::cast_possible_wrap)]
@@ -275,7 +417,17 @@ fn main() -> ExitCode {
     })
     .expect("Error setting Ctrl-C handler");
 
-    let cli = match Cli::try_parse() {
+    // Git runs the native shim of a hook in place of the hook script.
+    let args = match cli::Shim::current() {
+        Some(shim) => {
+            if let Some(code) = shim.forward() {
+                return code;
+            }
+            shim.args
+        }
+        None => std::env::args_os().collect(),
+    };
+    let cli = match Cli::try_parse_from(args) {
         Ok(cli) => cli,
         Err(err) => err.exit(),
     };
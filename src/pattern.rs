@@ -0,0 +1,352 @@
+use std::borrow::Cow;
+
+use fancy_regex::Regex;
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// Compile a file pattern.
+///
+/// Patterns of configs and manifests are written for Python's `re`,
+/// so they are translated to match the same paths here.
+pub(crate) fn compile(pattern: &str) -> Result<Regex, Box<fancy_regex::Error>> {
+    Ok(Regex::new(&translate(pattern))?)
+}
+
+/// Translate the constructs of a Python pattern that are spelled or behave differently here.
+pub(crate) fn translate(pattern: &str) -> Cow<'_, str> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::with_capacity(pattern.len());
+    let mut idx = 0;
+
+    // Global flags at the start: `a` and `L` are not supported, and `u` is the default.
+    let mut verbose = false;
+    let mut ascii = false;
+    if let Some((flags, _)) = pattern
+        .strip_prefix("(?")
+        .and_then(|rest| rest.split_once(')'))
+        .filter(|(flags, _)| !flags.is_empty() && flags.chars().all(|c| "aiLmsux".contains(c)))
+    {
+        verbose = flags.contains('x');
+        ascii = flags.contains('a');
+        let kept: String = flags.chars().filter(|c| !"aLu".contains(*c)).collect();
+        if !kept.is_empty() {
+            out.push_str(&format!("(?{kept})"));
+        }
+        idx = flags.len() + 3;
+    }
+
+    let mut in_class = false;
+    // Whether verbose mode is enabled outside of each open group, to restore when it closes.
+    let mut groups = Vec::new();
+    while idx < chars.len() {
+        let c = chars[idx];
+
+        if c == '\\' {
+            let Some(&next) = chars.get(idx + 1) else {
+                out.push(c);
+                break;
+            };
+            match next {
+                // `\Z` is the end of the string in Python, `\z` here.
+                'Z' if !in_class => out.push_str("\\z"),
+                'd' | 'D' | 'w' | 'W' | 's' | 'S' if ascii => out.push_str(ascii_class(next)),
+                _ => {
+                    out.push(c);
+                    out.push(next);
+                }
+            }
+            idx += 2;
+            continue;
+        }
+
+        if in_class {
+            match c {
+                ']' => {
+                    in_class = false;
+                    out.push(c);
+                }
+                // Nested classes and set operations here, literals in Python.
+                '[' => out.push_str("\\["),
+                '&' | '-' | '~' if chars.get(idx + 1) == Some(&c) => {
+                    out.push(c);
+                    out.push('\\');
+                    out.push(c);
+                    idx += 1;
+                }
+                // Ignored in a class with `(?x)` here, but not in Python.
+                ' ' if verbose => out.push_str("\\x20"),
+                '\t' if verbose => out.push_str("\\t"),
+                '\n' if verbose => out.push_str("\\n"),
+                '#' if verbose => out.push_str("\\#"),
+                _ => out.push(c),
+            }
+            idx += 1;
+            continue;
+        }
+
+        match c {
+            '[' => {
+                in_class = true;
+                out.push(c);
+                // A `]` right after the opening bracket is a literal.
+                for literal in ['^', ']'] {
+                    if chars.get(idx + 1) == Some(&literal) {
+                        out.push(literal);
+                        idx += 1;
+                    }
+                }
+            }
+            // A named backreference `(?P=name)` is `\k<name>` here.
+            '(' if chars[idx..].starts_with(&['(', '?', 'P', '=']) => {
+                let name: String = chars[idx + 4..].iter().take_while(|c| **c != ')').collect();
+                out.push_str(&format!("\\k<{name}>"));
+                idx += name.chars().count() + 4;
+            }
+            '(' => {
+                out.push(c);
+                // Flags like `(?x)` apply to the rest of the group, `(?x:...)` to their own group.
+                let flags: String = chars[idx + 1..]
+                    .iter()
+                    .take_while(|c| **c == '?' || **c == '-' || c.is_ascii_alphabetic())
+                    .collect();
+                let end = chars.get(idx + 1 + flags.len()).copied();
+                if let Some(flags) = flags
+                    .strip_prefix('?')
+                    .filter(|_| matches!(end, Some(':' | ')')))
+                {
+                    if end == Some(':') {
+                        groups.push(verbose);
+                    }
+                    if let Some((on, off)) = flags.split_once('-') {
+                        verbose = (verbose || on.contains('x')) && !off.contains('x');
+                    } else {
+                        verbose |= flags.contains('x');
+                    }
+                    out.push('?');
+                    out.push_str(flags);
+                    out.extend(end);
+                    idx += flags.len() + 3;
+                    continue;
+                }
+                groups.push(verbose);
+            }
+            ')' => {
+                out.push(c);
+                if let Some(outer) = groups.pop() {
+                    verbose = outer;
+                }
+            }
+            // `{,n}` is `{0,n}`.
+            '{' if chars.get(idx + 1) == Some(&',') => out.push_str("{0"),
+            // Comments with `(?x)`, copied as-is so brackets in them don't open a class.
+            '#' if verbose => {
+                while idx < chars.len() && chars[idx] != '\n' {
+                    out.push(chars[idx]);
+                    idx += 1;
+                }
+                continue;
+            }
+            _ => out.push(c),
+        }
+        idx += 1;
+    }
+
+    if out == pattern {
+        Cow::Borrowed(pattern)
+    } else {
+        Cow::Owned(out)
+    }
+}
+
+/// Compile gitignore-style file patterns.
+///
+/// A pattern without a `/` matches at any depth, a leading `/` anchors it to the root,
+/// a trailing `/` only matches directories, and a directory matches the files under it.
+pub(crate) fn compile_globs(patterns: &[String]) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        for glob in gitignore_globs(pattern)? {
+            builder.add(glob);
+        }
+    }
+    builder.build()
+}
+
+/// The globs matching the paths of a gitignore-style pattern.
+fn gitignore_globs(pattern: &str) -> Result<Vec<Glob>, globset::Error> {
+    let glob = |pattern: &str| GlobBuilder::new(pattern).literal_separator(true).build();
+
+    let (pattern, dir_only) = match pattern.strip_suffix('/') {
+        Some(dir) => (dir, true),
+        None => (pattern, false),
+    };
+    let pattern = if let Some(anchored) = pattern.strip_prefix('/') {
+        anchored.to_string()
+    } else if pattern.contains('/') {
+        pattern.to_string()
+    } else {
+        format!("**/{pattern}")
+    };
+
+    let mut globs = Vec::with_capacity(2);
+    if !dir_only {
+        globs.push(glob(&pattern)?);
+    }
+    globs.push(glob(&format!("{pattern}/**"))?);
+    Ok(globs)
+}
+
+/// The patterns of an ignore file, in the format of `.gitignore`.
+///
+/// Later patterns take precedence, and patterns starting with `!` include files again.
+pub(crate) struct IgnoreFile {
+    globs: GlobSet,
+    /// Whether the pattern of each glob is negated.
+    negated: Vec<bool>,
+}
+
+impl IgnoreFile {
+    pub(crate) fn parse(content: &str) -> Result<Self, globset::Error> {
+        let mut builder = GlobSetBuilder::new();
+        let mut negated = Vec::new();
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (pattern, negate) = match line.strip_prefix('!') {
+                Some(pattern) => (pattern, true),
+                // A leading `\` escapes a literal `#` or `!`.
+                None => (line.strip_prefix('\\').unwrap_or(line), false),
+            };
+            for glob in gitignore_globs(pattern)? {
+                builder.add(glob);
+                negated.push(negate);
+            }
+        }
+        Ok(Self {
+            globs: builder.build()?,
+            negated,
+        })
+    }
+
+    pub(crate) fn is_ignored(&self, path: &str) -> bool {
+        self.globs
+            .matches(path)
+            .into_iter()
+            .max()
+            .is_some_and(|last| !self.negated[last])
+    }
+}
+
+/// The ASCII-only version of a class escape, for patterns with the `(?a)` flag.
+fn ascii_class(escape: char) -> &'static str {
+    match escape {
+        'd' => "[0-9]",
+        'D' => "[^0-9]",
+        'w' => "[0-9A-Za-z_]",
+        'W' => "[^0-9A-Za-z_]",
+        's' => "[\\t\\n\\x0B\\x0C\\r\\x20]",
+        'S' => "[^\\t\\n\\x0B\\x0C\\r\\x20]",
+        _ => unreachable!("not a class escape"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translate_python() {
+        let cases = [
+            (r"^src/.*\.py$", r"^src/.*\.py$"),
+            (r"^src/.*\.py\Z", r"^src/.*\.py\z"),
+            (r"(?P<dir>a|b)/(?P=dir)", r"(?P<dir>a|b)/\k<dir>"),
+            (r"a{,3}", r"a{0,3}"),
+            (r"[[a]", r"[\[a]"),
+            (r"[a&&b--c~~d]", r"[a&\&b-\-c~\~d]"),
+            (r"[]a]", r"[]a]"),
+            ("(?x)^( [a #] # [x\n)$", "(?x)^( [a\\x20\\#] # [x\n)$"),
+            (r"(?au)\d[\w]", r"[0-9][[0-9A-Za-z_]]"),
+            (r"(?ai)x", r"(?i)x"),
+            ("a(?x:[ ])[ ]", r"a(?x:[\x20])[ ]"),
+            ("(?x)(?-x:[ ])[ ]", r"(?x)(?-x:[ ])[\x20]"),
+            ("(?x)((?-x)[ ])[ ]", r"(?x)((?-x)[ ])[\x20]"),
+        ];
+        for (pattern, expected) in cases {
+            assert_eq!(translate(pattern), expected, "translating `{pattern}`");
+        }
+    }
+
+    #[test]
+    fn gitignore_globs() -> anyhow::Result<()> {
+        let globs = compile_globs(&[
+            "*.md".to_string(),
+            "/build".to_string(),
+            "fixtures/".to_string(),
+            "docs/*.txt".to_string(),
+        ])?;
+        for (path, expected) in [
+            ("README.md", true),
+            ("docs/guide.md", true),
+            ("build/out.js", true),
+            ("src/build/out.js", false),
+            ("tests/fixtures/a.py", true),
+            ("tests/fixtures", false),
+            ("docs/a.txt", true),
+            ("docs/api/a.txt", false),
+            ("src/main.rs", false),
+        ] {
+            assert_eq!(globs.is_match(path), expected, "matching `{path}`");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ignore_file() -> anyhow::Result<()> {
+        let ignore = IgnoreFile::parse(indoc::indoc! {r"
+            # Generated code
+            *.pb.go
+            vendor/
+            !vendor/patched/
+            \#notes
+        "})?;
+        for (path, expected) in [
+            ("api/api.pb.go", true),
+            ("api/api.go", false),
+            ("vendor/lib/a.go", true),
+            ("vendor/patched/a.go", false),
+            ("#notes", true),
+        ] {
+            assert_eq!(ignore.is_ignored(path), expected, "matching `{path}`");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn match_like_python() -> anyhow::Result<()> {
+        let re = compile(r"(?x)^(src/[a b]\.txt)\Z")?;
+        assert!(re.is_match("src/ .txt")?);
+        assert!(!re.is_match("src/.txt")?);
+
+        let re = compile(r"^(?P<dir>src|lib)/(?P=dir)_.*\.py$")?;
+        assert!(re.is_match("src/src_a.py")?);
+        assert!(!re.is_match("src/lib_a.py")?);
+
+        let re = compile(indoc::indoc! {r"
+            (?x)^(
+                # Generated files (see `make gen`) [don't edit]
+                docs/api/.*\.md|  # API docs
+                tests/fixtures/[a\ b#]/  # fixtures with ` ` or `#` in their names
+            )$
+        "})?;
+        assert!(re.is_match("docs/api/a.md")?);
+        assert!(re.is_match("tests/fixtures/#/")?);
+        assert!(re.is_match("tests/fixtures/ /")?);
+        assert!(!re.is_match("src/a.py")?);
+
+        let re = compile(r"(?a)^\w+$")?;
+        assert!(re.is_match("abc_1")?);
+        assert!(!re.is_match("é")?);
+        Ok(())
+    }
+}
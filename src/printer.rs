@@ -27,8 +27,10 @@ use indicatif::ProgressDrawTarget;
 pub enum Printer {
     /// A printer that prints to standard streams (e.g., stdout).
     Default,
-    /// A printer that suppresses all output.
+    /// A printer that suppresses all output, except for failures.
     Quiet,
+    /// A printer that suppresses all output.
+    Silent,
     /// A printer that prints all output, including debug messages.
     Verbose,
     /// A printer that prints to standard streams, excluding all progress outputs
@@ -41,6 +43,7 @@ impl Printer {
         match self {
             Self::Default => ProgressDrawTarget::stderr(),
             Self::Quiet => ProgressDrawTarget::hidden(),
+            Self::Silent => ProgressDrawTarget::hidden(),
             // Confusingly, hide the progress bar when in verbose mode.
             // Otherwise, it gets interleaved with debug messages.
             Self::Verbose => ProgressDrawTarget::hidden(),
@@ -53,16 +56,26 @@ impl Printer {
         match self {
             Self::Default => Stdout::Enabled,
             Self::Quiet => Stdout::Disabled,
+            Self::Silent => Stdout::Disabled,
             Self::Verbose => Stdout::Enabled,
             Self::NoProgress => Stdout::Enabled,
         }
     }
 
+    /// Return the [`Stdout`] for reporting failures, which are shown in quiet mode too.
+    pub fn failures(self) -> Stdout {
+        match self {
+            Self::Silent => Stdout::Disabled,
+            _ => Stdout::Enabled,
+        }
+    }
+
     /// Return the [`Stderr`] for this printer.
     pub fn stderr(self) -> Stderr {
         match self {
             Self::Default => Stderr::Enabled,
             Self::Quiet => Stderr::Disabled,
+            Self::Silent => Stderr::Disabled,
             Self::Verbose => Stderr::Enabled,
             Self::NoProgress => Stderr::Enabled,
         }
@@ -81,7 +94,9 @@ impl std::fmt::Write for Stdout {
             Self::Enabled => {
                 #[allow(clippy::print_stdout, clippy::ignored_unit_patterns)]
                 {
-                    print!("{s}");
+                    if !crate::client::write_stdout(s) {
+                        print!("{s}");
+                    }
                 }
             }
             Self::Disabled => {}
@@ -103,7 +118,9 @@ impl std::fmt::Write for Stderr {
             Self::Enabled => {
                 #[allow(clippy::print_stderr, clippy::ignored_unit_patterns)]
                 {
-                    eprint!("{s}");
+                    if !crate::client::write_stderr(s) {
+                        eprint!("{s}");
+                    }
                 }
             }
             Self::Disabled => {}
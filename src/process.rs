@@ -26,16 +26,24 @@
 
 /// Adapt [axoprocess] to use [`tokio::process::Process`] instead of [`std::process::Command`].
 use std::{
+    collections::HashSet,
     ffi::OsStr,
+    fmt::Write as _,
     path::Path,
     process::{CommandArgs, CommandEnvs, ExitStatus, Stdio},
+    sync::{LazyLock, Mutex},
+    time::Duration,
 };
 
 use miette::Diagnostic;
 use owo_colors::OwoColorize;
 use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tracing::trace;
 
+use crate::config::ResourceLimits;
+use crate::printer::Stderr;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// An error from executing a Command
@@ -69,17 +77,26 @@ pub struct Cmd {
     pub inner: tokio::process::Command,
     summary: String,
     check_status: bool,
+    kill_on_drop: bool,
+    stream_prefix: Option<String>,
+    pty: bool,
+    limits: ResourceLimits,
 }
 
 /// Constructors
 impl Cmd {
     /// Create a new Command with an additional "summary" of what this is trying to do
     pub fn new(command: impl AsRef<OsStr>, summary: impl Into<String>) -> Self {
-        let inner = tokio::process::Command::new(command);
+        let mut inner = tokio::process::Command::new(command);
+        crate::client::apply(&mut inner);
         Self {
             summary: summary.into(),
             inner,
             check_status: true,
+            kill_on_drop: false,
+            stream_prefix: None,
+            pty: false,
+            limits: ResourceLimits::default(),
         }
     }
 }
@@ -110,6 +127,63 @@ impl Cmd {
         self.check_status = checked;
         self
     }
+
+    /// Set whether the command should be killed if [`Cmd::output`][] is cancelled
+    /// before the command exits, e.g. when it times out.
+    ///
+    /// The command is started in its own process group on Unix, or assigned to a job object on
+    /// Windows, and the whole group is killed, so that any processes it spawned are killed too.
+    /// The group is also interrupted on ctrl-c, see [`interrupt_running`][].
+    ///
+    /// Defaults to `false`.
+    pub fn kill_on_drop(&mut self, kill_on_drop: bool) -> &mut Self {
+        self.kill_on_drop = kill_on_drop;
+        self.inner.kill_on_drop(kill_on_drop);
+        #[cfg(unix)]
+        if kill_on_drop {
+            self.inner.process_group(0);
+        }
+        self
+    }
+
+    /// Relay the output of [`Cmd::output`][] to stderr line by line as it is produced,
+    /// each line prefixed with `[prefix]`. The output is still captured and returned.
+    ///
+    /// Defaults to `None`, which only captures the output.
+    pub fn stream(&mut self, prefix: Option<String>) -> &mut Self {
+        self.stream_prefix = prefix;
+        self
+    }
+
+    /// Limit the resources the command and the processes it spawns can use.
+    ///
+    /// On Unix, they're rlimits of each process. On Windows, they're limits of the job object
+    /// of the command, so they only apply with [`Cmd::kill_on_drop`][], and the number of open
+    /// files isn't supported.
+    ///
+    /// Defaults to no limits.
+    pub fn limits(&mut self, limits: ResourceLimits) -> &mut Self {
+        self.limits = limits;
+        #[cfg(unix)]
+        if limits != ResourceLimits::default() {
+            // SAFETY: only calls `getrlimit` and `setrlimit`, which are async-signal-safe.
+            unsafe {
+                self.inner.pre_exec(move || set_rlimits(&limits));
+            }
+        }
+        self
+    }
+
+    /// Attach the stdout and stderr of [`Cmd::output`][] to a pseudo-terminal, so that the
+    /// command keeps its colors and progress output. Both streams are captured in `stdout`.
+    ///
+    /// Only supported on Unix, elsewhere the output is captured through pipes as usual.
+    ///
+    /// Defaults to `false`.
+    pub fn pty(&mut self, pty: bool) -> &mut Self {
+        self.pty = pty;
+        self
+    }
 }
 
 /// Execution APIs
@@ -136,7 +210,16 @@ impl Cmd {
     /// but logged, with the error wrapped, and status checked (by default)
     pub async fn output(&mut self) -> Result<std::process::Output> {
         self.log_command();
-        let res = self.inner.output().await.map_err(|cause| Error::Exec {
+        let res = if self.pty {
+            self.output_pty().await
+        } else if let Some(prefix) = self.stream_prefix.clone() {
+            self.output_streamed(&prefix).await
+        } else if self.kill_on_drop {
+            self.output_killable().await
+        } else {
+            self.inner.output().await
+        };
+        let res = res.map_err(|cause| Error::Exec {
             summary: self.summary.clone(),
             cmd: self.get_program().to_string_lossy().to_string(),
             cause,
@@ -145,6 +228,27 @@ impl Cmd {
         Ok(res)
     }
 
+    /// Like [`Cmd::output`][], but calls `on_progress` with each line of stderr as it arrives.
+    ///
+    /// Lines may be terminated by `\r` as well as `\n`, as used by progress output that
+    /// redraws the same line, e.g. `git fetch --progress`.
+    pub async fn output_with_progress(
+        &mut self,
+        on_progress: impl FnMut(&str),
+    ) -> Result<std::process::Output> {
+        self.log_command();
+        let res = self
+            .output_progress(on_progress)
+            .await
+            .map_err(|cause| Error::Exec {
+                summary: self.summary.clone(),
+                cmd: self.get_program().to_string_lossy().to_string(),
+                cause,
+            })?;
+        self.maybe_check_status(res.status)?;
+        Ok(res)
+    }
+
     /// Equivalent to [`std::process::Command::status`][]
     /// but logged, with the error wrapped, and status checked (by default)
     pub async fn status(&mut self) -> Result<ExitStatus> {
@@ -159,6 +263,420 @@ impl Cmd {
     }
 }
 
+impl Cmd {
+    /// Like [`tokio::process::Command::output`][], but kills the process group of the
+    /// command if the returned future is dropped before the command exits.
+    async fn output_killable(&mut self) -> std::io::Result<std::process::Output> {
+        self.inner
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let child = self.inner.spawn()?;
+        let guard = ProcessGroupGuard::new(&child, true, &self.limits);
+        let output = child.wait_with_output().await;
+        guard.disarm();
+        output
+    }
+
+    /// Like [`Cmd::output_killable`][], but relays each line of output to stderr as it arrives.
+    async fn output_streamed(&mut self, prefix: &str) -> std::io::Result<std::process::Output> {
+        self.inner
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = self.inner.spawn()?;
+        let guard = ProcessGroupGuard::new(&child, self.kill_on_drop, &self.limits);
+
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let (stdout, stderr, status) = tokio::try_join!(
+            relay_lines(stdout, prefix),
+            relay_lines(stderr, prefix),
+            child.wait()
+        )?;
+        guard.disarm();
+
+        Ok(std::process::Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+impl Cmd {
+    async fn output_progress(
+        &mut self,
+        mut on_progress: impl FnMut(&str),
+    ) -> std::io::Result<std::process::Output> {
+        use tokio::io::AsyncReadExt;
+
+        self.inner
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = self.inner.spawn()?;
+        let guard = ProcessGroupGuard::new(&child, self.kill_on_drop, &self.limits);
+
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let read_stdout = async {
+            let mut output = Vec::new();
+            stdout.read_to_end(&mut output).await?;
+            Ok(output)
+        };
+        let read_stderr = async {
+            let mut output = Vec::new();
+            let mut buf = [0; 4096];
+            let mut start = 0;
+            loop {
+                let n = stderr.read(&mut buf).await?;
+                if n == 0 {
+                    break;
+                }
+                output.extend_from_slice(&buf[..n]);
+                while let Some(end) = output[start..]
+                    .iter()
+                    .position(|&b| b == b'\r' || b == b'\n')
+                {
+                    let line = String::from_utf8_lossy(&output[start..start + end]);
+                    if !line.trim().is_empty() {
+                        on_progress(line.trim());
+                    }
+                    start += end + 1;
+                }
+            }
+            Ok(output)
+        };
+        let (stdout, stderr, status) = tokio::try_join!(read_stdout, read_stderr, child.wait())?;
+        guard.disarm();
+
+        Ok(std::process::Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+impl Cmd {
+    /// Like [`Cmd::output_streamed`][], but with the output attached to a pseudo-terminal.
+    #[cfg(unix)]
+    async fn output_pty(&mut self) -> std::io::Result<std::process::Output> {
+        use tokio::io::AsyncReadExt;
+
+        let (master, slave) = open_pty()?;
+        self.inner
+            .stdin(Stdio::null())
+            .stdout(slave.try_clone()?)
+            .stderr(slave);
+        let child = self.inner.spawn();
+        // Close our copies of the slave, otherwise reading the master never ends.
+        self.inner.stdout(Stdio::null()).stderr(Stdio::null());
+        let mut child = child?;
+        let guard = ProcessGroupGuard::new(&child, self.kill_on_drop, &self.limits);
+
+        let mut reader = PtyReader(tokio::fs::File::from_std(std::fs::File::from(master)));
+        let read = async {
+            if let Some(prefix) = self.stream_prefix.as_deref() {
+                relay_lines(reader, prefix).await
+            } else {
+                let mut output = Vec::new();
+                reader.read_to_end(&mut output).await?;
+                Ok(output)
+            }
+        };
+        let (output, status) = tokio::try_join!(read, child.wait())?;
+        guard.disarm();
+
+        Ok(std::process::Output {
+            status,
+            stdout: strip_carriage_returns(&output),
+            stderr: Vec::new(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    async fn output_pty(&mut self) -> std::io::Result<std::process::Output> {
+        if let Some(prefix) = self.stream_prefix.clone() {
+            self.output_streamed(&prefix).await
+        } else {
+            self.output_killable().await
+        }
+    }
+}
+
+/// Open a pseudo-terminal, returning the master and slave ends.
+#[cfg(unix)]
+fn open_pty() -> std::io::Result<(std::os::fd::OwnedFd, std::os::fd::OwnedFd)> {
+    use std::os::fd::{FromRawFd, OwnedFd};
+
+    let mut master = -1;
+    let mut slave = -1;
+    let mut size = libc::winsize {
+        ws_row: 24,
+        ws_col: 80,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let ret = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::addr_of_mut!(size),
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+    for fd in [&master, &slave] {
+        use std::os::fd::AsRawFd;
+        if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok((master, slave))
+}
+
+/// Reads the master end of a pseudo-terminal, treating `EIO` as the end of the output,
+/// which is what Linux returns once every process attached to the terminal has exited.
+#[cfg(unix)]
+struct PtyReader(tokio::fs::File);
+
+#[cfg(unix)]
+impl AsyncRead for PtyReader {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        match std::pin::Pin::new(&mut self.0).poll_read(cx, buf) {
+            std::task::Poll::Ready(Err(err)) if err.raw_os_error() == Some(libc::EIO) => {
+                std::task::Poll::Ready(Ok(()))
+            }
+            poll => poll,
+        }
+    }
+}
+
+/// Translate the `\r\n` line endings written by a terminal back to `\n`.
+#[cfg(unix)]
+fn strip_carriage_returns(output: &[u8]) -> Vec<u8> {
+    let mut stripped = Vec::with_capacity(output.len());
+    for (i, &byte) in output.iter().enumerate() {
+        if byte == b'\r' && output.get(i + 1) == Some(&b'\n') {
+            continue;
+        }
+        stripped.push(byte);
+    }
+    stripped
+}
+
+/// Read `reader` to the end, printing each line to stderr prefixed with `[prefix]`.
+async fn relay_lines(reader: impl AsyncRead + Unpin, prefix: &str) -> std::io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(reader);
+    let mut output = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).await? == 0 {
+            break;
+        }
+        output.extend_from_slice(&line);
+        let _ = writeln!(
+            Stderr::Enabled,
+            "{} {}",
+            format!("[{prefix}]").dimmed(),
+            String::from_utf8_lossy(&line).trim_end_matches(['\n', '\r'])
+        );
+    }
+    Ok(output)
+}
+
+/// The process groups of the running commands started with [`Cmd::kill_on_drop`][],
+/// interrupted by [`interrupt_running`][].
+static RUNNING_GROUPS: LazyLock<Mutex<HashSet<usize>>> = LazyLock::new(Mutex::default);
+
+/// How long interrupted commands get to exit before they're killed.
+const INTERRUPT_GRACE: Duration = Duration::from_millis(500);
+
+/// Interrupt the running commands with all the processes they spawned, and kill those still
+/// running after a grace period.
+///
+/// Commands run in their own process groups don't get the ctrl-c of the terminal, so it's
+/// forwarded to them as `SIGINT`. Must run before the cleanup restores the stashed changes,
+/// so no hook writes files after that.
+pub fn interrupt_running() {
+    let groups: Vec<_> = RUNNING_GROUPS.lock().unwrap().iter().copied().collect();
+    if groups.is_empty() {
+        return;
+    }
+
+    #[cfg(unix)]
+    {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+        let signal = |group: usize, signal| unsafe { libc::kill(-(group as i32), signal) == 0 };
+        for &group in &groups {
+            signal(group, libc::SIGINT);
+        }
+        let deadline = std::time::Instant::now() + INTERRUPT_GRACE;
+        while std::time::Instant::now() < deadline && groups.iter().any(|&group| signal(group, 0)) {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    for group in groups {
+        kill_group(group);
+    }
+}
+
+/// The process group of a command: the group it leads on Unix, or a job object it's assigned
+/// to on Windows, with the resource limits set on it.
+#[cfg(unix)]
+fn process_group(child: &tokio::process::Child, _limits: &ResourceLimits) -> Option<usize> {
+    child.id().map(|pid| pid as usize)
+}
+
+#[cfg(windows)]
+fn process_group(child: &tokio::process::Child, limits: &ResourceLimits) -> Option<usize> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_PROCESS_MEMORY, JOB_OBJECT_LIMIT_PROCESS_TIME,
+    };
+
+    let process = child.raw_handle()?;
+    unsafe {
+        let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+        if job.is_null() {
+            return None;
+        }
+
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+        if let Some(seconds) = limits.cpu_time {
+            // In units of 100 nanoseconds.
+            info.BasicLimitInformation.PerProcessUserTimeLimit =
+                i64::try_from(seconds.saturating_mul(10_000_000)).unwrap_or(i64::MAX);
+            info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_TIME;
+        }
+        if let Some(memory) = limits.memory {
+            info.ProcessMemoryLimit =
+                usize::try_from(memory.saturating_mul(1024 * 1024)).unwrap_or(usize::MAX);
+            info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_MEMORY;
+        }
+        if info.BasicLimitInformation.LimitFlags != 0 {
+            SetInformationJobObject(
+                job,
+                JobObjectExtendedLimitInformation,
+                std::ptr::addr_of!(info).cast(),
+                u32::try_from(size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>())
+                    .expect("job limits fit in u32"),
+            );
+        }
+
+        if AssignProcessToJobObject(job, process) == 0 {
+            CloseHandle(job);
+            return None;
+        }
+        Some(job as usize)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn process_group(_child: &tokio::process::Child, _limits: &ResourceLimits) -> Option<usize> {
+    None
+}
+
+/// Apply the resource limits to the current process, never raising the hard limits.
+#[cfg(unix)]
+fn set_rlimits(limits: &ResourceLimits) -> std::io::Result<()> {
+    for (resource, value) in [
+        (libc::RLIMIT_CPU, limits.cpu_time),
+        (
+            libc::RLIMIT_AS,
+            limits.memory.map(|mib| mib.saturating_mul(1024 * 1024)),
+        ),
+        (libc::RLIMIT_NOFILE, limits.open_files),
+    ] {
+        let Some(value) = value else {
+            continue;
+        };
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        if unsafe { libc::getrlimit(resource, &mut limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let value = libc::rlim_t::try_from(value)
+            .unwrap_or(libc::RLIM_INFINITY)
+            .min(limit.rlim_max);
+        limit.rlim_cur = value;
+        limit.rlim_max = value;
+        if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn kill_group(group: usize) {
+    #[cfg(unix)]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    unsafe {
+        libc::kill(-(group as i32), libc::SIGKILL);
+    }
+    #[cfg(windows)]
+    unsafe {
+        windows_sys::Win32::System::JobObjects::TerminateJobObject(group as _, 1);
+    }
+}
+
+fn release_group(group: usize) {
+    RUNNING_GROUPS.lock().unwrap().remove(&group);
+    #[cfg(windows)]
+    unsafe {
+        windows_sys::Win32::Foundation::CloseHandle(group as _);
+    }
+}
+
+/// Kills the process group of a command when dropped, so the processes it spawned are
+/// killed too.
+struct ProcessGroupGuard(Option<usize>);
+
+impl ProcessGroupGuard {
+    fn new(child: &tokio::process::Child, kill_on_drop: bool, limits: &ResourceLimits) -> Self {
+        let group = if kill_on_drop {
+            process_group(child, limits)
+        } else {
+            None
+        };
+        if let Some(group) = group {
+            RUNNING_GROUPS.lock().unwrap().insert(group);
+        }
+        Self(group)
+    }
+
+    fn disarm(mut self) {
+        if let Some(group) = self.0.take() {
+            release_group(group);
+        }
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        if let Some(group) = self.0.take() {
+            kill_group(group);
+            release_group(group);
+        }
+    }
+}
+
 /// Transparently forwarded [`std::process::Command`][] APIs
 impl Cmd {
     /// Forwards to [`std::process::Command::arg`][]
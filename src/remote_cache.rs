@@ -0,0 +1,355 @@
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use reqwest::header::AUTHORIZATION;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tracing::debug;
+use url::Url;
+
+use crate::env_vars::EnvVars;
+use crate::fs::clone_dir;
+use crate::hook::Hook;
+use crate::http;
+use crate::process::Cmd;
+use crate::user_config::UserConfig;
+use crate::{warn_user, warn_user_once};
+
+/// The remote cache settings of the user config, for example:
+///
+/// ```toml
+/// [remote_cache]
+/// url = "https://cache.example.com/prefligit/"
+/// upload = false
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteCacheConfig {
+    /// Where the repos and environments are kept, an `http(s)://` URL, or a `file://` URL of
+    /// a shared directory.
+    url: String,
+    /// Upload the repos and environments missing in the cache, set to false for clients
+    /// that only read it. Default is true.
+    #[serde(default = "default_upload")]
+    upload: bool,
+}
+
+fn default_upload() -> bool {
+    true
+}
+
+#[derive(Debug)]
+enum Backend {
+    /// An HTTP server, or an S3-compatible store, where archives are read by `GET`
+    /// and written by `PUT` below the base URL, each with its digest in `<key>.tar.gz.sha256`.
+    Http { base: Url, token: Option<String> },
+    /// A directory, like a mount shared by the machines, where the entries are kept unpacked
+    /// so they can be cloned, in `<key>/content` with their digest in `<key>/sha256`.
+    Directory(PathBuf),
+}
+
+/// A cache of cloned repos and installed environments shared between machines, so only
+/// the first of them clones and installs them.
+///
+/// The cache is a best effort, failing to use it is reported and falls back to cloning
+/// and installing locally.
+#[derive(Debug)]
+pub struct RemoteCache {
+    backend: Backend,
+    upload: bool,
+}
+
+static REMOTE_CACHE: LazyLock<Option<RemoteCache>> = LazyLock::new(|| {
+    let config = UserConfig::get().remote_cache.as_ref()?;
+    match RemoteCache::new(config) {
+        Ok(cache) => Some(cache),
+        Err(err) => {
+            warn_user_once!("Ignoring the remote cache `{}`: {err}", config.url);
+            None
+        }
+    }
+});
+
+/// The key of a repo in the cache, the name of its directory that hashes its URL and rev.
+pub(crate) fn repo_key(path: &Path) -> String {
+    format!(
+        "repos/{}",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    )
+}
+
+/// The key of the environment of `hook` in the cache, from the platform and the inputs of the
+/// environment, like its language version and additional dependencies.
+///
+/// Returns `None` if the environment isn't identified by its inputs, it's not cached then.
+pub(crate) fn env_key(hook: &Hook) -> Option<String> {
+    Some(format!(
+        "envs/{}-{}-{}-{}",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        hook.language.as_str(),
+        hook.env_digest()?
+    ))
+}
+
+impl RemoteCache {
+    fn new(config: &RemoteCacheConfig) -> Result<Self> {
+        let mut url = Url::parse(&config.url)?;
+        let backend = match url.scheme() {
+            "http" | "https" => {
+                if !url.path().ends_with('/') {
+                    url.set_path(&format!("{}/", url.path()));
+                }
+                Backend::Http {
+                    base: url,
+                    token: std::env::var(EnvVars::PREFLIGIT_REMOTE_CACHE_TOKEN).ok(),
+                }
+            }
+            "file" => Backend::Directory(
+                url.to_file_path()
+                    .map_err(|()| anyhow::anyhow!("Invalid directory"))?,
+            ),
+            scheme => anyhow::bail!("Unsupported scheme `{scheme}`"),
+        };
+        Ok(Self {
+            backend,
+            upload: config.upload,
+        })
+    }
+
+    /// The remote cache of the user config, if any.
+    pub(crate) fn get() -> Option<&'static Self> {
+        REMOTE_CACHE.as_ref()
+    }
+
+    /// Restore the directory `dir` from the entry of `key`, once its content is verified to
+    /// have the digest recorded when it was uploaded.
+    ///
+    /// Returns `false` if the cache doesn't have it, or it can't be read.
+    pub(crate) async fn restore(&self, key: &str, dir: &Path) -> bool {
+        match self.try_restore(key, dir).await {
+            Ok(restored) => restored,
+            Err(err) => {
+                warn_user!("Failed to restore `{key}` from the remote cache: {err:#}");
+                false
+            }
+        }
+    }
+
+    async fn try_restore(&self, key: &str, dir: &Path) -> Result<bool> {
+        let parent = dir.parent().context("No parent directory")?;
+        fs_err::create_dir_all(parent)?;
+        // Restore next to the target, and move it in place once complete.
+        let temp = tempfile::Builder::new()
+            .prefix("restore")
+            .tempdir_in(parent)?;
+        let content = temp.path().join("content");
+        match &self.backend {
+            Backend::Http { base, token } => {
+                let archive = temp.path().join("archive.tar.gz");
+                // Entries without a digest are incomplete uploads.
+                let Some(expected) =
+                    get(base, token.as_deref(), &format!("{key}.tar.gz.sha256")).await?
+                else {
+                    debug!(key, "Not in the remote cache");
+                    return Ok(false);
+                };
+                let Some(digest) = download(base, token.as_deref(), key, &archive).await? else {
+                    debug!(key, "Not in the remote cache");
+                    return Ok(false);
+                };
+                verify(&String::from_utf8_lossy(&expected), &digest)?;
+                fs_err::create_dir_all(&content)?;
+                Cmd::new("tar", "extract cache archive")
+                    .arg("-xzf")
+                    .arg(&archive)
+                    .arg("-C")
+                    .arg(&content)
+                    .check(true)
+                    .output()
+                    .await?;
+            }
+            // Environments can be hundreds of MB, clone them instead of copying.
+            Backend::Directory(root) => {
+                let entry = root.join(key);
+                if !entry.is_dir() {
+                    debug!(key, "Not in the remote cache");
+                    return Ok(false);
+                }
+                let expected = fs_err::read_to_string(entry.join("sha256"))?;
+                clone_dir(&entry.join("content"), &content)?;
+                verify(&expected, &tree_digest(&content)?)?;
+            }
+        }
+        fs_err::rename(&content, dir)?;
+
+        debug!(key, dir = %dir.display(), "Restored from the remote cache");
+        Ok(true)
+    }
+
+    /// Upload the directory `dir` as the entry of `key`, unless uploads are turned off.
+    pub(crate) async fn save(&self, key: &str, dir: &Path) {
+        if !self.upload {
+            return;
+        }
+        if let Err(err) = self.try_save(key, dir).await {
+            warn_user!("Failed to upload `{key}` to the remote cache: {err:#}");
+        }
+    }
+
+    async fn try_save(&self, key: &str, dir: &Path) -> Result<()> {
+        match &self.backend {
+            Backend::Http { base, token } => {
+                let temp = tempfile::tempdir()?;
+                let archive = temp.path().join("archive.tar.gz");
+                create_archive(dir, &archive).await?;
+                let body = tokio::fs::read(&archive).await?;
+                let digest = format!("{:x}", Sha256::digest(&body));
+                // The digest goes last, it marks the entry as complete.
+                put(base, token.as_deref(), &format!("{key}.tar.gz"), body).await?;
+                put(
+                    base,
+                    token.as_deref(),
+                    &format!("{key}.tar.gz.sha256"),
+                    digest.into(),
+                )
+                .await?;
+            }
+            // Clone next to the entry, and rename it with its digest in place once complete,
+            // so readers never see a partial one.
+            Backend::Directory(root) => {
+                let dest = root.join(key);
+                if dest.is_dir() {
+                    return Ok(());
+                }
+                let parent = dest.parent().context("No parent directory")?;
+                fs_err::create_dir_all(parent)?;
+                let temp = tempfile::Builder::new().prefix("save").tempdir_in(parent)?;
+                let entry = temp.path().join("entry");
+                let content = entry.join("content");
+                clone_dir(dir, &content)?;
+                fs_err::write(entry.join("sha256"), tree_digest(&content)?)?;
+                fs_err::rename(&entry, &dest)?;
+            }
+        }
+
+        debug!(key, dir = %dir.display(), "Uploaded to the remote cache");
+        Ok(())
+    }
+}
+
+/// Get `path` from an HTTP backend, returns `None` if there is none.
+async fn get(base: &Url, token: Option<&str>, path: &str) -> Result<Option<Vec<u8>>> {
+    let Some(response) = send_get(base, token, path).await? else {
+        return Ok(None);
+    };
+    Ok(Some(response.bytes().await?.to_vec()))
+}
+
+async fn send_get(
+    base: &Url,
+    token: Option<&str>,
+    path: &str,
+) -> Result<Option<reqwest::Response>> {
+    let mut request = http::client().get(base.join(path)?);
+    if let Some(token) = token {
+        request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+    }
+    let response = request.send().await?;
+    if matches!(
+        response.status(),
+        StatusCode::NOT_FOUND | StatusCode::FORBIDDEN
+    ) {
+        return Ok(None);
+    }
+    Ok(Some(response.error_for_status()?))
+}
+
+/// Download the archive of `key` from an HTTP backend, returns its digest, or `None` if
+/// there is none.
+async fn download(
+    base: &Url,
+    token: Option<&str>,
+    key: &str,
+    dest: &Path,
+) -> Result<Option<String>> {
+    let Some(mut response) = send_get(base, token, &format!("{key}.tar.gz")).await? else {
+        return Ok(None);
+    };
+    let mut hasher = Sha256::new();
+    let mut file = tokio::fs::File::create(dest).await?;
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.sync_all().await?;
+    Ok(Some(format!("{:x}", hasher.finalize())))
+}
+
+/// Upload `body` to an HTTP backend as `path`.
+async fn put(base: &Url, token: Option<&str>, path: &str, body: Vec<u8>) -> Result<()> {
+    let mut request = http::client().put(base.join(path)?).body(body);
+    if let Some(token) = token {
+        request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+    }
+    request.send().await?.error_for_status()?;
+    Ok(())
+}
+
+/// Check the digest of a restored entry is the one recorded when it was uploaded.
+fn verify(expected: &str, digest: &str) -> Result<()> {
+    let expected = expected.trim();
+    if expected != digest {
+        anyhow::bail!("Digest mismatch, expected {expected} but got {digest}");
+    }
+    Ok(())
+}
+
+/// A sha256 digest of the tree at `dir`: the paths, types, and contents or link targets of
+/// the entries, in order.
+fn tree_digest(dir: &Path) -> Result<String> {
+    fn visit(hasher: &mut Sha256, root: &Path, dir: &Path) -> Result<()> {
+        let mut entries = fs_err::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(fs_err::DirEntry::file_name);
+        for entry in entries {
+            let path = entry.path();
+            let relative = path.strip_prefix(root)?;
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            let file_type = entry.file_type()?;
+            if file_type.is_symlink() {
+                hasher.update(b"link\0");
+                hasher.update(fs_err::read_link(&path)?.to_string_lossy().as_bytes());
+            } else if file_type.is_dir() {
+                hasher.update(b"dir\0");
+                visit(hasher, root, &path)?;
+            } else {
+                hasher.update(b"file\0");
+                std::io::copy(&mut fs_err::File::open(&path)?, hasher)?;
+            }
+            hasher.update([0]);
+        }
+        Ok(())
+    }
+
+    let mut hasher = Sha256::new();
+    visit(&mut hasher, dir, dir)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Archive the content of `dir` into the gzipped tarball `archive`.
+async fn create_archive(dir: &Path, archive: &Path) -> Result<()> {
+    Cmd::new("tar", "create cache archive")
+        .arg("-czf")
+        .arg(archive)
+        .arg("-C")
+        .arg(dir)
+        .arg(".")
+        .check(true)
+        .output()
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use crate::env_vars::EnvVars;
+use crate::{warn_user, warn_user_once};
+
+/// The number of times clones, fetches and downloads are retried when they fail.
+const DEFAULT_RETRIES: u32 = 3;
+
+static NETWORK_RETRIES: LazyLock<u32> = LazyLock::new(|| {
+    let Some(value) = std::env::var_os(EnvVars::PREFLIGIT_NETWORK_RETRIES) else {
+        return DEFAULT_RETRIES;
+    };
+    value.to_string_lossy().parse().unwrap_or_else(|_| {
+        warn_user_once!(
+            "Invalid `{}` value `{}`, using {DEFAULT_RETRIES}",
+            EnvVars::PREFLIGIT_NETWORK_RETRIES,
+            value.to_string_lossy()
+        );
+        DEFAULT_RETRIES
+    })
+});
+
+/// Exponential backoff before the given retry attempt: 0.5s, 1s, 2s, ..., up to 16s.
+pub(crate) fn backoff(attempt: u32) -> Duration {
+    Duration::from_millis(500 << (attempt - 1).min(5))
+}
+
+/// Run an operation that touches the network, retrying it with exponential backoff
+/// when it fails, up to `PREFLIGIT_NETWORK_RETRIES` times.
+///
+/// `what` describes the operation in the warnings, like ``clone `repo@rev` ``.
+/// The operation must start over from scratch on every attempt.
+pub(crate) async fn retry_network<T, E, F, Fut>(what: &str, mut operation: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let retries = *NETWORK_RETRIES;
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < retries => {
+                attempt += 1;
+                let backoff = backoff(attempt);
+                warn_user!(
+                    "Failed to {what}, retrying in {backoff:?} ({attempt}/{retries}): {}",
+                    err.to_string().lines().next().unwrap_or_default()
+                );
+                tokio::time::sleep(backoff).await;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
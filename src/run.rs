@@ -1,14 +1,17 @@
 use std::cmp::max;
 use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, LazyLock};
 
 use futures::StreamExt;
 use tracing::trace;
 
+use crate::client;
 use crate::env_vars::EnvVars;
 use crate::hook::Hook;
+use crate::user_config::UserConfig;
 
-pub static CONCURRENCY: LazyLock<usize> = LazyLock::new(|| {
+static DEFAULT_CONCURRENCY: LazyLock<usize> = LazyLock::new(|| {
     if std::env::var_os(EnvVars::PRE_COMMIT_NO_CONCURRENCY).is_some() {
         1
     } else {
@@ -18,46 +21,115 @@ pub static CONCURRENCY: LazyLock<usize> = LazyLock::new(|| {
     }
 });
 
+/// The number of jobs requested by `--jobs` or the `jobs` config option, `0` means auto.
+static JOBS: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the number of jobs used to run hooks, `0` means auto.
+pub fn set_jobs(jobs: usize) {
+    JOBS.store(jobs, Ordering::Relaxed);
+}
+
+/// The number of jobs used to run hooks, clone repos and install environments.
+pub fn concurrency() -> usize {
+    match JOBS.load(Ordering::Relaxed) {
+        0 => *DEFAULT_CONCURRENCY,
+        jobs => jobs,
+    }
+}
+
 fn target_concurrency(serial: bool) -> usize {
     if serial {
         1
     } else {
-        *CONCURRENCY
+        concurrency()
+    }
+}
+
+/// Room left for what isn't known when splitting the files: the variables hooks add to the
+/// environment, like `PATH`, and the entry resolved to the full path of its executable.
+const HEADROOM: usize = 4096;
+
+/// The maximum length of the command line of a hook, from `max_command_length` of the user
+/// config, or the limit of the platform less the current environment.
+static MAX_CLI_LENGTH: LazyLock<usize> = LazyLock::new(|| {
+    if let Some(length) = UserConfig::get().max_command_length {
+        return length;
+    }
+    let length = platform_max_cli_length()
+        .saturating_sub(environment_length())
+        .saturating_sub(HEADROOM);
+    trace!(length, "Maximum command line length");
+    length
+});
+
+/// The space of the arguments of a new process, which it shares with the environment.
+#[cfg(unix)]
+fn platform_max_cli_length() -> usize {
+    // Linux derives it from the stack size limit.
+    let arg_max = unsafe { libc::sysconf(libc::_SC_ARG_MAX) };
+    usize::try_from(arg_max)
+        .ok()
+        .filter(|&arg_max| arg_max > 0)
+        .unwrap_or(1 << 17)
+}
+
+/// The maximum length of the command line of `CreateProcess`, in UTF-16 units.
+#[cfg(not(unix))]
+fn platform_max_cli_length() -> usize {
+    (1 << 15) - 1
+}
+
+/// The space the environment inherited by hooks takes from the arguments.
+fn environment_length() -> usize {
+    if cfg!(unix) {
+        // `key=value` NUL-terminated strings, and pointers to them in `envp`.
+        client::vars_os()
+            .into_iter()
+            .map(|(key, value)| key.len() + value.len() + 2 + size_of::<usize>())
+            .sum()
+    } else {
+        // The environment block of Windows is separate from the command line.
+        0
+    }
+}
+
+/// The space an argument takes on the command line.
+fn arg_length(arg: &str) -> usize {
+    if cfg!(unix) {
+        // A NUL-terminated string, and a pointer to it in `argv`.
+        arg.len() + 1 + size_of::<usize>()
+    } else {
+        // Joined by spaces, and quoted when needed, with the quotes and backslashes escaped.
+        let length = arg.encode_utf16().count() + 1;
+        if arg.is_empty() || arg.contains([' ', '\t', '"']) {
+            length + 2 + arg.chars().filter(|&c| c == '"' || c == '\\').count()
+        } else {
+            length
+        }
     }
 }
 
 /// Iterator that yields partitions of filenames that fit within the maximum command line length.
 struct Partitions<'a> {
-    hook: &'a Hook,
     filenames: &'a [&'a String],
-    concurrency: usize,
     current_index: usize,
     command_length: usize,
     max_per_batch: usize,
     max_cli_length: usize,
 }
 
-// TODO: do a more accurate calculation
 impl<'a> Partitions<'a> {
     fn new(hook: &'a Hook, filenames: &'a [&'a String], concurrency: usize) -> Self {
         let max_per_batch = max(4, filenames.len().div_ceil(concurrency));
-        // TODO: subtract the env size
-        let max_cli_length = if cfg!(unix) {
-            1 << 12
-        } else {
-            (1 << 15) - 2048 // UNICODE_STRING max - headroom
-        };
         let command_length =
-            hook.entry.len() + hook.args.iter().map(String::len).sum::<usize>() + hook.args.len();
+            arg_length(&hook.entry) + hook.args.iter().map(|arg| arg_length(arg)).sum::<usize>();
 
         Self {
-            hook,
             filenames,
-            concurrency,
             current_index: 0,
             command_length,
             max_per_batch,
-            max_cli_length,
+            max_cli_length: *MAX_CLI_LENGTH,
         }
     }
 }
@@ -77,13 +149,16 @@ impl<'a> Iterator for Partitions<'a> {
         }
 
         let mut current = Vec::new();
-        let mut current_length = self.command_length + 1;
+        let mut current_length = self.command_length;
 
         while self.current_index < self.filenames.len() {
             let filename = self.filenames[self.current_index];
-            let length = filename.len() + 1;
+            let length = arg_length(filename);
 
-            if current_length + length > self.max_cli_length || current.len() >= self.max_per_batch
+            // A file too long to fit with the command still runs alone, rather than not at all.
+            if !current.is_empty()
+                && (current_length + length > self.max_cli_length
+                    || current.len() >= self.max_per_batch)
             {
                 break;
             }
@@ -93,11 +168,7 @@ impl<'a> Iterator for Partitions<'a> {
             self.current_index += 1;
         }
 
-        if current.is_empty() {
-            None
-        } else {
-            Some(current)
-        }
+        Some(current)
     }
 }
 
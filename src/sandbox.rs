@@ -0,0 +1,336 @@
+//! An opt-in sandbox for hooks, which denies them network access and only lets them write to
+//! the repo, their environment and the temporary directory.
+//!
+//! On Linux, hooks run in new user, mount and network namespaces, where every mount except the
+//! writable directories is remounted read-only. On macOS, they run under `sandbox-exec` with a
+//! profile that denies the same. It's not supported elsewhere.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Deserialize;
+
+use crate::hook::Hook;
+use crate::process::Cmd;
+use crate::user_config::UserConfig;
+
+/// The sandbox settings of the user config, for example:
+///
+/// ```toml
+/// [sandbox]
+/// enabled = true
+/// writable = ["/home/me/.cache"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Run all hooks in the sandbox, like `run --sandbox`.
+    #[serde(default)]
+    enabled: bool,
+    /// More directories hooks can write to, like the caches of tools.
+    #[serde(default)]
+    writable: Vec<PathBuf>,
+}
+
+/// Whether `--sandbox` was passed.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Run hooks in the sandbox, besides when the user config enables it.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.fetch_or(enabled, Ordering::Relaxed);
+}
+
+/// Whether hooks run in the sandbox.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+        || UserConfig::get()
+            .sandbox
+            .as_ref()
+            .is_some_and(|config| config.enabled)
+}
+
+/// The sandbox a hook runs in.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    #[cfg(target_os = "linux")]
+    namespaces: std::sync::Arc<linux::Namespaces>,
+    #[cfg(target_os = "macos")]
+    writable: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// The sandbox of a hook running in `work_dir`, or in the current directory if it's `None`,
+    /// or `None` if hooks don't run in the sandbox.
+    pub fn for_hook(hook: &Hook, work_dir: Option<&Path>) -> anyhow::Result<Option<Self>> {
+        if !enabled() {
+            return Ok(None);
+        }
+
+        let work_dir = match work_dir {
+            Some(dir) => dir.to_path_buf(),
+            None => std::env::current_dir()?,
+        };
+        let mut writable = vec![work_dir, std::env::temp_dir()];
+        writable.extend(hook.environment_dir());
+        if let Some(config) = UserConfig::get().sandbox.as_ref() {
+            writable.extend(config.writable.iter().cloned());
+        }
+        // Mounts are resolved through symlinks, and missing directories can't be written anyway.
+        let writable: Vec<_> = writable
+            .into_iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .collect();
+
+        #[cfg(target_os = "linux")]
+        {
+            Ok(Some(Self {
+                namespaces: std::sync::Arc::new(linux::Namespaces::new(&writable)?),
+            }))
+        }
+        #[cfg(target_os = "macos")]
+        {
+            Ok(Some(Self { writable }))
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        {
+            let _ = writable;
+            anyhow::bail!("The sandbox is only supported on Linux and macOS")
+        }
+    }
+}
+
+/// Create the command of a hook, run in the sandbox if there's one.
+pub fn hook_command(sandbox: Option<&Sandbox>, program: impl AsRef<OsStr>, summary: &str) -> Cmd {
+    let Some(sandbox) = sandbox else {
+        return Cmd::new(program, summary);
+    };
+
+    #[cfg(target_os = "linux")]
+    {
+        let mut cmd = Cmd::new(program, summary);
+        let namespaces = sandbox.namespaces.clone();
+        // SAFETY: only makes system calls with the arguments prepared before forking.
+        unsafe {
+            cmd.inner.pre_exec(move || namespaces.enter());
+        }
+        cmd
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let mut cmd = Cmd::new("sandbox-exec", summary);
+        cmd.arg("-p").arg(macos::profile(sandbox.writable.len()));
+        for (index, dir) in sandbox.writable.iter().enumerate() {
+            let mut param = std::ffi::OsString::from(format!("WRITABLE_{index}="));
+            param.push(dir);
+            cmd.arg("-D").arg(param);
+        }
+        cmd.arg(program);
+        cmd
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = sandbox;
+        Cmd::new(program, summary)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::{CStr, CString};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::ptr::null;
+
+    /// What's needed to enter the namespaces of the sandbox, prepared before forking since
+    /// the child can't allocate.
+    #[derive(Debug)]
+    pub(super) struct Namespaces {
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+        /// The directories hooks can write to.
+        writable: Vec<CString>,
+        /// The mount points to remount read-only.
+        read_only: Vec<CString>,
+    }
+
+    impl Namespaces {
+        pub(super) fn new(writable: &[PathBuf]) -> io::Result<Self> {
+            let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+            // `/proc` must stay writable to map the ids of the nested namespace, and the files
+            // of other namespaces in it can't be written from a user namespace anyway.
+            let read_only = mount_points()?
+                .into_iter()
+                .filter(|mount_point| !mount_point.starts_with("/proc"))
+                .filter(|mount_point| !writable.iter().any(|dir| mount_point.starts_with(dir)))
+                .map(|mount_point| c_path(&mount_point))
+                .collect::<io::Result<_>>()?;
+            Ok(Self {
+                uid_map: format!("{uid} {uid} 1").into_bytes(),
+                gid_map: format!("{gid} {gid} 1").into_bytes(),
+                writable: writable
+                    .iter()
+                    .map(|dir| c_path(dir))
+                    .collect::<io::Result<_>>()?,
+                read_only,
+            })
+        }
+
+        /// Move the current process into the namespaces, run in the child before `exec`.
+        pub(super) fn enter(&self) -> io::Result<()> {
+            unsafe {
+                check(libc::unshare(
+                    libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET,
+                ))?;
+                self.map_ids()?;
+                check(libc::mount(
+                    null(),
+                    c"/".as_ptr(),
+                    null(),
+                    libc::MS_REC | libc::MS_PRIVATE,
+                    null(),
+                ))?;
+
+                // Bind mounts of the writable directories stay writable below read-only parents.
+                for dir in &self.writable {
+                    check(libc::mount(
+                        dir.as_ptr(),
+                        dir.as_ptr(),
+                        null(),
+                        libc::MS_BIND | libc::MS_REC,
+                        null(),
+                    ))?;
+                }
+                for mount_point in &self.read_only {
+                    let mut stat: libc::statvfs = std::mem::zeroed();
+                    if libc::statvfs(mount_point.as_ptr(), &mut stat) != 0 {
+                        // Hidden below another mount.
+                        continue;
+                    }
+                    check(libc::mount(
+                        null(),
+                        mount_point.as_ptr(),
+                        null(),
+                        libc::MS_REMOUNT | libc::MS_BIND | libc::MS_RDONLY | locked_flags(&stat),
+                        null(),
+                    ))?;
+                }
+
+                // The working directory still refers to the mount it was on before.
+                let mut cwd = [0 as libc::c_char; libc::PATH_MAX as usize];
+                if !libc::getcwd(cwd.as_mut_ptr(), cwd.len()).is_null() {
+                    check(libc::chdir(cwd.as_ptr()))?;
+                }
+
+                // The mounts are locked in a nested namespace, so even a hook running as root
+                // can't make them writable again.
+                check(libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNS))?;
+                self.map_ids()
+            }
+        }
+
+        /// Map the user and group to themselves in the new user namespace.
+        fn map_ids(&self) -> io::Result<()> {
+            write_file(c"/proc/self/setgroups", b"deny")?;
+            write_file(c"/proc/self/uid_map", &self.uid_map)?;
+            write_file(c"/proc/self/gid_map", &self.gid_map)
+        }
+    }
+
+    /// The flags of a mount that must be kept when remounting it in a user namespace.
+    fn locked_flags(stat: &libc::statvfs) -> libc::c_ulong {
+        [
+            (libc::ST_NOSUID, libc::MS_NOSUID),
+            (libc::ST_NODEV, libc::MS_NODEV),
+            (libc::ST_NOEXEC, libc::MS_NOEXEC),
+            (libc::ST_NOATIME, libc::MS_NOATIME),
+            (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+            (libc::ST_RELATIME, libc::MS_RELATIME),
+        ]
+        .into_iter()
+        .filter(|(st, _)| stat.f_flag & st != 0)
+        .fold(0, |flags, (_, ms)| flags | ms)
+    }
+
+    /// The mount points of the current mount namespace.
+    fn mount_points() -> io::Result<Vec<PathBuf>> {
+        let mountinfo = fs_err::read_to_string("/proc/self/mountinfo")?;
+        Ok(mountinfo
+            .lines()
+            .filter_map(|line| line.split(' ').nth(4))
+            .map(|field| PathBuf::from(unescape(field)))
+            .collect())
+    }
+
+    /// Undo the octal escapes of spaces, tabs, newlines and backslashes in `mountinfo`.
+    fn unescape(field: &str) -> String {
+        let mut unescaped = String::with_capacity(field.len());
+        let mut rest = field;
+        while let Some(index) = rest.find('\\') {
+            unescaped.push_str(&rest[..index]);
+            let code = rest
+                .get(index + 1..index + 4)
+                .and_then(|code| u8::from_str_radix(code, 8).ok());
+            if let Some(code) = code {
+                unescaped.push(char::from(code));
+                rest = &rest[index + 4..];
+            } else {
+                unescaped.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+        unescaped.push_str(rest);
+        unescaped
+    }
+
+    fn c_path(path: &Path) -> io::Result<CString> {
+        CString::new(path.as_os_str().as_bytes()).map_err(io::Error::from)
+    }
+
+    fn check(ret: libc::c_int) -> io::Result<()> {
+        if ret == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write_file(path: &CStr, content: &[u8]) -> io::Result<()> {
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            check(fd)?;
+            let written = libc::write(fd, content.as_ptr().cast(), content.len());
+            let result = if written < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            };
+            libc::close(fd);
+            result
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::fmt::Write;
+
+    /// A profile denying network access and writes outside `WRITABLE_<n>` directories,
+    /// passed as parameters so that they don't need quoting.
+    pub(super) fn profile(writable: usize) -> String {
+        let mut profile = String::from(
+            "(version 1)\n\
+             (allow default)\n\
+             (deny network* (local ip) (remote ip))\n\
+             (deny file-write*)\n\
+             (allow file-write* (literal \"/dev/null\") (literal \"/dev/zero\") \
+             (regex #\"^/dev/tty\") (regex #\"^/dev/fd/\")",
+        );
+        for index in 0..writable {
+            let _ = write!(profile, " (subpath (param \"WRITABLE_{index}\"))");
+        }
+        profile.push_str(")\n");
+        profile
+    }
+}
@@ -1,17 +1,25 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
 use etcetera::BaseStrategy;
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior};
 use thiserror::Error;
 use tracing::debug;
+use url::Url;
 
-use crate::config::RemoteRepo;
+use crate::config::{self, Config, RemoteRepo};
 use crate::env_vars::EnvVars;
-use crate::fs::{copy_dir_all, LockedFile};
-use crate::git::clone_repo;
+use crate::fs::{symlink, LockedFile};
+use crate::git::{clone_repo, CloneProgress};
 use crate::hook::{Hook, Repo};
+use crate::identify::{FileHeader, FileStamp};
+use crate::remote_cache::{self, RemoteCache};
+use crate::retry::retry_network;
+use crate::user_config::UserConfig;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -19,6 +27,10 @@ pub enum Error {
     HomeNotFound,
     #[error("Local hook {0} does not need env")]
     LocalHookNoNeedEnv(String),
+    #[error("Repo `{0}` is not a local directory")]
+    NotADirectory(String),
+    #[error("Store schema version {0} is newer than the supported version {1}, upgrade prefligit or run `prefligit clean`")]
+    UnsupportedSchema(usize, usize),
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
@@ -29,6 +41,8 @@ pub enum Error {
     Repo(#[from] crate::hook::Error),
     #[error(transparent)]
     Git(#[from] crate::git::Error),
+    #[error("Failed to clone `{0}`")]
+    Clone(String, #[source] crate::git::Error),
 }
 
 static STORE_HOME: LazyLock<Option<PathBuf>> = LazyLock::new(|| {
@@ -45,7 +59,146 @@ static STORE_HOME: LazyLock<Option<PathBuf>> = LazyLock::new(|| {
     }
 });
 
-/// A store for managing repos.
+/// The store of the Python pre-commit, read to import its repos.
+#[derive(Debug)]
+pub struct PreCommitStore {
+    pub repos: Vec<PreCommitRepo>,
+    pub configs: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct PreCommitRepo {
+    pub repo: String,
+    pub rev: String,
+    pub path: PathBuf,
+}
+
+impl PreCommitStore {
+    /// The default location of the store, like pre-commit finds it.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(EnvVars::PRE_COMMIT_HOME) {
+            return Some(path.into());
+        }
+        let cache = match std::env::var_os(EnvVars::XDG_CACHE_HOME) {
+            Some(cache) => PathBuf::from(cache),
+            None => etcetera::home_dir().ok()?.join(".cache"),
+        };
+        Some(cache.join("pre-commit"))
+    }
+
+    /// Read the repos and configs recorded in the store at `path`.
+    ///
+    /// The copies of repos pre-commit makes for hooks with additional dependencies,
+    /// and its placeholder repo of local hooks, are left out.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let conn = Connection::open_with_flags(
+            path.join("db.db"),
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )?;
+
+        let mut stmt = conn.prepare("SELECT repo, ref, path FROM repos")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(PreCommitRepo {
+                    repo: row.get(0)?,
+                    rev: row.get(1)?,
+                    path: PathBuf::from(row.get::<_, String>(2)?),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let names: HashSet<_> = rows
+            .iter()
+            .map(|repo| (repo.repo.clone(), repo.rev.clone()))
+            .collect();
+        // Copies are named `<repo>:<deps>`.
+        let is_copy = |repo: &PreCommitRepo| {
+            repo.repo
+                .rsplit_once(':')
+                .is_some_and(|(base, _)| names.contains(&(base.to_string(), repo.rev.clone())))
+        };
+        let repos = rows
+            .into_iter()
+            .filter(|repo| repo.repo != LOCAL_NAME && !is_copy(repo) && repo.path.is_dir())
+            .collect();
+
+        // Older stores have no `configs` table.
+        let configs = match conn.prepare("SELECT path FROM configs") {
+            Ok(mut stmt) => stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .map(|path| path.map(PathBuf::from))
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Self { repos, configs })
+    }
+}
+
+/// A config file that has used the store.
+#[derive(Debug)]
+pub struct ConfigUse {
+    pub path: PathBuf,
+    /// Seconds since the Unix epoch.
+    pub last_used: u64,
+}
+
+/// A repo removed from the store to keep it under its maximum size.
+#[derive(Debug)]
+pub struct EvictedRepo {
+    pub repo: String,
+    pub rev: String,
+    /// The number of environments removed with it.
+    pub envs: usize,
+    /// The bytes freed.
+    pub size: u64,
+}
+
+/// The results of a run of hooks, recorded for `prefligit stats`.
+#[derive(Debug)]
+pub struct RunRecord {
+    /// Seconds since the Unix epoch.
+    pub started_at: u64,
+    pub duration: f64,
+    pub success: bool,
+    pub hooks: Vec<HookRunRecord>,
+}
+
+#[derive(Debug)]
+pub struct HookRunRecord {
+    pub hook_id: String,
+    pub status: String,
+    pub duration: Option<f64>,
+}
+
+/// A summary of the recorded runs of a repo.
+#[derive(Debug)]
+pub struct RunStats {
+    pub runs: usize,
+    pub failed_runs: usize,
+    pub average_duration: f64,
+    pub hooks: Vec<HookStats>,
+}
+
+/// A summary of the recorded runs of a hook, excluding runs where it was skipped.
+#[derive(Debug)]
+pub struct HookStats {
+    pub hook_id: String,
+    pub runs: usize,
+    pub failures: usize,
+    pub average_duration: f64,
+}
+
+/// How long to wait for other processes writing to the database.
+const DB_BUSY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often the last use of a config is updated, to keep runs from writing to the database.
+const CONFIG_USE_INTERVAL: u64 = 24 * 60 * 60;
+
+/// The repo name and rev local hooks are installed from.
+const LOCAL_NAME: &str = "local";
+const LOCAL_REV: &str = "1";
+
+/// A store for managing repos, hook environments and the tools to install them.
 #[derive(Debug)]
 pub struct Store {
     path: PathBuf,
@@ -74,7 +227,26 @@ impl Store {
         self.conn.as_ref().expect("store not initialized")
     }
 
+    /// The path to the directory of cloned repos.
+    fn repos_path(&self) -> PathBuf {
+        self.path.join("repos")
+    }
+
+    /// The path to the directory of installed hook environments.
+    fn envs_path(&self) -> PathBuf {
+        self.path.join("envs")
+    }
+
     /// Initialize the store.
+    ///
+    /// The store keeps three kinds of entries, each in its own directory and table:
+    /// - `repos`: hook repos, keyed by their url and rev.
+    /// - `envs`: hook environments, keyed by their language, language version and
+    ///   a hash of what's installed into them. An environment can be shared by several repos,
+    ///   it has a row for each repo using it.
+    /// - `toolchains`: tools used to install environments, like `uv`, keyed by their name, version and platform.
+    ///
+    /// The configs using the store are recorded with the repos they reference, for `prefligit gc`.
     pub fn init(self) -> Result<Self, Error> {
         fs_err::create_dir_all(&self.path)?;
 
@@ -88,27 +260,15 @@ impl Store {
             Err(err) => return Err(err.into()),
         }
 
-        let _lock = self.lock()?;
-
         // Init the database.
+        // WAL mode lets processes read while another one writes, and writers wait for each other.
         let db = self.path.join("db.db");
-        let conn = if db.try_exists()? {
-            debug!(db = %db.display(), "Opening database");
-            Connection::open(&db)?
-        } else {
-            debug!(db = %db.display(), "Creating database");
-            let conn = Connection::open(&db)?;
-            conn.execute(
-                "CREATE TABLE repos (
-                    repo TEXT NOT NULL,
-                    ref TEXT NOT NULL,
-                    path TEXT NOT NULL,
-                    PRIMARY KEY (repo, ref)
-                );",
-                [],
-            )?;
-            conn
-        };
+        debug!(db = %db.display(), "Opening database");
+        let mut conn = Connection::open(&db)?;
+        conn.busy_timeout(DB_BUSY_TIMEOUT)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+
+        Self::migrate(&mut conn)?;
 
         Ok(Self {
             conn: Some(conn),
@@ -116,6 +276,39 @@ impl Store {
         })
     }
 
+    /// Bring the database up to the current schema version.
+    ///
+    /// Only take the write lock of the database to migrate, other processes keep reading
+    /// the store while one of them installs something.
+    fn migrate(conn: &mut Connection) -> Result<(), Error> {
+        let version = schema_version(conn)?;
+        if version > MIGRATIONS.len() {
+            return Err(Error::UnsupportedSchema(version, MIGRATIONS.len()));
+        }
+        if version == MIGRATIONS.len() {
+            return Ok(());
+        }
+
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        // Another process may have migrated the database meanwhile.
+        let version = schema_version(&tx)?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            debug!(version = index + 1, "Migrating store database");
+            migration(&tx)?;
+        }
+        tx.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL);
+             DELETE FROM schema_version;",
+        )?;
+        tx.execute(
+            "INSERT INTO schema_version (version) VALUES (?)",
+            [MIGRATIONS.len()],
+        )?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
     /// List all repos.
     pub fn repos(&self) -> Result<Vec<Repo>, Error> {
         let mut stmt = self.conn().prepare("SELECT repo, ref, path FROM repos")?;
@@ -135,143 +328,1040 @@ impl Store {
             .collect::<Result<Vec<_>, Error>>()
     }
 
-    // Append dependencies to the repo name as the key.
-    fn repo_name(repo: &str, deps: &[String]) -> String {
-        let mut name = repo.to_string();
-        if !deps.is_empty() {
-            name.push(':');
-            name.push_str(&deps.join(","));
+    fn get_repo(&self, repo: &str, rev: &str) -> Result<Option<PathBuf>, Error> {
+        let mut stmt = self
+            .conn()
+            .prepare("SELECT path FROM repos WHERE repo = ? AND ref = ?")?;
+        let mut rows = stmt.query([repo, rev])?;
+        let Some(row) = rows.next()? else {
+            return Ok(None);
+        };
+        Ok(Some(PathBuf::from(row.get::<_, String>(0)?)))
+    }
+
+    fn insert_repo(&self, repo: &str, rev: &str, path: &Path) -> Result<(), Error> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO repos (repo, ref, path) VALUES (?, ?, ?)",
+            [repo, rev, &path.to_string_lossy()],
+        )?;
+        Ok(())
+    }
+
+    /// The path of a repo in the store, named by a hash of its url and rev.
+    fn repo_path(&self, repo: &str, rev: &str) -> PathBuf {
+        self.repos_path()
+            .join(format!("{:x}", md5::compute(format!("{repo}\0{rev}"))))
+    }
+
+    /// Remove a repo at the given revision from the store, including the environments
+    /// no other repo uses.
+    ///
+    /// Returns the paths of the removed repo and environments.
+    pub async fn remove_repo(&self, repo: &str, rev: &str) -> Result<Vec<PathBuf>, Error> {
+        let conn = self.conn();
+        let query = |sql: &str| -> Result<Vec<PathBuf>, Error> {
+            let mut stmt = conn.prepare(sql)?;
+            let paths = stmt
+                .query_map([repo, rev], |row| row.get::<_, String>(0))?
+                .map(|path| path.map(PathBuf::from))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(paths)
+        };
+
+        let mut paths = query(
+            "SELECT DISTINCT path FROM envs WHERE repo = ?1 AND ref = ?2
+             AND path NOT IN (SELECT path FROM envs WHERE repo != ?1 OR ref != ?2)",
+        )?;
+        paths.extend(query(
+            "SELECT path FROM repos WHERE repo = ?1 AND ref = ?2",
+        )?);
+
+        for path in &paths {
+            let _lock = Self::lock_entry(path).await?;
+            remove_dir(path)?;
         }
-        name
+        conn.execute("DELETE FROM envs WHERE repo = ?1 AND ref = ?2", [repo, rev])?;
+        conn.execute(
+            "DELETE FROM repos WHERE repo = ?1 AND ref = ?2",
+            [repo, rev],
+        )?;
+
+        Ok(paths)
     }
 
-    fn get_repo(
-        &self,
-        repo: &str,
-        rev: &str,
-        deps: &[String],
-    ) -> Result<Option<(String, String, String)>, Error> {
-        let repo_name = Self::repo_name(repo, deps);
+    /// The key of the environment of a hook, or `None` if the hook doesn't need one.
+    ///
+    /// Besides the language and its version, environments are keyed by a hash of
+    /// the additional dependencies of the hook, and the repo for languages that install it
+    /// into the environment. Hooks of different repos share the environment otherwise.
+    fn env_key(hook: &Hook) -> Option<(&str, String, String)> {
+        let language = hook.language.environment_dir()?;
+        // Versions can be paths to interpreters, keep them in a single directory name.
+        let version: String = hook
+            .language_version
+            .as_str()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        let mut deps = if hook.language.installs_repo() {
+            hook.path().to_string_lossy().to_string()
+        } else {
+            String::new()
+        };
+        for dep in &hook.additional_dependencies {
+            deps.push('\0');
+            deps.push_str(dep);
+        }
+        let deps = format!("{:x}", md5::compute(deps));
+
+        Some((language, version, deps))
+    }
+
+    /// The path of the environment of a hook, or `None` if the hook doesn't need one.
+    pub fn env_path(&self, hook: &Hook) -> Option<PathBuf> {
+        let (language, version, deps) = Self::env_key(hook)?;
+        Some(
+            self.envs_path()
+                .join(format!("{language}-{version}-{deps}")),
+        )
+    }
+
+    /// Record the environment of a hook as installed and used by the repo of the hook.
+    pub fn insert_env(&self, hook: &Hook) -> Result<(), Error> {
+        let Some((language, version, deps)) = Self::env_key(hook) else {
+            return Ok(());
+        };
+        let path = self.env_path(hook).expect("hook has an environment");
+        let (repo, rev) = match hook.repo() {
+            Repo::Remote { url, rev, .. } => (url.as_str(), rev.as_str()),
+            Repo::Local { .. } | Repo::Meta { .. } => (LOCAL_NAME, LOCAL_REV),
+        };
+        // Check first, inserting takes the write lock of the database even if the row exists.
+        let exists = self.conn().query_row(
+            "SELECT COUNT(*) FROM envs
+             WHERE language = ? AND version = ? AND deps = ? AND repo = ? AND ref = ?",
+            [language, &version, &deps, repo, rev],
+            |row| row.get::<_, usize>(0),
+        )? > 0;
+        if exists {
+            return Ok(());
+        }
+        self.conn().execute(
+            "INSERT OR IGNORE INTO envs (language, version, deps, path, repo, ref)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            [
+                language,
+                &version,
+                &deps,
+                &path.to_string_lossy(),
+                repo,
+                rev,
+            ],
+        )?;
+        Ok(())
+    }
 
+    /// Record a config file as a user of the store, with the repos it references.
+    pub fn mark_config_used(&self, path: &Path, config: &Config) -> Result<(), Error> {
+        let path = std::path::absolute(path)?.to_string_lossy().to_string();
+        let repos = config_repos(config);
+        let now = now();
+
+        // Avoid writing to the database on every run.
+        let last_used: Option<u64> = self
+            .conn()
+            .query_row(
+                "SELECT last_used FROM configs WHERE path = ?",
+                [&path],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if last_used.is_some_and(|last_used| now.saturating_sub(last_used) < CONFIG_USE_INTERVAL)
+            && self.repos_of_config(&path)? == repos
+        {
+            return Ok(());
+        }
+
+        self.record_config(&path, &repos, Some(now))
+    }
+
+    /// The repos a recorded config references.
+    fn repos_of_config(&self, path: &str) -> Result<BTreeSet<(String, String)>, Error> {
         let mut stmt = self
             .conn()
-            .prepare("SELECT repo, ref, path FROM repos WHERE repo = ? AND ref = ?")?;
-        let mut rows = stmt.query([repo_name.as_str(), rev])?;
-        let Some(row) = rows.next()? else {
-            return Ok(None);
+            .prepare("SELECT repo, ref FROM config_repos WHERE config = ?")?;
+        let repos = stmt
+            .query_map([path], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        Ok(repos)
+    }
+
+    /// Record a config with the repos it references, and when it was last used if `last_used` is set.
+    fn record_config(
+        &self,
+        path: &str,
+        repos: &BTreeSet<(String, String)>,
+        last_used: Option<u64>,
+    ) -> Result<(), Error> {
+        let tx = self.conn().unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO configs (path, last_used) VALUES (?1, COALESCE(?2, 0))
+             ON CONFLICT (path) DO UPDATE SET last_used = COALESCE(?2, last_used)",
+            rusqlite::params![path, last_used],
+        )?;
+        tx.execute("DELETE FROM config_repos WHERE config = ?", [path])?;
+        for (repo, rev) in repos {
+            tx.execute(
+                "INSERT INTO config_repos (config, repo, ref) VALUES (?, ?, ?)",
+                [path, repo, rev],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Update the repos a recorded config references, after its file changed.
+    pub fn update_config(&self, path: &Path, config: &Config) -> Result<(), Error> {
+        let path = std::path::absolute(path)?.to_string_lossy().to_string();
+        self.record_config(&path, &config_repos(config), None)
+    }
+
+    /// Forget a config file recorded by [`Store::mark_config_used`].
+    pub fn unmark_config(&self, path: &Path) -> Result<(), Error> {
+        let path = std::path::absolute(path)?;
+        let path = path.to_string_lossy();
+        self.conn()
+            .execute("DELETE FROM config_repos WHERE config = ?", [&path])?;
+        self.conn()
+            .execute("DELETE FROM configs WHERE path = ?", [&path])?;
+        Ok(())
+    }
+
+    /// List all config files that have used the store.
+    pub fn configs(&self) -> Result<Vec<ConfigUse>, Error> {
+        let mut stmt = self.conn().prepare("SELECT path, last_used FROM configs")?;
+        let configs = stmt
+            .query_map([], |row| {
+                Ok(ConfigUse {
+                    path: PathBuf::from(row.get::<_, String>(0)?),
+                    last_used: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(configs)
+    }
+
+    /// List the repos no recorded config references.
+    pub fn unreferenced_repos(&self) -> Result<Vec<(String, String)>, Error> {
+        let mut stmt = self.conn().prepare(
+            "SELECT repo, ref FROM repos WHERE NOT EXISTS
+             (SELECT 1 FROM config_repos c WHERE c.repo = repos.repo AND c.ref = repos.ref)",
+        )?;
+        let repos = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(repos)
+    }
+
+    /// Remove the repos and environments left in the store without being recorded,
+    /// like environments of repos that are gone.
+    ///
+    /// Returns the number of removed repos and environments.
+    pub async fn remove_orphans(&self) -> Result<(usize, usize), Error> {
+        let recorded = |path: &Path| -> Result<bool, Error> {
+            let count = self.conn().query_row(
+                "SELECT (SELECT COUNT(*) FROM repos WHERE path = ?1)
+                      + (SELECT COUNT(*) FROM envs WHERE path = ?1)",
+                [path.to_string_lossy()],
+                |row| row.get::<_, usize>(0),
+            )?;
+            Ok(count > 0)
         };
-        Ok(Some((row.get(0)?, row.get(1)?, row.get(2)?)))
+
+        let mut removed = [0, 0];
+        for (dir, removed) in [self.repos_path(), self.envs_path()]
+            .into_iter()
+            .zip(&mut removed)
+        {
+            let Ok(entries) = fs_err::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries {
+                let path = entry?.path();
+                // Skip lock files, and clones in progress.
+                if !path.is_dir()
+                    || path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with("clone"))
+                {
+                    continue;
+                }
+                if recorded(&path)? {
+                    continue;
+                }
+                // Entries are recorded after they're created, wait for the ones being created.
+                let _lock = Self::lock_entry(&path).await?;
+                if !recorded(&path)? {
+                    debug!(path = %path.display(), "Removing orphan store entry");
+                    remove_dir(&path)?;
+                    *removed += 1;
+                }
+            }
+        }
+        Ok((removed[0], removed[1]))
+    }
+
+    /// The size of the repos and environments in bytes.
+    ///
+    /// Entries are measured once, the first time their size is needed.
+    pub fn size(&self) -> Result<u64, Error> {
+        for table in ["repos", "envs"] {
+            let mut stmt = self.conn().prepare(&format!(
+                "SELECT DISTINCT path FROM {table} WHERE size IS NULL"
+            ))?;
+            let paths = stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            for path in paths {
+                let size = dir_size(Path::new(&path));
+                self.conn().execute(
+                    &format!("UPDATE {table} SET size = ?1 WHERE path = ?2"),
+                    rusqlite::params![size, path],
+                )?;
+            }
+        }
+
+        // Environments have a row for each repo using them.
+        let size = self.conn().query_row(
+            "SELECT (SELECT COALESCE(SUM(size), 0) FROM repos)
+                  + (SELECT COALESCE(SUM(size), 0) FROM (SELECT DISTINCT path, size FROM envs))",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(size)
     }
 
-    fn insert_repo(&self, repo: &str, rev: &str, path: &str, deps: &[String]) -> Result<(), Error> {
-        let repo_name = Self::repo_name(repo, deps);
+    /// Remove the least recently used repos, with the environments no other repo uses,
+    /// until the repos and environments take at most `max_size` bytes.
+    ///
+    /// The repos of `config`, the config being run, and of the recorded configs that still exist
+    /// are kept. The others are ordered by the last use of the configs that referenced them, so
+    /// the ones no config references go first.
+    pub async fn prune_to_size(
+        &self,
+        max_size: u64,
+        config: &Config,
+    ) -> Result<Vec<EvictedRepo>, Error> {
+        let mut size = self.size()?;
+        if size <= max_size {
+            return Ok(Vec::new());
+        }
+
+        let mut keep = config_repos(config);
+        for recorded in self.configs()? {
+            // Keep the repos when it can't be told whether the config is gone.
+            if !matches!(recorded.path.try_exists(), Ok(false)) {
+                keep.extend(self.repos_of_config(&recorded.path.to_string_lossy())?);
+            }
+        }
+        let mut stmt = self.conn().prepare(
+            "SELECT r.repo, r.ref FROM repos r
+             LEFT JOIN config_repos cr ON cr.repo = r.repo AND cr.ref = r.ref
+             LEFT JOIN configs c ON c.path = cr.config
+             GROUP BY r.repo, r.ref
+             ORDER BY COALESCE(MAX(c.last_used), 0), r.repo, r.ref",
+        )?;
+        let candidates = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut evicted = Vec::new();
+        for (repo, rev) in candidates {
+            if size <= max_size {
+                break;
+            }
+            if keep.contains(&(repo.clone(), rev.clone())) {
+                continue;
+            }
+            let freed: u64 = self.conn().query_row(
+                "SELECT (SELECT COALESCE(SUM(size), 0) FROM repos WHERE repo = ?1 AND ref = ?2)
+                      + (SELECT COALESCE(SUM(size), 0) FROM (
+                            SELECT DISTINCT path, size FROM envs WHERE repo = ?1 AND ref = ?2
+                            AND path NOT IN (SELECT path FROM envs WHERE repo != ?1 OR ref != ?2)))",
+                [&repo, &rev],
+                |row| row.get(0),
+            )?;
+            let paths = self.remove_repo(&repo, &rev).await?;
+            debug!(repo, rev, freed, "Evicted repo from the store");
+            size = size.saturating_sub(freed);
+            evicted.push(EvictedRepo {
+                repo,
+                rev,
+                envs: paths.len().saturating_sub(1),
+                size: freed,
+            });
+        }
+        Ok(evicted)
+    }
+
+    /// Record the results of a run of the hooks of a repo.
+    pub fn record_run(&self, repo: &Path, run: &RunRecord) -> Result<(), Error> {
+        let repo = std::path::absolute(repo)?;
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO runs (repo, started_at, duration, success) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                repo.to_string_lossy(),
+                run.started_at,
+                run.duration,
+                run.success
+            ],
+        )?;
+        let run_id = conn.last_insert_rowid();
+        let mut stmt = conn.prepare(
+            "INSERT INTO hook_runs (run_id, hook_id, status, duration) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for hook in &run.hooks {
+            stmt.execute(rusqlite::params![
+                run_id,
+                hook.hook_id,
+                hook.status,
+                hook.duration
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// Summarize the last `limit` recorded runs of a repo.
+    pub fn run_stats(&self, repo: &Path, limit: usize) -> Result<RunStats, Error> {
+        let repo = std::path::absolute(repo)?.to_string_lossy().to_string();
+        let conn = self.conn();
+        let (runs, failed_runs, average_duration) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(NOT success), 0), COALESCE(AVG(duration), 0.0)
+             FROM (SELECT success, duration FROM runs WHERE repo = ?1 ORDER BY id DESC LIMIT ?2)",
+            rusqlite::params![repo, limit],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
 
+        let mut stmt = conn.prepare(
+            "SELECT hook_id, COUNT(*), SUM(status = 'failed'), AVG(duration)
+             FROM hook_runs
+             WHERE status != 'skipped'
+               AND run_id IN (SELECT id FROM runs WHERE repo = ?1 ORDER BY id DESC LIMIT ?2)
+             GROUP BY hook_id
+             ORDER BY AVG(duration) DESC, hook_id",
+        )?;
+        let hooks = stmt
+            .query_map(rusqlite::params![repo, limit], |row| {
+                Ok(HookStats {
+                    hook_id: row.get(0)?,
+                    runs: row.get(1)?,
+                    failures: row.get(2)?,
+                    average_duration: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(RunStats {
+            runs,
+            failed_runs,
+            average_duration,
+            hooks,
+        })
+    }
+
+    /// The cached headers of the files of a repo, by their path relative to it.
+    pub fn file_headers<'a>(
+        &self,
+        repo: &Path,
+        paths: impl IntoIterator<Item = &'a String>,
+    ) -> Result<HashMap<String, (FileStamp, FileHeader)>, Error> {
+        let repo = std::path::absolute(repo)?.to_string_lossy().to_string();
+        let mut stmt = self.conn().prepare_cached(
+            "SELECT size, mtime, mode, shebang, text FROM file_headers WHERE repo = ?1 AND path = ?2",
+        )?;
+        let mut headers = HashMap::new();
+        for path in paths {
+            let header = stmt
+                .query_row(rusqlite::params![repo, path], |row| {
+                    let stamp = FileStamp {
+                        size: row.get(0)?,
+                        mtime: row.get(1)?,
+                        mode: row.get(2)?,
+                    };
+                    // The shebang can't have line breaks, so its command is stored one per line.
+                    let shebang = row
+                        .get::<_, Option<String>>(3)?
+                        .map(|shebang| shebang.lines().map(ToString::to_string).collect());
+                    let header = FileHeader {
+                        shebang,
+                        text: row.get(4)?,
+                    };
+                    Ok((stamp, header))
+                })
+                .optional()?;
+            if let Some(header) = header {
+                headers.insert(path.clone(), header);
+            }
+        }
+        Ok(headers)
+    }
+
+    /// Cache the headers of files of a repo, read to tag them.
+    pub fn update_file_headers(
+        &self,
+        repo: &Path,
+        headers: &[(&String, FileStamp, FileHeader)],
+    ) -> Result<(), Error> {
+        let repo = std::path::absolute(repo)?.to_string_lossy().to_string();
+        let tx = self.conn().unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO file_headers (repo, path, size, mtime, mode, shebang, text)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for (path, stamp, header) in headers {
+                stmt.execute(rusqlite::params![
+                    repo,
+                    path,
+                    stamp.size,
+                    stamp.mtime,
+                    stamp.mode,
+                    header.shebang.as_ref().map(|shebang| shebang.join("\n")),
+                    header.text,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The keys of the last successful runs of the hooks of a repo, by hook.
+    pub fn hook_cache(&self, repo: &Path) -> Result<HashMap<String, String>, Error> {
+        let repo = std::path::absolute(repo)?.to_string_lossy().to_string();
         let mut stmt = self
             .conn()
-            .prepare("INSERT INTO repos (repo, ref, path) VALUES (?, ?, ?)")?;
-        stmt.execute([repo_name.as_str(), rev, path])?;
+            .prepare("SELECT hook, key FROM hook_cache WHERE repo = ?")?;
+        let keys = stmt
+            .query_map([repo], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        Ok(keys)
+    }
+
+    /// Record the keys of hooks of a repo that passed, replacing their previous keys.
+    pub fn update_hook_cache(&self, repo: &Path, keys: &[(String, String)]) -> Result<(), Error> {
+        let repo = std::path::absolute(repo)?.to_string_lossy().to_string();
+        let tx = self.conn().unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO hook_cache (repo, hook, key) VALUES (?1, ?2, ?3)",
+            )?;
+            for (hook, key) in keys {
+                stmt.execute([&repo, hook, key])?;
+            }
+        }
+        tx.commit()?;
         Ok(())
     }
 
-    /// Prepare a local repo for a local hook.
-    /// All local hooks with same additional dependencies, e.g. no dependencies,
-    /// are stored in the same directory (even they use different language).
-    pub fn prepare_local_repo(&self, hook: &Hook, deps: &[String]) -> Result<PathBuf, Error> {
-        const LOCAL_NAME: &str = "local";
-        const LOCAL_REV: &str = "1";
+    /// Forget the file headers and hook runs of repos that are gone.
+    pub fn remove_stale_caches(&self) -> Result<(), Error> {
+        let conn = self.conn();
+        for table in ["file_headers", "hook_cache"] {
+            let mut stmt = conn.prepare(&format!("SELECT DISTINCT repo FROM {table}"))?;
+            let repos = stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            for repo in repos {
+                if !Path::new(&repo).is_dir() {
+                    debug!(repo, table, "Forgetting the caches of a repo");
+                    conn.execute(&format!("DELETE FROM {table} WHERE repo = ?1"), [&repo])?;
+                }
+            }
+        }
+        Ok(())
+    }
 
+    /// Prepare a local repo for a local hook.
+    /// All local hooks are installed from the same placeholder repo, even they use different language.
+    pub async fn prepare_local_repo(&self, hook: &Hook) -> Result<PathBuf, Error> {
         if hook.language.environment_dir().is_none() {
             return Err(Error::LocalHookNoNeedEnv(hook.id.clone()));
         }
 
-        let path = if let Some((_, _, path)) = self.get_repo(LOCAL_NAME, LOCAL_REV, deps)? {
-            path
-        } else {
-            let temp = tempfile::Builder::new()
-                .prefix("repo")
-                .keep(true)
-                .tempdir_in(&self.path)?;
-
-            let path = temp.path().to_string_lossy().to_string();
-            debug!(hook = hook.id, path, "Preparing local repo");
-            make_local_repo(LOCAL_NAME, temp.path())?;
-            self.insert_repo(LOCAL_NAME, LOCAL_REV, &path, deps)?;
-            path
-        };
+        if let Some(path) = self.get_repo(LOCAL_NAME, LOCAL_REV)? {
+            return Ok(path);
+        }
+
+        let path = self.repo_path(LOCAL_NAME, LOCAL_REV);
+        let _lock = Self::lock_entry(&path).await?;
+        // Another process may have prepared it while waiting for the lock.
+        if let Some(path) = self.get_repo(LOCAL_NAME, LOCAL_REV)? {
+            return Ok(path);
+        }
+        debug!(hook = hook.id, path = %path.display(), "Preparing local repo");
+        make_local_repo(LOCAL_NAME, &path)?;
+        self.insert_repo(LOCAL_NAME, LOCAL_REV, &path)?;
 
-        Ok(PathBuf::from(path))
+        Ok(path)
     }
 
     /// Clone a remote repo into the store.
+    /// Returns true if the repo has been cloned into the store.
+    pub fn is_cloned(&self, repo_config: &RemoteRepo) -> Result<bool, Error> {
+        // Linked repos are never cloned.
+        if repo_config.is_linked() {
+            return Ok(true);
+        }
+        Ok(self
+            .get_repo(repo_config.repo.as_str(), repo_config.rev.as_str())?
+            .is_some())
+    }
+
     pub async fn prepare_remote_repo(
         &self,
         repo_config: &RemoteRepo,
-        deps: &[String],
+        progress: CloneProgress<'_>,
     ) -> Result<PathBuf, Error> {
-        if let Some((_, _, path)) = self.get_repo(
+        if repo_config.is_linked() {
+            return self.link_repo(repo_config).await;
+        }
+
+        if let Some(path) = self.get_repo(repo_config.repo.as_str(), repo_config.rev.as_str())? {
+            return Ok(path);
+        }
+
+        self.clone_repo(
             repo_config.repo.as_str(),
-            repo_config.rev.as_str(),
-            deps.as_ref(),
-        )? {
-            return Ok(PathBuf::from(path));
+            &repo_config.rev,
+            &UserConfig::get().rewrite_url(repo_config.repo.as_str()),
+            &repo_config.rev,
+            progress,
+        )
+        .await
+    }
+
+    /// Clone `source_rev` of the repo at `source` into the store, as `repo` at `rev`.
+    async fn clone_repo(
+        &self,
+        repo: &str,
+        rev: &str,
+        source: &str,
+        source_rev: &str,
+        progress: CloneProgress<'_>,
+    ) -> Result<PathBuf, Error> {
+        let path = self.repo_path(repo, rev);
+        let _lock = Self::lock_entry(&path).await?;
+        // Another process may have cloned it while waiting for the lock.
+        if let Some(path) = self.get_repo(repo, rev)? {
+            return Ok(path);
         }
 
-        // Clone and checkout the repo.
-        let temp = tempfile::Builder::new()
-            .prefix("repo")
-            .keep(true)
-            .tempdir_in(&self.path)?;
-        let path = temp.path().to_string_lossy().to_string();
+        let cache = RemoteCache::get();
+        let key = remote_cache::repo_key(&path);
+        if let Some(cache) = cache {
+            remove_dir(&path)?;
+            if cache.restore(&key, &path).await {
+                self.insert_repo(repo, rev, &path)?;
+                return Ok(path);
+            }
+        }
 
-        if deps.is_empty() {
+        // Clone into a temporary directory first, so an interrupted clone is never used.
+        fs_err::create_dir_all(self.repos_path())?;
+        let name = format!("{repo}@{rev}");
+        let temp = retry_network(&format!("clone `{name}`"), || async {
+            let temp = tempfile::Builder::new()
+                .prefix("clone")
+                .tempdir_in(self.repos_path())?;
             debug!(
-                target = path,
-                repo = format!("{}@{}", repo_config.repo, repo_config.rev),
+                target = %temp.path().display(),
+                repo = name,
+                source,
                 "Cloning repo",
             );
-            clone_repo(repo_config.repo.as_str(), &repo_config.rev, temp.path()).await?;
+            clone_repo(source, source_rev, temp.path(), progress).await?;
+            Ok::<_, Error>(temp)
+        })
+        .await
+        .map_err(|err| match err {
+            Error::Git(err) => Error::Clone(name.clone(), err),
+            err => err,
+        })?;
+
+        remove_dir(&path)?;
+        fs_err::rename(temp.into_path(), &path)?;
+        self.insert_repo(repo, rev, &path)?;
+
+        if let Some(cache) = cache {
+            cache.save(&key, &path).await;
+        }
+
+        Ok(path)
+    }
+
+    /// Import a repo cloned by the Python pre-commit, see [`PreCommitStore`].
+    ///
+    /// The checked out commit is cloned from its directory, leaving out the environments
+    /// installed inside. Returns `false` if the repo is in the store already.
+    pub async fn import_repo(&self, repo: &PreCommitRepo) -> Result<bool, Error> {
+        if self.get_repo(&repo.repo, &repo.rev)?.is_some() {
+            return Ok(false);
+        }
+        let source = Url::from_directory_path(&repo.path)
+            .map_err(|()| Error::NotADirectory(repo.path.display().to_string()))?;
+        self.clone_repo(
+            &repo.repo,
+            &repo.rev,
+            source.as_str(),
+            "HEAD",
+            &|_, _, _| {},
+        )
+        .await?;
+        Ok(true)
+    }
+
+    /// Link the entries of a local directory repo into the store.
+    ///
+    /// Changes to the hooks in the directory take effect without cloning again.
+    async fn link_repo(&self, repo_config: &RemoteRepo) -> Result<PathBuf, Error> {
+        let Ok(source) = repo_config.repo.to_file_path() else {
+            return Err(Error::NotADirectory(repo_config.repo.to_string()));
+        };
+
+        // Files may have been added or removed since the last run, relink if they changed.
+        let entries: BTreeSet<_> = fs_err::read_dir(&source)?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .filter(|name| name.as_ref().is_ok_and(|name| name != ".git"))
+            .collect::<Result<_, _>>()?;
+        if let Some(path) = self.get_repo(repo_config.repo.as_str(), "")? {
+            if links(&path) == entries {
+                return Ok(path);
+            }
+        }
+
+        let path = self.repo_path(repo_config.repo.as_str(), "");
+        let _lock = Self::lock_entry(&path).await?;
+        let path = if let Some(path) = self.get_repo(repo_config.repo.as_str(), "")? {
+            path
         } else {
-            // FIXME: Do not copy env dir.
-            // TODO: use hardlink?
-            // Optimization: This is an optimization from the Python pre-commit implementation.
-            // Copy already cloned base remote repo.
-            let (_, _, base_repo_path) = self
-                .get_repo(repo_config.repo.as_str(), repo_config.rev.as_str(), &[])?
-                .expect("base repo should be cloned before");
-            debug!(
-                source = base_repo_path,
-                target = path,
-                deps = deps.join(","),
-                "Preparing {}@{} by copying",
-                repo_config.repo,
-                repo_config.rev,
-            );
-            copy_dir_all(base_repo_path, &path)?;
+            debug!(target = %path.display(), repo = %repo_config.repo, "Linking repo");
+            fs_err::create_dir_all(&path)?;
+            self.insert_repo(repo_config.repo.as_str(), "", &path)?;
+            path
+        };
+
+        for entry in fs_err::read_dir(&path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_symlink() {
+                // Links to directories are directories on Windows.
+                fs_err::remove_file(entry.path()).or_else(|_| fs_err::remove_dir(entry.path()))?;
+            }
+        }
+        for name in &entries {
+            symlink(source.join(name), path.join(name))?;
         }
 
-        self.insert_repo(
-            repo_config.repo.as_str(),
-            repo_config.rev.as_str(),
-            &path,
-            deps,
-        )?;
+        Ok(path)
+    }
 
-        Ok(PathBuf::from(path))
+    /// Lock a repo or an environment of the store, given its path.
+    ///
+    /// Entries are locked while they're created or removed, so processes preparing
+    /// different entries don't wait for each other.
+    pub async fn lock_entry(path: &Path) -> Result<LockedFile, std::io::Error> {
+        let mut lock = path.as_os_str().to_owned();
+        lock.push(".lock");
+        let lock = PathBuf::from(lock);
+        if let Some(parent) = lock.parent() {
+            fs_err::create_dir_all(parent)?;
+        }
+        LockedFile::acquire(&lock, path.display()).await
     }
 
-    /// Lock the store.
-    pub fn lock(&self) -> Result<LockedFile, std::io::Error> {
-        LockedFile::acquire_blocking(self.path.join(".lock"), "store")
+    /// The path to the directory where unstaged changes are saved while running hooks.
+    pub fn patches_path(&self) -> PathBuf {
+        self.path.join("patches")
     }
 
-    pub async fn lock_async(&self) -> Result<LockedFile, std::io::Error> {
-        LockedFile::acquire(self.path.join(".lock"), "store").await
+    /// The path to the directory holding the full output of truncated hook output.
+    pub fn logs_path(&self) -> PathBuf {
+        self.path.join("logs")
     }
 
-    /// The path to the tool directory in the store.
+    /// The path to the directory of a tool in the store.
+    ///
+    /// Tools that manage their own installs, like the Python versions installed by `uv`,
+    /// use it directly.
     pub fn tools_path(&self, tool: ToolBucket) -> PathBuf {
         self.path.join("tools").join(tool.as_str())
     }
+
+    /// The path to a version of a tool for the current platform.
+    pub fn toolchain_path(&self, tool: ToolBucket, version: &str) -> PathBuf {
+        self.tools_path(tool)
+            .join(format!("{version}-{}", platform()))
+    }
+
+    /// Record a version of a tool as installed at [`Store::toolchain_path`].
+    pub fn insert_toolchain(&self, tool: ToolBucket, version: &str) -> Result<(), Error> {
+        let path = self.toolchain_path(tool, version);
+        self.conn().execute(
+            "INSERT OR REPLACE INTO toolchains (tool, version, platform, path) VALUES (?, ?, ?, ?)",
+            [tool.as_str(), version, &platform(), &path.to_string_lossy()],
+        )?;
+        Ok(())
+    }
+}
+
+/// The platform toolchains are installed for, like `linux-x86_64`.
+fn platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// The names of the links in a linked repo.
+fn links(path: &Path) -> BTreeSet<OsString> {
+    let Ok(entries) = fs_err::read_dir(path) else {
+        return BTreeSet::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|ty| ty.is_symlink()))
+        .map(|entry| entry.file_name())
+        .collect()
+}
+
+/// The repos in the store a config references, by url and rev.
+fn config_repos(config: &Config) -> BTreeSet<(String, String)> {
+    config
+        .repos
+        .iter()
+        .filter_map(|repo| match repo {
+            config::Repo::Remote(repo) => Some((repo.repo.to_string(), repo.rev.clone())),
+            config::Repo::Local(_) => Some((LOCAL_NAME.to_string(), LOCAL_REV.to_string())),
+            config::Repo::Meta(_) => None,
+        })
+        .collect()
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Remove a directory of the store, if it exists.
+fn remove_dir(path: &Path) -> Result<(), Error> {
+    match fs_err::remove_dir_all(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// The total size of the files in a directory, without following symlinks.
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs_err::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// A step upgrading the store database from the previous schema version.
+type Migration = fn(&Connection) -> Result<(), Error>;
+
+/// The migrations of the store database, the schema version is the number of them applied.
+///
+/// Stores created before the database was versioned run all of them, so every migration
+/// must cope with the tables it creates or changes existing already.
+/// Append new migrations here, never change the ones released.
+const MIGRATIONS: &[Migration] = &[
+    create_tables,
+    split_buckets,
+    track_config_repos,
+    cache_file_headers,
+    track_entry_sizes,
+    cache_hook_runs,
+];
+
+/// The schema version of the database, `0` for stores created before it was versioned.
+fn schema_version(conn: &Connection) -> Result<usize, Error> {
+    if !has_table(conn, "schema_version")? {
+        return Ok(0);
+    }
+    let version = conn
+        .query_row("SELECT MAX(version) FROM schema_version", [], |row| {
+            row.get::<_, Option<usize>>(0)
+        })?
+        .unwrap_or_default();
+    Ok(version)
+}
+
+fn has_table(conn: &Connection, name: &str) -> Result<bool, Error> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+        [name],
+        |row| row.get::<_, usize>(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool, Error> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?",
+        [table, column],
+        |row| row.get::<_, usize>(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Version 1: the repos, the configs and the run history.
+fn create_tables(conn: &Connection) -> Result<(), Error> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS repos (
+            repo TEXT NOT NULL,
+            ref TEXT NOT NULL,
+            path TEXT NOT NULL,
+            PRIMARY KEY (repo, ref)
+        );
+        CREATE TABLE IF NOT EXISTS configs (
+            path TEXT NOT NULL,
+            PRIMARY KEY (path)
+        );
+        CREATE TABLE IF NOT EXISTS runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            repo TEXT NOT NULL,
+            started_at INTEGER NOT NULL,
+            duration REAL NOT NULL,
+            success INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS hook_runs (
+            run_id INTEGER NOT NULL REFERENCES runs (id),
+            hook_id TEXT NOT NULL,
+            status TEXT NOT NULL,
+            duration REAL
+        );",
+    )?;
+    Ok(())
+}
+
+/// Version 2: environments and toolchains kept apart from the repos.
+///
+/// The repos of older stores have environments installed inside, and copies of them for
+/// every set of additional dependencies, so they are removed and cloned again instead.
+fn split_buckets(conn: &Connection) -> Result<(), Error> {
+    if !has_table(conn, "envs")? {
+        debug!("Removing repos of the legacy store layout");
+        let mut stmt = conn.prepare("SELECT path FROM repos")?;
+        let paths = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for path in paths {
+            remove_dir(Path::new(&path))?;
+        }
+        conn.execute("DELETE FROM repos", [])?;
+    }
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS envs (
+            language TEXT NOT NULL,
+            version TEXT NOT NULL,
+            deps TEXT NOT NULL,
+            path TEXT NOT NULL,
+            repo TEXT NOT NULL,
+            ref TEXT NOT NULL,
+            PRIMARY KEY (language, version, deps, repo, ref),
+            FOREIGN KEY (repo, ref) REFERENCES repos (repo, ref)
+        );
+        CREATE TABLE IF NOT EXISTS toolchains (
+            tool TEXT NOT NULL,
+            version TEXT NOT NULL,
+            platform TEXT NOT NULL,
+            path TEXT NOT NULL,
+            PRIMARY KEY (tool, version, platform)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Version 3: the repos each config uses and when it was last used, for `gc`.
+fn track_config_repos(conn: &Connection) -> Result<(), Error> {
+    if !has_column(conn, "configs", "last_used")? {
+        conn.execute(
+            "ALTER TABLE configs ADD COLUMN last_used INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS config_repos (
+            config TEXT NOT NULL REFERENCES configs (path),
+            repo TEXT NOT NULL,
+            ref TEXT NOT NULL,
+            PRIMARY KEY (config, repo, ref)
+        );",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Version 4: the headers of the files hooks run on, read to tag them.
+fn cache_file_headers(conn: &Connection) -> Result<(), Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS file_headers (
+            repo TEXT NOT NULL,
+            path TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            mtime INTEGER NOT NULL,
+            mode INTEGER NOT NULL,
+            shebang TEXT,
+            text INTEGER,
+            PRIMARY KEY (repo, path)
+        );",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Version 5: the sizes of repos and environments, for the maximum store size.
+fn track_entry_sizes(conn: &Connection) -> Result<(), Error> {
+    for table in ["repos", "envs"] {
+        if !has_column(conn, table, "size")? {
+            conn.execute(&format!("ALTER TABLE {table} ADD COLUMN size INTEGER"), [])?;
+        }
+    }
+    Ok(())
+}
+
+/// Version 6: the keys of the last successful runs of hooks, to skip them when unchanged.
+fn cache_hook_runs(conn: &Connection) -> Result<(), Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS hook_cache (
+            repo TEXT NOT NULL,
+            hook TEXT NOT NULL,
+            key TEXT NOT NULL,
+            PRIMARY KEY (repo, hook)
+        );",
+        [],
+    )?;
+    Ok(())
 }
 
 #[derive(Copy, Clone)]
@@ -308,3 +1398,47 @@ fn make_local_repo(_repo: &str, path: &Path) -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hook::Project;
+
+    /// The environments of a `lint` hook of `language` in two linked repos, with the
+    /// additional dependencies of each.
+    async fn env_paths(language: &str, deps: [&str; 2]) -> anyhow::Result<Vec<PathBuf>> {
+        let dir = tempfile::tempdir()?;
+        let mut config = "repos:\n".to_string();
+        for (name, deps) in ["hooks-a", "hooks-b"].into_iter().zip(deps) {
+            fs_err::create_dir_all(dir.path().join(name))?;
+            fs_err::write(
+                dir.path().join(name).join(".pre-commit-hooks.yaml"),
+                format!("- id: lint\n  name: lint\n  entry: lint\n  language: {language}\n"),
+            )?;
+            config.push_str(&format!(
+                "  - repo: ./{name}\n    hooks:\n      - id: lint\n        additional_dependencies: [{deps}]\n"
+            ));
+        }
+        let config_path = dir.path().join(".pre-commit-config.yaml");
+        fs_err::write(&config_path, config)?;
+
+        let store = Store::from_path(dir.path().join("store")).init()?;
+        let hooks = Project::new(config_path)?.init_hooks(&store, None).await?;
+        Ok(hooks
+            .iter()
+            .map(|hook| store.env_path(hook).expect("hook has an environment"))
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn env_of_installed_repo() -> anyhow::Result<()> {
+        let paths = env_paths("node", ["prettier", "prettier"]).await?;
+        assert_eq!(paths[0], paths[1]);
+
+        // Environments with the repo installed into them are not shared with other repos.
+        let paths = env_paths("python", ["black", "black"]).await?;
+        assert_ne!(paths[0], paths[1]);
+
+        Ok(())
+    }
+}
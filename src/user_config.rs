@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use etcetera::BaseStrategy;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::config::FileTypeRules;
+use crate::env_vars::EnvVars;
+use crate::fs::Simplified;
+use crate::remote_cache::RemoteCacheConfig;
+use crate::sandbox::SandboxConfig;
+use crate::warn_user_once;
+
+/// The settings of the current user, shared by all repos.
+///
+/// It's read from `~/.config/prefligit/config.toml` by default, for example:
+///
+/// ```toml
+/// ca_certificates = "/etc/corp/ca.pem"
+///
+/// [url."https://git.example.com/github/"]
+/// instead_of = ["https://github.com/"]
+///
+/// [remote_cache]
+/// url = "https://cache.example.com/prefligit/"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+    /// Rewrite rules of URLs, keyed by the URL to use instead.
+    #[serde(default)]
+    url: BTreeMap<String, UrlRewrite>,
+    /// Custom file types for all repos, the config file can override them.
+    #[serde(default)]
+    pub file_types: BTreeMap<String, FileTypeRules>,
+    /// A PEM file of CA certificates to trust besides the built-in ones, like the one of a
+    /// TLS-intercepting proxy. Git, uv, pip and npm use it too.
+    pub ca_certificates: Option<PathBuf>,
+    /// Trust the CA certificates of the system besides the built-in ones.
+    #[serde(default)]
+    pub native_certificates: bool,
+    /// A cache of repos and environments shared with other machines.
+    pub remote_cache: Option<RemoteCacheConfig>,
+    /// The maximum length of the command lines of hooks, files passed to them are split into
+    /// batches that fit. By default, it's the limit of the platform less the environment.
+    pub max_command_length: Option<usize>,
+    /// The maximum size of the repos and environments in the store in MiB. After a run exceeding
+    /// it, the least recently used repos are removed with their environments.
+    pub max_store_size: Option<u64>,
+    /// Run hooks without network access and only let them write to the repo, their
+    /// environment and the temporary directory.
+    pub sandbox: Option<SandboxConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UrlRewrite {
+    /// The URL prefixes to replace, like `url.<base>.insteadOf` of git.
+    instead_of: Vec<String>,
+}
+
+static USER_CONFIG: LazyLock<UserConfig> = LazyLock::new(|| {
+    let path = std::env::var_os(EnvVars::PREFLIGIT_USER_CONFIG)
+        .map(PathBuf::from)
+        .or_else(|| {
+            etcetera::choose_base_strategy()
+                .map(|dirs| dirs.config_dir().join("prefligit").join("config.toml"))
+                .ok()
+        });
+    let Some(path) = path else {
+        return UserConfig::default();
+    };
+
+    let content = match fs_err::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return UserConfig::default();
+        }
+        Err(err) => {
+            warn_user_once!("Failed to read the user config: {err}");
+            return UserConfig::default();
+        }
+    };
+    debug!(path = %path.display(), "Loading user config");
+    toml::from_str(&content).unwrap_or_else(|err| {
+        warn_user_once!(
+            "Ignoring invalid user config `{}`: {err}",
+            path.user_display()
+        );
+        UserConfig::default()
+    })
+});
+
+impl UserConfig {
+    pub fn get() -> &'static Self {
+        &USER_CONFIG
+    }
+
+    /// Rewrite a URL with the longest matching `instead_of` prefix, like git does.
+    pub fn rewrite_url<'a>(&self, url: &'a str) -> Cow<'a, str> {
+        let Some((base, prefix)) = self
+            .url
+            .iter()
+            .flat_map(|(base, rewrite)| rewrite.instead_of.iter().map(move |prefix| (base, prefix)))
+            .filter(|(_, prefix)| url.starts_with(prefix.as_str()))
+            .max_by_key(|(_, prefix)| prefix.len())
+        else {
+            return Cow::Borrowed(url);
+        };
+        let rewritten = format!("{base}{}", &url[prefix.len()..]);
+        debug!(url, rewritten, "Rewriting URL");
+        Cow::Owned(rewritten)
+    }
+}
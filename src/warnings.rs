@@ -26,8 +26,6 @@ use std::sync::{LazyLock, Mutex};
 
 // macro hygiene: The user might not have direct dependencies on those crates
 #[doc(hidden)]
-pub use anstream;
-#[doc(hidden)]
 pub use owo_colors;
 
 /// Whether user-facing warnings are enabled.
@@ -47,13 +45,18 @@ pub fn disable() {
 #[macro_export]
 macro_rules! warn_user {
     ($($arg:tt)*) => {
-        use $crate::warnings::anstream::eprintln;
+        use std::fmt::Write as _;
         use $crate::warnings::owo_colors::OwoColorize;
 
         if $crate::warnings::ENABLED.load(std::sync::atomic::Ordering::SeqCst) {
             let message = format!("{}", format_args!($($arg)*));
             let formatted = message.bold();
-            eprintln!("{}{} {formatted}", "warning".yellow().bold(), ":".bold());
+            let _ = writeln!(
+                $crate::printer::Stderr::Enabled,
+                "{}{} {formatted}",
+                "warning".yellow().bold(),
+                ":".bold()
+            );
         }
     };
 }
@@ -65,14 +68,20 @@ pub static WARNINGS: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Mutex::def
 #[macro_export]
 macro_rules! warn_user_once {
     ($($arg:tt)*) => {
-        use $crate::warnings::anstream::eprintln;
-        use $crate::warnings::::owo_colors::OwoColorize;
+        use std::fmt::Write as _;
+        use $crate::warnings::owo_colors::OwoColorize;
 
         if $crate::warnings::ENABLED.load(std::sync::atomic::Ordering::SeqCst) {
             if let Ok(mut states) = $crate::warnings::WARNINGS.lock() {
                 let message = format!("{}", format_args!($($arg)*));
                 if states.insert(message.clone()) {
-                    eprintln!("{}{} {}", "warning".yellow().bold(), ":".bold(), message.bold());
+                    let _ = writeln!(
+                        $crate::printer::Stderr::Enabled,
+                        "{}{} {}",
+                        "warning".yellow().bold(),
+                        ":".bold(),
+                        message.bold()
+                    );
                 }
             }
         }
@@ -0,0 +1,304 @@
+use std::process::Command;
+
+use anyhow::Result;
+use assert_cmd::assert::OutputAssertExt;
+use assert_fs::fixture::{FileWriteStr, PathChild};
+
+use crate::common::{cmd_snapshot, TestContext};
+
+mod common;
+
+/// Commit the files of `repo`, and tag the commit if `tag` is given.
+fn commit(repo: &assert_fs::fixture::ChildPath, tag: Option<&str>) {
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@test.com"])
+        .args(["commit", "-m", "update"])
+        .current_dir(repo)
+        .assert()
+        .success();
+    if let Some(tag) = tag {
+        Command::new("git")
+            .args(["tag", tag])
+            .current_dir(repo)
+            .assert()
+            .success();
+    }
+}
+
+/// The config file, with the path of the hook repo and the commit hashes replaced.
+fn read_config(context: &TestContext, repo: &assert_fs::fixture::ChildPath) -> Result<String> {
+    let content = fs_err::read_to_string(context.workdir().child(".pre-commit-config.yaml"))?;
+    let content = content.replace(&repo.display().to_string(), "[HOOK_REPO]");
+    Ok(regex::Regex::new(r"[0-9a-f]{40}")?
+        .replace_all(&content, "[HASH]")
+        .into_owned())
+}
+
+fn hook_repo(context: &TestContext) -> Result<assert_fs::fixture::ChildPath> {
+    let repo = context.workdir().child("hook-repo");
+    repo.child(".pre-commit-hooks.yaml")
+        .write_str(indoc::indoc! {r"
+            - id: echo
+              name: echo
+              entry: echo
+              language: system
+        "})?;
+    Command::new("git")
+        .arg("init")
+        .current_dir(&repo)
+        .assert()
+        .success();
+    commit(&repo, Some("v1.0.0"));
+
+    repo.child("README.md").write_str("# Hooks")?;
+    commit(&repo, Some("v1.1.0"));
+    repo.child("CHANGELOG.md").write_str("# Changes")?;
+    commit(&repo, None);
+
+    Ok(repo)
+}
+
+#[test]
+fn autoupdate() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    let repo = hook_repo(&context)?;
+
+    context.write_pre_commit_config(&indoc::formatdoc! {r"
+        # The hooks of the project.
+        repos:
+          - repo: file://{}
+            rev: 'v1.0.0'  # the first release
+            hooks:
+              - id: echo
+
+          - repo: local
+            hooks:
+              - id: greet
+                name: greet
+                language: system
+                entry: echo
+    ", repo.display()});
+
+    cmd_snapshot!(context.filters(), context.autoupdate(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [file://[TEMP_DIR]/hook-repo] updating v1.0.0 -> v1.1.0
+
+    ----- stderr -----
+    Updated `.pre-commit-config.yaml`
+    ");
+    insta::assert_snapshot!(
+        read_config(&context, &repo)?,
+        @r"
+    # The hooks of the project.
+    repos:
+      - repo: file://[HOOK_REPO]
+        rev: 'v1.1.0'  # the first release
+        hooks:
+          - id: echo
+
+      - repo: local
+        hooks:
+          - id: greet
+            name: greet
+            language: system
+            entry: echo
+    "
+    );
+
+    cmd_snapshot!(context.filters(), context.autoupdate(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [file://[TEMP_DIR]/hook-repo] already up to date
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+#[test]
+fn autoupdate_freeze() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    let repo = hook_repo(&context)?;
+
+    context.write_pre_commit_config(&indoc::formatdoc! {r"
+        repos:
+          - repo: file://{}
+            rev: v1.0.0
+            hooks:
+              - id: echo
+    ", repo.display()});
+
+    let filters = [(r"[0-9a-f]{40}", "[HASH]")]
+        .into_iter()
+        .chain(context.filters())
+        .collect::<Vec<_>>();
+
+    cmd_snapshot!(filters.clone(), context.autoupdate().arg("--freeze"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [file://[TEMP_DIR]/hook-repo] updating v1.0.0 -> [HASH]
+
+    ----- stderr -----
+    Updated `.pre-commit-config.yaml`
+    ");
+    insta::assert_snapshot!(
+        read_config(&context, &repo)?,
+        @r"
+    repos:
+      - repo: file://[HOOK_REPO]
+        rev: [HASH]  # frozen: v1.1.0
+        hooks:
+          - id: echo
+    "
+    );
+
+    // Without `--freeze`, the frozen comment is removed.
+    cmd_snapshot!(filters.clone(), context.autoupdate().arg("--bleeding-edge"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [file://[TEMP_DIR]/hook-repo] updating [HASH] -> [HASH]
+
+    ----- stderr -----
+    Updated `.pre-commit-config.yaml`
+    ");
+    insta::assert_snapshot!(
+        read_config(&context, &repo)?,
+        @r"
+    repos:
+      - repo: file://[HOOK_REPO]
+        rev: [HASH]
+        hooks:
+          - id: echo
+    "
+    );
+
+    Ok(())
+}
+
+/// A rev without the hooks used in the config is not updated to.
+#[test]
+fn autoupdate_missing_hook() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    let repo = hook_repo(&context)?;
+
+    repo.child(".pre-commit-hooks.yaml")
+        .write_str(indoc::indoc! {r"
+            - id: print
+              name: print
+              entry: echo
+              language: system
+        "})?;
+    commit(&repo, Some("v2.0.0"));
+
+    context.write_pre_commit_config(&indoc::formatdoc! {r"
+        repos:
+          - repo: file://{}
+            rev: v1.0.0
+            hooks:
+              - id: echo
+    ", repo.display()});
+
+    cmd_snapshot!(context.filters(), context.autoupdate(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    [file://[TEMP_DIR]/hook-repo] failed: Cannot update to `v2.0.0`, it doesn't have the hooks `echo`
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// A rev that can't be edited in place fails its repo only.
+#[test]
+fn autoupdate_merge_key() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    let repo = hook_repo(&context)?;
+
+    context.write_pre_commit_config(&indoc::formatdoc! {r"
+        repos:
+          - repo: file://{0}
+            rev: v1.0.0
+            hooks:
+              - id: echo
+          - <<: {{ repo: 'file://{0}', rev: v1.0.0 }}
+            hooks:
+              - id: echo
+    ", repo.display()});
+
+    cmd_snapshot!(context.filters(), context.autoupdate(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    [file://[TEMP_DIR]/hook-repo] updating v1.0.0 -> v1.1.0
+    [file://[TEMP_DIR]/hook-repo] failed: Failed to find the `rev` of repo #2 in the config
+
+    ----- stderr -----
+    Updated `.pre-commit-config.yaml`
+    ");
+    insta::assert_snapshot!(read_config(&context, &repo)?, @r"
+    repos:
+      - repo: file://[HOOK_REPO]
+        rev: v1.1.0
+        hooks:
+          - id: echo
+      - <<: { repo: 'file://[HOOK_REPO]', rev: v1.0.0 }
+        hooks:
+          - id: echo
+    ");
+
+    Ok(())
+}
+
+/// The config is left unchanged if editing a rev changes more than the rev.
+#[test]
+fn autoupdate_alias() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    let repo = hook_repo(&context)?;
+
+    context.write_pre_commit_config(&indoc::formatdoc! {r"
+        repos:
+          - repo: file://{}
+            rev: &rev v1.0.0
+            hooks:
+              - id: echo
+                args: [*rev]
+    ", repo.display()});
+
+    cmd_snapshot!(context.filters(), context.autoupdate(), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+    [file://[TEMP_DIR]/hook-repo] updating v1.0.0 -> v1.1.0
+
+    ----- stderr -----
+    error: The updated config doesn't read as expected, `.pre-commit-config.yaml` is left unchanged
+    ");
+    insta::assert_snapshot!(read_config(&context, &repo)?, @r"
+    repos:
+      - repo: file://[HOOK_REPO]
+        rev: &rev v1.0.0
+        hooks:
+          - id: echo
+            args: [*rev]
+    ");
+
+    Ok(())
+}
@@ -116,6 +116,11 @@ impl TestContext {
         let mut cmd = Command::new(bin);
         cmd.current_dir(self.workdir());
         cmd.env("PREFLIGIT_HOME", &*self.home_dir);
+        cmd.env("PREFLIGIT_USER_CONFIG", self.home_dir.join("config.toml"));
+        cmd.env_remove("COLUMNS");
+        cmd.env_remove("CI");
+        cmd.env_remove("GITHUB_ACTIONS");
+        cmd.env_remove("GITHUB_STEP_SUMMARY");
         cmd
     }
 
@@ -131,6 +136,24 @@ impl TestContext {
         command
     }
 
+    pub fn gc(&self) -> Command {
+        let mut command = self.command();
+        command.arg("gc");
+        command
+    }
+
+    pub fn import_cache(&self) -> Command {
+        let mut command = self.command();
+        command.arg("import-cache");
+        command
+    }
+
+    pub fn stats(&self) -> Command {
+        let mut command = self.command();
+        command.arg("stats");
+        command
+    }
+
     pub fn validate_config(&self) -> Command {
         let mut command = self.command();
         command.arg("validate-config");
@@ -155,6 +178,36 @@ impl TestContext {
         command
     }
 
+    pub fn migrate_config(&self) -> Command {
+        let mut command = self.command();
+        command.arg("migrate-config");
+        command
+    }
+
+    pub fn lint_config(&self) -> Command {
+        let mut command = self.command();
+        command.arg("config").arg("lint");
+        command
+    }
+
+    pub fn autoupdate(&self) -> Command {
+        let mut command = self.command();
+        command.arg("autoupdate");
+        command
+    }
+
+    pub fn resolve_config(&self) -> Command {
+        let mut command = self.command();
+        command.arg("config").arg("resolve");
+        command
+    }
+
+    pub fn schema(&self) -> Command {
+        let mut command = self.command();
+        command.arg("schema");
+        command
+    }
+
     pub fn sample_config(&self) -> Command {
         let mut command = self.command();
         command.arg("sample-config");
@@ -177,6 +230,10 @@ impl TestContext {
         &self.temp_dir
     }
 
+    pub fn home_dir(&self) -> &ChildPath {
+        &self.home_dir
+    }
+
     /// Initialize a sample project for prefligit.
     pub fn init_project(&self) {
         Command::new("git")
@@ -0,0 +1,229 @@
+use assert_cmd::assert::OutputAssertExt;
+use assert_fs::prelude::*;
+
+use crate::common::{cmd_snapshot, TestContext};
+
+mod common;
+
+/// The number of repos or environments in the store.
+fn entries(context: &TestContext, dir: &str) -> usize {
+    fs_err::read_dir(context.home_dir().child(dir).path())
+        .map(|entries| {
+            entries
+                .filter(|entry| entry.as_ref().is_ok_and(|entry| entry.path().is_dir()))
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+/// Repos no config references are removed, with the environments only they use.
+#[test]
+fn gc() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context
+        .workdir()
+        .child("hooks/.pre-commit-hooks.yaml")
+        .write_str(indoc::indoc! {r"
+            - id: linked
+              name: linked
+              entry: linked
+              language: node
+              additional_dependencies: [prettier]
+        "})?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: ./hooks
+            hooks:
+              - id: linked
+          - repo: local
+            hooks:
+              - id: local
+                name: local
+                entry: local
+                language: node
+    "});
+    context.git_add(".");
+    context.run().assert().success();
+    assert_eq!(entries(&context, "repos"), 2);
+    assert_eq!(entries(&context, "envs"), 2);
+
+    // Everything is still used.
+    cmd_snapshot!(context.filters(), context.gc(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    0 repo(s) and 0 environment(s) removed
+
+    ----- stderr -----
+    ");
+
+    // The config no longer uses the linked repo.
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: local
+                name: local
+                entry: local
+                language: node
+    "});
+    cmd_snapshot!(context.filters(), context.gc(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    1 repo(s) and 1 environment(s) removed
+
+    ----- stderr -----
+    ");
+    assert_eq!(entries(&context, "repos"), 1);
+    assert_eq!(entries(&context, "envs"), 1);
+
+    // Configs that are gone don't keep anything.
+    fs_err::remove_file(context.workdir().child(".pre-commit-config.yaml").path())?;
+    cmd_snapshot!(context.filters(), context.gc(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    1 repo(s) and 1 environment(s) removed
+
+    ----- stderr -----
+    ");
+    assert_eq!(entries(&context, "repos"), 0);
+    assert_eq!(entries(&context, "envs"), 0);
+
+    Ok(())
+}
+
+/// Runs exceeding the maximum store size remove the repos least recently used, but not the
+/// ones of the config being run.
+#[test]
+fn max_store_size() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context
+        .workdir()
+        .child("hooks/.pre-commit-hooks.yaml")
+        .write_str(indoc::indoc! {r"
+            - id: linked
+              name: linked
+              entry: linked
+              language: node
+              additional_dependencies: [prettier]
+        "})?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: ./hooks
+            hooks:
+              - id: linked
+          - repo: local
+            hooks:
+              - id: local
+                name: local
+                entry: local
+                language: node
+    "});
+    context.git_add(".");
+    context.run().assert().success();
+    assert_eq!(entries(&context, "repos"), 2);
+    assert_eq!(entries(&context, "envs"), 2);
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: local
+                name: local
+                entry: local
+                language: node
+    "});
+    context
+        .home_dir()
+        .child("config.toml")
+        .write_str("max_store_size = 0")?;
+    context.git_add(".");
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    local....................................................................Passed
+
+    ----- stderr -----
+    Removed `file://[TEMP_DIR]/hooks/` from the store to keep it under 0 MiB (1 environment(s), 0.0 MiB)
+    ");
+    assert_eq!(entries(&context, "repos"), 1);
+    assert_eq!(entries(&context, "envs"), 1);
+
+    Ok(())
+}
+
+/// The repos of other configs are kept while the configs exist.
+#[test]
+fn max_store_size_other_config() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    for (dir, dependency) in [("hooks", "prettier"), ("other-hooks", "eslint")] {
+        context
+            .workdir()
+            .child(dir)
+            .child(".pre-commit-hooks.yaml")
+            .write_str(&format!(
+                "- id: linked\n  name: linked\n  entry: linked\n  language: node\n  additional_dependencies: [{dependency}]\n"
+            ))?;
+    }
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: ./hooks
+            hooks:
+              - id: linked
+    "});
+    context
+        .workdir()
+        .child("other.yaml")
+        .write_str(indoc::indoc! {r"
+            repos:
+              - repo: ./other-hooks
+                hooks:
+                  - id: linked
+        "})?;
+    context.git_add(".");
+    context.run().assert().success();
+    context.run().arg("-c").arg("other.yaml").assert().success();
+    assert_eq!(entries(&context, "repos"), 2);
+    assert_eq!(entries(&context, "envs"), 2);
+
+    context
+        .home_dir()
+        .child("config.toml")
+        .write_str("max_store_size = 0")?;
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    linked...................................................................Passed
+
+    ----- stderr -----
+    ");
+    assert_eq!(entries(&context, "repos"), 2);
+    assert_eq!(entries(&context, "envs"), 2);
+
+    // Once the other config is gone, its repo is removed.
+    fs_err::remove_file(context.workdir().child("other.yaml").path())?;
+    context.git_add(".");
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    linked...................................................................Passed
+
+    ----- stderr -----
+    Removed `file://[TEMP_DIR]/other-hooks/` from the store to keep it under 0 MiB (1 environment(s), 0.0 MiB)
+    ");
+    assert_eq!(entries(&context, "repos"), 1);
+    assert_eq!(entries(&context, "envs"), 1);
+
+    Ok(())
+}
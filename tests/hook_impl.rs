@@ -1,5 +1,7 @@
 use std::process::Command;
 
+use assert_cmd::assert::OutputAssertExt;
+use assert_fs::fixture::{FileWriteStr, PathChild};
 use common::TestContext;
 use indoc::indoc;
 
@@ -56,3 +58,328 @@ fn hook_impl() {
       .pre-commit-config.yaml
     "#);
 }
+
+#[test]
+fn allow_missing_config() {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+
+    context
+        .workdir()
+        .child("file.txt")
+        .write_str("Hello, world!\n")
+        .unwrap();
+    context.git_add(".");
+
+    // Without `--allow-missing-config`, a missing config blocks the commit.
+    context.install().assert().success();
+
+    let mut commit = Command::new("git");
+    commit
+        .arg("commit")
+        .arg("-q")
+        .current_dir(context.workdir())
+        .arg("-m")
+        .arg("Initial commit");
+
+    cmd_snapshot!(context.filters(), commit, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Config file not found: .pre-commit-config.yaml
+    - To temporarily silence this, run `PRE_COMMIT_ALLOW_NO_CONFIG=1 git ...`
+    - To permanently silence this, install hooks with the `--allow-missing-config` flag
+    - To uninstall hooks, run `prefligit uninstall`
+    ");
+
+    // An empty `PRE_COMMIT_ALLOW_NO_CONFIG` is the same as unset.
+    let mut commit = Command::new("git");
+    commit
+        .arg("commit")
+        .arg("-q")
+        .current_dir(context.workdir())
+        .env("PRE_COMMIT_ALLOW_NO_CONFIG", "")
+        .arg("-m")
+        .arg("Initial commit");
+
+    cmd_snapshot!(context.filters(), commit, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Config file not found: .pre-commit-config.yaml
+    - To temporarily silence this, run `PRE_COMMIT_ALLOW_NO_CONFIG=1 git ...`
+    - To permanently silence this, install hooks with the `--allow-missing-config` flag
+    - To uninstall hooks, run `prefligit uninstall`
+    ");
+
+    // `PRE_COMMIT_ALLOW_NO_CONFIG` silences the error temporarily.
+    let mut commit = Command::new("git");
+    commit
+        .arg("commit")
+        .arg("-q")
+        .current_dir(context.workdir())
+        .env("PRE_COMMIT_ALLOW_NO_CONFIG", "1")
+        .arg("-m")
+        .arg("Initial commit");
+
+    cmd_snapshot!(context.filters(), commit, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    `.pre-commit-config.yaml` config file not found. Skipping `prefligit`.
+    ");
+
+    // With `--allow-missing-config`, the hook script skips the run.
+    context
+        .install()
+        .arg("--allow-missing-config")
+        .arg("--overwrite")
+        .assert()
+        .success();
+
+    context
+        .workdir()
+        .child("file.txt")
+        .write_str("Hello, world again!\n")
+        .unwrap();
+    context.git_add(".");
+
+    let mut commit = Command::new("git");
+    commit
+        .arg("commit")
+        .arg("-q")
+        .current_dir(context.workdir())
+        .arg("-m")
+        .arg("Second commit");
+
+    cmd_snapshot!(context.filters(), commit, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    `.pre-commit-config.yaml` config file not found. Skipping `prefligit`.
+    ");
+}
+
+/// The hook script falls back to `PATH` when the installed binary no longer exists.
+#[cfg(unix)]
+#[test]
+fn binary_not_found() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+
+    context.write_pre_commit_config(indoc! { r"
+        repos:
+        - repo: local
+          hooks:
+           - id: fail
+             name: fail
+             language: fail
+             entry: always fail
+             always_run: true
+    "});
+    context.git_add(".");
+    context.install().assert().success();
+
+    // Pretend the binary was moved after installing the hook.
+    let hook = context.workdir().child(".git/hooks/pre-commit");
+    let current_exe = assert_cmd::cargo::cargo_bin("prefligit");
+    let script = context
+        .read(".git/hooks/pre-commit")
+        .replace(&*current_exe.to_string_lossy(), "/nonexistent/prefligit");
+    hook.write_str(&script)?;
+
+    // Found in `PATH`.
+    let path = std::env::join_paths(
+        std::iter::once(current_exe.parent().unwrap().to_path_buf())
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )?;
+    let mut commit = Command::new("git");
+    commit
+        .arg("commit")
+        .arg("-q")
+        .current_dir(context.workdir())
+        .env("PATH", path)
+        .arg("-m")
+        .arg("Initial commit");
+
+    cmd_snapshot!(context.filters(), commit, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
+      always fail
+
+      .pre-commit-config.yaml
+    ");
+
+    // Not found anywhere.
+    let mut commit = Command::new("git");
+    commit
+        .arg("commit")
+        .arg("-q")
+        .current_dir(context.workdir())
+        .env("PATH", "/usr/bin:/bin")
+        .arg("-m")
+        .arg("Initial commit");
+
+    cmd_snapshot!(context.filters(), commit, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    `prefligit` not found at /nonexistent/prefligit or in PATH.
+    hint: install it with `pip install prefligit` or see https://github.com/j178/prefligit#installation
+    ");
+
+    Ok(())
+}
+
+/// Installed hook scripts are run by the daemon of the repo when it is running.
+#[cfg(unix)]
+#[test]
+fn daemon() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc! { r"
+        repos:
+        - repo: local
+          hooks:
+           - id: fail
+             name: fail
+             language: fail
+             entry: always fail
+             always_run: true
+           - id: env
+             name: env
+             language: system
+             entry: printenv CLIENT_VAR
+             always_run: true
+             verbose: true
+    "});
+    context.git_add(".");
+
+    let mut daemon = context
+        .command()
+        .arg("daemon")
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+
+    // Wait for the daemon to listen.
+    let sockets = context.home_dir().child("daemon");
+    for _ in 0..100 {
+        if sockets.exists() && std::fs::read_dir(&sockets)?.next().is_some() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+
+    let hook_impl = || {
+        context
+            .command()
+            .args([
+                "hook-impl",
+                "--hook-type",
+                "pre-commit",
+                "--hook-dir",
+                ".git/hooks",
+            ])
+            .arg("-v")
+            .env("CLIENT_VAR", "from the client")
+            .output()
+    };
+    let output = hook_impl()?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains(
+        "fail.....................................................................Failed"
+    ));
+    assert!(stdout.contains("always fail"));
+    // Hooks run with the environment of the hook script.
+    assert!(stdout.contains("from the client"));
+    assert!(stderr.contains("Forwarding to daemon"));
+
+    // A changed config is loaded again.
+    context.write_pre_commit_config(indoc! { r"
+        repos:
+        - repo: local
+          hooks:
+           - id: pass
+             name: pass
+             language: system
+             entry: 'true'
+             always_run: true
+    "});
+    context.git_add(".");
+    let output = hook_impl()?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout.contains(
+        "pass.....................................................................Passed"
+    ));
+
+    // The files are listed from the index of the client, like the temporary one of
+    // `git commit -a`.
+    context.write_pre_commit_config(indoc! { r"
+        repos:
+        - repo: local
+          hooks:
+           - id: echo
+             name: echo
+             language: system
+             entry: echo
+             verbose: true
+    "});
+    context.git_add(".");
+    context.workdir().child("a.txt").write_str("a")?;
+    fs_err::copy(
+        context.workdir().join(".git/index"),
+        context.workdir().join(".git/other-index"),
+    )?;
+    Command::new("git")
+        .args(["add", "a.txt"])
+        .env("GIT_INDEX_FILE", ".git/other-index")
+        .current_dir(context.workdir())
+        .assert()
+        .success();
+
+    let output = hook_impl()?;
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("a.txt"));
+    let output = context
+        .command()
+        .args([
+            "hook-impl",
+            "--hook-type",
+            "pre-commit",
+            "--hook-dir",
+            ".git/hooks",
+        ])
+        .env("GIT_INDEX_FILE", ".git/other-index")
+        .output()?;
+    daemon.kill()?;
+    daemon.wait()?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{stdout}");
+    assert!(stdout.contains("a.txt"), "{stdout}");
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::process::Command;
+
+use assert_cmd::assert::OutputAssertExt;
+use assert_fs::prelude::*;
+
+use crate::common::{cmd_snapshot, TestContext};
+
+mod common;
+
+/// Repos cloned by the Python pre-commit are used without cloning them again.
+#[test]
+fn import_cache() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    // A pre-commit cache with a repo, its copy for additional dependencies and the local repo.
+    let cache = context.workdir().child("pre-commit-cache");
+    let repo = cache.child("repo1234");
+    repo.child(".pre-commit-hooks.yaml")
+        .write_str(indoc::indoc! {r"
+        - id: echo
+          name: echo
+          entry: echo
+          language: system
+    "})?;
+    repo.child("py_env-python3/bin").create_dir_all()?;
+    Command::new("git")
+        .arg("init")
+        .current_dir(&repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["add", ".pre-commit-hooks.yaml"])
+        .current_dir(&repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@test.com"])
+        .args(["commit", "-m", "init"])
+        .current_dir(&repo)
+        .assert()
+        .success();
+
+    let conn = rusqlite::Connection::open(cache.child("db.db").path())?;
+    conn.execute_batch(indoc::indoc! {"
+        CREATE TABLE repos (repo TEXT NOT NULL, ref TEXT NOT NULL, path TEXT NOT NULL, PRIMARY KEY (repo, ref));
+        CREATE TABLE configs (path TEXT NOT NULL, PRIMARY KEY (path));
+    "})?;
+    let config = context.workdir().child(".pre-commit-config.yaml");
+    for (name, path) in [
+        ("https://github.com/example/hook-repo", repo.path()),
+        ("https://github.com/example/hook-repo:black", repo.path()),
+        ("local", cache.child("repo5678").path()),
+    ] {
+        conn.execute(
+            "INSERT INTO repos (repo, ref, path) VALUES (?, 'v1.0.0', ?)",
+            [name, &path.to_string_lossy()],
+        )?;
+    }
+    conn.execute(
+        "INSERT INTO configs (path) VALUES (?)",
+        [config.to_string_lossy()],
+    )?;
+    drop(conn);
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: https://github.com/example/hook-repo
+            rev: v1.0.0
+            hooks:
+              - id: echo
+                always_run: true
+    "});
+    context.git_add(".pre-commit-config.yaml");
+
+    cmd_snapshot!(context.filters(), context.import_cache().arg("--from").arg(&*cache), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Imported https://github.com/example/hook-repo@v1.0.0
+    Imported 1 repo(s) from `pre-commit-cache`
+
+    ----- stderr -----
+    ");
+
+    // The environments installed by pre-commit are left out.
+    let imported = fs_err::read_dir(context.home_dir().child("repos").path())?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .find(|path| path.as_ref().is_ok_and(|path| path.is_dir()))
+        .expect("repo imported")?;
+    assert!(imported.join(".pre-commit-hooks.yaml").is_file());
+    assert!(!imported.join("py_env-python3").exists());
+
+    // No cloning.
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    echo.....................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    // Imported repos are kept by the configs of pre-commit.
+    cmd_snapshot!(context.filters(), context.gc(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    0 repo(s) and 0 environment(s) removed
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
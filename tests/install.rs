@@ -1,6 +1,8 @@
+use std::process::Command;
+
 use assert_cmd::assert::OutputAssertExt;
 use assert_fs::assert::PathAssert;
-use assert_fs::fixture::{FileWriteStr, PathChild};
+use assert_fs::fixture::{FileWriteStr, PathChild, PathCreateDir};
 use insta::assert_snapshot;
 use predicates::prelude::predicate;
 
@@ -27,17 +29,25 @@ fn install() -> anyhow::Result<()> {
         { filters => context.filters() },
         {
             assert_snapshot!(context.read(".git/hooks/pre-commit"), @r##"
-            #!/usr/bin/env bash
+            #!/bin/sh
             # File generated by prefligit: https://github.com/j178/prefligit
-            # ID: 182c10f181da4464a3eec51b83331688
-
-            ARGS=(hook-impl --hook-type=pre-commit)
+            # ID: 0e1f8a7c2b9d4c6e8f3a5b7d9c1e2f40
 
             HERE="$(cd "$(dirname "$0")" && pwd)"
-            ARGS+=(--hook-dir "$HERE" -- "$@")
-            PREFLIGIT="[CURRENT_EXE]"
-
-            exec "$PREFLIGIT" "${ARGS[@]}"
+            PREFLIGIT=[CURRENT_EXE]
+
+            # The binary may have been moved or upgraded since the hook was installed.
+            if [ ! -x "$PREFLIGIT" ]; then
+                if command -v prefligit > /dev/null 2>&1; then
+                    PREFLIGIT="prefligit"
+                else
+                    echo "\`prefligit\` not found at $PREFLIGIT or in PATH." 1>&2
+                    echo "hint: install it with \`pip install prefligit\` or see https://github.com/j178/prefligit#installation" 1>&2
+                    exit 1
+                fi
+            fi
+
+            exec "$PREFLIGIT" hook-impl --hook-type=pre-commit --config=.pre-commit-config.yaml --hook-dir "$HERE" -- "$@"
             "##);
         }
     );
@@ -62,17 +72,25 @@ fn install() -> anyhow::Result<()> {
         { filters => context.filters() },
         {
             assert_snapshot!(context.read(".git/hooks/pre-commit"), @r##"
-            #!/usr/bin/env bash
+            #!/bin/sh
             # File generated by prefligit: https://github.com/j178/prefligit
-            # ID: 182c10f181da4464a3eec51b83331688
-
-            ARGS=(hook-impl --hook-type=pre-commit)
+            # ID: 0e1f8a7c2b9d4c6e8f3a5b7d9c1e2f40
 
             HERE="$(cd "$(dirname "$0")" && pwd)"
-            ARGS+=(--hook-dir "$HERE" -- "$@")
-            PREFLIGIT="[CURRENT_EXE]"
-
-            exec "$PREFLIGIT" "${ARGS[@]}"
+            PREFLIGIT=[CURRENT_EXE]
+
+            # The binary may have been moved or upgraded since the hook was installed.
+            if [ ! -x "$PREFLIGIT" ]; then
+                if command -v prefligit > /dev/null 2>&1; then
+                    PREFLIGIT="prefligit"
+                else
+                    echo "\`prefligit\` not found at $PREFLIGIT or in PATH." 1>&2
+                    echo "hint: install it with \`pip install prefligit\` or see https://github.com/j178/prefligit#installation" 1>&2
+                    exit 1
+                fi
+            fi
+
+            exec "$PREFLIGIT" hook-impl --hook-type=pre-commit --config=.pre-commit-config.yaml --hook-dir "$HERE" -- "$@"
             "##);
         }
     );
@@ -86,17 +104,25 @@ fn install() -> anyhow::Result<()> {
         { filters => context.filters() },
         {
             assert_snapshot!(context.read(".git/hooks/post-commit"), @r##"
-            #!/usr/bin/env bash
+            #!/bin/sh
             # File generated by prefligit: https://github.com/j178/prefligit
-            # ID: 182c10f181da4464a3eec51b83331688
-
-            ARGS=(hook-impl --hook-type=post-commit)
+            # ID: 0e1f8a7c2b9d4c6e8f3a5b7d9c1e2f40
 
             HERE="$(cd "$(dirname "$0")" && pwd)"
-            ARGS+=(--hook-dir "$HERE" -- "$@")
-            PREFLIGIT="[CURRENT_EXE]"
-
-            exec "$PREFLIGIT" "${ARGS[@]}"
+            PREFLIGIT=[CURRENT_EXE]
+
+            # The binary may have been moved or upgraded since the hook was installed.
+            if [ ! -x "$PREFLIGIT" ]; then
+                if command -v prefligit > /dev/null 2>&1; then
+                    PREFLIGIT="prefligit"
+                else
+                    echo "\`prefligit\` not found at $PREFLIGIT or in PATH." 1>&2
+                    echo "hint: install it with \`pip install prefligit\` or see https://github.com/j178/prefligit#installation" 1>&2
+                    exit 1
+                fi
+            fi
+
+            exec "$PREFLIGIT" hook-impl --hook-type=post-commit --config=.pre-commit-config.yaml --hook-dir "$HERE" -- "$@"
             "##);
         }
     );
@@ -118,17 +144,25 @@ fn install() -> anyhow::Result<()> {
         { filters => context.filters() },
         {
             assert_snapshot!(context.read(".git/hooks/pre-commit"), @r##"
-            #!/usr/bin/env bash
+            #!/bin/sh
             # File generated by prefligit: https://github.com/j178/prefligit
-            # ID: 182c10f181da4464a3eec51b83331688
-
-            ARGS=(hook-impl --hook-type=pre-commit)
+            # ID: 0e1f8a7c2b9d4c6e8f3a5b7d9c1e2f40
 
             HERE="$(cd "$(dirname "$0")" && pwd)"
-            ARGS+=(--hook-dir "$HERE" -- "$@")
-            PREFLIGIT="[CURRENT_EXE]"
-
-            exec "$PREFLIGIT" "${ARGS[@]}"
+            PREFLIGIT=[CURRENT_EXE]
+
+            # The binary may have been moved or upgraded since the hook was installed.
+            if [ ! -x "$PREFLIGIT" ]; then
+                if command -v prefligit > /dev/null 2>&1; then
+                    PREFLIGIT="prefligit"
+                else
+                    echo "\`prefligit\` not found at $PREFLIGIT or in PATH." 1>&2
+                    echo "hint: install it with \`pip install prefligit\` or see https://github.com/j178/prefligit#installation" 1>&2
+                    exit 1
+                fi
+            fi
+
+            exec "$PREFLIGIT" hook-impl --hook-type=pre-commit --config=.pre-commit-config.yaml --hook-dir "$HERE" -- "$@"
             "##);
         }
     );
@@ -136,17 +170,25 @@ fn install() -> anyhow::Result<()> {
         { filters => context.filters() },
         {
             assert_snapshot!(context.read(".git/hooks/post-commit"), @r##"
-            #!/usr/bin/env bash
+            #!/bin/sh
             # File generated by prefligit: https://github.com/j178/prefligit
-            # ID: 182c10f181da4464a3eec51b83331688
-
-            ARGS=(hook-impl --hook-type=post-commit)
+            # ID: 0e1f8a7c2b9d4c6e8f3a5b7d9c1e2f40
 
             HERE="$(cd "$(dirname "$0")" && pwd)"
-            ARGS+=(--hook-dir "$HERE" -- "$@")
-            PREFLIGIT="[CURRENT_EXE]"
-
-            exec "$PREFLIGIT" "${ARGS[@]}"
+            PREFLIGIT=[CURRENT_EXE]
+
+            # The binary may have been moved or upgraded since the hook was installed.
+            if [ ! -x "$PREFLIGIT" ]; then
+                if command -v prefligit > /dev/null 2>&1; then
+                    PREFLIGIT="prefligit"
+                else
+                    echo "\`prefligit\` not found at $PREFLIGIT or in PATH." 1>&2
+                    echo "hint: install it with \`pip install prefligit\` or see https://github.com/j178/prefligit#installation" 1>&2
+                    exit 1
+                fi
+            fi
+
+            exec "$PREFLIGIT" hook-impl --hook-type=post-commit --config=.pre-commit-config.yaml --hook-dir "$HERE" -- "$@"
             "##);
         }
     );
@@ -250,3 +292,182 @@ fn init_template_dir() {
     try `git config --global init.templateDir '.git'`?
     "#);
 }
+
+/// A native shim runs the hooks without a shell.
+#[test]
+fn install_native() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+        - repo: local
+          hooks:
+           - id: fail
+             name: fail
+             language: fail
+             entry: always fail
+             always_run: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.install().arg("--native"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    prefligit installed at .git/hooks/pre-commit
+
+    ----- stderr -----
+    ");
+
+    // The hook is an executable, not a script.
+    let hook = fs_err::read(context.workdir().join(".git/hooks/pre-commit"))?;
+    assert!(!hook.starts_with(b"#!"));
+    insta::with_settings!(
+        { filters => context.filters() },
+        {
+            assert_snapshot!(context.read(".git/hooks/pre-commit.prefligit.json"), @r#"
+            {
+              "id": "0e1f8a7c2b9d4c6e8f3a5b7d9c1e2f40",
+              "prefligit": "[CURRENT_EXE]",
+              "args": [
+                "hook-impl",
+                "--hook-type=pre-commit",
+                "--config=.pre-commit-config.yaml"
+              ]
+            }
+            "#);
+        }
+    );
+
+    let mut commit = Command::new("git");
+    commit
+        .args(["commit", "-m", "Initial commit"])
+        .current_dir(context.workdir());
+    cmd_snapshot!(context.filters(), commit, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
+      always fail
+
+      .pre-commit-config.yaml
+    ");
+
+    cmd_snapshot!(context.filters(), context.uninstall(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Uninstalled pre-commit
+
+    ----- stderr -----
+    ");
+    context
+        .workdir()
+        .child(".git/hooks/pre-commit.prefligit.json")
+        .assert(predicate::path::missing());
+
+    Ok(())
+}
+
+/// Hooks aren't installed in the repo when git runs them from another directory.
+#[test]
+fn hooks_path_set() {
+    let context = TestContext::new();
+    context.init_project();
+    Command::new("git")
+        .args(["config", "core.hooksPath", "hooks"])
+        .current_dir(context.workdir())
+        .assert()
+        .success();
+
+    cmd_snapshot!(context.filters(), context.install(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Cowardly refusing to install hooks with `core.hooksPath` set.
+    hint: `git config --unset-all core.hooksPath` to fix this.
+    ");
+}
+
+/// `uninstall --purge` removes the cached repos only used by this repository.
+#[test]
+fn uninstall_purge() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    // A hook repository served from the local filesystem.
+    let hook_repo = context.workdir().child("hook-repo");
+    hook_repo.create_dir_all()?;
+    hook_repo
+        .child(".pre-commit-hooks.yaml")
+        .write_str(indoc::indoc! {r"
+            - id: echo
+              name: echo
+              entry: echo
+              language: system
+        "})?;
+    Command::new("git")
+        .arg("init")
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@test.com"])
+        .args(["commit", "-m", "init"])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["tag", "v1.0.0"])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+
+    context.write_pre_commit_config(&indoc::formatdoc! {r"
+        repos:
+          - repo: file://{}
+            rev: v1.0.0
+            hooks:
+              - id: echo
+    ", hook_repo.display()});
+    context.git_add(".pre-commit-config.yaml");
+
+    context.install().assert().success();
+    context.run().assert().success();
+
+    cmd_snapshot!(context.filters(), context.uninstall().arg("--purge"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Uninstalled pre-commit
+    Removed cached repo file://[TEMP_DIR]/hook-repo@v1.0.0
+
+    ----- stderr -----
+    ");
+
+    // Nothing left to remove.
+    context.install().assert().success();
+    cmd_snapshot!(context.filters(), context.uninstall().arg("--purge"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Uninstalled pre-commit
+    No cached repos to remove
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
@@ -29,5 +29,7 @@ fn docker() {
       Hello, world! .pre-commit-config.yaml
 
     ----- stderr -----
+    Cloning https://github.com/j178/pre-commit-docker-hooks@master
+    Installing hello-world
     "#);
 }
@@ -78,3 +78,56 @@ fn docker_image() -> Result<()> {
     "#);
     Ok(())
 }
+
+/// The container of a hook that times out is killed along with `docker run`.
+#[test]
+fn timeout() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    Command::new("docker")
+        .args(["pull", "alpine:3.20"])
+        .assert()
+        .success();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: sleepy
+                name: sleepy
+                language: docker_image
+                entry: alpine:3.20 sleep 300
+                pass_filenames: false
+                always_run: true
+                timeout: 2
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    sleepy...................................................................Failed
+    - hook id: sleepy
+    - timed out after [TIME]
+
+    ----- stderr -----
+    ");
+
+    // The container is killed in the background.
+    let running = || {
+        let output = Command::new("docker")
+            .args(["ps", "--quiet", "--filter", "ancestor=alpine:3.20"])
+            .output()
+            .unwrap();
+        !output.stdout.is_empty()
+    };
+    for _ in 0..20 {
+        if !running() {
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+    anyhow::bail!("The container of the hook is still running");
+}
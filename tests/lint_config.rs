@@ -0,0 +1,100 @@
+use anyhow::Result;
+use assert_fs::fixture::{FileWriteStr, PathChild};
+
+use crate::common::{cmd_snapshot, TestContext};
+
+mod common;
+
+#[test]
+fn lint_config() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context
+        .workdir()
+        .child("src/main.py")
+        .write_str("print('Hello')")?;
+    context
+        .workdir()
+        .child("docs/index.md")
+        .write_str("# Hello")?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        default_stages: [commit]
+        files: ^src/
+        repos:
+          - repo: https://github.com/pre-commit/pre-commit-hooks
+            rev: main
+            hooks:
+              - id: trailing-whitespace
+                stages: [commit, push]
+              - id: trailing-whitespace
+                args: [--markdown-linebreak-ext=md]
+          - repo: https://github.com/psf/black
+            rev: 24.1.0
+            hooks:
+              - id: black
+          - repo: local
+            hooks:
+              - id: markdownlint
+                name: markdownlint
+                language: node
+                entry: markdownlint
+                files: \.md$
+                additional_dependencies: [markdownlint-cli, '@scope/pkg@1.0.0']
+              - id: mypy
+                name: mypy
+                language: python
+                entry: mypy
+                additional_dependencies: ['types-requests>=2', 'types-pyyaml==6.0.12']
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.lint_config(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    warning: `default_stages` uses the deprecated stage `commit`
+      help: use `pre-commit` instead
+    warning: Hook `trailing-whitespace` uses the deprecated stage `commit`
+      help: use `pre-commit` instead
+    warning: Hook `trailing-whitespace` uses the deprecated stage `push`
+      help: use `pre-push` instead
+    warning: Repo `https://github.com/pre-commit/pre-commit-hooks` uses the mutable rev `main`
+      help: pin it to a tag or a commit, `prefligit autoupdate` updates it to the latest tag
+    warning: Hook id `trailing-whitespace` is used by 2 hooks
+      help: give each of them a distinct `alias`, to run them separately
+    warning: Hook `markdownlint` matches no files after the top-level `files` and `exclude`
+      help: widen the top-level patterns to include the files of the hook, or remove the hook
+    warning: Hook `markdownlint` has the unpinned dependency `markdownlint-cli`
+      help: pin it like `markdownlint-cli@<version>`, to get the same environment every time
+    warning: Hook `mypy` has the unpinned dependency `types-requests>=2`
+      help: pin it like `types-requests==<version>`, to get the same environment every time
+
+    ----- stderr -----
+    warning: The stage `commit` at the top level of `.pre-commit-config.yaml` is deprecated, use `pre-commit` instead
+    warning: The stage `commit` in hook `trailing-whitespace` of `.pre-commit-config.yaml` is deprecated, use `pre-commit` instead
+    warning: The stage `push` in hook `trailing-whitespace` of `.pre-commit-config.yaml` is deprecated, use `pre-push` instead
+    Found 8 issues in `.pre-commit-config.yaml`
+    ");
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: https://github.com/psf/black
+            rev: 24.1.0
+            hooks:
+              - id: black
+                stages: [pre-commit]
+    "});
+
+    cmd_snapshot!(context.filters(), context.lint_config(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    No issues found in `.pre-commit-config.yaml`
+    ");
+
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use assert_fs::fixture::PathChild;
+
+use crate::common::{cmd_snapshot, TestContext};
+
+mod common;
+
+#[test]
+fn migrate_config() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        fail_fast: true
+        repos:
+          - repo: local
+            hooks:
+              - id: greet
+                name: greet
+                language: system
+                entry: python3 -c 'print("Hello")'
+                always_run: true
+                verbose: true
+    "#});
+
+    cmd_snapshot!(context.filters(), context.migrate_config(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    Migrated `.pre-commit-config.yaml` to `.pre-commit-config.toml`
+    ");
+    insta::assert_snapshot!(
+        fs_err::read_to_string(context.workdir().child(".pre-commit-config.toml"))?,
+        @r#"
+    fail_fast = true
+
+    [[repos]]
+    repo = "local"
+
+    [[repos.hooks]]
+    id = "greet"
+    name = "greet"
+    language = "system"
+    entry = """python3 -c 'print("Hello")'"""
+    always_run = true
+    verbose = true
+    "#
+    );
+
+    // The TOML config is used when there is no YAML config.
+    context.git_add(".");
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    greet....................................................................Passed
+    - hook id: greet
+    - duration: [TIME]
+      Hello
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.migrate_config().arg("--to").arg("yaml"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    Migrated `.pre-commit-config.toml` to `.pre-commit-config.yaml`
+    ");
+    insta::assert_snapshot!(
+        fs_err::read_to_string(context.workdir().child(".pre-commit-config.yaml"))?,
+        @r#"
+    fail_fast: true
+    repos:
+    - repo: local
+      hooks:
+      - id: greet
+        name: greet
+        language: system
+        entry: python3 -c 'print("Hello")'
+        always_run: true
+        verbose: true
+    "#
+    );
+
+    Ok(())
+}
+
+/// Legacy keys and stages are renamed in place, keeping the comments and the formatting.
+#[test]
+fn migrate_legacy() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        # The hooks of the project.
+        default_stages: [commit, 'push']  # before sharing
+        repos:
+          - repo: https://github.com/pre-commit/pre-commit-hooks
+            sha: 'v5.0.0'  # the last release
+            hooks:
+              - id: trailing-whitespace
+                stages:
+                  - commit
+                  - manual
+
+          - repo: local
+            hooks:
+              - id: greet
+                name: greet
+                language: system
+                entry: echo
+                stages: [merge-commit]
+    "});
+
+    cmd_snapshot!(context.filters(), context.migrate_config().arg("--to").arg("yaml"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    Migrated the legacy keys and stages of `.pre-commit-config.yaml`
+    ");
+    insta::assert_snapshot!(
+        fs_err::read_to_string(context.workdir().child(".pre-commit-config.yaml"))?,
+        @r"
+    # The hooks of the project.
+    default_stages: [pre-commit, 'pre-push']  # before sharing
+    repos:
+      - repo: https://github.com/pre-commit/pre-commit-hooks
+        rev: 'v5.0.0'  # the last release
+        hooks:
+          - id: trailing-whitespace
+            stages:
+              - pre-commit
+              - manual
+
+      - repo: local
+        hooks:
+          - id: greet
+            name: greet
+            language: system
+            entry: echo
+            stages: [pre-merge-commit]
+    "
+    );
+
+    cmd_snapshot!(context.filters(), context.migrate_config().arg("--to").arg("yaml"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    `.pre-commit-config.yaml` is already in YAML
+    ");
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::process::Command;
+
+use assert_cmd::assert::OutputAssertExt;
+use assert_fs::prelude::*;
+
+use crate::common::{cmd_snapshot, TestContext};
+
+mod common;
+
+/// The entries in a directory of the remote cache.
+fn entries(dir: &assert_fs::fixture::ChildPath) -> usize {
+    fs_err::read_dir(dir.path())
+        .map(Iterator::count)
+        .unwrap_or(0)
+}
+
+/// Repos and environments are restored from the remote cache instead of cloned and installed.
+#[test]
+fn restore_from_remote_cache() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let hook_repo = context.workdir().child("hook-repo");
+    hook_repo
+        .child(".pre-commit-hooks.yaml")
+        .write_str(indoc::indoc! {r"
+            - id: lint
+              name: lint
+              entry: lint
+              language: node
+        "})?;
+    Command::new("git")
+        .arg("init")
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@test.com"])
+        .args(["commit", "-m", "init"])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["tag", "v1.0.0"])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+
+    let cache = context.workdir().child("cache");
+    let write_user_config = || {
+        context
+            .home_dir()
+            .child("config.toml")
+            .write_str(&indoc::formatdoc! {r#"
+                [remote_cache]
+                url = "file://{}"
+            "#, cache.display()})
+    };
+    write_user_config()?;
+    context.write_pre_commit_config(&indoc::formatdoc! {r"
+        repos:
+          - repo: file://{}
+            rev: v1.0.0
+            hooks:
+              - id: lint
+                always_run: true
+    ", hook_repo.display()});
+    context.git_add(".pre-commit-config.yaml");
+
+    context.run().assert().success();
+    assert_eq!(entries(&cache.child("repos")), 1);
+    assert_eq!(entries(&cache.child("envs")), 1);
+
+    // Without the store and the hook repo, everything comes from the cache.
+    context.clean().assert().success();
+    write_user_config()?;
+    fs_err::remove_dir_all(hook_repo.path())?;
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    lint.....................................................................Passed
+
+    ----- stderr -----
+    Cloning file://[TEMP_DIR]/hook-repo@v1.0.0
+    Installing lint
+    ");
+    assert!(context.home_dir().child("repos").path().is_dir());
+
+    // An environment that changed since it was uploaded is installed again.
+    let env = fs_err::read_dir(cache.child("envs").path())?
+        .next()
+        .expect("the environment is cached")?
+        .path();
+    fs_err::write(env.join("content").join("tampered"), "")?;
+    context.clean().assert().success();
+    write_user_config()?;
+
+    let filters: Vec<_> = context
+        .filters()
+        .into_iter()
+        .chain([
+            (r"envs/[\w-]+", "envs/[KEY]"),
+            (r"\b[0-9a-f]{64}\b", "[DIGEST]"),
+        ])
+        .collect();
+    cmd_snapshot!(filters, context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    lint.....................................................................Passed
+
+    ----- stderr -----
+    Cloning file://[TEMP_DIR]/hook-repo@v1.0.0
+    Installing lint
+    warning: Failed to restore `envs/[KEY]` from the remote cache: Digest mismatch, expected [DIGEST] but got [DIGEST]
+    ");
+    assert!(!fs_err::read_dir(context.home_dir().child("envs").path())?
+        .filter_map(Result::ok)
+        .any(|entry| entry.path().join("tampered").exists()));
+
+    Ok(())
+}
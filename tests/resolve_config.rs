@@ -0,0 +1,289 @@
+use crate::common::{cmd_snapshot, TestContext};
+
+mod common;
+
+#[test]
+fn resolve_config() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        default_stages: [pre-commit, pre-push]
+        default_language_version:
+          python: '3.12'
+        exclude: ^vendor/
+        repos:
+          - repo: local
+            hooks:
+              - id: lint
+                name: lint
+                language: python
+                entry: ruff check
+                args: [--fix]
+                env:
+                  RUFF_CACHE_DIR: .cache
+              - id: check
+                name: check
+                language: system
+                entry: make check
+                stages: [manual]
+                pass_filenames: false
+          - repo: meta
+            hooks:
+              - id: identity
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.resolve_config(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    config: .pre-commit-config.yaml
+    files: null
+    exclude: ^vendor/
+    files_glob: []
+    exclude_glob: []
+    fail_fast: false
+    hooks:
+    - id: lint
+      repo: local
+      name: lint
+      entry: ruff check
+      language: python
+      language_version: '3.12'
+      stages:
+      - pre-commit
+      - pre-push
+      files: null
+      exclude: null
+      files_glob: []
+      exclude_glob: []
+      types:
+      - file
+      types_or: []
+      exclude_types: []
+      additional_dependencies: []
+      args:
+      - --fix
+      always_run: false
+      fail_fast: false
+      pass_filenames: true
+      require_serial: false
+      verbose: false
+      timeout: null
+      retries: 0
+      depends_on: []
+      read_only: false
+      profiles: []
+      tags: []
+      env:
+        RUFF_CACHE_DIR: .cache
+      clean_env: false
+      pass_env: []
+      working_directory: null
+    - id: check
+      repo: local
+      name: check
+      entry: make check
+      language: system
+      language_version: default
+      stages:
+      - manual
+      files: null
+      exclude: null
+      files_glob: []
+      exclude_glob: []
+      types:
+      - file
+      types_or: []
+      exclude_types: []
+      additional_dependencies: []
+      args: []
+      always_run: false
+      fail_fast: false
+      pass_filenames: false
+      require_serial: false
+      verbose: false
+      timeout: null
+      retries: 0
+      depends_on: []
+      read_only: false
+      profiles: []
+      tags: []
+      env: {}
+      clean_env: false
+      pass_env: []
+      working_directory: null
+    - id: identity
+      repo: meta
+      name: identity
+      entry: ''
+      language: system
+      language_version: default
+      stages:
+      - pre-commit
+      - pre-push
+      files: null
+      exclude: null
+      files_glob: []
+      exclude_glob: []
+      types:
+      - file
+      types_or: []
+      exclude_types: []
+      additional_dependencies: []
+      args: []
+      always_run: false
+      fail_fast: false
+      pass_filenames: true
+      require_serial: false
+      verbose: true
+      timeout: null
+      retries: 0
+      depends_on: []
+      read_only: false
+      profiles: []
+      tags: []
+      env: {}
+      clean_env: false
+      pass_env: []
+      working_directory: null
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.resolve_config().arg("--format").arg("json"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {
+      "config": ".pre-commit-config.yaml",
+      "files": null,
+      "exclude": "^vendor/",
+      "files_glob": [],
+      "exclude_glob": [],
+      "fail_fast": false,
+      "hooks": [
+        {
+          "id": "lint",
+          "repo": "local",
+          "name": "lint",
+          "entry": "ruff check",
+          "language": "python",
+          "language_version": "3.12",
+          "stages": [
+            "pre-commit",
+            "pre-push"
+          ],
+          "files": null,
+          "exclude": null,
+          "files_glob": [],
+          "exclude_glob": [],
+          "types": [
+            "file"
+          ],
+          "types_or": [],
+          "exclude_types": [],
+          "additional_dependencies": [],
+          "args": [
+            "--fix"
+          ],
+          "always_run": false,
+          "fail_fast": false,
+          "pass_filenames": true,
+          "require_serial": false,
+          "verbose": false,
+          "timeout": null,
+          "retries": 0,
+          "depends_on": [],
+          "read_only": false,
+          "profiles": [],
+          "tags": [],
+          "env": {
+            "RUFF_CACHE_DIR": ".cache"
+          },
+          "clean_env": false,
+          "pass_env": [],
+          "working_directory": null
+        },
+        {
+          "id": "check",
+          "repo": "local",
+          "name": "check",
+          "entry": "make check",
+          "language": "system",
+          "language_version": "default",
+          "stages": [
+            "manual"
+          ],
+          "files": null,
+          "exclude": null,
+          "files_glob": [],
+          "exclude_glob": [],
+          "types": [
+            "file"
+          ],
+          "types_or": [],
+          "exclude_types": [],
+          "additional_dependencies": [],
+          "args": [],
+          "always_run": false,
+          "fail_fast": false,
+          "pass_filenames": false,
+          "require_serial": false,
+          "verbose": false,
+          "timeout": null,
+          "retries": 0,
+          "depends_on": [],
+          "read_only": false,
+          "profiles": [],
+          "tags": [],
+          "env": {},
+          "clean_env": false,
+          "pass_env": [],
+          "working_directory": null
+        },
+        {
+          "id": "identity",
+          "repo": "meta",
+          "name": "identity",
+          "entry": "",
+          "language": "system",
+          "language_version": "default",
+          "stages": [
+            "pre-commit",
+            "pre-push"
+          ],
+          "files": null,
+          "exclude": null,
+          "files_glob": [],
+          "exclude_glob": [],
+          "types": [
+            "file"
+          ],
+          "types_or": [],
+          "exclude_types": [],
+          "additional_dependencies": [],
+          "args": [],
+          "always_run": false,
+          "fail_fast": false,
+          "pass_filenames": true,
+          "require_serial": false,
+          "verbose": true,
+          "timeout": null,
+          "retries": 0,
+          "depends_on": [],
+          "read_only": false,
+          "profiles": [],
+          "tags": [],
+          "env": {},
+          "clean_env": false,
+          "pass_env": [],
+          "working_directory": null
+        }
+      ]
+    }
+
+    ----- stderr -----
+    "#);
+}
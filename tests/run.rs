@@ -52,6 +52,8 @@ fn run_basic() -> Result<()> {
     check json...............................................................Passed
 
     ----- stderr -----
+    Cloning https://github.com/pre-commit/pre-commit-hooks@v5.0.0
+    Installing trailing-whitespace
     "#);
 
     context.git_add(".");
@@ -121,6 +123,10 @@ fn same_repo() -> Result<()> {
     trim trailing whitespace.................................................Passed
 
     ----- stderr -----
+    Cloning https://github.com/pre-commit/pre-commit-hooks@v5.0.0
+    Cloning https://github.com/pre-commit/pre-commit-hooks@v4.6.0
+    Installing trailing-whitespace
+    Installing trailing-whitespace
     "#);
 
     Ok(())
@@ -180,6 +186,7 @@ fn local_need_install() {
     local....................................................................Passed
 
     ----- stderr -----
+    Installing local
     "#);
 }
 
@@ -369,7 +376,7 @@ fn skips() {
     trailing-whitespace......................................................Failed
     - hook id: trailing-whitespace
     - exit code: 1
-    fix end of files........................................................Skipped
+    fix end of files.......................................(skipped by SKIP)Skipped
     check json...............................................................Failed
     - hook id: check-json
     - exit code: 1
@@ -381,14 +388,42 @@ fn skips() {
     success: false
     exit_code: 1
     ----- stdout -----
-    trailing-whitespace.....................................................Skipped
-    fix end of files........................................................Skipped
+    trailing-whitespace....................................(skipped by SKIP)Skipped
+    fix end of files.......................................(skipped by SKIP)Skipped
     check json...............................................................Failed
     - hook id: check-json
     - exit code: 1
 
     ----- stderr -----
     "#);
+
+    // `--skip` is combined with `SKIP`.
+    cmd_snapshot!(context.filters(), context.run().env("SKIP", "trailing-whitespace").arg("--skip").arg("check-json"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    trailing-whitespace....................................(skipped by SKIP)Skipped
+    fix end of files.........................................................Failed
+    - hook id: end-of-file-fixer
+    - exit code: 1
+    check json...........................................(skipped by --skip)Skipped
+
+    ----- stderr -----
+    ");
+
+    // Glob patterns match hook ids.
+    cmd_snapshot!(context.filters(), context.run().env("SKIP", "*-whitespace,check-*"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    trailing-whitespace....................................(skipped by SKIP)Skipped
+    fix end of files.........................................................Failed
+    - hook id: end-of-file-fixer
+    - exit code: 1
+    check json.............................................(skipped by SKIP)Skipped
+
+    ----- stderr -----
+    ");
 }
 
 /// Test global `files`, `exclude`, and hook level `files`, `exclude`.
@@ -610,294 +645,4396 @@ fn fail_fast() {
     "#);
 }
 
-/// Run from a subdirectory. File arguments should be fixed to be relative to the root.
+/// `fail_fast` of a repo skips the remaining hooks of that repo, later repos still run.
 #[test]
-fn subdirectory() -> Result<()> {
+fn repo_fail_fast() {
     let context = TestContext::new();
     context.init_project();
 
-    let cwd = context.workdir();
-    let child = cwd.child("foo/bar/baz");
-    child.create_dir_all()?;
-
     context.write_pre_commit_config(indoc::indoc! {r#"
         repos:
           - repo: local
+            fail_fast: true
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: first
+                name: first
                 language: system
-                entry: python3 -c 'print("Hello"); exit(1)'
+                entry: python3 -c 'print("Fixing files"); exit(1)'
+                always_run: true
+              - id: second
+                name: second
+                language: system
+                entry: python3 -V
+                always_run: true
+                depends_on: [first]
+          - repo: local
+            hooks:
+              - id: third
+                name: third
+                language: system
+                entry: python3 -c 'print("Checked")'
                 always_run: true
+                verbose: true
+                depends_on: [second]
     "#});
-
     context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run().current_dir(&child).arg("--files").arg("file.txt"), @r#"
+    cmd_snapshot!(context.filters(), context.run(), @r"
     success: false
     exit_code: 1
     ----- stdout -----
-    trailing-whitespace......................................................Failed
-    - hook id: trailing-whitespace
+    first....................................................................Failed
+    - hook id: first
     - exit code: 1
-      Hello
+      Fixing files
+    third....................................................................Passed
+    - hook id: third
+    - duration: [TIME]
+      Checked
 
     ----- stderr -----
-    "#);
+    ");
 
-    Ok(())
+    cmd_snapshot!(context.filters(), context.run().arg("--parallel"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    first....................................................................Failed
+    - hook id: first
+    - exit code: 1
+      Fixing files
+    third....................................................................Passed
+    - hook id: third
+    - duration: [TIME]
+      Checked
+
+    ----- stderr -----
+    ");
 }
 
-/// Test hook `log_file` option.
+/// `system` hooks with `skip_if_missing_entry` are skipped if their executable is not installed.
 #[test]
-fn log_file() {
+fn skip_if_missing_entry() {
     let context = TestContext::new();
     context.init_project();
 
-    context.write_pre_commit_config(indoc::indoc! {r#"
+    context.write_pre_commit_config(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: missing
+                name: missing
                 language: system
-                entry: python3 -c 'print("Fixing files"); exit(1)'
+                entry: not-an-installed-tool --check
                 always_run: true
-                log_file: log.txt
-    "#});
+                skip_if_missing_entry: true
+              - id: installed
+                name: installed
+                language: system
+                entry: python3 -V
+                always_run: true
+                skip_if_missing_entry: true
+    "});
     context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run(), @r#"
-    success: false
-    exit_code: 1
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
     ----- stdout -----
-    trailing-whitespace......................................................Failed
-    - hook id: trailing-whitespace
-    - exit code: 1
+    missing................................................(entry not found)Skipped
+    - `not-an-installed-tool` is not installed
+    installed................................................................Passed
 
     ----- stderr -----
-    "#);
-
-    let log = context.read("log.txt");
-    assert_eq!(log, "Fixing files");
+    ");
 }
 
-/// Pass pre-commit environment variables to the hook.
+/// File patterns are matched like Python's `re` does.
 #[test]
-fn pass_env_vars() {
+fn python_regex() -> Result<()> {
     let context = TestContext::new();
-
     context.init_project();
 
-    context.write_pre_commit_config(indoc::indoc! {r#"
+    context.write_pre_commit_config(indoc::indoc! {r"
+        exclude: |
+          (?x)^(
+            src/src_[#]\.py
+          )\Z
         repos:
           - repo: local
             hooks:
-              - id: env-vars
-                name: Pass environment
+              - id: same-dir
+                name: same-dir
                 language: system
-                entry: python3 -c "import os, sys; print(os.getenv('PRE_COMMIT')); sys.exit(1)"
-                always_run: true
-    "#});
+                entry: python3 -c 'import sys; print(sys.argv[1:])'
+                files: ^(?P<dir>src|lib)/(?P=dir)_.*\.py\Z
+                verbose: true
+    "});
+    let cwd = context.workdir();
+    for file in [
+        "src/src_a.py",
+        "src/lib_b.py",
+        "lib/lib_c.py",
+        "src/src_#.py",
+    ] {
+        cwd.child(file).write_str("")?;
+    }
+    context.git_add(".");
 
-    cmd_snapshot!(context.filters(), context.run(), @r###"
-    success: false
-    exit_code: 1
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
     ----- stdout -----
-    Pass environment.........................................................Failed
-    - hook id: env-vars
-    - exit code: 1
-      1
+    same-dir.................................................................Passed
+    - hook id: same-dir
+    - duration: [TIME]
+      ['lib/lib_c.py', 'src/src_a.py']
 
     ----- stderr -----
-    "###);
+    ");
+
+    Ok(())
 }
 
+/// `files_glob` and `exclude_glob` select files with gitignore-style patterns.
 #[test]
-fn staged_files_only() -> Result<()> {
+fn glob_patterns() -> Result<()> {
     let context = TestContext::new();
     context.init_project();
-    context.write_pre_commit_config(indoc::indoc! {r#"
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        exclude_glob: /vendor
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: python
+                name: python
                 language: system
-                entry: python3 -c 'print(open("file.txt", "rt").read())'
+                entry: python3 -c 'import sys; print(sys.argv[1:])'
+                files_glob: ['*.py', '*.pyi']
+                exclude_glob: tests/fixtures/
                 verbose: true
-                types: [text]
-   "#});
-
-    context
-        .workdir()
-        .child("file.txt")
-        .write_str("Hello, world!")?;
+    "});
+    let cwd = context.workdir();
+    for file in [
+        "main.py",
+        "src/lib.pyi",
+        "src/vendor/six.py",
+        "vendor/six.py",
+        "tests/fixtures/bad.py",
+        "README.md",
+    ] {
+        cwd.child(file).write_str("")?;
+    }
     context.git_add(".");
 
-    // Non-staged files should be stashed and restored.
-    context
-        .workdir()
-        .child("file.txt")
-        .write_str("Hello world again!")?;
-
-    let filters: Vec<_> = context
-        .filters()
-        .into_iter()
-        .chain([(r"/\d+-\d+.patch", "/[TIME]-[PID].patch")])
-        .collect();
-
-    cmd_snapshot!(filters, context.run(), @r#"
+    cmd_snapshot!(context.filters(), context.run(), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    trailing-whitespace......................................................Passed
-    - hook id: trailing-whitespace
+    python...................................................................Passed
+    - hook id: python
     - duration: [TIME]
-      Hello, world!
+      ['main.py', 'src/lib.pyi', 'src/vendor/six.py']
 
     ----- stderr -----
-    Non-staged changes detected, saving to `[HOME]/[TIME]-[PID].patch`
-
-    Restored working tree changes from `[HOME]/[TIME]-[PID].patch`
-    "#);
-
-    let content = context.read("file.txt");
-    assert_snapshot!(content, @"Hello world again!");
+    ");
 
     Ok(())
 }
 
-#[cfg(unix)]
+/// `${VAR}` in `entry` and `args` is expanded from the `env` of the hook or the environment.
 #[test]
-fn restore_on_interrupt() -> Result<()> {
+fn expand_env() {
     let context = TestContext::new();
     context.init_project();
-    // The hook will sleep for 3 seconds.
-    context.write_pre_commit_config(indoc::indoc! {r#"
+
+    context.write_pre_commit_config(indoc::indoc! {r"
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: expand
+                name: expand
                 language: system
-                entry: python3 -c 'import time; open("out.txt", "wt").write(open("file.txt", "rt").read()); time.sleep(10)'
+                entry: python3 -c 'import sys; print(sys.argv[1:])' ${GREETING}
+                args: ['${NAME:-world}', '${FROM_HOST}', '$${NAME}', '$HOME', '${UNSET}']
+                env:
+                  GREETING: hello
+                always_run: true
+                pass_filenames: false
                 verbose: true
-                types: [text]
-   "#});
-
-    context
-        .workdir()
-        .child("file.txt")
-        .write_str("Hello, world!")?;
+              - id: literal
+                name: literal
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:])' ${GREETING}
+                expand_env: false
+                always_run: true
+                pass_filenames: false
+                verbose: true
+    "});
     context.git_add(".");
 
-    // Non-staged files should be stashed and restored.
-    context
-        .workdir()
-        .child("file.txt")
-        .write_str("Hello world again!")?;
-
-    let mut child = context.run().spawn()?;
-    let child_id = child.id();
-
-    // Send an interrupt signal to the process.
-    let handle = std::thread::spawn(move || {
-        std::thread::sleep(std::time::Duration::from_secs(1));
-        #[allow(clippy::cast_possible_wrap)]
-        unsafe {
-            libc::kill(child_id as i32, libc::SIGINT)
-        };
-    });
-
-    handle.join().unwrap();
-    child.wait()?;
-
-    let content = context.read("out.txt");
-    assert_snapshot!(content, @"Hello, world!");
-
-    let content = context.read("file.txt");
-    assert_snapshot!(content, @"Hello world again!");
+    cmd_snapshot!(context.filters(), context.run().env("FROM_HOST", "host"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    expand...................................................................Passed
+    - hook id: expand
+    - duration: [TIME]
+      ['hello', 'world', 'host', '${NAME}', '$HOME', '']
+    literal..................................................................Passed
+    - hook id: literal
+    - duration: [TIME]
+      ['${GREETING}']
 
-    Ok(())
+    ----- stderr -----
+    warning: Environment variable `UNSET` used by hook `expand` is not set, expanding to an empty string
+    ");
 }
 
-/// When in merge conflict, runs on files that have conflicts fixed.
+/// `default_language_version` applies to hooks without a `language_version`, or with `default`.
 #[test]
-fn merge_conflicts() -> Result<()> {
+fn default_language_version() {
     let context = TestContext::new();
     context.init_project();
 
-    // Create a merge conflict.
-    let cwd = context.workdir();
-    cwd.child("file.txt").write_str("Hello, world!")?;
-    context.git_add(".");
-    context.configure_git_author();
-    context.git_commit("Initial commit");
-
-    Command::new("git")
-        .arg("checkout")
-        .arg("-b")
-        .arg("feature")
-        .current_dir(cwd)
-        .assert()
-        .success();
-    cwd.child("file.txt").write_str("Hello, world again!")?;
-    context.git_add(".");
-    context.git_commit("Feature commit");
-
-    Command::new("git")
-        .arg("checkout")
-        .arg("master")
-        .current_dir(cwd)
-        .assert()
-        .success();
-    cwd.child("file.txt")
-        .write_str("Hello, world from master!")?;
-    context.git_add(".");
-    context.git_commit("Master commit");
-
-    Command::new("git")
-        .arg("merge")
-        .arg("feature")
-        .current_dir(cwd)
-        .assert()
-        .code(1);
-
     context.write_pre_commit_config(indoc::indoc! {r"
+        default_language_version:
+          system: '1.0'
         repos:
           - repo: local
             hooks:
-              - id: trailing-whitespace
-                name: trailing-whitespace
+              - id: unset
+                name: unset
                 language: system
-                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
-                verbose: true
+                entry: python3 -V
+                always_run: true
+              - id: default
+                name: default
+                language: system
+                entry: python3 -V
+                language_version: default
+                always_run: true
     "});
+    context.git_add(".");
 
-    // Abort on merge conflicts.
-    cmd_snapshot!(context.filters(), context.run(), @r#"
-    success: false
-    exit_code: 1
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
     ----- stdout -----
+    unset....................................................................Passed
+    default..................................................................Passed
 
     ----- stderr -----
-    You have unmerged paths. Resolve them before running prefligit.
-    "#);
+    warning: Language system does not need environment, but language_version is set
+    warning: Language system does not need environment, but language_version is set
+    ");
+}
 
-    // Fix the conflict and run again.
-    context.git_add(".");
-    cmd_snapshot!(context.filters(), context.run(), @r#"
+/// Hooks in `ci.skip` are skipped when running in CI.
+#[test]
+fn ci_skip() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        ci:
+          autoupdate_schedule: monthly
+          skip: [network]
+        repos:
+          - repo: local
+            hooks:
+              - id: network
+                name: network
+                language: system
+                entry: python3 -V
+                always_run: true
+              - id: offline
+                name: offline
+                language: system
+                entry: python3 -V
+                always_run: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    trailing-whitespace......................................................Passed
-    - hook id: trailing-whitespace
+    network..................................................................Passed
+    offline..................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().env("CI", "true"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    network.............................................(skipped by ci.skip)Skipped
+    offline..................................................................Passed
+
+    ----- stderr -----
+    ");
+}
+
+/// `--max-failures` stops the run after that many hooks have failed.
+#[test]
+fn max_failures() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: first
+                name: first
+                language: system
+                entry: python3 -c 'exit(1)'
+                always_run: true
+              - id: second
+                name: second
+                language: system
+                entry: python3 -V
+                always_run: true
+              - id: third
+                name: third
+                language: system
+                entry: python3 -c 'exit(1)'
+                always_run: true
+              - id: fourth
+                name: fourth
+                language: system
+                entry: python3 -c 'exit(1)'
+                always_run: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--max-failures").arg("2"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    first....................................................................Failed
+    - hook id: first
+    - exit code: 1
+    second...................................................................Passed
+    third....................................................................Failed
+    - hook id: third
+    - exit code: 1
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--max-failures").arg("0"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: invalid value '0' for '--max-failures <N>': number would be zero for non-zero type
+
+    For more information, try '--help'.
+    ");
+}
+
+/// Repos of multiple `--config` files are merged in order.
+#[test]
+fn multiple_configs() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: base
+                name: base
+                language: system
+                entry: python3 -c 'print("base")'
+                always_run: true
+                verbose: true
+    "#});
+    context
+        .workdir()
+        .child("team.yaml")
+        .write_str(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: team
+                name: team
+                language: system
+                entry: python3 -c 'print("team")'
+                always_run: true
+                verbose: true
+    "#})?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("-c").arg(".pre-commit-config.yaml").arg("-c").arg("team.yaml"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    base.....................................................................Passed
+    - hook id: base
     - duration: [TIME]
-      ['.pre-commit-config.yaml', 'file.txt']
+      base
+    team.....................................................................Passed
+    - hook id: team
+    - duration: [TIME]
+      team
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("-c").arg("team.yaml,.pre-commit-config.yaml"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    team.....................................................................Passed
+    - hook id: team
+    - duration: [TIME]
+      team
+    base.....................................................................Passed
+    - hook id: base
+    - duration: [TIME]
+      base
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// A config can extend other configs, inheriting their repos and the options it doesn't set.
+#[test]
+fn extends() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.workdir().child("base").create_dir_all()?;
+    context
+        .workdir()
+        .child("base/base.yaml")
+        .write_str(indoc::indoc! {r"
+        fail_fast: true
+        exclude: excluded.txt
+        repos:
+          - repo: local
+            hooks:
+              - id: base
+                name: base
+                language: system
+                entry: python3 -c 'import sys; print(sys.argv[1:]); exit(1)'
+                files: \.txt$
+    "})?;
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        extends: base/base.yaml
+        fail_fast: false
+        repos:
+          - repo: local
+            hooks:
+              - id: project
+                name: project
+                language: system
+                entry: python3 -c 'print("project")'
+                always_run: true
+                verbose: true
+    "#});
+    context.workdir().child("file.txt").write_str("Hello")?;
+    context.workdir().child("excluded.txt").write_str("Hello")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    base.....................................................................Failed
+    - hook id: base
+    - exit code: 1
+      ['file.txt']
+    project..................................................................Passed
+    - hook id: project
+    - duration: [TIME]
+      project
+
+    ----- stderr -----
+    ");
+
+    // Extending a config that extends it back is an error.
+    context
+        .workdir()
+        .child("base/base.yaml")
+        .write_str("extends: ../.pre-commit-config.yaml\nrepos: []\n")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Config file `base/../.pre-commit-config.yaml` extends itself
+    ");
+
+    Ok(())
+}
+
+/// With `--workspace`, each config in a subdirectory runs on the files under its directory.
+#[test]
+fn workspace() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let config = indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: show
+                name: show
+                language: system
+                entry: python3 -c 'import os, sys; print(os.path.basename(os.getcwd()), sorted(sys.argv[1:]))'
+                files: \.txt$
+                verbose: true
+    "};
+    context.write_pre_commit_config(config);
+    context.workdir().child("app/sub").create_dir_all()?;
+    context
+        .workdir()
+        .child("app/.pre-commit-config.yaml")
+        .write_str(config)?;
+    context.workdir().child("root.txt").write_str("Hello")?;
+    context.workdir().child("app/main.txt").write_str("Hello")?;
+    context
+        .workdir()
+        .child("app/sub/nested.txt")
+        .write_str("Hello")?;
+    context.git_add(".");
+
+    let filters = context
+        .filters()
+        .into_iter()
+        .chain([(r"\S+ \['root.txt'\]", "[ROOT] ['root.txt']")])
+        .collect::<Vec<_>>();
+
+    cmd_snapshot!(filters, context.run().arg("--workspace"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Running hooks for `.`:
+    show.....................................................................Passed
+    - hook id: show
+    - duration: [TIME]
+      [ROOT] ['root.txt']
+    Running hooks for `app`:
+    show.....................................................................Passed
+    - hook id: show
+    - duration: [TIME]
+      app ['main.txt', 'sub/nested.txt']
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Run from a subdirectory. File arguments should be fixed to be relative to the root.
+#[test]
+fn subdirectory() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let cwd = context.workdir();
+    let child = cwd.child("foo/bar/baz");
+    child.create_dir_all()?;
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'print("Hello"); exit(1)'
+                always_run: true
+    "#});
+
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().current_dir(&child).arg("--files").arg("file.txt"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    trailing-whitespace......................................................Failed
+    - hook id: trailing-whitespace
+    - exit code: 1
+      Hello
 
     ----- stderr -----
     "#);
 
     Ok(())
 }
+
+/// Only the files changed in the index are run on, not the ones added with `--intent-to-add`.
+#[test]
+fn staged_files() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: echo
+                name: echo
+                language: system
+                entry: echo
+                verbose: true
+    "});
+    let cwd = context.workdir();
+    cwd.child("changed.txt").write_str("changed")?;
+    cwd.child("unchanged.txt").write_str("unchanged")?;
+    context.git_add(".");
+    context.git_commit("Initial commit");
+
+    cwd.child("changed.txt").write_str("changed again")?;
+    cwd.child("added.txt").write_str("added")?;
+    cwd.child("intent.txt").write_str("intent")?;
+    context.git_add("changed.txt");
+    context.git_add("added.txt");
+    Command::new("git")
+        .args(["add", "--intent-to-add", "intent.txt"])
+        .current_dir(cwd)
+        .assert()
+        .success();
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    echo.....................................................................Passed
+    - hook id: echo
+    - duration: [TIME]
+      added.txt changed.txt
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Commit with the hook installed, after changing `a.txt` without staging it.
+fn commit_unstaged(context: &TestContext, args: &[&str]) -> Command {
+    context.init_project();
+    context.configure_git_author();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: echo
+                name: echo
+                language: system
+                entry: echo
+                verbose: true
+    "});
+    context.workdir().child("a.txt").write_str("a").unwrap();
+    context.git_add(".");
+    context.git_commit("Initial commit");
+    context.install().assert().success();
+    context
+        .workdir()
+        .child("a.txt")
+        .write_str("changed")
+        .unwrap();
+
+    let mut commit = Command::new("git");
+    commit
+        .args(["commit", "-m", "Change"])
+        .args(args)
+        .current_dir(context.workdir());
+    commit
+}
+
+/// `git commit -a` stages the changes in a temporary index, in `GIT_INDEX_FILE`.
+#[test]
+fn commit_all() {
+    let context = TestContext::new();
+    let mut commit = commit_unstaged(&context, &["-a"]);
+    let filters: Vec<_> = context
+        .filters()
+        .into_iter()
+        .chain([(r"\[master [0-9a-f]+\]", "[master [HASH]]")])
+        .collect();
+
+    cmd_snapshot!(filters, commit, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [master [HASH]] Change
+     1 file changed, 1 insertion(+), 1 deletion(-)
+
+    ----- stderr -----
+    echo.....................................................................Passed
+    - hook id: echo
+    - duration: [TIME]
+      a.txt
+    ");
+}
+
+/// `git commit <paths>` stages the paths in a temporary index, in `GIT_INDEX_FILE`.
+#[test]
+fn commit_paths() {
+    let context = TestContext::new();
+    let mut commit = commit_unstaged(&context, &["--", "a.txt"]);
+    let filters: Vec<_> = context
+        .filters()
+        .into_iter()
+        .chain([(r"\[master [0-9a-f]+\]", "[master [HASH]]")])
+        .collect();
+
+    cmd_snapshot!(filters, commit, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [master [HASH]] Change
+     1 file changed, 1 insertion(+), 1 deletion(-)
+
+    ----- stderr -----
+    echo.....................................................................Passed
+    - hook id: echo
+    - duration: [TIME]
+      a.txt
+    ");
+}
+
+/// Test hook `log_file` option.
+#[test]
+fn log_file() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'print("Fixing files"); exit(1)'
+                always_run: true
+                log_file: log.txt
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    trailing-whitespace......................................................Failed
+    - hook id: trailing-whitespace
+    - exit code: 1
+
+    ----- stderr -----
+    "#);
+
+    let log = context.read("log.txt");
+    assert_eq!(log, "Fixing files");
+}
+
+/// Pass pre-commit environment variables to the hook.
+#[test]
+fn pass_env_vars() {
+    let context = TestContext::new();
+
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: env-vars
+                name: Pass environment
+                language: system
+                entry: python3 -c "import os, sys; print(os.getenv('PRE_COMMIT')); sys.exit(1)"
+                always_run: true
+    "#});
+
+    cmd_snapshot!(context.filters(), context.run(), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Pass environment.........................................................Failed
+    - hook id: env-vars
+    - exit code: 1
+      1
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn staged_files_only() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'print(open("file.txt", "rt").read())'
+                verbose: true
+                types: [text]
+   "#});
+
+    context
+        .workdir()
+        .child("file.txt")
+        .write_str("Hello, world!")?;
+    context.git_add(".");
+
+    // Non-staged files should be stashed and restored.
+    context
+        .workdir()
+        .child("file.txt")
+        .write_str("Hello world again!")?;
+
+    let filters: Vec<_> = context
+        .filters()
+        .into_iter()
+        .chain([(r"/\d+-\d+.patch", "/[TIME]-[PID].patch")])
+        .collect();
+
+    cmd_snapshot!(filters, context.run(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    trailing-whitespace......................................................Passed
+    - hook id: trailing-whitespace
+    - duration: [TIME]
+      Hello, world!
+
+    ----- stderr -----
+    Non-staged changes detected, saving to `[HOME]/patches/[TIME]-[PID].patch`
+
+    Restored working tree changes from `[HOME]/patches/[TIME]-[PID].patch`
+    "#);
+
+    let content = context.read("file.txt");
+    assert_snapshot!(content, @"Hello world again!");
+
+    Ok(())
+}
+
+/// Unstaged changes to binary files and files with CRLF line endings are restored exactly.
+#[test]
+fn staged_files_only_binary_and_crlf() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: check
+                name: check
+                language: system
+                entry: python3 -c 'exit(0)'
+   "});
+
+    let cwd = context.workdir();
+    cwd.child("crlf.txt").write_str("a\r\nb\r\n")?;
+    cwd.child("data.bin").write_binary(&[0, 1, 2, 0xff])?;
+    context.git_add(".");
+
+    cwd.child("crlf.txt").write_str("a\r\nb\r\nc\r\n")?;
+    cwd.child("data.bin")
+        .write_binary(&[0, 1, 2, 3, 0xfe, 0xff])?;
+
+    context.run().assert().success();
+
+    assert_eq!(
+        fs_err::read(cwd.child("crlf.txt"))?,
+        b"a\r\nb\r\nc\r\n".to_vec()
+    );
+    assert_eq!(
+        fs_err::read(cwd.child("data.bin"))?,
+        vec![0, 1, 2, 3, 0xfe, 0xff]
+    );
+
+    Ok(())
+}
+
+/// With `--no-stash`, hooks see unstaged changes and modifications are still detected.
+#[test]
+fn no_stash() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: show
+                name: show
+                language: system
+                entry: python3 -c 'print(open("file.txt", "rt").read())'
+                verbose: true
+                types: [text]
+              - id: fix
+                name: fix
+                language: system
+                entry: python3 -c 'open("file.txt", "at").write("!")'
+                types: [text]
+   "#});
+
+    let cwd = context.workdir();
+    cwd.child("file.txt").write_str("Hello, world!")?;
+    context.git_add(".");
+    cwd.child("file.txt").write_str("Hello world again!")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--no-stash"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    show.....................................................................Passed
+    - hook id: show
+    - duration: [TIME]
+      Hello world again!
+    fix......................................................................Failed
+    - hook id: fix
+    - files were modified by this hook
+
+    ----- stderr -----
+    ");
+
+    assert_snapshot!(context.read("file.txt"), @"Hello world again!!");
+
+    Ok(())
+}
+
+/// `--autofix-stage` stages the changes hooks make to staged files.
+#[test]
+fn autofix_stage() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'import sys, pathlib; [pathlib.Path(f).write_text("".join(l.rstrip() + "\n" for l in pathlib.Path(f).read_text().splitlines())) for f in sys.argv[1:]]'
+                files: \.txt$
+   "#});
+
+    let cwd = context.workdir();
+    cwd.child("a.txt").write_str("Hello, world!  \n")?;
+    cwd.child("b.txt").write_str("x\n")?;
+    context.git_add(".");
+    // Unstaged changes are kept out of the index.
+    cwd.child("b.txt").write_str("x\ny\n")?;
+
+    let filters: Vec<_> = context
+        .filters()
+        .into_iter()
+        .chain([(r"/\d+-\d+.patch", "/[TIME]-[PID].patch")])
+        .collect();
+
+    cmd_snapshot!(filters, context.run().arg("--autofix-stage"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    trailing-whitespace......................................................Passed
+    - hook id: trailing-whitespace
+    - files were modified by this hook
+
+    ----- stderr -----
+    Non-staged changes detected, saving to `[HOME]/patches/[TIME]-[PID].patch`
+    Staged fixes made by hooks:
+      a.txt
+
+    Restored working tree changes from `[HOME]/patches/[TIME]-[PID].patch`
+    ");
+
+    // The fix is staged, the unstaged change of `b.txt` is not.
+    let staged = Command::new("git")
+        .arg("show")
+        .arg(":a.txt")
+        .arg(":b.txt")
+        .current_dir(cwd)
+        .output()?;
+    assert_snapshot!(String::from_utf8_lossy(&staged.stdout), @r"
+    Hello, world!
+    x
+    ");
+    assert_snapshot!(context.read("a.txt"), @"Hello, world!");
+    assert_snapshot!(context.read("b.txt"), @r"
+    x
+    y
+    ");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn restore_on_interrupt() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    // The hook will sleep for 3 seconds.
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'import time; open("out.txt", "wt").write(open("file.txt", "rt").read()); time.sleep(10)'
+                verbose: true
+                types: [text]
+   "#});
+
+    context
+        .workdir()
+        .child("file.txt")
+        .write_str("Hello, world!")?;
+    context.git_add(".");
+
+    // Non-staged files should be stashed and restored.
+    context
+        .workdir()
+        .child("file.txt")
+        .write_str("Hello world again!")?;
+
+    let mut child = context.run().spawn()?;
+    let child_id = child.id();
+
+    // Send an interrupt signal to the process.
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        #[allow(clippy::cast_possible_wrap)]
+        unsafe {
+            libc::kill(child_id as i32, libc::SIGINT)
+        };
+    });
+
+    handle.join().unwrap();
+    child.wait()?;
+
+    let content = context.read("out.txt");
+    assert_snapshot!(content, @"Hello, world!");
+
+    let content = context.read("file.txt");
+    assert_snapshot!(content, @"Hello world again!");
+
+    Ok(())
+}
+
+/// On interrupt, the processes spawned by hooks are stopped too.
+#[cfg(unix)]
+#[test]
+fn interrupt_hook_processes() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: background
+                name: background
+                language: system
+                entry: sh -c 'sleep 2 && touch late.txt'
+                pass_filenames: false
+                always_run: true
+   "});
+    context.git_add(".");
+
+    let mut child = context.run().spawn()?;
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    #[allow(clippy::cast_possible_wrap)]
+    unsafe {
+        libc::kill(child.id() as i32, libc::SIGINT);
+    }
+    child.wait()?;
+
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    assert!(!context.workdir().child("late.txt").exists());
+
+    Ok(())
+}
+
+/// When in merge conflict, runs on files that have conflicts fixed.
+#[test]
+fn merge_conflicts() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    // Create a merge conflict.
+    let cwd = context.workdir();
+    cwd.child("file.txt").write_str("Hello, world!")?;
+    context.git_add(".");
+    context.configure_git_author();
+    context.git_commit("Initial commit");
+
+    Command::new("git")
+        .arg("checkout")
+        .arg("-b")
+        .arg("feature")
+        .current_dir(cwd)
+        .assert()
+        .success();
+    cwd.child("file.txt").write_str("Hello, world again!")?;
+    context.git_add(".");
+    context.git_commit("Feature commit");
+
+    Command::new("git")
+        .arg("checkout")
+        .arg("master")
+        .current_dir(cwd)
+        .assert()
+        .success();
+    cwd.child("file.txt")
+        .write_str("Hello, world from master!")?;
+    context.git_add(".");
+    context.git_commit("Master commit");
+
+    Command::new("git")
+        .arg("merge")
+        .arg("feature")
+        .current_dir(cwd)
+        .assert()
+        .code(1);
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing-whitespace
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                verbose: true
+    "});
+
+    // Abort on merge conflicts.
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    You have unmerged paths. Resolve them before running prefligit.
+      file.txt
+    ");
+
+    // Fix the conflict and run again.
+    context.git_add(".");
+    cmd_snapshot!(context.filters(), context.run(), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    trailing-whitespace......................................................Passed
+    - hook id: trailing-whitespace
+    - duration: [TIME]
+      ['.pre-commit-config.yaml', 'file.txt']
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// Run on files changed between two revisions, since their merge base.
+#[test]
+fn from_ref_to_ref() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.configure_git_author();
+
+    let cwd = context.workdir();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: show-files
+                name: show files
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                verbose: true
+    "});
+    cwd.child("a.txt").write_str("a")?;
+    context.git_add(".");
+    context.git_commit("Initial commit");
+
+    Command::new("git")
+        .arg("checkout")
+        .arg("-b")
+        .arg("feature")
+        .current_dir(cwd)
+        .assert()
+        .success();
+    cwd.child("b.txt").write_str("b")?;
+    cwd.child("a.txt").write_str("a changed")?;
+    context.git_add(".");
+    context.git_commit("Feature commit");
+
+    // Changes on `master` after the branch point are not included.
+    Command::new("git")
+        .arg("checkout")
+        .arg("master")
+        .current_dir(cwd)
+        .assert()
+        .success();
+    cwd.child("c.txt").write_str("c")?;
+    context.git_add(".");
+    context.git_commit("Master commit");
+
+    Command::new("git")
+        .arg("checkout")
+        .arg("feature")
+        .current_dir(cwd)
+        .assert()
+        .success();
+
+    cmd_snapshot!(context.filters(), context.run().arg("--from-ref").arg("master").arg("--to-ref").arg("feature"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      ['a.txt', 'b.txt']
+
+    ----- stderr -----
+    ");
+
+    // Short flags.
+    cmd_snapshot!(context.filters(), context.run().arg("-s").arg("master").arg("-o").arg("HEAD"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      ['a.txt', 'b.txt']
+
+    ----- stderr -----
+    ");
+
+    // `--since` diffs against the merge base with `HEAD`.
+    cmd_snapshot!(context.filters(), context.run().arg("--since").arg("master"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      ['a.txt', 'b.txt']
+
+    ----- stderr -----
+    ");
+
+    // Unrelated histories fall back to a direct diff.
+    Command::new("git")
+        .args(["checkout", "--orphan", "unrelated"])
+        .current_dir(cwd)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["rm", "-rfq", "."])
+        .current_dir(cwd)
+        .assert()
+        .success();
+    cwd.child("d.txt").write_str("d")?;
+    context.git_add("d.txt");
+    context.git_commit("Unrelated commit");
+    Command::new("git")
+        .args(["checkout", "feature"])
+        .current_dir(cwd)
+        .assert()
+        .success();
+
+    cmd_snapshot!(context.filters(), context.run().arg("--from-ref").arg("unrelated").arg("--to-ref").arg("feature"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      ['.pre-commit-config.yaml', 'a.txt', 'b.txt']
+
+    ----- stderr -----
+    ");
+
+    // Other errors are reported as is.
+    cmd_snapshot!(context.filters(), context.run().arg("--from-ref").arg("no-such-branch").arg("--to-ref").arg("feature"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    fatal: Not a valid object name no-such-branch
+    error: failed to get merge base (status: exit status: 128)
+    ");
+
+    Ok(())
+}
+
+/// Read the file list from stdin or a file.
+#[test]
+fn files_from() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let cwd = context.workdir();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: show-files
+                name: show files
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                verbose: true
+    "});
+    cwd.child("a.txt").write_str("a")?;
+    cwd.child("b.txt").write_str("b")?;
+    cwd.child("c.txt").write_str("c")?;
+    context.git_add(".");
+
+    // Newline-delimited from stdin.
+    cmd_snapshot!(context.filters(), context.run().arg("--files-from").arg("-").pass_stdin("a.txt\nb.txt\n"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      ['a.txt', 'b.txt']
+
+    ----- stderr -----
+    ");
+
+    // NUL-delimited from stdin.
+    cmd_snapshot!(context.filters(), context.run().arg("--files-from").arg("-").pass_stdin("b.txt\0c.txt\0"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      ['b.txt', 'c.txt']
+
+    ----- stderr -----
+    ");
+
+    // An empty list runs on no files, not on the staged files.
+    cmd_snapshot!(context.filters(), context.run().arg("--files-from").arg("-").pass_stdin(""), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...........................................(no files to check)Skipped
+
+    ----- stderr -----
+    ");
+
+    // From a file.
+    cwd.child("files.lst").write_str("c.txt\n")?;
+    cmd_snapshot!(context.filters(), context.run().arg("--files-from").arg("files.lst"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      ['c.txt']
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Restrict the run to files under the given directories.
+#[test]
+fn directory() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let cwd = context.workdir();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: show-files
+                name: show files
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                verbose: true
+    "});
+    cwd.child("root.txt").write_str("root")?;
+    cwd.child("app/a.txt").write_str("a")?;
+    cwd.child("app/sub/b.txt").write_str("b")?;
+    cwd.child("lib/c.txt").write_str("c")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--directory").arg("app"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      ['app/a.txt', 'app/sub/b.txt']
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("-d").arg("app/sub").arg("-d").arg("lib").arg("--all-files"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      ['app/sub/b.txt', 'lib/c.txt']
+
+    ----- stderr -----
+    ");
+
+    // Relative to the current directory.
+    cmd_snapshot!(context.filters(), context.run().current_dir(cwd.child("app")).arg("-d").arg("."), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show files...............................................................Passed
+    - hook id: show-files
+    - duration: [TIME]
+      ['app/a.txt', 'app/sub/b.txt']
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Run multiple hooks by id in one invocation.
+#[test]
+fn multiple_hook_ids() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: hook-1
+                name: hook 1
+                language: system
+                entry: python3 -V
+                always_run: true
+              - id: hook-2
+                name: hook 2
+                language: system
+                entry: python3 -V
+                always_run: true
+              - id: hook-3
+                name: hook 3
+                language: system
+                entry: python3 -V
+                alias: third
+                always_run: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("hook-1").arg("third"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    hook 1...................................................................Passed
+    hook 3...................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("hook-2").arg("hook-4").arg("hook-5"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    No hook found for id `hook-4`
+    No hook found for id `hook-5`
+    ");
+}
+
+/// `--dry-run` prints the files each hook would receive without running it.
+#[test]
+fn dry_run() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let cwd = context.workdir();
+    cwd.child("file.txt").write_str("Hello, world!\n")?;
+    cwd.child("valid.json").write_str("{}\n")?;
+    cwd.child("main.py").write_str("print('abc')\n")?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: trailing-whitespace
+                name: trailing whitespace
+                language: system
+                entry: python3 -c 'exit(1)'
+                types: [text]
+                exclude: \.py$
+              - id: check-json
+                name: check json
+                language: system
+                entry: python3 -c 'exit(1)'
+                types: [json]
+              - id: check-toml
+                name: check toml
+                language: system
+                entry: python3 -c 'exit(1)'
+                types: [toml]
+              - id: always
+                name: always
+                language: system
+                entry: python3 -c 'exit(1)'
+                always_run: true
+                pass_filenames: false
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--dry-run").arg("--skip").arg("check-json"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    trailing whitespace
+    - hook id: trailing-whitespace
+    - files (3):
+      .pre-commit-config.yaml
+      file.txt
+      valid.json
+    check json...........................................(skipped by --skip)Skipped
+    check toml...........................................(no files to check)Skipped
+    always
+    - hook id: always
+    - filenames are not passed to this hook
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Hooks running longer than `timeout` are killed and reported as failed.
+#[cfg(unix)]
+#[test]
+fn timeout() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: slow
+                name: slow
+                language: system
+                entry: sh -c 'sleep 30 & sleep 30'
+                pass_filenames: false
+                always_run: true
+                timeout: 1
+              - id: fast
+                name: fast
+                language: system
+                entry: python3 -c "print('done')"
+                pass_filenames: false
+                always_run: true
+                verbose: true
+              - id: sleepy
+                name: sleepy
+                language: system
+                entry: sleep 30
+                pass_filenames: false
+                always_run: true
+    "#});
+    context.git_add(".");
+
+    let start = std::time::Instant::now();
+    cmd_snapshot!(context.filters(), context.run().arg("slow").arg("fast"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    slow.....................................................................Failed
+    - hook id: slow
+    - timed out after [TIME]
+    fast.....................................................................Passed
+    - hook id: fast
+    - duration: [TIME]
+      done
+
+    ----- stderr -----
+    ");
+    assert!(start.elapsed() < std::time::Duration::from_secs(20));
+
+    // `--timeout` applies to hooks without their own `timeout`.
+    cmd_snapshot!(context.filters(), context.run().arg("sleepy").arg("--timeout").arg("1"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    sleepy...................................................................Failed
+    - hook id: sleepy
+    - timed out after [TIME]
+
+    ----- stderr -----
+    ");
+}
+
+/// The resource limits of a hook apply to its processes.
+#[cfg(unix)]
+#[test]
+fn resource_limits() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: limits
+                name: limits
+                language: system
+                entry: python3 -c 'import resource as r; print([r.getrlimit(l) for l in (r.RLIMIT_CPU, r.RLIMIT_AS, r.RLIMIT_NOFILE)])'
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                limits:
+                  cpu_time: 60
+                  memory: 4096
+                  open_files: 64
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    limits...................................................................Passed
+    - hook id: limits
+    - duration: [TIME]
+      [(60, 60), (4294967296, 4294967296), (64, 64)]
+
+    ----- stderr -----
+    ");
+}
+
+/// The memory limit is of the address space, a modest one still leaves room for common tools.
+#[cfg(unix)]
+#[test]
+fn memory_limit() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: python
+                name: python
+                language: system
+                entry: python3 -c 'import json, subprocess; print(json.dumps([1, 2]))'
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                limits:
+                  memory: 256
+              - id: git
+                name: git
+                language: system
+                entry: git ls-files
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                limits:
+                  memory: 256
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    python...................................................................Passed
+    - hook id: python
+    - duration: [TIME]
+      [1, 2]
+    git......................................................................Passed
+    - hook id: git
+    - duration: [TIME]
+      .pre-commit-config.yaml
+
+    ----- stderr -----
+    ");
+}
+
+/// Hooks in the sandbox can't reach the network or write outside the repo.
+#[cfg(target_os = "linux")]
+#[test]
+fn sandbox() -> Result<()> {
+    // Unprivileged user namespaces may be disabled.
+    if !Command::new("unshare")
+        .args(["--user", "true"])
+        .status()
+        .is_ok_and(|status| status.success())
+    {
+        return Ok(());
+    }
+
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: sandboxed
+                name: sandboxed
+                language: system
+                entry: python3 check.py
+                pass_filenames: false
+                always_run: true
+                verbose: true
+    "});
+    context
+        .workdir()
+        .child("check.py")
+        .write_str(indoc::indoc! {r#"
+        import os, socket
+        for path in ("inside.txt", os.path.join(os.environ["OUTSIDE"], "outside.txt")):
+            try:
+                open(path, "w").close()
+                print(f"wrote {os.path.basename(path)}")
+            except OSError as e:
+                print(f"can't write {os.path.basename(path)}: {e.strerror}")
+        try:
+            socket.create_connection(("127.0.0.1", 9), timeout=1)
+        except OSError as e:
+            print(f"can't connect: {e.strerror}")
+    "#})?;
+    context.git_add(".");
+    // The test directories are in the temporary directory, which hooks can write to.
+    let tmp = context.workdir().child("tmp");
+    tmp.create_dir_all()?;
+
+    cmd_snapshot!(context.filters(), context.run()
+        .arg("--sandbox")
+        .env("TMPDIR", tmp.as_os_str())
+        .env("OUTSIDE", context.home_dir().as_os_str()), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    sandboxed................................................................Passed
+    - hook id: sandboxed
+    - duration: [TIME]
+      wrote inside.txt
+      can't write outside.txt: Read-only file system
+      can't connect: Network is unreachable
+
+    ----- stderr -----
+    ");
+
+    assert!(!context.home_dir().child("outside.txt").exists());
+
+    Ok(())
+}
+
+/// Hooks that passed before with the same inputs are skipped with the `cache` option.
+#[test]
+fn run_cache() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        cache: true
+        repos:
+          - repo: local
+            hooks:
+              - id: count
+                name: count
+                language: system
+                entry: python3 -c "import sys; print(len(sys.argv) - 1, 'files')"
+                verbose: true
+    "#});
+    context.workdir().child("a.txt").write_str("a")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    count....................................................................Passed
+    - hook id: count
+    - duration: [TIME]
+      2 files
+
+    ----- stderr -----
+    ");
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    count............................................(unchanged since passed)Passed
+
+    ----- stderr -----
+    ");
+
+    // Changed contents run the hook again.
+    context.workdir().child("a.txt").write_str("b")?;
+    context.git_add(".");
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    count....................................................................Passed
+    - hook id: count
+    - duration: [TIME]
+      2 files
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--no-cache"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    count....................................................................Passed
+    - hook id: count
+    - duration: [TIME]
+      2 files
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Environments are rebuilt when the `language_version` or `additional_dependencies` of their
+/// hook differ from the ones they were installed with, leaving other environments alone.
+#[test]
+fn rebuild_changed_environments() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let envs = || -> Result<Vec<String>> {
+        let mut envs = fs_err::read_dir(context.home_dir().child("envs").path())?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        envs.sort();
+        Ok(envs)
+    };
+    let config = |version: &str, deps: &str| {
+        context.write_pre_commit_config(&format!(
+            indoc::indoc! {r"
+            repos:
+              - repo: local
+                hooks:
+                  - id: changed
+                    name: changed
+                    entry: changed
+                    language: node
+                    language_version: '{}'
+                    additional_dependencies: [{}]
+                  - id: unchanged
+                    name: unchanged
+                    entry: unchanged
+                    language: node
+        "},
+            version, deps
+        ));
+        context.git_add(".");
+    };
+
+    config("18", "prettier");
+    context.run().assert().success();
+    let installed = envs()?;
+    assert_eq!(installed.len(), 2);
+    let unchanged = installed
+        .iter()
+        .find(|env| env.starts_with("node_env-default-"))
+        .expect("environment of the unchanged hook")
+        .clone();
+    let state = context
+        .home_dir()
+        .child("envs")
+        .child(&unchanged)
+        .child(".install_state_v3");
+    let installed_at = fs_err::metadata(state.path())?.modified()?;
+
+    // A new environment for the new version, the other one is reused.
+    config("20", "prettier");
+    context.run().assert().success();
+    let after_version = envs()?;
+    assert_eq!(after_version.len(), 3);
+    assert!(after_version
+        .iter()
+        .any(|env| env.starts_with("node_env-20-")));
+
+    // And for the new dependencies.
+    config("20", "prettier, eslint");
+    context.run().assert().success();
+    assert_eq!(envs()?.len(), 4);
+    assert_eq!(fs_err::metadata(state.path())?.modified()?, installed_at);
+
+    // An environment installed with other inputs than it's recorded by is rebuilt in place.
+    let recorded = fs_err::read_to_string(state.path())?;
+    let tampered = recorded.replace(
+        "\"language_version\":\"default\"",
+        "\"language_version\":\"16\"",
+    );
+    assert_ne!(tampered, recorded);
+    state.write_str(&tampered)?;
+    context.run().assert().success();
+    assert_eq!(envs()?.len(), 4);
+    assert_eq!(fs_err::read_to_string(state.path())?, recorded);
+
+    Ok(())
+}
+
+/// Failing hooks are re-run up to `retries` times.
+#[test]
+fn retries() {
+    let context = TestContext::new();
+    context.init_project();
+
+    // Fails on the first two attempts, then passes.
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: flaky
+                name: flaky
+                language: system
+                entry: python3 -c "import os; n = len(os.listdir('attempts')); open(f'attempts/{n}', 'w').close(); print(f'attempt {n}'); exit(n < 2)"
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                retries: 2
+              - id: broken
+                name: broken
+                language: system
+                entry: python3 -c "print('broken'); exit(1)"
+                pass_filenames: false
+                always_run: true
+                retries: 1
+    "#});
+    context.git_add(".");
+    context
+        .workdir()
+        .child("attempts")
+        .create_dir_all()
+        .unwrap();
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    flaky....................................................................Passed
+    - hook id: flaky
+    - duration: [TIME]
+    - retries: 2
+      attempt 2
+    broken...................................................................Failed
+    - hook id: broken
+    - retries: 1
+    - exit code: 1
+      broken
+
+    ----- stderr -----
+    ");
+}
+
+/// `--jobs` and the `jobs` config option control how files are split into batches.
+#[test]
+fn jobs() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let cwd = context.workdir();
+    for i in 0..7 {
+        cwd.child(format!("file{i}.txt")).write_str("")?;
+    }
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        jobs: 2
+        repos:
+          - repo: local
+            hooks:
+              - id: count
+                name: count
+                language: system
+                entry: python3 -c "import sys; print(len(sys.argv) - 1)"
+                verbose: true
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    count....................................................................Passed
+    - hook id: count
+    - duration: [TIME]
+      4
+      4
+
+    ----- stderr -----
+    ");
+
+    // `-j1` overrides the config and runs in a single batch.
+    cmd_snapshot!(context.filters(), context.run().arg("-j1"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    count....................................................................Passed
+    - hook id: count
+    - duration: [TIME]
+      8
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Read-only hooks run concurrently with `--parallel`, respecting `depends_on`.
+#[test]
+fn parallel() {
+    let context = TestContext::new();
+    context.init_project();
+
+    // `a` only finishes early if `b` runs at the same time, `c` waits for `a`.
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        jobs: 4
+        repos:
+          - repo: local
+            hooks:
+              - id: a
+                name: a
+                language: system
+                entry: python3 -c "import os, time; [time.sleep(0.1) for _ in range(100) if not os.path.exists('b.started')]; print('a saw b' if os.path.exists('b.started') else 'a alone'); open('a.done', 'w').close()"
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                read_only: true
+              - id: b
+                name: b
+                language: system
+                entry: python3 -c "open('b.started', 'w').close(); print('b')"
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                read_only: true
+              - id: c
+                name: c
+                language: system
+                entry: python3 -c "import os; print('c after a' if os.path.exists('a.done') else 'c before a')"
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                read_only: true
+                depends_on: [a]
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--parallel"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    a........................................................................Passed
+    - hook id: a
+    - duration: [TIME]
+      a saw b
+    b........................................................................Passed
+    - hook id: b
+    - duration: [TIME]
+      b
+    c........................................................................Passed
+    - hook id: c
+    - duration: [TIME]
+      c after a
+
+    ----- stderr -----
+    ");
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: a
+                name: a
+                language: system
+                entry: python3 -c "exit(0)"
+                depends_on: [missing]
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--parallel"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Hook `a` depends on unknown hook `missing`
+    ");
+}
+
+/// With `--parallel`, hooks that may modify files run on their own, so the changes of one
+/// hook are not blamed on another.
+#[test]
+fn parallel_modifying_hooks() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        jobs: 4
+        repos:
+          - repo: local
+            hooks:
+              - id: fix-a
+                name: fix a
+                language: system
+                entry: python3 -c 'import time; time.sleep(0.5); open("a.txt", "a").write("fixed\n")'
+                pass_filenames: false
+                always_run: true
+              - id: fix-b
+                name: fix b
+                language: system
+                entry: python3 -c 'print("a.txt fixed" if "fixed" in open("a.txt").read() else "a.txt not fixed"); open("b.txt", "a").write("fixed\n")'
+                pass_filenames: false
+                always_run: true
+                verbose: true
+              - id: check
+                name: check
+                language: system
+                entry: python3 -c 'print("checked")'
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                read_only: true
+    "#});
+
+    let cwd = context.workdir();
+    cwd.child("a.txt").write_str("a\n")?;
+    cwd.child("b.txt").write_str("b\n")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--parallel").arg("--diff"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fix a....................................................................Failed
+    - hook id: fix-a
+    - files were modified by this hook
+    diff --git a/a.txt b/a.txt
+    index 7898192..d75aaee 100644
+    --- a/a.txt
+    +++ b/a.txt
+    @@ -1 +1,2 @@
+     a
+    +fixed
+    fix b....................................................................Failed
+    - hook id: fix-b
+    - duration: [TIME]
+    - files were modified by this hook
+    diff --git a/b.txt b/b.txt
+    index 6178079..ba4e40a 100644
+    --- a/b.txt
+    +++ b/b.txt
+    @@ -1 +1,2 @@
+     b
+    +fixed
+      a.txt fixed
+    check....................................................................Passed
+    - hook id: check
+    - duration: [TIME]
+      checked
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Hooks with `profiles` only run when one of their profiles is selected.
+#[test]
+fn profiles() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: fast
+                name: fast
+                language: system
+                entry: python3 -c "exit(0)"
+                always_run: true
+              - id: slow
+                name: slow
+                language: system
+                entry: python3 -c "exit(0)"
+                always_run: true
+                profiles: [ci]
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    fast.....................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--profile").arg("ci"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    fast.....................................................................Passed
+    slow.....................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    // Hooks selected by id run regardless of the profile.
+    cmd_snapshot!(context.filters(), context.run().arg("slow"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    slow.....................................................................Passed
+
+    ----- stderr -----
+    ");
+}
+
+/// Select hooks by their language or tags.
+#[test]
+fn select_by_language_and_tag() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: format
+                name: format
+                language: system
+                entry: python3 -c "exit(0)"
+                always_run: true
+                tags: [formatter]
+              - id: lint
+                name: lint
+                language: system
+                entry: python3 -c "exit(0)"
+                always_run: true
+                tags: [linter]
+              - id: fail
+                name: fail
+                language: fail
+                entry: always fails
+                always_run: true
+                tags: [linter]
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--tag").arg("formatter"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    format...................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--language").arg("system").arg("--tag").arg("linter"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    lint.....................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--language").arg("fail"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
+      always fails
+
+      .pre-commit-config.yaml
+
+    ----- stderr -----
+    ");
+}
+
+/// Hook `env` is set when running the hook, over the process environment.
+#[test]
+fn hook_env() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: env
+                name: env
+                language: system
+                entry: python3 -c "import os; print(os.environ['CACHE_DIR'], os.environ['MODE'])"
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                env:
+                  CACHE_DIR: .cache
+                  MODE: strict
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().env("MODE", "lenient"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    env......................................................................Passed
+    - hook id: env
+    - duration: [TIME]
+      .cache strict
+
+    ----- stderr -----
+    ");
+}
+
+/// Hooks with `clean_env` only see the host variables listed in `pass_env`.
+#[test]
+fn clean_env() {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        clean_env: true
+        repos:
+          - repo: local
+            hooks:
+              - id: clean
+                name: clean
+                language: system
+                entry: python3 -c "import os; print(os.environ.get('LEAKED'), os.environ.get('KEEP'), os.environ.get('PRE_COMMIT'))"
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                pass_env: [KEEP]
+              - id: dirty
+                name: dirty
+                language: system
+                entry: python3 -c "import os; print(os.environ.get('LEAKED'), os.environ.get('KEEP'))"
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                clean_env: false
+    "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().env("LEAKED", "1").env("KEEP", "2"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    clean....................................................................Passed
+    - hook id: clean
+    - duration: [TIME]
+      None 2 1
+    dirty....................................................................Passed
+    - hook id: dirty
+    - duration: [TIME]
+      1 2
+
+    ----- stderr -----
+    ");
+}
+
+/// `--from-index` runs hooks on the staged contents of files.
+#[test]
+fn from_index() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: clean
+                name: clean
+                language: system
+                entry: git diff --exit-code
+                pass_filenames: false
+                read_only: true
+              - id: show
+                name: show
+                language: system
+                entry: python3 -c "import sys; [print(f, open(f).read().splitlines()) for f in sys.argv[1:]]"
+                files: \.txt$
+                verbose: true
+                read_only: true
+              - id: fix
+                name: fix
+                language: system
+                entry: python3 -c "import sys; [open(f, 'a').write('fixed\n') for f in sys.argv[1:]]"
+                files: \.txt$
+    "#});
+
+    let cwd = context.workdir();
+    cwd.child("file.txt").write_str("staged\n")?;
+    context.git_add(".");
+    cwd.child("file.txt").write_str("staged\nunstaged\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--from-index"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    clean....................................................................Passed
+    show.....................................................................Passed
+    - hook id: show
+    - duration: [TIME]
+      file.txt ['staged']
+    fix......................................................................Failed
+    - hook id: fix
+    - files were modified by this hook
+
+    ----- stderr -----
+    ");
+
+    // Git run by concurrent hooks uses the checkout as the working tree too.
+    cmd_snapshot!(context.filters(), context.run().arg("--from-index").arg("--parallel"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    clean....................................................................Passed
+    show.....................................................................Passed
+    - hook id: show
+    - duration: [TIME]
+      file.txt ['staged']
+    fix......................................................................Failed
+    - hook id: fix
+    - files were modified by this hook
+
+    ----- stderr -----
+    ");
+
+    // The working tree is left untouched.
+    assert_snapshot!(context.read("file.txt"), @r"
+    staged
+    unstaged
+    ");
+
+    Ok(())
+}
+
+/// `--diff` shows the changes made by each hook below its result.
+#[test]
+fn per_hook_diff() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: fix-a
+                name: fix a
+                language: system
+                entry: python3 -c 'open("a.txt", "a").write("fixed\n")'
+                pass_filenames: false
+                always_run: true
+              - id: fix-b
+                name: fix b
+                language: system
+                entry: python3 -c 'open("b.txt", "a").write("fixed\n")'
+                pass_filenames: false
+                always_run: true
+   "#});
+
+    let cwd = context.workdir();
+    cwd.child("a.txt").write_str("a\n")?;
+    cwd.child("b.txt").write_str("b\n")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--diff"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fix a....................................................................Failed
+    - hook id: fix-a
+    - files were modified by this hook
+    diff --git a/a.txt b/a.txt
+    index 7898192..d75aaee 100644
+    --- a/a.txt
+    +++ b/a.txt
+    @@ -1 +1,2 @@
+     a
+    +fixed
+    fix b....................................................................Failed
+    - hook id: fix-b
+    - files were modified by this hook
+    diff --git a/b.txt b/b.txt
+    index 6178079..ba4e40a 100644
+    --- a/b.txt
+    +++ b/b.txt
+    @@ -1 +1,2 @@
+     b
+    +fixed
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// `--summary` prints hook counts, modified files and duration after all hooks.
+#[test]
+fn run_summary() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: fix
+                name: fix
+                language: system
+                entry: python3 -c 'open("a.txt", "a").write("fixed\n")'
+                pass_filenames: false
+                always_run: true
+              - id: pass
+                name: pass
+                language: system
+                entry: python3 -c 'pass'
+                pass_filenames: false
+                always_run: true
+              - id: no-files
+                name: no files
+                language: system
+                entry: python3 -c 'pass'
+                files: \.py$
+   "#});
+
+    let cwd = context.workdir();
+    cwd.child("a.txt").write_str("a\n")?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--summary"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fix......................................................................Failed
+    - hook id: fix
+    - files were modified by this hook
+    pass.....................................................................Passed
+    no files.............................................(no files to check)Skipped
+    1 passed, 1 failed, 1 skipped, 1 files modified in [TIME]
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// `--include-untracked` also runs hooks on untracked files that are not ignored.
+#[test]
+fn include_untracked() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: show
+                name: show
+                language: system
+                entry: python3 -c 'import sys; print(sorted(sys.argv[1:]))'
+                files: \.txt$
+                verbose: true
+   "});
+
+    let cwd = context.workdir();
+    cwd.child("staged.txt").write_str("staged")?;
+    cwd.child(".gitignore").write_str("ignored.txt\n")?;
+    context.git_add(".");
+    cwd.child("untracked.txt").write_str("untracked")?;
+    cwd.child("new/nested.txt").write_str("untracked")?;
+    cwd.child("ignored.txt").write_str("ignored")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--include-untracked"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    show.....................................................................Passed
+    - hook id: show
+    - duration: [TIME]
+      ['new/nested.txt', 'staged.txt', 'untracked.txt']
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Status lines fit the terminal width, truncating long hook names.
+#[test]
+fn narrow_terminal() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: long
+                name: a hook with a very long name that does not fit
+                language: system
+                entry: python3 -c 'pass'
+                always_run: true
+              - id: no-files
+                name: another hook with a long name
+                language: system
+                entry: python3 -c 'pass'
+                files: \.py$
+   "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().env("COLUMNS", "40"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    a hook with a very long name t….Passed
+    another ho….(no files to check)Skipped
+
+    ----- stderr -----
+    ");
+}
+
+/// Streamed hooks print their output as it is produced, prefixed with the hook id.
+#[test]
+fn stream_output() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: streamed
+                name: streamed
+                language: system
+                entry: python3 -c 'print("line 1"); print("line 2"); raise SystemExit(1)'
+                pass_filenames: false
+                always_run: true
+                stream: true
+              - id: buffered
+                name: buffered
+                language: system
+                entry: python3 -c 'print("buffered output"); raise SystemExit(1)'
+                pass_filenames: false
+                always_run: true
+   "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    streamed.................................................................Failed
+    - hook id: streamed
+    - exit code: 1
+    buffered.................................................................Failed
+    - hook id: buffered
+    - exit code: 1
+      buffered output
+
+    ----- stderr -----
+    [streamed] line 1
+    [streamed] line 2
+    ");
+
+    // `--stream` streams the output of all hooks.
+    cmd_snapshot!(context.filters(), context.run().arg("--stream"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    streamed.................................................................Failed
+    - hook id: streamed
+    - exit code: 1
+    buffered.................................................................Failed
+    - hook id: buffered
+    - exit code: 1
+
+    ----- stderr -----
+    [streamed] line 1
+    [streamed] line 2
+    [buffered] buffered output
+    ");
+}
+
+/// Hooks with `pty: true` run in a pseudo-terminal.
+#[cfg(unix)]
+#[test]
+fn pty() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: pty
+                name: pty
+                language: system
+                entry: python3 -c 'import sys; print(sys.stdout.isatty(), sys.stderr.isatty()); print("err", file=sys.stderr)'
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                pty: true
+              - id: no-pty
+                name: no pty
+                language: system
+                entry: python3 -c 'import sys; print(sys.stdout.isatty(), sys.stderr.isatty())'
+                pass_filenames: false
+                always_run: true
+                verbose: true
+   "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pty......................................................................Passed
+    - hook id: pty
+    - duration: [TIME]
+      True True
+      err
+    no pty...................................................................Passed
+    - hook id: no-pty
+    - duration: [TIME]
+      False False
+
+    ----- stderr -----
+    ");
+}
+
+/// Output longer than `max_output_lines` is truncated, the full output is saved to a file.
+#[test]
+fn max_output_lines() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        max_output_lines: 3
+        repos:
+          - repo: local
+            hooks:
+              - id: noisy
+                name: noisy
+                language: system
+                entry: python3 -c 'for i in range(10):print(i)'
+                pass_filenames: false
+                always_run: true
+                verbose: true
+              - id: limited
+                name: limited
+                language: system
+                entry: python3 -c 'for i in range(10):print(i)'
+                pass_filenames: false
+                always_run: true
+                verbose: true
+                max_output_lines: 5
+   "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    noisy....................................................................Passed
+    - hook id: noisy
+    - duration: [TIME]
+      0
+      1
+      2
+      ... 7 more lines, full output in [HOME]/logs/noisy.log
+    limited..................................................................Passed
+    - hook id: limited
+    - duration: [TIME]
+      0
+      1
+      2
+      3
+      4
+      ... 5 more lines, full output in [HOME]/logs/limited.log
+
+    ----- stderr -----
+    ");
+
+    let log = context.home_dir().child("logs/noisy.log");
+    log.assert("0\n1\n2\n3\n4\n5\n6\n7\n8\n9");
+}
+
+/// `--quiet` only prints failing hooks, `-qq` prints nothing.
+#[test]
+fn quiet() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: pass
+                name: pass
+                language: system
+                entry: python3 -c 'print("passed")'
+                pass_filenames: false
+                always_run: true
+                verbose: true
+              - id: fail
+                name: fail
+                language: system
+                entry: python3 -c 'print("failed"); raise SystemExit(1)'
+                pass_filenames: false
+                always_run: true
+              - id: no-files
+                name: no files
+                language: system
+                entry: python3 -c 'pass'
+                files: \.py$
+   "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("-q"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
+      failed
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--parallel").arg("-q"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
+      failed
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.run().arg("-qq"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+}
+
+/// `--output-format json` prints a report of the results of all hooks.
+#[test]
+fn json_report() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: fix
+                name: fix
+                language: system
+                entry: python3 -c 'import sys; open(sys.argv[1], "a").write("fixed\n"); print("fixing")'
+                files: \.txt$
+              - id: no-files
+                name: no files
+                language: system
+                entry: python3 -c 'pass'
+                files: \.py$
+   "#});
+
+    let cwd = context.workdir();
+    cwd.child("a.txt").write_str("a\n")?;
+    context.git_add(".");
+
+    let filters = context
+        .filters()
+        .into_iter()
+        .chain([(
+            r#""duration": \d+\.\d+(e-\d+)?"#,
+            r#""duration": [DURATION]"#,
+        )])
+        .collect::<Vec<_>>();
+
+    cmd_snapshot!(filters.clone(), context.run().arg("--output-format").arg("json"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    {
+      "passed": 0,
+      "failed": 1,
+      "skipped": 1,
+      "hooks": [
+        {
+          "id": "fix",
+          "name": "fix",
+          "status": "failed",
+          "duration": [DURATION],
+          "exit_code": 0,
+          "files": [
+            "a.txt"
+          ],
+          "output": "fixing/n",
+          "files_modified": [
+            "a.txt"
+          ]
+        },
+        {
+          "id": "no-files",
+          "name": "no files",
+          "status": "skipped",
+          "duration": null,
+          "exit_code": null,
+          "files": [],
+          "output": "",
+          "files_modified": []
+        }
+      ]
+    }
+
+    ----- stderr -----
+    "#);
+
+    // With an output file, the report is written to the file.
+    context.git_add(".");
+    cmd_snapshot!(filters, context.run().arg("--output-format=json").arg("--output-file").arg("report.json"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fix......................................................................Failed
+    - hook id: fix
+    - files were modified by this hook
+      fixing
+    no files.............................................(no files to check)Skipped
+
+    ----- stderr -----
+    ");
+
+    let report = context.read("report.json");
+    assert!(report.contains(r#""id": "fix""#));
+
+    Ok(())
+}
+
+/// `--output-format tap` prints a TAP report of the results of all hooks.
+#[test]
+fn tap_report() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: pass
+                name: pass
+                language: system
+                entry: python3 -c 'pass'
+                pass_filenames: false
+                always_run: true
+              - id: fail
+                name: fail
+                language: system
+                entry: python3 -c 'print("line 1"); print("line 2"); raise SystemExit(3)'
+                pass_filenames: false
+                always_run: true
+              - id: no-files
+                name: no files
+                language: system
+                entry: python3 -c 'pass'
+                files: \.py$
+   "#});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--output-format").arg("tap"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    TAP version 13
+    1..3
+    ok 1 - pass
+    not ok 2 - fail
+      ---
+      id: fail
+      exit_code: 3
+      files_modified: []
+      output: |-
+        line 1
+        line 2
+      ...
+    ok 3 - no files # SKIP
+
+    ----- stderr -----
+    ");
+}
+
+/// In GitHub Actions, failures are annotated and a summary is written to the job summary.
+#[test]
+fn github_actions() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: fix
+                name: fix
+                language: system
+                entry: python3 -c 'import sys; open(sys.argv[1], "a").write("fixed\n")'
+                files: \.txt$
+              - id: fail
+                name: fail
+                language: system
+                entry: python3 -c 'raise SystemExit(2)'
+                pass_filenames: false
+                always_run: true
+              - id: no-files
+                name: no files
+                language: system
+                entry: python3 -c 'pass'
+                files: \.py$
+   "#});
+
+    let cwd = context.workdir();
+    cwd.child("a.txt").write_str("a\n")?;
+    context.git_add(".");
+
+    let summary = context.home_dir().child("summary.md");
+    cmd_snapshot!(context.filters(), context.run()
+        .arg("--color=never")
+        .env("GITHUB_ACTIONS", "true")
+        .env("GITHUB_STEP_SUMMARY", &*summary), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    fix......................................................................Failed
+    - hook id: fix
+    - files were modified by this hook
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 2
+    no files.............................................(no files to check)Skipped
+    All changes made by hooks:
+    diff --git a/a.txt b/a.txt
+    index 7898192..d75aaee 100644
+    --- a/a.txt
+    +++ b/a.txt
+    @@ -1 +1,2 @@
+     a
+    +fixed
+    ::error file=a.txt,title=fix::File was modified by hook `fix`
+    ::error title=fail::Hook `fail` failed with exit code 2
+
+    ----- stderr -----
+    ");
+
+    let summary = context.read(summary.path());
+    let summary = regex::Regex::new(r"\d+\.\d+s")?.replace_all(&summary, "[TIME]");
+    insta::assert_snapshot!(summary, @r"
+    ### prefligit
+
+    0 passed, 2 failed, 1 skipped
+
+    | Hook | Status | Duration | Files modified |
+    | --- | --- | --- | --- |
+    | fix | :x: Failed | [TIME] | 1 |
+    | fail | :x: Failed | [TIME] | 0 |
+    | no files | :fast_forward: Skipped |  | 0 |
+    ");
+
+    Ok(())
+}
+
+/// `--output-format rdjson` converts the output of failed hooks into reviewdog diagnostics.
+#[test]
+fn rdjson_report() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: lint
+                name: lint
+                language: system
+                entry: python3 lint.py
+                pass_filenames: false
+                always_run: true
+              - id: custom
+                name: custom
+                language: system
+                entry: python3 custom.py
+                pass_filenames: false
+                always_run: true
+                output_pattern: 'ERROR in (?P<file>\S+) line (?P<line>\d+): (?P<message>.+)'
+   "});
+
+    let cwd = context.workdir();
+    cwd.child("lint.py").write_str(indoc::indoc! {r#"
+        print("a.py:1:5: E101 bad indent")
+        print("b.py:3: missing newline")
+        print("2 errors")
+        raise SystemExit(1)
+    "#})?;
+    cwd.child("custom.py").write_str(indoc::indoc! {r#"
+        print("ERROR in c.py line 7: oops")
+        raise SystemExit(1)
+    "#})?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--output-format").arg("rdjson"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    {
+      "source": {
+        "name": "prefligit"
+      },
+      "diagnostics": [
+        {
+          "message": "E101 bad indent",
+          "location": {
+            "path": "a.py",
+            "range": {
+              "start": {
+                "line": 1,
+                "column": 5
+              }
+            }
+          },
+          "severity": "ERROR",
+          "source": {
+            "name": "lint"
+          }
+        },
+        {
+          "message": "missing newline",
+          "location": {
+            "path": "b.py",
+            "range": {
+              "start": {
+                "line": 3
+              }
+            }
+          },
+          "severity": "ERROR",
+          "source": {
+            "name": "lint"
+          }
+        },
+        {
+          "message": "oops",
+          "location": {
+            "path": "c.py",
+            "range": {
+              "start": {
+                "line": 7
+              }
+            }
+          },
+          "severity": "ERROR",
+          "source": {
+            "name": "custom"
+          }
+        }
+      ]
+    }
+
+    ----- stderr -----
+    "#);
+
+    Ok(())
+}
+
+/// `-z` prints NUL-terminated records without decoration.
+#[test]
+fn null_output() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: pass
+                name: pass
+                language: system
+                entry: python3 -c 'pass'
+              - id: fail
+                name: fail
+                language: system
+                entry: python3 -c 'raise SystemExit(1)'
+                pass_filenames: false
+                always_run: true
+              - id: no-files
+                name: no files
+                language: system
+                entry: python3 -c 'pass'
+                files: \.py$
+   "});
+
+    let cwd = context.workdir();
+    cwd.child("a file.txt").write_str("a")?;
+    cwd.child("new\nline.txt").write_str("b")?;
+    context.git_add(".");
+
+    let output = context.run().arg("-z").output()?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "pass\tpassed\0fail\tfailed\0no-files\tskipped\0"
+    );
+
+    let output = context.run().arg("-z").arg("--dry-run").output()?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "pass\t.pre-commit-config.yaml\0pass\ta file.txt\0pass\tnew\nline.txt\0"
+    );
+
+    Ok(())
+}
+
+/// `--timings` prints the hooks sorted by duration after running hooks.
+#[test]
+fn timings() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: fast
+                name: fast
+                language: system
+                entry: python3 -c 'pass'
+                pass_filenames: false
+                always_run: true
+              - id: slow
+                name: slow
+                language: system
+                entry: python3 -c 'import time; time.sleep(0.5)'
+                pass_filenames: false
+                always_run: true
+              - id: no-files
+                name: no files
+                language: system
+                entry: python3 -c 'pass'
+                files: \.py$
+   "});
+    context.git_add(".");
+
+    let filters = context
+        .filters()
+        .into_iter()
+        .chain([(r" +\d+\.\d%", " [PERCENT]")])
+        .collect::<Vec<_>>();
+
+    cmd_snapshot!(filters, context.run().arg("--timings"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    fast.....................................................................Passed
+    slow.....................................................................Passed
+    no files.............................................(no files to check)Skipped
+    Hook timings:
+        [TIME] [PERCENT] slow
+        [TIME] [PERCENT] fast
+
+    ----- stderr -----
+    ");
+}
+
+/// Hooks slower than `warn_slow_hooks` show their duration without `--verbose`.
+#[test]
+fn warn_slow_hooks() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        warn_slow_hooks: 1s
+        repos:
+          - repo: local
+            hooks:
+              - id: fast
+                name: fast
+                language: system
+                entry: 'true'
+                pass_filenames: false
+                always_run: true
+              - id: slow
+                name: slow
+                language: system
+                entry: sleep 3
+                pass_filenames: false
+                always_run: true
+   "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    fast.....................................................................Passed
+    slow.....................................................................Passed ([TIME], slower than [TIME])
+
+    ----- stderr -----
+    ");
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        warn_slow_hooks: soon
+        repos: []
+   "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Failed to parse `.pre-commit-config.yaml`
+      caused by: Invalid duration, expected a value like `[TIME]` or `[TIME]`
+    ");
+}
+
+/// Clones are reported as plain lines when stderr is not a terminal.
+#[test]
+fn clone_progress() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let hook_repo = context.workdir().child("hook-repo");
+    hook_repo.create_dir_all()?;
+    hook_repo
+        .child(".pre-commit-hooks.yaml")
+        .write_str(indoc::indoc! {r"
+            - id: echo
+              name: echo
+              entry: echo
+              language: system
+        "})?;
+    Command::new("git")
+        .arg("init")
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@test.com"])
+        .args(["commit", "-m", "init"])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["tag", "v1.0.0"])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+
+    context.write_pre_commit_config(&indoc::formatdoc! {r"
+        repos:
+          - repo: file://{}
+            rev: v1.0.0
+            hooks:
+              - id: echo
+                always_run: true
+    ", hook_repo.display()});
+    context.git_add(".pre-commit-config.yaml");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    echo.....................................................................Passed
+
+    ----- stderr -----
+    Cloning file://[TEMP_DIR]/hook-repo@v1.0.0
+    ");
+
+    // The repo is cloned only once.
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    echo.....................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Hook repos are cloned shallow, and revs the remote can't serve directly are fetched
+/// with the full history.
+#[test]
+fn shallow_clone() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let hook_repo = context.workdir().child("hook-repo");
+    hook_repo.create_dir_all()?;
+    Command::new("git")
+        .arg("init")
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    for message in ["first", "second"] {
+        hook_repo
+            .child(".pre-commit-hooks.yaml")
+            .write_str(&indoc::formatdoc! {r"
+                - id: echo
+                  name: echo
+                  entry: echo {message}
+                  language: system
+            "})?;
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(&hook_repo)
+            .assert()
+            .success();
+        Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@test.com"])
+            .args(["commit", "-m", message])
+            .current_dir(&hook_repo)
+            .assert()
+            .success();
+    }
+    Command::new("git")
+        .args(["tag", "v1.0.0"])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD~1"])
+        .current_dir(&hook_repo)
+        .output()?;
+    let first = String::from_utf8(output.stdout)?.trim().to_string();
+
+    let write_config = |rev: &str| {
+        context.write_pre_commit_config(&indoc::formatdoc! {r"
+            repos:
+              - repo: file://{}
+                rev: {rev}
+                hooks:
+                  - id: echo
+                    always_run: true
+                    pass_filenames: false
+                    verbose: true
+        ", hook_repo.display()});
+        context.git_add(".pre-commit-config.yaml");
+    };
+    let cloned = |marker: &str| -> Result<bool> {
+        for entry in fs_err::read_dir(context.home_dir().child("repos").path())? {
+            let path = entry?.path();
+            if path.join(".git").join(marker).exists() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    };
+
+    write_config("v1.0.0");
+    context.run().assert().success();
+    assert!(cloned("shallow")?);
+
+    // Abbreviated hashes can't be fetched directly.
+    write_config(&first);
+    let output = context.run().assert().success().get_output().stdout.clone();
+    assert!(String::from_utf8(output)?.contains("first"));
+
+    Ok(())
+}
+
+/// Failed clones are retried with backoff before giving up.
+#[test]
+fn retry_clone() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: file:///non-existent/hook-repo
+            rev: v1.0.0
+            hooks:
+              - id: echo
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().env("PREFLIGIT_NETWORK_RETRIES", "1"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    Cloning file:///non-existent/hook-repo@v1.0.0
+    warning: Failed to clone `file:///non-existent/hook-repo@v1.0.0`, retrying in [TIME] (1/1): failed to git full clone (status: exit status: 128)
+    error: Failed to clone `file:///non-existent/hook-repo@v1.0.0`
+      caused by: failed to git full clone (status: exit status: 128)
+    ");
+}
+
+/// `--live` falls back to the line-based output when not running in a terminal.
+#[test]
+fn live_fallback() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: pass
+                name: pass
+                language: system
+                entry: python3 -c 'pass'
+                pass_filenames: false
+                always_run: true
+              - id: fail
+                name: fail
+                language: system
+                entry: python3 -c 'exit(1)'
+                pass_filenames: false
+                always_run: true
+   "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--live"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    pass.....................................................................Passed
+    fail.....................................................................Failed
+    - hook id: fail
+    - exit code: 1
+
+    ----- stderr -----
+    ");
+}
+
+/// `--interactive` needs a terminal to show the hook picker.
+#[test]
+fn interactive_without_terminal() {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: local
+                name: local
+                language: system
+                entry: echo
+   "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--interactive"), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: `--interactive` requires a terminal
+    ");
+}
+
+/// Repos can be directories relative to the config file, their hooks are used as they are.
+#[test]
+fn local_path_repo() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let manifest = context
+        .workdir()
+        .child("tools/hooks")
+        .child(".pre-commit-hooks.yaml");
+    manifest.write_str(indoc::indoc! {r"
+        - id: no-todo
+          name: no-todo
+          entry: Remove the TODO files
+          language: fail
+          files: TODO
+    "})?;
+    context.workdir().child("TODO").write_str("")?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: ./tools/hooks
+            hooks:
+              - id: no-todo
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    no-todo..................................................................Failed
+    - hook id: no-todo
+    - exit code: 1
+      Remove the TODO files
+
+      TODO
+
+    ----- stderr -----
+    ");
+
+    // Changes to the hooks take effect without cloning.
+    manifest.write_str(indoc::indoc! {r"
+        - id: no-todo
+          name: no-todo
+          entry: Move the TODO files to issues
+          language: fail
+          files: TODO
+    "})?;
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    no-todo..................................................................Failed
+    - hook id: no-todo
+    - exit code: 1
+      Move the TODO files to issues
+
+      TODO
+
+    ----- stderr -----
+    ");
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: ../missing
+            hooks:
+              - id: no-todo
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Failed to parse `.pre-commit-config.yaml`
+      caused by: repos: Repo directory `../missing` does not exist at line 2 column 3
+     --> .pre-commit-config.yaml:2:3
+      |
+    2 |   - repo: ../missing
+      |   ^
+    ");
+
+    Ok(())
+}
+
+/// Hooks can be used from a subdirectory of a repo.
+#[test]
+fn repo_subdirectory() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let hook_repo = context.workdir().child("monorepo");
+    for (dir, message) in [
+        ("linters/foo", "Failed by foo"),
+        ("linters/bar", "Failed by bar"),
+    ] {
+        hook_repo
+            .child(dir)
+            .child(".pre-commit-hooks.yaml")
+            .write_str(&indoc::formatdoc! {r"
+                - id: lint
+                  name: lint
+                  entry: {message}
+                  language: fail
+                  always_run: true
+                  pass_filenames: false
+            "})?;
+    }
+    Command::new("git")
+        .arg("init")
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@test.com"])
+        .args(["commit", "-m", "init"])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["tag", "v1.0.0"])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+
+    context.write_pre_commit_config(&indoc::formatdoc! {r"
+        repos:
+          - repo: file://{repo}
+            rev: v1.0.0
+            subdirectory: linters/foo
+            hooks:
+              - id: lint
+          - repo: file://{repo}
+            rev: v1.0.0
+            subdirectory: linters/bar
+            hooks:
+              - id: lint
+    ", repo = hook_repo.display()});
+    context.git_add(".pre-commit-config.yaml");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    lint.....................................................................Failed
+    - hook id: lint
+    - exit code: 1
+      Failed by foo
+    lint.....................................................................Failed
+    - hook id: lint
+    - exit code: 1
+      Failed by bar
+
+    ----- stderr -----
+    Cloning file://[TEMP_DIR]/monorepo@v1.0.0
+    ");
+
+    context.write_pre_commit_config(&indoc::formatdoc! {r"
+        repos:
+          - repo: file://{repo}
+            rev: v1.0.0
+            subdirectory: ../outside
+            hooks:
+              - id: lint
+    ", repo = hook_repo.display()});
+    context.git_add(".pre-commit-config.yaml");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Failed to parse `.pre-commit-config.yaml`
+      caused by: repos: Invalid remote repo: Expected a relative path inside the repo, got `../outside` at line 2 column 3
+     --> .pre-commit-config.yaml:2:3
+      |
+    2 |   - repo: file://[TEMP_DIR]/monorepo
+      |   ^
+    ");
+
+    Ok(())
+}
+
+/// Hook repos are cloned from the mirrors of the user config.
+#[test]
+fn url_rewrite() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    let mirror = context.workdir().child("mirror");
+    let hook_repo = mirror.child("hook-repo");
+    hook_repo
+        .child(".pre-commit-hooks.yaml")
+        .write_str(indoc::indoc! {r"
+            - id: echo
+              name: echo
+              entry: echo
+              language: system
+        "})?;
+    Command::new("git")
+        .arg("init")
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@test.com"])
+        .args(["commit", "-m", "init"])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+    Command::new("git")
+        .args(["tag", "v1.0.0"])
+        .current_dir(&hook_repo)
+        .assert()
+        .success();
+
+    context
+        .home_dir()
+        .child("config.toml")
+        .write_str(&indoc::formatdoc! {r#"
+            [url."file://{}/"]
+            instead_of = ["https://github.com/example/"]
+        "#, mirror.display()})?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: https://github.com/example/hook-repo
+            rev: v1.0.0
+            hooks:
+              - id: echo
+                always_run: true
+    "});
+    context.git_add(".pre-commit-config.yaml");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    echo.....................................................................Passed
+
+    ----- stderr -----
+    Cloning https://github.com/example/hook-repo@v1.0.0
+    ");
+
+    Ok(())
+}
+
+/// The CA certificates of the user config are passed to the tools hooks run.
+#[test]
+fn ca_certificates() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context
+        .home_dir()
+        .child("config.toml")
+        .write_str("ca_certificates = \"/etc/corp/ca.pem\"\n")?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: certs
+                name: certs
+                entry: sh -c 'echo $SSL_CERT_FILE $GIT_SSL_CAINFO $PIP_CERT $NODE_EXTRA_CA_CERTS'
+                language: system
+                pass_filenames: false
+                verbose: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run()
+        .env_remove("SSL_CERT_FILE")
+        .env_remove("GIT_SSL_CAINFO")
+        .env_remove("PIP_CERT")
+        .env("NODE_EXTRA_CA_CERTS", "/etc/node/ca.pem"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    certs....................................................................Passed
+    - hook id: certs
+    - duration: [TIME]
+      /etc/corp/ca.pem /etc/corp/ca.pem /etc/corp/ca.pem /etc/node/ca.pem
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Custom file types of the config and the user config work in `types`.
+#[test]
+fn custom_file_types() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context
+        .workdir()
+        .child("api.proto")
+        .write_str("syntax = \"proto3\";")?;
+    context.workdir().child("Tiltfile").write_str("")?;
+    context.workdir().child("Tiltfile.dev").write_str("")?;
+    context.workdir().child("main.py").write_str("")?;
+    let script = context.workdir().child("build");
+    script.write_str("#!/usr/bin/env -S deno run\n")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs_err::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    context
+        .home_dir()
+        .child("config.toml")
+        .write_str(indoc::indoc! {r#"
+            [file_types.protobuf]
+            extensions = ["proto"]
+
+            [file_types.starlark]
+            extensions = ["star"]
+        "#})?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        file_types:
+          starlark:
+            filenames: [Tiltfile]
+          typescript:
+            interpreters: [deno]
+        repos:
+          - repo: local
+            hooks:
+              - id: protobuf
+                name: protobuf
+                language: system
+                entry: echo
+                types: [protobuf]
+                verbose: true
+              - id: starlark
+                name: starlark
+                language: system
+                entry: echo
+                types: [starlark]
+                verbose: true
+              - id: typescript
+                name: typescript
+                language: system
+                entry: echo
+                types_or: [typescript]
+                verbose: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    protobuf.................................................................Passed
+    - hook id: protobuf
+    - duration: [TIME]
+      api.proto
+    starlark.................................................................Passed
+    - hook id: starlark
+    - duration: [TIME]
+      Tiltfile Tiltfile.dev
+    typescript...............................................................Passed
+    - hook id: typescript
+    - duration: [TIME]
+      build
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// The file headers cached by a run are read again once the files change.
+#[cfg(unix)]
+#[test]
+fn cached_file_headers() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let context = TestContext::new();
+    context.init_project();
+
+    let script = context.workdir().child("script");
+    script.write_str("#!/usr/bin/env python3\n")?;
+    fs_err::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: python
+                name: python
+                language: system
+                entry: echo
+                types: [python]
+                verbose: true
+              - id: shell
+                name: shell
+                language: system
+                entry: echo
+                types: [shell]
+                verbose: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    python...................................................................Passed
+    - hook id: python
+    - duration: [TIME]
+      script
+    shell................................................(no files to check)Skipped
+
+    ----- stderr -----
+    ");
+
+    script.write_str("#!/bin/bash\n")?;
+
+    cmd_snapshot!(context.filters(), context.run().arg("--all-files"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    python...............................................(no files to check)Skipped
+    shell....................................................................Passed
+    - hook id: shell
+    - duration: [TIME]
+      script
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Files are split into batches that fit in `max_command_length` of the user config, a file
+/// longer than it still runs alone.
+#[cfg(unix)]
+#[test]
+fn max_command_length() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context
+        .home_dir()
+        .child("config.toml")
+        .write_str("max_command_length = 60\n")?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: batches
+                name: batches
+                language: system
+                entry: echo
+                require_serial: true
+                verbose: true
+    "});
+    for name in [
+        "a.txt",
+        "b.txt",
+        "c.txt",
+        &format!("{}.txt", "d".repeat(80)),
+    ] {
+        context.workdir().child(name).write_str("")?;
+    }
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    batches..................................................................Passed
+    - hook id: batches
+    - duration: [TIME]
+      b.txt
+      dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd.txt
+      c.txt .pre-commit-config.yaml
+      a.txt
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Files matching `.prefligitignore` are not passed to any hook.
+#[test]
+fn ignore_file() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    for file in [
+        "src/main.py",
+        "src/api_pb2.py",
+        "vendor/lib.py",
+        "vendor/patched/lib.py",
+    ] {
+        context.workdir().child(file).write_str("")?;
+    }
+    context
+        .workdir()
+        .child(".prefligitignore")
+        .write_str(indoc::indoc! {r"
+            # Generated code
+            *_pb2.py
+            vendor/
+            !vendor/patched/
+        "})?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: echo
+                name: echo
+                language: system
+                entry: echo
+                types: [python]
+                verbose: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    echo.....................................................................Passed
+    - hook id: echo
+    - duration: [TIME]
+      src/main.py vendor/patched/lib.py
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Run hooks inside a subdirectory, with the filenames relative to it.
+#[test]
+fn working_directory() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context
+        .workdir()
+        .child("main.go")
+        .write_str("package main")?;
+    context
+        .workdir()
+        .child("pkg/lib/lib.go")
+        .write_str("package lib")?;
+    context
+        .workdir()
+        .child("pkg/pkg.go")
+        .write_str("package pkg")?;
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: in-pkg
+                name: in-pkg
+                language: system
+                entry: ls
+                working_directory: pkg/
+                verbose: true
+              - id: in-root
+                name: in-root
+                language: system
+                entry: ls
+                working_directory: .
+                files: \.go$
+                verbose: true
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    in-pkg...................................................................Passed
+    - hook id: in-pkg
+    - duration: [TIME]
+      lib/lib.go
+      pkg.go
+    in-root..................................................................Passed
+    - hook id: in-root
+    - duration: [TIME]
+      main.go
+      pkg/lib/lib.go
+      pkg/pkg.go
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
+
+/// Hooks of different repos share an environment if they have the same language version and dependencies.
+#[test]
+fn share_environments() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    for repo in ["hooks-a", "hooks-b"] {
+        context
+            .workdir()
+            .child(repo)
+            .child(".pre-commit-hooks.yaml")
+            .write_str(&indoc::formatdoc! {r"
+                - id: {repo}
+                  name: {repo}
+                  entry: {repo}
+                  language: node
+            "})?;
+    }
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: ./hooks-a
+            hooks:
+              - id: hooks-a
+              - id: hooks-a
+                alias: with-deps
+                additional_dependencies: [prettier]
+          - repo: ./hooks-b
+            hooks:
+              - id: hooks-b
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    hooks-a..................................................................Passed
+    hooks-a..................................................................Passed
+    hooks-b..................................................................Passed
+
+    ----- stderr -----
+    Installing hooks-a
+    Installing hooks-a
+    ");
+
+    let envs = fs_err::read_dir(context.home_dir().child("envs").path())?
+        .filter(|entry| entry.as_ref().is_ok_and(|entry| entry.path().is_dir()))
+        .count();
+    assert_eq!(envs, 2);
+
+    Ok(())
+}
+
+/// Installed environments are reused until the state they were installed with no longer matches.
+#[test]
+fn reuse_installed_environments() -> Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: node
+                name: node
+                entry: node
+                language: node
+    "});
+    context.git_add(".");
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    node.....................................................................Passed
+
+    ----- stderr -----
+    Installing node
+    ");
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    node.....................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    // A state recorded with other inputs installs the environment again.
+    let env = fs_err::read_dir(context.home_dir().child("envs").path())?
+        .find(|entry| entry.as_ref().is_ok_and(|entry| entry.path().is_dir()))
+        .expect("environment installed")?;
+    fs_err::write(env.path().join(".install_state_v3"), "{}")?;
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    node.....................................................................Passed
+
+    ----- stderr -----
+    Installing node
+    ");
+
+    Ok(())
+}
+
+/// Runs with everything installed don't wait for the locks of the store entries.
+#[test]
+fn run_installed_without_locks() -> Result<()> {
+    use fs2::FileExt;
+
+    let context = TestContext::new();
+    context.init_project();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: node
+                name: node
+                entry: node
+                language: node
+    "});
+    context.git_add(".");
+    context.run().assert().success();
+
+    // Hold the locks like another process installing into the store.
+    let mut locks = Vec::new();
+    for dir in ["repos", "envs"] {
+        for entry in fs_err::read_dir(context.home_dir().child(dir).path())? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "lock") {
+                let file = fs_err::File::open(&path)?;
+                file.file().lock_exclusive()?;
+                locks.push(file);
+            }
+        }
+    }
+    assert!(!locks.is_empty());
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    node.....................................................................Passed
+
+    ----- stderr -----
+    ");
+
+    Ok(())
+}
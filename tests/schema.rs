@@ -0,0 +1,37 @@
+use anyhow::Result;
+
+use crate::common::TestContext;
+
+mod common;
+
+#[test]
+fn schema() -> Result<()> {
+    let context = TestContext::new();
+
+    let output = context.schema().output()?;
+    assert!(output.status.success());
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(schema["title"], "Config");
+    assert_eq!(schema["required"], serde_json::json!(["repos"]));
+    let repo_kinds: Vec<_> = schema["definitions"]["Repo"]["anyOf"]
+        .as_array()
+        .expect("repo kinds")
+        .iter()
+        .map(|kind| kind["$ref"].as_str().unwrap_or_default())
+        .collect();
+    insta::assert_debug_snapshot!(repo_kinds, @r##"
+    [
+        "#/definitions/RemoteRepo",
+        "#/definitions/LocalRepo",
+        "#/definitions/MetaRepo",
+    ]
+    "##);
+
+    let output = context.schema().arg("manifest").output()?;
+    assert!(output.status.success());
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(schema["type"], "array");
+    assert_eq!(schema["items"]["$ref"], "#/definitions/ManifestHook");
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use assert_cmd::assert::OutputAssertExt;
+
+use crate::common::{cmd_snapshot, TestContext};
+
+mod common;
+
+#[test]
+fn stats() {
+    let context = TestContext::new();
+    context.init_project();
+
+    cmd_snapshot!(context.filters(), context.stats(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    No runs recorded for this repo
+
+    ----- stderr -----
+    ");
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: pass
+                name: pass
+                language: system
+                entry: python3 -c 'import time; time.sleep(0.2)'
+                pass_filenames: false
+                always_run: true
+              - id: fail
+                name: fail
+                language: system
+                entry: python3 -c 'exit(1)'
+                pass_filenames: false
+                always_run: true
+              - id: no-files
+                name: no files
+                language: system
+                entry: python3 -c 'pass'
+                files: \.py$
+   "});
+    context.git_add(".");
+
+    context.run().assert().failure();
+    context.run().env("SKIP", "fail").assert().success();
+
+    cmd_snapshot!(context.filters(), context.stats(), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Last 2 runs: 1 passed, 1 failed, average [TIME]
+
+    Hook   Runs  Failures   Average
+    pass      2      0.0%     [TIME]
+    fail      1    100.0%     [TIME]
+
+    ----- stderr -----
+    ");
+
+    cmd_snapshot!(context.filters(), context.stats().arg("--last").arg("1"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Last 1 runs: 1 passed, 0 failed, average [TIME]
+
+    Hook   Runs  Failures   Average
+    pass      1      0.0%     [TIME]
+
+    ----- stderr -----
+    ");
+}
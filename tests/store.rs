@@ -0,0 +1,265 @@
+use assert_cmd::assert::OutputAssertExt;
+use assert_fs::prelude::*;
+use rusqlite::Connection;
+
+use crate::common::{cmd_snapshot, TestContext};
+
+mod common;
+
+fn write_config(context: &TestContext) {
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: local
+            hooks:
+              - id: local
+                name: local
+                entry: echo
+                language: system
+                pass_filenames: false
+    "});
+    context.git_add(".");
+}
+
+/// Stores created before the database was versioned are migrated in place.
+#[test]
+fn migrate_unversioned_store() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    write_config(&context);
+
+    // A store of the layout where environments were installed inside the repos.
+    let legacy_repo = context.home_dir().child("repoabc123");
+    legacy_repo.child("py_env-default").create_dir_all()?;
+    let conn = Connection::open(context.home_dir().child("db.db").path())?;
+    conn.execute_batch(
+        "CREATE TABLE repos (
+            repo TEXT NOT NULL,
+            ref TEXT NOT NULL,
+            path TEXT NOT NULL,
+            PRIMARY KEY (repo, ref)
+        );
+        CREATE TABLE configs (
+            path TEXT NOT NULL,
+            PRIMARY KEY (path)
+        );",
+    )?;
+    conn.execute(
+        "INSERT INTO repos (repo, ref, path) VALUES ('https://github.com/abc/hooks', 'v1.0.0', ?)",
+        [legacy_repo.path().to_string_lossy()],
+    )?;
+    drop(conn);
+
+    context.run().assert().success();
+
+    assert!(!legacy_repo.path().exists());
+    let conn = Connection::open(context.home_dir().child("db.db").path())?;
+    let version: usize =
+        conn.query_row("SELECT version FROM schema_version", [], |row| row.get(0))?;
+    assert_eq!(version, 6);
+    let repos: usize = conn.query_row(
+        "SELECT COUNT(*) FROM repos WHERE repo = 'https://github.com/abc/hooks'",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(repos, 0);
+    let configs: usize = conn.query_row(
+        "SELECT COUNT(*) FROM configs WHERE last_used > 0",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(configs, 1);
+
+    Ok(())
+}
+
+/// Stores migrated by a newer version are not touched.
+#[test]
+fn newer_store_schema() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    write_config(&context);
+
+    let conn = Connection::open(context.home_dir().child("db.db").path())?;
+    conn.execute_batch(
+        "CREATE TABLE schema_version (version INTEGER NOT NULL);
+         INSERT INTO schema_version (version) VALUES (100);",
+    )?;
+    drop(conn);
+
+    cmd_snapshot!(context.filters(), context.run(), @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Store schema version 100 is newer than the supported version 6, upgrade prefligit or run `prefligit clean`
+    ");
+
+    Ok(())
+}
+
+/// Stores of schema version 1 have environments installed inside the repos, the repos are
+/// removed and the other tables migrated.
+#[test]
+fn migrate_v1_store() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    write_config(&context);
+
+    let legacy_repo = context.home_dir().child("repoabc123");
+    legacy_repo.child("node_env-default").create_dir_all()?;
+    let conn = Connection::open(context.home_dir().child("db.db").path())?;
+    conn.execute_batch(
+        "CREATE TABLE repos (
+            repo TEXT NOT NULL,
+            ref TEXT NOT NULL,
+            path TEXT NOT NULL,
+            PRIMARY KEY (repo, ref)
+        );
+        CREATE TABLE configs (
+            path TEXT NOT NULL,
+            PRIMARY KEY (path)
+        );
+        CREATE TABLE runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            repo TEXT NOT NULL,
+            started_at INTEGER NOT NULL,
+            duration REAL NOT NULL,
+            success INTEGER NOT NULL
+        );
+        CREATE TABLE hook_runs (
+            run_id INTEGER NOT NULL REFERENCES runs (id),
+            hook_id TEXT NOT NULL,
+            status TEXT NOT NULL,
+            duration REAL
+        );
+        CREATE TABLE schema_version (version INTEGER NOT NULL);
+        INSERT INTO schema_version (version) VALUES (1);
+        INSERT INTO configs (path) VALUES ('/old/.pre-commit-config.yaml');
+        INSERT INTO runs (repo, started_at, duration, success) VALUES ('/old', 1, 1.0, 1);",
+    )?;
+    conn.execute(
+        "INSERT INTO repos (repo, ref, path) VALUES ('https://github.com/abc/hooks', 'v1.0.0', ?)",
+        [legacy_repo.path().to_string_lossy()],
+    )?;
+    drop(conn);
+
+    context.run().assert().success();
+
+    assert!(!legacy_repo.path().exists());
+    let conn = Connection::open(context.home_dir().child("db.db").path())?;
+    let version: usize =
+        conn.query_row("SELECT version FROM schema_version", [], |row| row.get(0))?;
+    assert_eq!(version, 6);
+    let repos: usize = conn.query_row("SELECT COUNT(*) FROM repos", [], |row| row.get(0))?;
+    assert_eq!(repos, 0);
+    // The configs and the run history are kept.
+    let configs: usize = conn.query_row(
+        "SELECT COUNT(*) FROM configs WHERE path = '/old/.pre-commit-config.yaml'",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(configs, 1);
+    let runs: usize =
+        conn.query_row("SELECT COUNT(*) FROM runs WHERE repo = '/old'", [], |row| {
+            row.get(0)
+        })?;
+    assert_eq!(runs, 1);
+
+    Ok(())
+}
+
+/// Write a hook repo in `dir` of the project with a node hook.
+fn write_hook_repo(context: &TestContext, dir: &str) -> anyhow::Result<()> {
+    context
+        .workdir()
+        .child(dir)
+        .child(".pre-commit-hooks.yaml")
+        .write_str(indoc::indoc! {r"
+            - id: lint
+              name: lint
+              entry: lint
+              language: node
+        "})?;
+    Ok(())
+}
+
+/// The environments in the store, and the repos each of them is recorded for.
+fn envs(context: &TestContext) -> anyhow::Result<Vec<(String, usize)>> {
+    let conn = Connection::open(context.home_dir().child("db.db").path())?;
+    let mut stmt = conn.prepare("SELECT path, COUNT(*) FROM envs GROUP BY path ORDER BY path")?;
+    let envs = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    Ok(envs)
+}
+
+/// The number of environment directories in the store.
+fn env_dirs(context: &TestContext) -> anyhow::Result<usize> {
+    let mut count = 0;
+    for entry in fs_err::read_dir(context.home_dir().child("envs").path())? {
+        if entry?.path().is_dir() {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Hooks of different repos with the same language, version and dependencies share
+/// an environment.
+#[test]
+fn shared_env() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    write_hook_repo(&context, "hooks-a")?;
+    write_hook_repo(&context, "hooks-b")?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: ./hooks-a
+            hooks:
+              - id: lint
+                additional_dependencies: [prettier]
+          - repo: ./hooks-b
+            hooks:
+              - id: lint
+                additional_dependencies: [prettier]
+    "});
+    context.git_add(".");
+
+    context.run().assert().success();
+
+    let envs = envs(&context)?;
+    assert_eq!(envs.len(), 1);
+    assert_eq!(envs[0].1, 2);
+    assert_eq!(env_dirs(&context)?, 1);
+
+    Ok(())
+}
+
+/// Hooks with different dependencies get their own environments.
+#[test]
+fn separate_envs() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    write_hook_repo(&context, "hooks-a")?;
+    write_hook_repo(&context, "hooks-b")?;
+    context.write_pre_commit_config(indoc::indoc! {r"
+        repos:
+          - repo: ./hooks-a
+            hooks:
+              - id: lint
+                additional_dependencies: [prettier]
+          - repo: ./hooks-b
+            hooks:
+              - id: lint
+                additional_dependencies: [eslint]
+    "});
+    context.git_add(".");
+
+    context.run().assert().success();
+
+    let envs = envs(&context)?;
+    assert_eq!(envs.len(), 2);
+    assert!(envs.iter().all(|(_, repos)| *repos == 1));
+    assert_eq!(env_dirs(&context)?, 2);
+
+    Ok(())
+}
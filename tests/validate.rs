@@ -44,7 +44,7 @@ fn validate_config() -> anyhow::Result<()> {
         "})?;
 
     // Validate multiple files.
-    cmd_snapshot!(context.filters(), context.validate_config().arg(".pre-commit-config.yaml").arg("config-1.yaml"), @r#"
+    cmd_snapshot!(context.filters(), context.validate_config().arg(".pre-commit-config.yaml").arg("config-1.yaml"), @r"
     success: false
     exit_code: 1
     ----- stdout -----
@@ -52,11 +52,153 @@ fn validate_config() -> anyhow::Result<()> {
     ----- stderr -----
     error: Failed to parse `config-1.yaml`
       caused by: repos: Invalid remote repo: missing field `rev` at line 2 column 3
-    "#);
+     --> config-1.yaml:2:3
+      |
+    2 |   - repo: https://github.com/pre-commit/pre-commit-hooks
+      |   ^
+    ");
 
     Ok(())
 }
 
+#[test]
+fn invalid_regex() {
+    let context = TestContext::new();
+
+    context.write_pre_commit_config(indoc::indoc! {r#"
+            exclude: ^tests/
+            repos:
+              - repo: local
+                hooks:
+                  - id: echo
+                    name: echo
+                    entry: echo
+                    language: system
+                    files: "^src/("
+        "#});
+
+    cmd_snapshot!(context.filters(), context.validate_config().arg(".pre-commit-config.yaml"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Failed to parse `.pre-commit-config.yaml`
+      caused by: repos: Invalid local repo: Invalid regex `^src/(`: Parsing error at position 6: Opening parenthesis without closing parenthesis at line 3 column 3
+     --> .pre-commit-config.yaml:9:17
+      |
+    9 |         files: "^src/("
+      |                 ^^^^^^
+    "#);
+}
+
+#[test]
+fn unexpected_keys() {
+    let context = TestContext::new();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+            fail_fst: true
+            repos:
+              - repo: https://github.com/pre-commit/pre-commit-hooks
+                rev: v5.0.0
+                files: ^src/
+                hooks:
+                  - id: trailing-whitespace
+                    exlude: ^tests/
+        "});
+
+    cmd_snapshot!(context.filters(), context.validate_config().arg(".pre-commit-config.yaml"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    warning: Unexpected key `fail_fst` at the top level of `.pre-commit-config.yaml`, did you mean `fail_fast`?
+    warning: Unexpected key `files` in repo `https://github.com/pre-commit/pre-commit-hooks` of `.pre-commit-config.yaml`, it belongs to hooks
+    warning: Unexpected key `exlude` in hook `trailing-whitespace` of `.pre-commit-config.yaml`, did you mean `exclude`?
+    ");
+
+    cmd_snapshot!(context.filters(), context.validate_config().arg("--strict").arg(".pre-commit-config.yaml"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Unexpected keys in config files:
+      - Unexpected key `fail_fst` at the top level of `.pre-commit-config.yaml`, did you mean `fail_fast`?
+      - Unexpected key `files` in repo `https://github.com/pre-commit/pre-commit-hooks` of `.pre-commit-config.yaml`, it belongs to hooks
+      - Unexpected key `exlude` in hook `trailing-whitespace` of `.pre-commit-config.yaml`, did you mean `exclude`?
+    ");
+}
+
+#[test]
+fn stages() {
+    let context = TestContext::new();
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+            default_stages: [commit]
+            repos:
+              - repo: https://github.com/pre-commit/pre-commit-hooks
+                rev: v5.0.0
+                hooks:
+                  - id: trailing-whitespace
+                    stages: [pre-commit, push]
+        "});
+
+    // The deprecated names still work.
+    cmd_snapshot!(context.filters(), context.validate_config().arg(".pre-commit-config.yaml"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    warning: The stage `commit` at the top level of `.pre-commit-config.yaml` is deprecated, use `pre-commit` instead
+    warning: The stage `push` in hook `trailing-whitespace` of `.pre-commit-config.yaml` is deprecated, use `pre-push` instead
+    ");
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+            repos:
+              - repo: https://github.com/pre-commit/pre-commit-hooks
+                rev: v5.0.0
+                hooks:
+                  - id: trailing-whitespace
+                    stages: [pre-comit]
+        "});
+
+    cmd_snapshot!(context.filters(), context.validate_config().arg(".pre-commit-config.yaml"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Failed to parse `.pre-commit-config.yaml`
+      caused by: repos: Invalid remote repo: Invalid stage `pre-comit`, did you mean `pre-commit`? at line 2 column 3
+     --> .pre-commit-config.yaml:2:3
+      |
+    2 |   - repo: https://github.com/pre-commit/pre-commit-hooks
+      |   ^
+    ");
+
+    context.write_pre_commit_config(indoc::indoc! {r"
+            default_stages: [build]
+            repos: []
+        "});
+
+    cmd_snapshot!(context.filters(), context.validate_config().arg(".pre-commit-config.yaml"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: Failed to parse `.pre-commit-config.yaml`
+      caused by: default_stages: Invalid stage `build`, expected one of `manual`, `commit-msg`, `post-checkout`, `post-commit`, `post-merge`, `post-rewrite`, `pre-commit`, `pre-merge-commit`, `pre-push`, `pre-rebase`, `prepare-commit-msg` at line 1 column 17
+     --> .pre-commit-config.yaml:1:17
+      |
+    1 | default_stages: [build]
+      |                 ^
+    ");
+}
+
 #[test]
 fn validate_manifest() -> anyhow::Result<()> {
     let context = TestContext::new();
@@ -104,7 +246,7 @@ fn validate_manifest() -> anyhow::Result<()> {
         "})?;
 
     // Validate multiple files.
-    cmd_snapshot!(context.filters(), context.validate_manifest().arg(".pre-commit-hooks.yaml").arg("hooks-1.yaml"), @r#"
+    cmd_snapshot!(context.filters(), context.validate_manifest().arg(".pre-commit-hooks.yaml").arg("hooks-1.yaml"), @r"
     success: false
     exit_code: 1
     ----- stdout -----
@@ -112,7 +254,11 @@ fn validate_manifest() -> anyhow::Result<()> {
     ----- stderr -----
     error: Failed to parse `hooks-1.yaml`
       caused by: .[0]: missing field `entry` at line 1 column 5
-    "#);
+     --> hooks-1.yaml:1:5
+      |
+    1 | -   id: check-added-large-files
+      |     ^
+    ");
 
     Ok(())
 }
@@ -0,0 +1,65 @@
+use std::io::{BufRead, BufReader, Read};
+use std::process::Stdio;
+use std::time::Duration;
+
+use assert_fs::prelude::*;
+
+use crate::common::TestContext;
+
+mod common;
+
+/// Files changed while hooks run are run on next, the changes the hooks make are not.
+#[cfg(unix)]
+#[test]
+fn watch() -> anyhow::Result<()> {
+    let context = TestContext::new();
+    context.init_project();
+    context.write_pre_commit_config(indoc::indoc! {r#"
+        repos:
+          - repo: local
+            hooks:
+              - id: fix
+                name: fix
+                language: system
+                entry: sh -c 'sleep 1; for file in "$@"; do echo fixed >> "$file"; done; echo "ran on $*"' --
+                verbose: true
+    "#});
+
+    let mut watch = context
+        .command()
+        .args(["watch", "--debounce", "100"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Wait for the watcher to start.
+    let mut stderr = BufReader::new(watch.stderr.take().expect("stderr is piped"));
+    let mut line = String::new();
+    while !line.contains("Watching for changes") {
+        line.clear();
+        if stderr.read_line(&mut line)? == 0 {
+            anyhow::bail!("`watch` exited before watching");
+        }
+    }
+
+    context.workdir().child("a.txt").write_str("a\n")?;
+    // Edit another file while the hook runs on the first one.
+    std::thread::sleep(Duration::from_millis(500));
+    context.workdir().child("b.txt").write_str("b\n")?;
+    std::thread::sleep(Duration::from_secs(4));
+
+    watch.kill()?;
+    watch.wait()?;
+    let mut stdout = String::new();
+    watch
+        .stdout
+        .take()
+        .expect("stdout is piped")
+        .read_to_string(&mut stdout)?;
+
+    assert_eq!(stdout.matches("ran on a.txt").count(), 1, "{stdout}");
+    assert_eq!(stdout.matches("ran on b.txt").count(), 1, "{stdout}");
+    assert_eq!(context.read("a.txt"), "a\nfixed\n");
+
+    Ok(())
+}